@@ -55,23 +55,36 @@ const MODULE_DOCS: &[(&str, &str)] = &[
     ("Object", include_str!("../docs/stdlib/object.md")),
     ("Iter", include_str!("../docs/stdlib/iter.md")),
     ("Http", include_str!("../docs/stdlib/http.md")),
+    ("Router", include_str!("../docs/stdlib/router.md")),
     ("Url", include_str!("../docs/stdlib/url.md")),
+    ("Dns", include_str!("../docs/stdlib/dns.md")),
+    ("Redis", include_str!("../docs/stdlib/redis.md")),
+    ("Postgres", include_str!("../docs/stdlib/postgres.md")),
+    ("Store", include_str!("../docs/stdlib/store.md")),
+    ("Csv", include_str!("../docs/stdlib/csv.md")),
     ("WS", include_str!("../docs/stdlib/ws.md")),
     ("Channel", include_str!("../docs/stdlib/channel.md")),
     ("LocalChannel", include_str!("../docs/stdlib/localchannel.md")),
+    ("SharedMap", include_str!("../docs/stdlib/sharedmap.md")),
+    ("Supervisor", include_str!("../docs/stdlib/supervisor.md")),
     ("Test", include_str!("../docs/stdlib/test.md")),
     // Native (Rust) modules.
     ("JSON", include_str!("../docs/stdlib/json.md")),
+    ("Toml", include_str!("../docs/stdlib/toml.md")),
+    ("Yaml", include_str!("../docs/stdlib/yaml.md")),
     ("IO", include_str!("../docs/stdlib/io.md")),
     ("Path", include_str!("../docs/stdlib/path.md")),
     ("Time", include_str!("../docs/stdlib/time.md")),
     ("DateTime", include_str!("../docs/stdlib/datetime.md")),
     ("Random", include_str!("../docs/stdlib/random.md")),
     ("Bytes", include_str!("../docs/stdlib/bytes.md")),
+    ("Compress", include_str!("../docs/stdlib/compress.md")),
     ("BigInt", include_str!("../docs/stdlib/bigint.md")),
     ("Os", include_str!("../docs/stdlib/os.md")),
     ("Net", include_str!("../docs/stdlib/net.md")),
     ("Deferred", include_str!("../docs/stdlib/deferred.md")),
+    ("Shared", include_str!("../docs/stdlib/shared.md")),
+    ("Atomic", include_str!("../docs/stdlib/atomic.md")),
 ];
 
 /// The builtins page has the same `### `sig`` shape but no module name.
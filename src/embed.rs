@@ -135,6 +135,68 @@ impl Session {
         crate::vm::rng::seed(seed);
     }
 
+    /// Set how many OS threads the pool that runs `spawn`ed actors
+    /// uses (see [`crate::vm::actor_pool`]), overriding the
+    /// `TIGR_ACTOR_THREADS` environment variable and the CPU count. The
+    /// pool is process-wide and sized once, when the first actor is
+    /// spawned: returns `false`, changing nothing, after that — and
+    /// for an `n` of 0, since the pool needs at least one thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_actor_threads(&self, n: usize) -> bool {
+        crate::vm::actor_pool::set_threads(n)
+    }
+
+    /// Preempt a green thread once it has run `n` loop iterations and
+    /// function calls, at the next of them, whenever another is ready
+    /// (see [`crate::vm::preempt`]). `0` turns preemption off, the
+    /// default. Applies to this session and, process-wide, to every
+    /// actor spawned from now on.
+    pub fn set_preemption(&mut self, n: u32) {
+        crate::vm::preempt::set_quantum(n);
+        self.vm.set_preempt_quantum(n);
+    }
+
+    /// Report native calls that run longer than `threshold`, to stderr
+    /// or to a hook set with [`crate::vm::preempt::set_slow_native_hook`].
+    /// `None` turns reporting off, the default. Like
+    /// [`set_preemption`](Session::set_preemption), applies to this
+    /// session and to actors spawned from now on.
+    pub fn set_slow_native(&mut self, threshold: Option<std::time::Duration>) {
+        crate::vm::preempt::set_slow_native(threshold);
+        self.vm.set_slow_native(threshold);
+    }
+
+    /// Run in simulation mode with `seed` (see [`crate::vm::sim`]):
+    /// `wait` timers, `Time` and `DateTime.now` read a virtual clock that
+    /// jumps to the next timer instead of sleeping, the next ready
+    /// coroutine and the `Random` stream are drawn from the seed, and
+    /// `Net` runs over an in-memory loopback network. The same seed
+    /// replays the same run. `None` turns it off. Enabling restarts the
+    /// virtual clock; like [`set_preemption`](Session::set_preemption)
+    /// it applies to this session and to actors spawned from now on.
+    pub fn set_simulation(&mut self, seed: Option<u64>) {
+        match seed {
+            Some(seed) => crate::vm::sim::enable(seed),
+            None => crate::vm::sim::disable(),
+        }
+        self.vm.set_simulation(seed);
+    }
+
+    /// Start recording a concurrency trace (see [`crate::vm::trace`]):
+    /// this session's green threads, offloaded calls and the actors it
+    /// spawns from now on. Any earlier trace is discarded.
+    pub fn start_trace(&mut self) {
+        crate::vm::trace::start();
+        self.vm.set_trace(crate::vm::trace::new_actor("main"));
+    }
+
+    /// Stop the trace and return it as Chrome trace-event JSON, for
+    /// Perfetto or `chrome://tracing`.
+    pub fn finish_trace(&mut self) -> String {
+        self.vm.set_trace(0);
+        crate::vm::trace::finish()
+    }
+
     /// Compile and run a whole top-level program against the persistent
     /// frame. Top-level functions and data become live frame-0 slots
     /// that survive across calls. May be invoked more than once; later
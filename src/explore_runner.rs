@@ -0,0 +1,127 @@
+//! `--sim SEED` and `--explore N` for `tigr <file.tg>` and `tigr test`.
+//!
+//! `--sim SEED` runs in deterministic simulation mode (see
+//! `tigr::vm::sim`); a failing run ends by printing its seed so it can be
+//! replayed exactly. `--explore N` re-runs the same command as a child
+//! process under N consecutive seeds — from `--sim`'s seed if one was
+//! given, otherwise from a random one — and stops at the first that
+//! fails, replaying that run's output. A fresh process per seed keeps
+//! every run's process-wide state (the virtual clock, the loopback
+//! network, the actor pool) apart.
+
+use std::process::{Command, ExitCode};
+
+use tigr::vm::sim;
+
+/// The simulation flags seen on a command line.
+#[derive(Default)]
+pub struct SimFlags {
+    /// `--sim SEED`.
+    pub seed: Option<u64>,
+    /// `--explore N`.
+    pub explore: Option<u32>,
+}
+
+impl SimFlags {
+    /// Take `arg` if it is `--sim` or `--explore`, in either the
+    /// `--flag N` or the `--flag=N` form, pulling a separate value from
+    /// `rest`. `None` if `arg` is some other argument; `Some(false)`
+    /// after reporting a bad value.
+    pub fn take<'a>(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = &'a String>,
+    ) -> Option<bool> {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg, None),
+        };
+        if name != "--sim" && name != "--explore" {
+            return None;
+        }
+        let value = value.or_else(|| rest.next().map(String::as_str));
+        Some(if name == "--sim" {
+            match value.and_then(sim::parse_seed) {
+                Some(seed) => {
+                    self.seed = Some(seed);
+                    true
+                }
+                None => {
+                    eprintln!("tigr: --sim needs a seed (a non-negative integer)");
+                    false
+                }
+            }
+        } else {
+            match value.and_then(|v| v.trim().parse().ok()).filter(|&n| n > 0) {
+                Some(n) => {
+                    self.explore = Some(n);
+                    true
+                }
+                None => {
+                    eprintln!("tigr: --explore needs a positive number of seeds");
+                    false
+                }
+            }
+        })
+    }
+}
+
+/// Drop every `--sim` / `--explore` flag (and its separate value) from
+/// `args`, the flag part of a command line.
+pub fn strip(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--sim" | "--explore" => {
+                it.next();
+            }
+            a if a.starts_with("--sim=") || a.starts_with("--explore=") => {}
+            _ => out.push(arg.clone()),
+        }
+    }
+    out
+}
+
+/// Tell the user how to replay a failed simulation run.
+pub fn replay_hint(seed: u64) {
+    eprintln!("tigr: failed under simulation seed {seed}; replay with --sim {seed}");
+}
+
+/// Run `tigr <before> --sim=SEED <after>` under `count` consecutive
+/// seeds from `start` (random when `None`), stopping at the first
+/// failure.
+pub fn explore(before: &[String], after: &[String], count: u32, start: Option<u64>) -> ExitCode {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("tigr: --explore cannot find its own executable: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let start = start.unwrap_or_else(sim::random_seed);
+    for i in 0..count {
+        let seed = start.wrapping_add(i.into());
+        let out = Command::new(&exe)
+            .args(before)
+            .arg(format!("--sim={seed}"))
+            .args(after)
+            .output();
+        let out = match out {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("tigr: --explore could not run seed {seed}: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if !out.status.success() {
+            print!("{}", String::from_utf8_lossy(&out.stdout));
+            eprint!("{}", String::from_utf8_lossy(&out.stderr));
+            eprintln!("tigr: seed {seed} failed, run {} of {count}", i + 1);
+            return ExitCode::FAILURE;
+        }
+    }
+    let last = start.wrapping_add(u64::from(count) - 1);
+    println!("tigr: explored {count} seeds ({start}..={last}), all passed");
+    ExitCode::SUCCESS
+}
@@ -1,14 +1,30 @@
 //! Tigr CLI.
 //!
-//! Usage: `tigr [<file.tg> [args...]]`. With no file argument we
+//! Usage: `tigr [run] [<file.tg> [args...]]`. With no file argument we
 //! launch the interactive REPL (v0.3 Phase 5).
 //!
+//! `--actor-threads N` sizes the pool `spawn`ed actors run on (see
+//! `tigr::vm::actor_pool`); it overrides `TIGR_ACTOR_THREADS`.
+//!
+//! `--preempt N` preempts a green thread after N loop iterations and
+//! calls, and `--slow-native MS` reports native calls that take longer
+//! than MS milliseconds (see `tigr::vm::preempt`). Both are off by
+//! default.
+//!
+//! `--sim SEED` runs in deterministic simulation mode, and `--explore N`
+//! runs under N seeds in turn (see `explore_runner`).
+//!
+//! `--trace FILE` records the run's scheduling — every green thread's
+//! states, offloaded calls and actor spawns — and writes it to FILE as
+//! a Chrome trace for Perfetto (see `tigr::vm::trace`).
+//!
 //! The `--legacy` flag is reserved for re-enabling the v0.1 tree-walking
 //! interpreter once `src/v01/` is wired back into the build.
 
 use std::cell::RefCell;
 use std::path::Path;
 use std::process::ExitCode;
+use std::time::Duration;
 use std::rc::Rc;
 
 use tigr::{repl, vm};
@@ -16,6 +32,7 @@ use tigr::vm::source_map::SourceMap;
 
 mod bench_runner;
 mod disasm_runner;
+mod explore_runner;
 mod test_runner;
 mod v01;
 
@@ -23,7 +40,7 @@ fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
     // `tigr test [path]` — discover and run test files.
     if args.get(1).map(String::as_str) == Some("test") {
-        return test_runner::run(args.get(2).map(String::as_str));
+        return test_runner::run(&args[2..]);
     }
     // `tigr disasm <file.tg> [-r]` — print the compiled bytecode.
     if args.get(1).map(String::as_str) == Some("disasm") {
@@ -41,16 +58,67 @@ fn main() -> ExitCode {
         println!("tigr {}", env!("CARGO_PKG_VERSION"));
         return ExitCode::SUCCESS;
     }
+    // `tigr run <file.tg>` — the same as `tigr <file.tg>`.
+    let first = if args.get(1).map(String::as_str) == Some("run") { 2 } else { 1 };
     let mut filename: Option<&str> = None;
     let mut legacy = false;
+    let mut sim_flags = explore_runner::SimFlags::default();
+    let mut trace_path: Option<&str> = None;
     // First non-flag arg is the script. Anything after that is
     // program-level args (visible from tigr via `Os.args`).
-    for arg in args.iter().skip(1) {
+    let mut rest = args.iter().skip(first);
+    while let Some(arg) = rest.next() {
         if filename.is_some() {
             break;
         }
+        if let Some(ok) = sim_flags.take(arg, &mut rest) {
+            if !ok {
+                return ExitCode::FAILURE;
+            }
+            continue;
+        }
         match arg.as_str() {
             "--legacy" => legacy = true,
+            "--actor-threads" => {
+                if !set_actor_threads(rest.next().map(String::as_str)) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            flag if flag.starts_with("--actor-threads=") => {
+                if !set_actor_threads(flag.strip_prefix("--actor-threads=")) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--preempt" => {
+                if !set_preempt(rest.next().map(String::as_str)) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            flag if flag.starts_with("--preempt=") => {
+                if !set_preempt(flag.strip_prefix("--preempt=")) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--slow-native" => {
+                if !set_slow_native(rest.next().map(String::as_str)) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            flag if flag.starts_with("--slow-native=") => {
+                if !set_slow_native(flag.strip_prefix("--slow-native=")) {
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--trace" => match rest.next() {
+                Some(path) => trace_path = Some(path),
+                None => {
+                    eprintln!("tigr: --trace needs a file to write the trace to");
+                    return ExitCode::FAILURE;
+                }
+            },
+            flag if flag.starts_with("--trace=") => {
+                trace_path = flag.strip_prefix("--trace=");
+            }
             "-h" | "--help" => {
                 print_usage();
                 return ExitCode::SUCCESS;
@@ -84,25 +152,102 @@ fn main() -> ExitCode {
         );
         return ExitCode::FAILURE;
     }
+    if let Some(count) = sim_flags.explore {
+        // Re-run everything up to the script minus the simulation flags,
+        // then the script and its own arguments untouched.
+        let at = args.iter().position(|a| a == filename).unwrap_or(args.len());
+        let before = explore_runner::strip(&args[first..at]);
+        return explore_runner::explore(&before, &args[at..], count, sim_flags.seed);
+    }
+    if let Some(seed) = sim_flags.seed {
+        vm::sim::enable(seed);
+    }
+    if trace_path.is_some() {
+        vm::trace::start();
+    }
     let sources = Rc::new(RefCell::new(SourceMap::new()));
-    match vm::run_file_with_map(Path::new(filename), sources.clone()) {
+    let result = vm::run_file_with_map(Path::new(filename), sources.clone());
+    if let Some(path) = trace_path {
+        if let Err(e) = vm::trace::write(Path::new(path)) {
+            eprintln!("tigr: could not write the trace to {path}: {e}");
+        }
+    }
+    match result {
         Ok((value, _)) => {
             println!("{value:?}");
             ExitCode::SUCCESS
         }
         Err(err) => {
             eprintln!("{}", err.render(&sources.borrow()));
+            if let Some(seed) = sim_flags.seed {
+                explore_runner::replay_hint(seed);
+            }
             ExitCode::FAILURE
         }
     }
 }
 
+/// Apply `--actor-threads`; reports a bad value and returns `false`.
+fn set_actor_threads(value: Option<&str>) -> bool {
+    match value.and_then(vm::actor_pool::parse_threads) {
+        Some(n) => vm::actor_pool::set_threads(n),
+        None => {
+            eprintln!("tigr: --actor-threads needs a positive integer");
+            false
+        }
+    }
+}
+
+/// Apply `--preempt`; reports a bad value and returns `false`.
+fn set_preempt(value: Option<&str>) -> bool {
+    match value.and_then(vm::preempt::parse_setting) {
+        Some(n) => {
+            vm::preempt::set_quantum(n);
+            true
+        }
+        None => {
+            eprintln!("tigr: --preempt needs a count of loop iterations and calls (0 for off)");
+            false
+        }
+    }
+}
+
+/// Apply `--slow-native`; reports a bad value and returns `false`.
+fn set_slow_native(value: Option<&str>) -> bool {
+    match value.and_then(vm::preempt::parse_setting) {
+        Some(ms) => {
+            let threshold = (ms > 0).then(|| Duration::from_millis(ms.into()));
+            vm::preempt::set_slow_native(threshold);
+            true
+        }
+        None => {
+            eprintln!("tigr: --slow-native needs a number of milliseconds (0 for off)");
+            false
+        }
+    }
+}
+
 fn print_usage() {
-    eprintln!("usage: tigr [<file.tg> [args...]]");
+    eprintln!("usage: tigr [run] [<file.tg> [args...]]");
     eprintln!("       tigr                       (interactive REPL)");
     eprintln!("       tigr test [<path>]         (discover and run *_test.tg / tests/)");
+    eprintln!("       tigr test --sim <seed> | --explore <n> [<path>]");
+    eprintln!("                                  (run the tests in simulation mode)");
     eprintln!("       tigr disasm <file.tg> [-r] (print compiled bytecode; -r for nested)");
     eprintln!("       tigr bench [<path>]        (discover and time bench/*.tg)");
     eprintln!("       tigr --version             (print the tigr version)");
+    eprintln!("       tigr --actor-threads <n> <file.tg>");
+    eprintln!("                                  (size the actor pool; default: TIGR_ACTOR_THREADS or CPU count)");
+    eprintln!("       tigr --preempt <n> <file.tg>");
+    eprintln!("                                  (preempt a green thread after n loop iterations and calls)");
+    eprintln!("       tigr --slow-native <ms> <file.tg>");
+    eprintln!("                                  (report native calls slower than ms milliseconds)");
+    eprintln!("       tigr --sim <seed> <file.tg>");
+    eprintln!("                                  (deterministic simulation: virtual time, seeded order;");
+    eprintln!("                                  spawned actors and pool IO still run in real time)");
+    eprintln!("       tigr --explore <n> <file.tg>");
+    eprintln!("                                  (run under n simulation seeds; stop at the first failure)");
+    eprintln!("       tigr run --trace <out.json> <file.tg>");
+    eprintln!("                                  (write a Chrome trace of the run's scheduling, for Perfetto)");
     eprintln!("       tigr --legacy <file.tg>    (v0.1 interpreter; not currently wired)");
 }
@@ -7,6 +7,10 @@
 //! `Test.suite(...)` result object `${passed, failed, ...}`, or an
 //! array of them; the runner reads the `passed`/`failed` fields. An
 //! uncaught error in a file counts as a file-level failure.
+//!
+//! `--sim SEED` runs every file in simulation mode, each from a fresh
+//! virtual clock and loopback network; `--explore N` runs the whole
+//! suite under N seeds (see `explore_runner`).
 
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
@@ -15,6 +19,7 @@ use std::rc::Rc;
 #[cfg(test)]
 use std::sync::Arc;
 
+use crate::explore_runner::{self, SimFlags};
 use crate::vm;
 use crate::vm::source_map::SourceMap;
 use crate::vm::value::Value;
@@ -100,10 +105,36 @@ fn aggregate(value: &Value, totals: &mut Totals) {
     }
 }
 
-/// Discover and run tests under `path` (default: the current
-/// directory). Returns a failure exit code if any test failed or any
-/// file errored.
-pub fn run(path: Option<&str>) -> ExitCode {
+/// Run `tigr test [--sim SEED | --explore N] [path]`: discover and run
+/// the tests under `path` (default: the current directory). Returns a
+/// failure exit code if any test failed or any file errored.
+pub fn run(args: &[String]) -> ExitCode {
+    let mut flags = SimFlags::default();
+    let mut path = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match flags.take(arg, &mut rest) {
+            Some(true) => {}
+            Some(false) => return ExitCode::FAILURE,
+            None => path = Some(arg.as_str()),
+        }
+    }
+    if let Some(count) = flags.explore {
+        let after = explore_runner::strip(args);
+        return explore_runner::explore(&["test".to_string()], &after, count, flags.seed);
+    }
+    let code = run_files(path, flags.seed);
+    if code == ExitCode::FAILURE {
+        if let Some(seed) = flags.seed {
+            explore_runner::replay_hint(seed);
+        }
+    }
+    code
+}
+
+/// Discover and run the tests under `path`, in simulation mode with
+/// `seed` if one is given.
+fn run_files(path: Option<&str>, seed: Option<u64>) -> ExitCode {
     let root = Path::new(path.unwrap_or("."));
     let files = discover(root);
     if files.is_empty() {
@@ -116,6 +147,9 @@ pub fn run(path: Option<&str>) -> ExitCode {
     let mut totals = Totals::default();
     for file in &files {
         println!("── {}", file.display());
+        if let Some(seed) = seed {
+            vm::sim::enable(seed);
+        }
         let sources = Rc::new(RefCell::new(SourceMap::new()));
         match vm::run_file_with_map(file, sources.clone()) {
             Ok((value, _)) => aggregate(&value, &mut totals),
@@ -5917,6 +5917,265 @@ fn go_alive_type_errors_on_a_non_handle() {
     assert_eq!(format!("{:?}", run(src)), "'caught'");
 }
 
+// ---- Opt-in preemption: a quantum of safe points ----
+
+/// Run `src` on a VM that preempts green threads every `quantum`
+/// loop iterations and calls. Set on the VM, not process-wide, so tests running in
+/// parallel keep cooperative scheduling.
+fn run_preempted(src: &str, quantum: u32) -> String {
+    use crate::vm::compile_source_with_id;
+    use crate::vm::source_map::SourceId;
+    use crate::vm::vm::Vm;
+    let main = compile_source_with_id(src, None, SourceId::UNKNOWN).unwrap();
+    let mut vm = Vm::new();
+    vm.set_preempt_quantum(quantum);
+    let v = vm.run(main).unwrap_or_else(|e| panic!("error: {e}"));
+    format!("{v:?}")
+}
+
+/// A coroutine that never yields runs to the end before a sibling gets
+/// a turn when scheduling is cooperative, and is interleaved with it
+/// under a quantum.
+const BUSY_AND_TICKER: &str = "
+    log := [];
+    busy := go fn() {
+        n := 0;
+        for (i, 0..100000) { n += i };
+        Array.push(log, 'busy')
+    };
+    ticker := go fn() { for (i, 0..2) { Array.push(log, i); yield } };
+    join(busy);
+    join(ticker);
+    log
+";
+
+#[test]
+fn preempt_off_keeps_scheduling_cooperative() {
+    assert_eq!(run_preempted(BUSY_AND_TICKER, 0), "[busy, 0, 1]");
+}
+
+#[test]
+fn preempt_switches_away_from_a_busy_coroutine() {
+    assert_eq!(run_preempted(BUSY_AND_TICKER, 500), "[0, 1, busy]");
+}
+
+/// A call is a safe point too: a busy recursion with no loop is still
+/// preempted.
+#[test]
+fn preempt_fires_at_calls() {
+    let src = "
+        log := [];
+        sum := fn(n) { if n == 0 { 0 } else { n + sum(n - 1) } };
+        busy := go fn() { sum(2000); Array.push(log, 'busy') };
+        ticker := go fn() { Array.push(log, 'tick') };
+        join(busy);
+        join(ticker);
+        log
+    ";
+    assert_eq!(run_preempted(src, 100), "[tick, busy]");
+}
+
+/// A generator body is never preempted: it hands values to its puller
+/// in order even while a sibling coroutine is ready.
+#[test]
+fn preempt_leaves_generators_alone() {
+    let src = "
+        g := gen fn() { for (i, 0..50) { n := 0; for (j, 0..100) { n += j }; yield i } };
+        other := go fn() { for (i, 0..20) { yield } };
+        xs := for[] (x, g()) { x };
+        join(other);
+        [#xs, xs[0], xs[49]]
+    ";
+    assert_eq!(run_preempted(src, 50), "[50, 0, 49]");
+}
+
+/// A preemption is not a cancellation point: a cancelled coroutine that
+/// never parks still runs to completion.
+#[test]
+fn preempt_is_not_a_cancellation_point() {
+    let src = "
+        h := go fn() { n := 0; for (i, 0..20000) { n += 1 }; n };
+        go_cancel(h);
+        join(h)
+    ";
+    assert_eq!(run_preempted(src, 100), "20000");
+}
+
+/// `wait` timers fire as before alongside a preempted busy coroutine.
+#[test]
+fn preempt_keeps_wait_timers() {
+    let src = "
+        log := [];
+        w := go fn() { wait(0.01); Array.push(log, 'woke') };
+        busy := go fn() { n := 0; for (i, 0..100000) { n += i }; Array.push(log, 'busy') };
+        join(w);
+        join(busy);
+        #log
+    ";
+    assert_eq!(run_preempted(src, 200), "2");
+}
+
+// ---- Simulation mode: seeded ordering and virtual `wait` time ----
+
+fn run_simulated(src: &str, seed: u64) -> String {
+    use crate::vm::compile_source_with_id;
+    use crate::vm::source_map::SourceId;
+    use crate::vm::vm::Vm;
+    let main = compile_source_with_id(src, None, SourceId::UNKNOWN).unwrap();
+    let mut vm = Vm::new();
+    vm.set_simulation(Some(seed));
+    let v = vm.run(main).unwrap_or_else(|e| panic!("error: {e}"));
+    format!("{v:?}")
+}
+
+/// Four coroutines that each log twice around a `yield`: the order
+/// depends only on which ready coroutine the scheduler picks.
+const FOUR_YIELDERS: &str = "
+    log := [];
+    hs := for[] (i, 0..4) { go fn() { Array.push(log, i); yield; Array.push(log, i) } };
+    for (h, hs) { join(h) };
+    log
+";
+
+#[test]
+fn sim_same_seed_replays_the_same_interleaving() {
+    for seed in [1, 42, 9_000_000_001] {
+        assert_eq!(run_simulated(FOUR_YIELDERS, seed), run_simulated(FOUR_YIELDERS, seed));
+    }
+}
+
+/// Different seeds explore different interleavings, not just the
+/// round-robin one.
+#[test]
+fn sim_seeds_vary_the_interleaving() {
+    let orders: std::collections::HashSet<String> =
+        (0..16).map(|seed| run_simulated(FOUR_YIELDERS, seed)).collect();
+    assert!(orders.len() > 1, "every seed gave {orders:?}");
+}
+
+/// Spawned actors run on the real clock, outside the seeded order, but
+/// a program that only spawns them from coroutines and joins them at
+/// the end still replays: the spawns happen in the seeded order and
+/// each `join` yields the same value.
+#[test]
+fn sim_replays_coroutines_that_spawn_actors() {
+    let src = "
+        log := [];
+        ts := [];
+        hs := for[] (i, 0..4) {
+            go fn() { Array.push(log, i); Array.push(ts, spawn fn() { i * 10 }); yield; Array.push(log, i) }
+        };
+        for (h, hs) { join(h) };
+        [log, for[] (t, ts) { join(t) }]
+    ";
+    for seed in [1, 42, 9_000_000_001] {
+        let first = run_simulated(src, seed);
+        assert_eq!(first, run_simulated(src, seed));
+        for n in ["0", "10", "20", "30"] {
+            assert!(first.contains(n), "{first}");
+        }
+    }
+}
+
+/// A long `wait` jumps the virtual clock instead of sleeping, and
+/// timers still fire in wake-time order.
+#[test]
+fn sim_wait_jumps_virtual_time() {
+    let src = "
+        log := [];
+        a := go fn() { wait(30); Array.push(log, 'slow') };
+        b := go fn() { wait(10); Array.push(log, 'fast') };
+        join(a);
+        join(b);
+        log
+    ";
+    let start = std::time::Instant::now();
+    assert_eq!(run_simulated(src, 7), "[fast, slow]");
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
+
+/// Coroutines spinning on `yield` still see a sibling's `wait` end:
+/// each `yield` moves the virtual clock on.
+#[test]
+fn sim_yield_spinners_let_time_pass() {
+    let src = "
+        done := false;
+        go fn() { wait(0.1); done = true };
+        go fn() { while (!done) { yield } };
+        while (!done) { yield };
+        done
+    ";
+    assert_eq!(run_simulated(src, 3), "true");
+}
+
+// ---- Concurrency tracing: scheduler events as a Chrome trace ----
+
+/// Run `src` with a trace recorded and return the trace JSON. Tracing
+/// is process-wide, so traced tests take turns.
+fn run_traced(src: &str) -> String {
+    use crate::vm::compile_source_with_id;
+    use crate::vm::source_map::SourceId;
+    use crate::vm::trace;
+    use crate::vm::vm::Vm;
+    static TURN: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    let main = compile_source_with_id(src, None, SourceId::UNKNOWN).unwrap();
+    trace::start();
+    let mut vm = Vm::new();
+    let result = vm.run(main);
+    let json = trace::finish();
+    result.unwrap_or_else(|e| panic!("error: {e}"));
+    json
+}
+
+/// Each `go` gets a track named after the function it started, with a
+/// slice for every state it passes through.
+#[test]
+fn trace_names_green_threads_and_their_parks() {
+    let json = run_traced("
+        worker := fn() { wait(0.01); 1 };
+        a := go worker;
+        b := go fn() { yield; 2 };
+        join(a) + join(b)
+    ");
+    assert!(json.starts_with("{\"traceEvents\":["), "{json}");
+    assert!(json.contains(r#""args":{"name":"go worker #1"}"#), "{json}");
+    assert!(json.contains(r#""args":{"name":"go <anonymous> #2"}"#), "{json}");
+    for state in ["running", "ready", "wait", "join #1"] {
+        assert!(json.contains(&format!(r#""name":"{state}","cat":"sched","ph":"X""#)), "{state}: {json}");
+    }
+}
+
+/// A call handed off while a sibling runs is an async slice, named for
+/// the native, from submission to completion.
+#[test]
+fn trace_records_offloaded_calls() {
+    let json = run_traced("
+        c := Channel.new();
+        go fn() { wait(0.01); Channel.send(c, 5) };
+        Channel.recv(c).value
+    ");
+    assert!(json.contains(r#""name":"recv","cat":"io","ph":"b""#), "{json}");
+    assert!(json.contains(r#""name":"recv","cat":"io","ph":"e""#), "{json}");
+    assert!(json.contains(r#""name":"recv","cat":"sched","ph":"X""#), "{json}");
+}
+
+/// A spawned actor is a process track of its own, named after its
+/// function, with a flow arrow from the spawner and an exit marker.
+#[test]
+fn trace_gives_each_actor_a_track() {
+    let json = run_traced("
+        child := fn() { 7 };
+        join(spawn child)
+    ");
+    assert!(json.contains(r#""args":{"name":"main"}"#), "{json}");
+    assert!(json.contains(r#""args":{"name":"child"}"#), "{json}");
+    assert!(json.contains(r#""name":"spawn child""#), "{json}");
+    assert!(json.contains(r#""cat":"actor","ph":"s""#), "{json}");
+    assert!(json.contains(r#""cat":"actor","ph":"f""#), "{json}");
+    assert!(json.contains(r#""name":"exit""#), "{json}");
+}
+
 // ---- Bytecode format limits: pool dedup, wide operands, big literals ----
 
 /// The constant pool deduplicates: a literal that appears many times —
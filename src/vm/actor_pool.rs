@@ -0,0 +1,303 @@
+//! The actor pool — the M:N runtime under `spawn`.
+//!
+//! A `spawn`ed actor used to get an OS thread of its own, so a
+//! `parallel[]` over ten thousand items started ten thousand threads.
+//! Actors now run on a fixed set of pool threads instead, many to a
+//! thread. Each actor is still a whole [`Vm`] with its own heap: a pool
+//! thread keeps every actor placed on it, swaps an actor's heap (and
+//! its `Random` stream) into the thread-locals for a turn, and swaps it
+//! back out when the turn ends. Heap isolation is exactly what it was —
+//! nothing a turn allocates is reachable from another actor, and values
+//! still cross between actors only as a [`Transfer`].
+//!
+//! A turn runs the actor in pooled mode (see `Vm::pooled`) until it
+//! finishes or every coroutine in it is parked. A parked actor costs no
+//! thread. Its waits complete through its [`CompletionMailbox`]: a
+//! `Channel.recv` or `join` is handed its result by the sending or
+//! finishing actor, IO arrives from the offload workers or the reactor,
+//! and the mailbox's waker puts the actor back on its pool thread's run
+//! queue. A parked `wait` is woken by the pool thread's own timer.
+//!
+//! An actor stays on the thread it was placed on for its whole life —
+//! a `Vm` holds `Rc`s and is not `Send` — so placement picks the pool
+//! thread with the fewest live actors. Scheduling between the actors of
+//! one thread is cooperative: a turn ends only at a park, a `yield`, or
+//! the actor's end, so a long computation holds its thread until then —
+//! unless preemption is on (see [`crate::vm::preempt`]): then an actor
+//! that has run for a quantum with nothing else of its own ready ends
+//! its turn, as if it had called `yield`.
+//!
+//! The size is fixed when the first actor is spawned: the value given
+//! to [`set_threads`] (the CLI's `--actor-threads`, or
+//! `Session::set_actor_threads`), else the `TIGR_ACTOR_THREADS`
+//! environment variable, else the number of CPUs.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Instant;
+
+use crate::vm::gc::{self, Heap};
+use crate::vm::rng;
+use crate::vm::sim;
+use crate::vm::task::{TaskHandle, TaskInner};
+use crate::vm::trace;
+use crate::vm::transfer::Transfer;
+use crate::vm::vm::{PoolTurn, Vm};
+
+/// The environment variable that sizes the pool when no explicit size
+/// was set.
+pub const THREADS_ENV: &str = "TIGR_ACTOR_THREADS";
+
+/// An explicit pool size from [`set_threads`]; `0` when unset.
+static REQUESTED: AtomicUsize = AtomicUsize::new(0);
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+/// Set the number of pool threads. Only a pool that has not started
+/// yet can be sized: returns `false`, changing nothing, once the first
+/// actor has been spawned, or for `n` of 0.
+pub fn set_threads(n: usize) -> bool {
+    if n == 0 || POOL.get().is_some() {
+        return false;
+    }
+    REQUESTED.store(n, Ordering::Relaxed);
+    true
+}
+
+/// The number of pool threads: the running pool's size, or the size
+/// it would start with.
+pub fn threads() -> usize {
+    match POOL.get() {
+        Some(pool) => pool.threads.len(),
+        None => configured_threads(),
+    }
+}
+
+/// Parse a pool size as given on the command line or in
+/// [`THREADS_ENV`]: a positive integer.
+pub fn parse_threads(s: &str) -> Option<usize> {
+    s.trim().parse().ok().filter(|&n| n > 0)
+}
+
+fn configured_threads() -> usize {
+    let requested = REQUESTED.load(Ordering::Relaxed);
+    if requested > 0 {
+        return requested;
+    }
+    if let Some(n) = std::env::var(THREADS_ENV).ok().as_deref().and_then(parse_threads) {
+        return n;
+    }
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Start the actor `transfer` encodes — a detached closure from `spawn`
+/// — on the pool, and return the `Task` its outcome will complete.
+/// `trace` is the actor's trace id ([`crate::vm::trace`]), `0` if none.
+pub fn spawn(transfer: Transfer, trace: u64) -> TaskHandle {
+    let task = TaskInner::new();
+    let pool = POOL.get_or_init(|| Pool::start(configured_threads()));
+    let home = pool
+        .threads
+        .iter()
+        .min_by_key(|t| t.live.load(Ordering::Relaxed))
+        .expect("the pool has at least one thread");
+    home.live.fetch_add(1, Ordering::Relaxed);
+    home.queue.lock().unwrap().spawned.push_back((transfer, task.clone(), trace));
+    home.wake.notify_one();
+    task
+}
+
+struct Pool {
+    threads: Vec<&'static PoolThread>,
+}
+
+impl Pool {
+    fn start(n: usize) -> Pool {
+        let threads = (0..n)
+            .map(|i| {
+                let shared: &'static PoolThread = Box::leak(Box::new(PoolThread {
+                    queue: Mutex::new(RunQueue::default()),
+                    wake: Condvar::new(),
+                    live: AtomicUsize::new(0),
+                }));
+                thread::Builder::new()
+                    .name(format!("tigr-actor-{i}"))
+                    .spawn(move || {
+                        sim::leave_clock();
+                        shared.run()
+                    })
+                    .expect("failed to start an actor pool thread");
+                shared
+            })
+            .collect();
+        Pool { threads }
+    }
+}
+
+/// The cross-thread half of one pool thread: where spawns and wakes
+/// for its actors are queued.
+struct PoolThread {
+    queue: Mutex<RunQueue>,
+    /// Signalled when `queue` gains work.
+    wake: Condvar,
+    /// Actors placed on this thread and not yet finished.
+    live: AtomicUsize,
+}
+
+#[derive(Default)]
+struct RunQueue {
+    /// New actors, not yet started.
+    spawned: VecDeque<(Transfer, TaskHandle, u64)>,
+    /// Parked or yielded actors that are ready for a turn, in order.
+    ready: VecDeque<u64>,
+    /// The ids in `ready`, so a burst of wakes queues an actor once.
+    queued: HashSet<u64>,
+}
+
+/// An actor between turns, with the thread-local state it runs under.
+struct Actor {
+    vm: Vm,
+    heap: Heap,
+    rng: u64,
+    task: TaskHandle,
+    /// The `wait` timer the actor last parked on, if any.
+    wake: Option<Instant>,
+}
+
+impl PoolThread {
+    /// Queue actor `id` for a turn. Called from any thread — it is the
+    /// waker of the actor's mailbox.
+    fn ready(&self, id: u64) {
+        let mut q = self.queue.lock().unwrap();
+        if q.queued.insert(id) {
+            q.ready.push_back(id);
+            drop(q);
+            self.wake.notify_one();
+        }
+    }
+
+    /// The pool thread's loop: start spawned actors, give ready ones a
+    /// turn, fire due timers, and sleep when there is nothing to do.
+    fn run(&'static self) {
+        let mut actors: HashMap<u64, Actor> = HashMap::new();
+        let mut timers: BinaryHeap<Reverse<(Instant, u64)>> = BinaryHeap::new();
+        let mut next_id: u64 = 0;
+        loop {
+            let (spawned, mut ready) = {
+                let mut q = self.queue.lock().unwrap();
+                loop {
+                    if !q.spawned.is_empty() || !q.ready.is_empty() {
+                        break;
+                    }
+                    let Some(Reverse((at, _))) = timers.peek() else {
+                        q = self.wake.wait(q).unwrap();
+                        continue;
+                    };
+                    let now = Instant::now();
+                    if *at <= now {
+                        break;
+                    }
+                    q = self.wake.wait_timeout(q, *at - now).unwrap().0;
+                }
+                q.queued.clear();
+                (std::mem::take(&mut q.spawned), std::mem::take(&mut q.ready))
+            };
+            let now = Instant::now();
+            while let Some(Reverse((at, id))) = timers.peek().copied() {
+                if at > now {
+                    break;
+                }
+                timers.pop();
+                // A timer is stale once its actor has parked again (or
+                // finished) since it was set.
+                if actors.get(&id).is_some_and(|a| a.wake == Some(at)) {
+                    ready.push_back(id);
+                }
+            }
+            for (transfer, task, trace) in spawned {
+                let id = next_id;
+                next_id += 1;
+                let mut heap = Heap::new();
+                gc::swap_heap(&mut heap);
+                let rng = rng::swap_state(0);
+                let mut vm = Vm::new();
+                // A spawned actor interleaves with the others in real
+                // time, so it stays out of a simulation: its own clock
+                // would race theirs.
+                vm.set_simulation(None);
+                vm.set_trace(trace);
+                vm.mailbox().set_waker(move || self.ready(id));
+                let mut actor = Actor { vm, heap, rng, task: task.clone(), wake: None };
+                let turn = actor.vm.start_pooled(task, transfer);
+                self.end_turn(id, actor, turn, &mut actors, &mut timers);
+            }
+            for id in ready {
+                let Some(mut actor) = actors.remove(&id) else {
+                    continue;
+                };
+                gc::swap_heap(&mut actor.heap);
+                actor.rng = rng::swap_state(actor.rng);
+                let turn = actor.vm.resume_pooled();
+                self.end_turn(id, actor, turn, &mut actors, &mut timers);
+            }
+        }
+    }
+
+    /// Swap the actor's thread-local state back out after a turn, then
+    /// file it by how the turn ended: complete its task, re-queue it, or
+    /// keep it parked (setting its timer).
+    fn end_turn(
+        &'static self,
+        id: u64,
+        mut actor: Actor,
+        turn: PoolTurn,
+        actors: &mut HashMap<u64, Actor>,
+        timers: &mut BinaryHeap<Reverse<(Instant, u64)>>,
+    ) {
+        gc::swap_heap(&mut actor.heap);
+        actor.rng = rng::swap_state(actor.rng);
+        match turn {
+            PoolTurn::Finished(outcome) => {
+                trace::exit(actor.vm.trace_actor());
+                let task = actor.task.clone();
+                drop(actor);
+                self.live.fetch_sub(1, Ordering::Relaxed);
+                task.complete(outcome);
+            }
+            PoolTurn::Yielded => {
+                actor.wake = None;
+                actors.insert(id, actor);
+                self.ready(id);
+            }
+            PoolTurn::Parked { wake } => {
+                actor.wake = wake;
+                if let Some(at) = wake {
+                    timers.push(Reverse((at, id)));
+                }
+                actors.insert(id, actor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_threads, set_threads};
+
+    #[test]
+    fn parse_threads_takes_a_positive_integer() {
+        assert_eq!(parse_threads("4"), Some(4));
+        assert_eq!(parse_threads(" 2\n"), Some(2));
+        assert_eq!(parse_threads("0"), None);
+        assert_eq!(parse_threads("-1"), None);
+        assert_eq!(parse_threads("many"), None);
+    }
+
+    #[test]
+    fn set_threads_refuses_zero() {
+        assert!(!set_threads(0));
+    }
+}
@@ -0,0 +1,85 @@
+//! Atomic integer counters shared between actors (`Atomic`).
+//!
+//! Like a [`Channel`](crate::vm::channel), an atomic lives outside any
+//! per-thread heap: it is an `Arc` around an [`AtomicI64`], `Send`, a
+//! GC leaf, and it crosses an actor boundary by handle clone, so every
+//! actor that holds it sees the one counter. Each operation is a
+//! single atomic instruction with sequentially consistent ordering,
+//! so a counter shared by many actors never needs an owner actor and
+//! a message round-trip per update.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+
+/// A shared handle to an atomic counter. Cloning bumps the `Arc`
+/// refcount; every holder reads and writes the same counter.
+pub type AtomicHandle = Arc<AtomicInner>;
+
+pub struct AtomicInner {
+    value: AtomicI64,
+}
+
+impl AtomicInner {
+    pub fn new(initial: i64) -> AtomicHandle {
+        Arc::new(AtomicInner { value: AtomicI64::new(initial) })
+    }
+
+    pub fn load(&self) -> i64 {
+        self.value.load(Ordering::SeqCst)
+    }
+
+    pub fn store(&self, v: i64) {
+        self.value.store(v, Ordering::SeqCst)
+    }
+
+    /// Add `delta` and return the new value. Raises `overflow`, leaving
+    /// the counter unchanged, if the sum does not fit an `Int`.
+    pub fn add(&self, delta: i64) -> Result<i64, RuntimeError> {
+        self.value
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| v.checked_add(delta))
+            .map(|prev| prev + delta)
+            .map_err(|_| RuntimeError::new(RuntimeErrorKind::Overflow, 0))
+    }
+
+    /// Set the counter to `new` if it holds `expected`. Returns whether
+    /// it did.
+    pub fn compare_and_set(&self, expected: i64, new: i64) -> bool {
+        self.value
+            .compare_exchange(expected, new, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_overflow_leaves_the_counter_alone() {
+        let a = AtomicInner::new(i64::MAX - 1);
+        assert_eq!(a.add(1).unwrap(), i64::MAX);
+        assert_eq!(a.add(1).unwrap_err().kind.kind_tag(), "overflow");
+        assert_eq!(a.load(), i64::MAX);
+    }
+
+    #[test]
+    fn concurrent_adds_are_not_lost() {
+        let a = AtomicInner::new(0);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let a = a.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        a.add(1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(a.load(), 8000);
+    }
+}
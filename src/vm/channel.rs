@@ -8,11 +8,26 @@
 //! decodes on its own. `ChannelInner` is `Send + Sync`, so a
 //! `ChannelHandle` rides through `Transfer::Channel` to a spawned
 //! actor.
+//!
+//! Besides the plain queue, a channel comes in three flavours that
+//! share the handle (and so cross actors and work in `select` the same
+//! way):
+//!
+//! * a **oneshot** carries a single message and closes as it is sent;
+//! * a **broadcast** channel is a hub that copies each message into
+//!   every *subscription* made from it. A subscription is a queue of
+//!   its own with a lag limit: once that many messages are waiting, a
+//!   new one pushes out the oldest, and the next receive reports how
+//!   many were lost with a `lagged` error;
+//! * a **watch** channel is a hub that keeps only its latest value.
+//!   Each subscription holds at most the newest value it has not yet
+//!   seen, so a receive waits for the next change and never lags.
 
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
+use crate::vm::offload::Signal;
 use crate::vm::transfer::Transfer;
 
 /// A shared handle to a channel. Cloning bumps the `Arc` refcount;
@@ -21,34 +36,138 @@ use crate::vm::transfer::Transfer;
 pub type ChannelHandle = Arc<ChannelInner>;
 
 pub struct ChannelInner {
-    queue: Mutex<VecDeque<Transfer>>,
-    not_empty: Condvar,
-    not_full: Condvar,
+    state: Mutex<ChannelState>,
     /// `None` for an unbounded channel; `Some(n)` caps the buffer at
     /// `n` and makes `send` block (backpressure) while full.
     capacity: Option<usize>,
     closed: AtomicBool,
+    flavour: Flavour,
+}
+
+/// Which kind of channel a [`ChannelInner`] is.
+enum Flavour {
+    /// A queue: each message goes to exactly one receiver.
+    Queue,
+    /// A queue for one message: the first `send` closes it.
+    Oneshot,
+    /// The sending side of a broadcast or watch channel. Holds no
+    /// messages itself; each `send` is copied into every subscription.
+    Hub(Mutex<Hub>),
+    /// A subscription to `hub`: a queue bounded at `capacity` that
+    /// drops its oldest message instead of blocking a sender.
+    Subscriber { hub: ChannelHandle, watch: bool },
+}
+
+/// A broadcast or watch channel's subscriptions.
+struct Hub {
+    /// Dropped subscriptions are pruned on the next `send`.
+    subscribers: Vec<Weak<ChannelInner>>,
+    /// The lag limit a subscription gets when none is given.
+    lag: usize,
+    /// For a watch channel, its current value; `None` for a broadcast.
+    current: Option<Transfer>,
+}
+
+/// A receive parked on an empty channel by [`ChannelInner::recv_then`],
+/// completed by the next `send` or by `close`.
+type ParkedRecv = Box<dyn FnOnce(RecvOutcome) + Send>;
+
+/// The completion of a [`ChannelInner::send_then`], run once the
+/// message is in the buffer (`Ok`) or the channel closed (`Err`).
+type SendDone = Box<dyn FnOnce(Result<(), ()>) + Send>;
+
+/// A send parked on a full bounded channel: the message and its
+/// completion.
+type ParkedSend = (Transfer, SendDone);
+
+/// A [`ChannelInner::peek_then`] waiting for the buffer to grow past
+/// an index: run with a copy of the message there, or `None` at close.
+type ParkedPeek = (usize, Box<dyn FnOnce(Option<Transfer>) + Send>);
+
+/// Everything behind the channel lock. `receivers` is non-empty only
+/// while `queue` is empty, and `senders` only while `queue` is full: a
+/// message meets a parked receiver, or a free slot meets a parked
+/// sender, the moment both exist.
+struct ChannelState {
+    queue: VecDeque<Transfer>,
+    receivers: VecDeque<ParkedRecv>,
+    senders: VecDeque<ParkedSend>,
+    peekers: Vec<ParkedPeek>,
+    /// Messages a broadcast subscription has dropped since its last
+    /// receive, reported by that receive as [`RecvOutcome::Lagged`].
+    missed: u64,
+    /// Parked `select`s waiting on this channel, fired by the next
+    /// message or the close.
+    signals: Vec<Arc<Signal>>,
+}
+
+impl ChannelState {
+    /// Take the front message. The slot it frees goes to the first
+    /// parked sender, whose completion is returned for the caller to
+    /// run once the lock is released.
+    fn pop(&mut self) -> Option<(Transfer, Option<SendDone>)> {
+        let msg = self.queue.pop_front()?;
+        let sent = self.senders.pop_front().map(|(m, done)| {
+            self.queue.push_back(m);
+            done
+        });
+        Some((msg, sent))
+    }
 }
 
-/// The outcome of a [`ChannelInner::recv`].
+/// The outcome of a [`ChannelInner::recv_then`].
 pub enum RecvOutcome {
     /// A message, still transfer-encoded — the caller decodes it into
     /// its own heap.
     Message(Transfer),
     /// The channel is closed and the buffer is drained.
     Closed,
+    /// A broadcast subscription fell behind and lost this many
+    /// messages; receiving again continues from the oldest it kept.
+    Lagged(u64),
 }
 
 impl ChannelInner {
     /// Create a channel. `capacity = None` is unbounded; `Some(n)`
     /// bounds the buffer at `n`.
     pub fn new(capacity: Option<usize>) -> ChannelHandle {
+        Self::with_flavour(capacity, Flavour::Queue)
+    }
+
+    /// Create a oneshot channel: one message, then closed.
+    pub fn oneshot() -> ChannelHandle {
+        Self::with_flavour(None, Flavour::Oneshot)
+    }
+
+    /// Create a broadcast channel whose subscriptions keep up to `lag`
+    /// messages unless they ask for another limit.
+    pub fn broadcast(lag: usize) -> ChannelHandle {
+        Self::hub(lag, None)
+    }
+
+    /// Create a watch channel holding `initial`.
+    pub fn watch(initial: Transfer) -> ChannelHandle {
+        Self::hub(1, Some(initial))
+    }
+
+    fn hub(lag: usize, current: Option<Transfer>) -> ChannelHandle {
+        let hub = Hub { subscribers: Vec::new(), lag, current };
+        Self::with_flavour(None, Flavour::Hub(Mutex::new(hub)))
+    }
+
+    fn with_flavour(capacity: Option<usize>, flavour: Flavour) -> ChannelHandle {
         Arc::new(ChannelInner {
-            queue: Mutex::new(VecDeque::new()),
-            not_empty: Condvar::new(),
-            not_full: Condvar::new(),
+            state: Mutex::new(ChannelState {
+                queue: VecDeque::new(),
+                receivers: VecDeque::new(),
+                senders: VecDeque::new(),
+                peekers: Vec::new(),
+                missed: 0,
+                signals: Vec::new(),
+            }),
             capacity,
             closed: AtomicBool::new(false),
+            flavour,
         })
     }
 
@@ -56,49 +175,192 @@ impl ChannelInner {
         self.closed.load(Ordering::Acquire)
     }
 
-    /// Enqueue a message. On a bounded channel, blocks while the
-    /// buffer is full. Returns `Err(())` if the channel is closed.
-    pub fn send(&self, msg: Transfer) -> Result<(), ()> {
-        let mut q = self.queue.lock().unwrap();
-        if let Some(cap) = self.capacity {
-            while q.len() >= cap && !self.is_closed() {
-                q = self.not_full.wait(q).unwrap();
+    /// Is this the sending side of a broadcast or watch channel — one
+    /// that is received from through [`subscribe`](Self::subscribe)?
+    pub fn is_hub(&self) -> bool {
+        matches!(self.flavour, Flavour::Hub(_))
+    }
+
+    /// Is this a subscription, which only receives?
+    pub fn is_subscription(&self) -> bool {
+        matches!(self.flavour, Flavour::Subscriber { .. })
+    }
+
+    /// Subscribe to a broadcast or watch channel. A broadcast
+    /// subscription sees every message sent from now on, keeping up to
+    /// `lag` (the channel's own limit when `None`) before it starts to
+    /// lose the oldest. A watch subscription waits for the next change.
+    /// `None` if this is not a hub.
+    pub fn subscribe(self: &Arc<Self>, lag: Option<usize>) -> Option<ChannelHandle> {
+        let Flavour::Hub(hub) = &self.flavour else {
+            return None;
+        };
+        let mut hub = hub.lock().unwrap();
+        let watch = hub.current.is_some();
+        let capacity = if watch { 1 } else { lag.unwrap_or(hub.lag) };
+        let sub = Self::with_flavour(
+            Some(capacity),
+            Flavour::Subscriber { hub: Arc::clone(self), watch },
+        );
+        if self.is_closed() {
+            sub.closed.store(true, Ordering::Release);
+        } else {
+            hub.subscribers.push(Arc::downgrade(&sub));
+        }
+        Some(sub)
+    }
+
+    /// A watch channel's current value, read from the channel or any
+    /// subscription to it. `None` for every other kind.
+    pub fn current(&self) -> Option<Transfer> {
+        match &self.flavour {
+            Flavour::Hub(hub) => hub.lock().unwrap().current.clone(),
+            Flavour::Subscriber { hub, .. } => hub.current(),
+            _ => None,
+        }
+    }
+
+    /// Hand `msg` to a subscription, pushing out its oldest message if
+    /// it is at its limit. A broadcast subscription counts what it
+    /// loses; a watch subscription only ever wants the newest value.
+    fn offer(&self, msg: Transfer) {
+        let mut st = self.state.lock().unwrap();
+        if self.is_closed() {
+            return;
+        }
+        let cap = self.capacity.unwrap_or(usize::MAX);
+        while st.queue.len() >= cap {
+            st.queue.pop_front();
+            if !matches!(self.flavour, Flavour::Subscriber { watch: true, .. }) {
+                st.missed += 1;
+            }
+        }
+        self.deliver(st, msg);
+    }
+
+    /// Copy `msg` into every live subscription of this hub, in order
+    /// with any other send (the hub lock is held throughout).
+    fn publish(&self, hub: &Mutex<Hub>, msg: Transfer) {
+        let mut hub = hub.lock().unwrap();
+        hub.subscribers.retain(|w| w.upgrade().is_some_and(|s| !s.is_closed()));
+        for sub in hub.subscribers.iter().filter_map(Weak::upgrade) {
+            sub.offer(msg.clone());
+        }
+        if hub.current.is_some() {
+            hub.current = Some(msg);
+        }
+    }
+
+    /// Enqueue a message, or hand it straight to a parked receiver.
+    /// Called with the channel open and room in the buffer.
+    fn deliver(&self, mut st: MutexGuard<'_, ChannelState>, msg: Transfer) {
+        if let Some(recv) = st.receivers.pop_front() {
+            drop(st);
+            recv(RecvOutcome::Message(msg));
+            return;
+        }
+        st.queue.push_back(msg);
+        let len = st.queue.len();
+        let mut seen = Vec::new();
+        let mut i = 0;
+        while i < st.peekers.len() {
+            if st.peekers[i].0 < len {
+                let (at, done) = st.peekers.swap_remove(i);
+                seen.push((st.queue[at].clone(), done));
+            } else {
+                i += 1;
             }
         }
+        let signals = std::mem::take(&mut st.signals);
+        drop(st);
+        for (msg, done) in seen {
+            done(Some(msg));
+        }
+        for signal in signals {
+            signal.fire();
+        }
+    }
+
+    /// Enqueue a message, or hand it to a parked receiver. `done` runs
+    /// with the result once the message is in the buffer — at once,
+    /// unless a bounded channel is full; then when a receiver frees a
+    /// slot, on that receiver's thread. `Err(())` means the channel is
+    /// closed.
+    pub fn send_then(&self, msg: Transfer, done: SendDone) {
+        let mut st = self.state.lock().unwrap();
         if self.is_closed() {
-            return Err(());
-        }
-        q.push_back(msg);
-        drop(q);
-        self.not_empty.notify_one();
-        wake_selectors();
-        Ok(())
-    }
-
-    /// Dequeue a message, blocking while the buffer is empty and the
-    /// channel is still open.
-    pub fn recv(&self) -> RecvOutcome {
-        let mut q = self.queue.lock().unwrap();
-        loop {
-            if let Some(msg) = q.pop_front() {
-                drop(q);
-                self.not_full.notify_one();
-                return RecvOutcome::Message(msg);
+            drop(st);
+            done(Err(()));
+            return;
+        }
+        match &self.flavour {
+            Flavour::Hub(hub) => {
+                drop(st);
+                self.publish(hub, msg);
+                done(Ok(()));
+                return;
             }
-            if self.is_closed() {
-                return RecvOutcome::Closed;
+            Flavour::Oneshot => {
+                // Closed under the lock, so a racing second `send`
+                // fails; the `close` after wakes any other receivers.
+                self.closed.store(true, Ordering::Release);
+                self.deliver(st, msg);
+                self.close();
+                done(Ok(()));
+                return;
             }
-            q = self.not_empty.wait(q).unwrap();
+            Flavour::Queue | Flavour::Subscriber { .. } => {}
         }
+        if let Some(cap) = self.capacity {
+            if st.queue.len() >= cap {
+                st.senders.push_back((msg, done));
+                return;
+            }
+        }
+        self.deliver(st, msg);
+        done(Ok(()));
+    }
+
+    /// Dequeue a message. `done` runs with the outcome as soon as there
+    /// is one — at once if a message is buffered or the channel is
+    /// closed, otherwise from the next `send` or `close`, on the
+    /// sender's thread.
+    pub fn recv_then(&self, done: Box<dyn FnOnce(RecvOutcome) + Send>) {
+        let mut st = self.state.lock().unwrap();
+        if st.missed > 0 {
+            let missed = std::mem::take(&mut st.missed);
+            drop(st);
+            done(RecvOutcome::Lagged(missed));
+            return;
+        }
+        if let Some((msg, sent)) = st.pop() {
+            drop(st);
+            if let Some(sent) = sent {
+                sent(Ok(()));
+            }
+            done(RecvOutcome::Message(msg));
+            return;
+        }
+        if self.is_closed() {
+            drop(st);
+            done(RecvOutcome::Closed);
+            return;
+        }
+        st.receivers.push_back(done);
     }
 
     /// Non-blocking receive. `None` means nothing is available right
     /// now and the channel is still open.
     pub fn try_recv(&self) -> Option<RecvOutcome> {
-        let mut q = self.queue.lock().unwrap();
-        if let Some(msg) = q.pop_front() {
-            drop(q);
-            self.not_full.notify_one();
+        let mut st = self.state.lock().unwrap();
+        if st.missed > 0 {
+            return Some(RecvOutcome::Lagged(std::mem::take(&mut st.missed)));
+        }
+        if let Some((msg, sent)) = st.pop() {
+            drop(st);
+            if let Some(done) = sent {
+                done(Ok(()));
+            }
             return Some(RecvOutcome::Message(msg));
         }
         if self.is_closed() {
@@ -108,100 +370,92 @@ impl ChannelInner {
         }
     }
 
-    /// Mark the channel closed and wake every blocked sender/receiver
-    /// so they observe it.
-    pub fn close(&self) {
-        self.closed.store(true, Ordering::Release);
-        self.not_empty.notify_all();
-        self.not_full.notify_all();
-        wake_selectors();
+    /// Look at the message `at` places from the front without taking
+    /// it. `done` runs with a copy as soon as the buffer holds one there
+    /// — at once, or from the `send` that puts it there — or with
+    /// `None` once the channel is closed short of it. Backs selective
+    /// `receive`, which scans a mailbox and leaves what it skips.
+    pub fn peek_then(&self, at: usize, done: Box<dyn FnOnce(Option<Transfer>) + Send>) {
+        let mut st = self.state.lock().unwrap();
+        if let Some(msg) = st.queue.get(at) {
+            let msg = msg.clone();
+            drop(st);
+            done(Some(msg));
+            return;
+        }
+        if self.is_closed() {
+            drop(st);
+            done(None);
+            return;
+        }
+        st.peekers.push((at, done));
     }
-}
-
-// ---- select: waiting on several channels at once -------------------
 
-/// Process-wide parker for `select`. A thread blocked in [`select`]
-/// waits on `wake`; every `send`/`close` calls [`wake_selectors`].
-/// `selectors` keeps the common no-select path off the global lock.
-struct SelectPark {
-    lock: Mutex<()>,
-    wake: Condvar,
-    selectors: AtomicUsize,
-}
-
-fn select_park() -> &'static SelectPark {
-    static PARK: OnceLock<SelectPark> = OnceLock::new();
-    PARK.get_or_init(|| SelectPark {
-        lock: Mutex::new(()),
-        wake: Condvar::new(),
-        selectors: AtomicUsize::new(0),
-    })
-}
-
-/// Wake every thread parked in `select`. A no-op (one atomic load)
-/// when no `select` is in progress.
-fn wake_selectors() {
-    let p = select_park();
-    if p.selectors.load(Ordering::Acquire) == 0 {
-        return;
+    /// Fire `signal` once a receive would not wait — at once if a
+    /// message is buffered or the channel is closed, otherwise at the
+    /// next `send` or `close`. Backs a green thread's `select`, which
+    /// parks on every arm at once and re-checks them when woken.
+    pub fn notify(&self, signal: &Arc<Signal>) {
+        let mut st = self.state.lock().unwrap();
+        if !st.queue.is_empty() || st.missed > 0 || self.is_closed() {
+            drop(st);
+            signal.fire();
+            return;
+        }
+        st.signals.retain(|s| !s.is_spent());
+        st.signals.push(Arc::clone(signal));
     }
-    let _g = p.lock.lock().unwrap();
-    p.wake.notify_all();
-}
 
-/// The outcome of a [`select`].
-pub enum SelectResult {
-    /// Channel `index` produced a message (still transfer-encoded).
-    Fired { index: usize, message: Transfer },
-    /// Nothing was ready and the caller permitted a non-blocking
-    /// return (an `else` arm).
-    ElseReady,
-    /// Every channel is closed and drained — a blocking `select` has
-    /// nothing left to wait for.
-    AllClosed,
-}
+    /// Take the message `at` places from the front, if there is one.
+    pub fn take_at(&self, at: usize) -> Option<Transfer> {
+        let mut st = self.state.lock().unwrap();
+        let msg = st.queue.remove(at)?;
+        let sent = st.senders.pop_front().map(|(m, done)| {
+            st.queue.push_back(m);
+            done
+        });
+        drop(st);
+        if let Some(done) = sent {
+            done(Ok(()));
+        }
+        Some(msg)
+    }
 
-/// Block until one of `chans` has a message; return which fired. A
-/// closed-and-drained channel is skipped; if all are closed, returns
-/// `AllClosed`. With `has_else`, returns `ElseReady` instead of
-/// blocking when nothing is ready.
-///
-/// The parker lock is held across the channel scan and the wait. This
-/// is deadlock-free: `send`/`close` acquire the parker lock only
-/// *after* releasing their channel lock, so no thread ever holds a
-/// channel lock while waiting on the parker lock.
-pub fn select(chans: &[ChannelHandle], has_else: bool) -> SelectResult {
-    if chans.is_empty() {
-        return if has_else { SelectResult::ElseReady } else { SelectResult::AllClosed };
-    }
-    let p = select_park();
-    p.selectors.fetch_add(1, Ordering::AcqRel);
-    let mut guard = p.lock.lock().unwrap();
-    let result = loop {
-        let mut hit = None;
-        let mut all_closed = true;
-        for (i, ch) in chans.iter().enumerate() {
-            match ch.try_recv() {
-                Some(RecvOutcome::Message(t)) => {
-                    hit = Some((i, t));
-                    break;
-                }
-                Some(RecvOutcome::Closed) => {} // channel dead — skip it
-                None => all_closed = false,
+    /// Mark the channel closed and complete every parked sender and
+    /// receiver so they observe it. Closing a hub closes every
+    /// subscription, which drains what it holds and then reports
+    /// closed; closing a subscription unsubscribes it.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        if let Flavour::Hub(hub) = &self.flavour {
+            let subs = std::mem::take(&mut hub.lock().unwrap().subscribers);
+            for sub in subs.iter().filter_map(Weak::upgrade) {
+                sub.close();
             }
         }
-        if let Some((index, message)) = hit {
-            break SelectResult::Fired { index, message };
+        // Taking the lock after the store orders this against a
+        // `send_then`/`recv_then` that checked the flag and is about to
+        // park: it parks before this drain, and is drained with the rest.
+        let (receivers, senders, peekers, signals) = {
+            let mut st = self.state.lock().unwrap();
+            (
+                std::mem::take(&mut st.receivers),
+                std::mem::take(&mut st.senders),
+                std::mem::take(&mut st.peekers),
+                std::mem::take(&mut st.signals),
+            )
+        };
+        for recv in receivers {
+            recv(RecvOutcome::Closed);
         }
-        if all_closed {
-            break SelectResult::AllClosed;
+        for (_, done) in peekers {
+            done(None);
         }
-        if has_else {
-            break SelectResult::ElseReady;
+        for (_, done) in senders {
+            done(Err(()));
         }
-        guard = p.wake.wait(guard).unwrap();
-    };
-    drop(guard);
-    p.selectors.fetch_sub(1, Ordering::AcqRel);
-    result
+        for signal in signals {
+            signal.fire();
+        }
+    }
 }
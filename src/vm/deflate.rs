@@ -0,0 +1,1548 @@
+//! DEFLATE (RFC 1951) with the zlib (RFC 1950) and gzip (RFC 1952)
+//! framings — the codec behind the `Compress` native module and the
+//! transparent response decoding in `Http`.
+//!
+//! Hand-rolled, no external crate and no platform dependency, so it
+//! builds for `wasm32` like the rest of the VM. Nothing here knows
+//! about tigr values: the native module checks arguments and keeps a
+//! streaming codec's state in its stream object between calls, which
+//! is why [`Inflater`] and [`Deflater`] are plain-data structs.
+//!
+//! The decoder works a block at a time: [`inflate_block`] either
+//! decodes one whole block or reports that the input ran short, so a
+//! streaming caller keeps the unread tail and retries once more bytes
+//! arrive. The encoder is a hash-chain LZ77 matcher feeding stored,
+//! fixed- or dynamic-Huffman blocks, whichever comes out smallest.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::OnceLock;
+
+/// How far back a DEFLATE back-reference may reach.
+pub const WINDOW: usize = 32 * 1024;
+
+/// Which container wraps the DEFLATE stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Bare RFC 1951 blocks, no header or checksum.
+    Raw,
+    /// A 2-byte header and an Adler-32 trailer.
+    Zlib,
+    /// A 10-byte (or longer) header and a CRC-32 + size trailer.
+    Gzip,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "deflate" => Some(Format::Raw),
+            "zlib" => Some(Format::Zlib),
+            "gzip" => Some(Format::Gzip),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Raw => "deflate",
+            Format::Zlib => "zlib",
+            Format::Gzip => "gzip",
+        }
+    }
+
+    /// The running checksum's starting value (Adler-32 starts at 1).
+    fn check_init(self) -> u32 {
+        if self == Format::Zlib { 1 } else { 0 }
+    }
+
+    fn update_check(self, check: u32, data: &[u8]) -> u32 {
+        match self {
+            Format::Raw => 0,
+            Format::Zlib => adler32(check, data),
+            Format::Gzip => crc32(check, data),
+        }
+    }
+
+    fn trailer_len(self) -> usize {
+        match self {
+            Format::Raw => 0,
+            Format::Zlib => 4,
+            Format::Gzip => 8,
+        }
+    }
+}
+
+/// Where and why compressed input was rejected. `offset` is the byte
+/// position in the input at which the problem was found. `too_large`
+/// marks a stream that was valid so far but decoded to more than the
+/// caller's output limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub offset: usize,
+    pub message: String,
+    pub too_large: bool,
+}
+
+impl Error {
+    fn new(offset: usize, message: impl Into<String>) -> Self {
+        Error { offset, message: message.into(), too_large: false }
+    }
+}
+
+// ---------------------------------------------------------------------
+// Checksums
+// ---------------------------------------------------------------------
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+static CRC_TABLE: [u32; 256] = crc_table();
+
+/// Continue a CRC-32 (the gzip / zip / PNG polynomial) over `data`.
+/// Start from `0`.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in data {
+        c = CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// Continue an Adler-32 over `data`. Start from `1`.
+pub fn adler32(adler: u32, data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (adler & 0xffff, adler >> 16);
+    // 5552 is the longest run whose sums cannot overflow a u32.
+    for chunk in data.chunks(5552) {
+        for &x in chunk {
+            a += x as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+// ---------------------------------------------------------------------
+// Shared tables
+// ---------------------------------------------------------------------
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order code-length code lengths are transmitted in.
+const CL_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const END_OF_BLOCK: usize = 256;
+
+/// Code lengths of the fixed literal/length (288) and distance (30)
+/// codes of a BTYPE=01 block.
+fn fixed_lengths() -> ([u8; 288], [u8; 30]) {
+    let mut lit = [0u8; 288];
+    for (sym, len) in lit.iter_mut().enumerate() {
+        *len = match sym {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    (lit, [5u8; 30])
+}
+
+// ---------------------------------------------------------------------
+// Inflate
+// ---------------------------------------------------------------------
+
+/// Why decoding a block stopped early.
+enum Fail {
+    /// The input ended mid-block; retry with more bytes.
+    Short,
+    Bad(&'static str),
+    /// The output passed the caller's limit.
+    Full,
+}
+
+type Step<T> = Result<T, Fail>;
+
+/// An LSB-first bit reader over a byte slice.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Bits<'_> {
+    fn available(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.pos)
+    }
+
+    /// The next `n` (<= 16) bits without consuming them, zero-filled
+    /// past the end of the input.
+    fn peek(&self, n: u32) -> u32 {
+        let first = self.pos >> 3;
+        let mut v: u32 = 0;
+        let rest = self.data.get(first..).unwrap_or(&[]);
+        for (i, &b) in rest.iter().take(4).enumerate() {
+            v |= (b as u32) << (8 * i);
+        }
+        (v >> (self.pos & 7)) & ((1u32 << n) - 1)
+    }
+
+    fn bits(&mut self, n: u32) -> Step<u32> {
+        if n as usize > self.available() {
+            return Err(Fail::Short);
+        }
+        let v = self.peek(n);
+        self.pos += n as usize;
+        Ok(v)
+    }
+}
+
+/// Codes no longer than this are decoded with one table lookup.
+const FAST_BITS: u32 = 9;
+
+/// A canonical Huffman decoding table.
+struct Huffman {
+    /// Number of codes of each length (index 0 counts unused symbols).
+    count: [u16; 16],
+    /// Symbols ordered by code.
+    symbol: Vec<u16>,
+    /// Indexed by the next `FAST_BITS` input bits: `symbol << 4 | len`
+    /// for a short code, `0` to take the bit-by-bit path.
+    fast: Vec<u16>,
+    /// `0` for a complete code, positive if incomplete, negative if
+    /// over-subscribed.
+    left: i32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut count = [0u16; 16];
+        for &l in lengths {
+            count[l as usize] += 1;
+        }
+        let mut left: i32 = 1;
+        if count[0] as usize != lengths.len() {
+            for &c in &count[1..] {
+                left = (left << 1) - c as i32;
+                if left < 0 {
+                    break;
+                }
+            }
+        } else {
+            left = 0;
+        }
+        let mut offs = [0u16; 16];
+        for len in 1..15 {
+            offs[len + 1] = offs[len] + count[len];
+        }
+        let mut symbol = vec![0u16; lengths.len()];
+        for (sym, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbol[offs[l as usize] as usize] = sym as u16;
+                offs[l as usize] += 1;
+            }
+        }
+        let mut fast = vec![0u16; 1 << FAST_BITS];
+        if left >= 0 {
+            let mut code: u32 = 0;
+            let mut index = 0usize;
+            for len in 1..=FAST_BITS {
+                for _ in 0..count[len as usize] {
+                    let rev = reverse(code, len);
+                    let entry = (symbol[index] << 4) | len as u16;
+                    let mut fill = rev;
+                    while fill < (1 << FAST_BITS) {
+                        fast[fill as usize] = entry;
+                        fill += 1 << len;
+                    }
+                    code += 1;
+                    index += 1;
+                }
+                code <<= 1;
+            }
+        }
+        Huffman { count, symbol, fast, left }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Step<usize> {
+        let entry = self.fast[bits.peek(FAST_BITS) as usize];
+        let len = (entry & 0xf) as usize;
+        if entry != 0 && len <= bits.available() {
+            bits.pos += len;
+            return Ok((entry >> 4) as usize);
+        }
+        // A long code, or one that may run past the end of the input.
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = self.count[len] as i32;
+            if code - count < first {
+                return Ok(self.symbol[(index + code - first) as usize] as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(Fail::Bad("invalid Huffman code"))
+    }
+}
+
+fn reverse(code: u32, len: u32) -> u32 {
+    code.reverse_bits() >> (32 - len)
+}
+
+fn fixed_tables() -> &'static (Huffman, Huffman) {
+    static TABLES: OnceLock<(Huffman, Huffman)> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let (lit, dist) = fixed_lengths();
+        (Huffman::new(&lit), Huffman::new(&dist))
+    })
+}
+
+/// How far [`inflate_block`] got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// The block ended at bit `next`; `last` if it was the final one.
+    Done { next: usize, last: bool },
+    /// The input ran out mid-block. Output up to bit `resume` has been
+    /// appended; call again with the same block start, more input and
+    /// this `resume` to carry on (`None`: nothing decoded yet).
+    Partial { resume: Option<usize> },
+}
+
+/// Decode the block starting at bit `bit` of `input`, appending its
+/// output to `out`. `out` must already hold the preceding output (at
+/// least the last [`WINDOW`] bytes) so back-references resolve.
+///
+/// `resume` continues a block an earlier call returned as
+/// [`Block::Partial`]: the header is parsed again from `bit` and
+/// decoding picks up at the last whole symbol, so a block arriving in
+/// many small pieces costs no more than one arriving whole.
+///
+/// Decoding stops with a `too_large` error once `out` grows past
+/// `limit` bytes, so a small input cannot expand without bound.
+pub fn inflate_block(
+    input: &[u8],
+    bit: usize,
+    resume: Option<usize>,
+    out: &mut Vec<u8>,
+    limit: usize,
+) -> Result<Block, Error> {
+    let mut bits = Bits { data: input, pos: bit };
+    let mut mark = resume;
+    match block(&mut bits, out, &mut mark, limit) {
+        Ok(last) => Ok(Block::Done { next: bits.pos, last }),
+        Err(Fail::Short) => Ok(Block::Partial { resume: mark }),
+        Err(Fail::Bad(msg)) => Err(Error::new(bits.pos / 8, msg)),
+        Err(Fail::Full) => Err(Error {
+            too_large: true,
+            ..Error::new(bits.pos / 8, "output exceeds the limit")
+        }),
+    }
+}
+
+/// Decode one block. `mark` is the bit position after the last symbol
+/// whose output is in `out`: where decoding resumes, and what is
+/// reported back if the input runs out.
+fn block(bits: &mut Bits, out: &mut Vec<u8>, mark: &mut Option<usize>, limit: usize) -> Step<bool> {
+    let last = bits.bits(1)? == 1;
+    match bits.bits(2)? {
+        0 => stored(bits, out, mark, limit)?,
+        1 => {
+            let (lit, dist) = fixed_tables();
+            codes(bits, out, lit, dist, mark, limit)?;
+        }
+        2 => {
+            let (lit, dist) = dynamic(bits)?;
+            codes(bits, out, &lit, &dist, mark, limit)?;
+        }
+        _ => return Err(Fail::Bad("invalid block type")),
+    }
+    Ok(last)
+}
+
+fn stored(bits: &mut Bits, out: &mut Vec<u8>, mark: &mut Option<usize>, limit: usize) -> Step<()> {
+    let start = bits.pos.div_ceil(8);
+    let data = bits.data;
+    if data.len() < start + 4 {
+        return Err(Fail::Short);
+    }
+    let len = u16::from_le_bytes([data[start], data[start + 1]]);
+    let nlen = u16::from_le_bytes([data[start + 2], data[start + 3]]);
+    if len != !nlen {
+        bits.pos = start * 8;
+        return Err(Fail::Bad("stored block length does not match its complement"));
+    }
+    let body = mark.map_or(start + 4, |m| m / 8);
+    let end = start + 4 + len as usize;
+    let have = end.min(data.len());
+    if body < start + 4 || body > have {
+        return Err(Fail::Bad("invalid resume point"));
+    }
+    if out.len() + (have - body) > limit {
+        return Err(Fail::Full);
+    }
+    out.extend_from_slice(&data[body..have]);
+    if have < end {
+        *mark = Some(have * 8);
+        return Err(Fail::Short);
+    }
+    bits.pos = end * 8;
+    Ok(())
+}
+
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+    mark: &mut Option<usize>,
+    limit: usize,
+) -> Step<()> {
+    if let Some(m) = *mark {
+        bits.pos = m;
+    }
+    loop {
+        if out.len() > limit {
+            return Err(Fail::Full);
+        }
+        *mark = Some(bits.pos);
+        let sym = lit.decode(bits)?;
+        if sym < 256 {
+            out.push(sym as u8);
+            continue;
+        }
+        if sym == END_OF_BLOCK {
+            return Ok(());
+        }
+        let s = sym - 257;
+        if s >= LEN_BASE.len() {
+            return Err(Fail::Bad("invalid length symbol"));
+        }
+        let len = LEN_BASE[s] as usize + bits.bits(LEN_EXTRA[s] as u32)? as usize;
+        let ds = dist.decode(bits)?;
+        if ds >= DIST_BASE.len() {
+            return Err(Fail::Bad("invalid distance symbol"));
+        }
+        let d = DIST_BASE[ds] as usize + bits.bits(DIST_EXTRA[ds] as u32)? as usize;
+        if d > out.len() {
+            return Err(Fail::Bad("distance too far back"));
+        }
+        let start = out.len() - d;
+        if d >= len {
+            out.extend_from_within(start..start + len);
+        } else {
+            // An overlapping copy repeats the last `d` bytes.
+            for i in 0..len {
+                let b = out[start + i];
+                out.push(b);
+            }
+        }
+    }
+}
+
+fn dynamic(bits: &mut Bits) -> Step<(Huffman, Huffman)> {
+    let nlen = bits.bits(5)? as usize + 257;
+    let ndist = bits.bits(5)? as usize + 1;
+    let ncode = bits.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(Fail::Bad("too many length or distance codes"));
+    }
+    let mut cl_lengths = [0u8; 19];
+    for &slot in &CL_ORDER[..ncode] {
+        cl_lengths[slot] = bits.bits(3)? as u8;
+    }
+    let cl = Huffman::new(&cl_lengths);
+    if cl.left != 0 {
+        return Err(Fail::Bad("invalid code length code"));
+    }
+    let mut lengths = [0u8; 286 + 30];
+    let mut index = 0;
+    while index < nlen + ndist {
+        let sym = cl.decode(bits)?;
+        if sym < 16 {
+            lengths[index] = sym as u8;
+            index += 1;
+            continue;
+        }
+        let (len, repeat) = match sym {
+            16 => {
+                if index == 0 {
+                    return Err(Fail::Bad("repeated length with no previous length"));
+                }
+                (lengths[index - 1], 3 + bits.bits(2)? as usize)
+            }
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if index + repeat > nlen + ndist {
+            return Err(Fail::Bad("too many code lengths"));
+        }
+        lengths[index..index + repeat].fill(len);
+        index += repeat;
+    }
+    if lengths[END_OF_BLOCK] == 0 {
+        return Err(Fail::Bad("missing end-of-block code"));
+    }
+    // An incomplete code is only allowed when it is a single code.
+    let lit = Huffman::new(&lengths[..nlen]);
+    if lit.left < 0 || (lit.left > 0 && nlen - lit.count[0] as usize != 1) {
+        return Err(Fail::Bad("invalid literal/length code"));
+    }
+    let dist = Huffman::new(&lengths[nlen..nlen + ndist]);
+    if dist.left < 0 || (dist.left > 0 && ndist - dist.count[0] as usize != 1) {
+        return Err(Fail::Bad("invalid distance code"));
+    }
+    Ok((lit, dist))
+}
+
+/// Parse a zlib header at the start of `data`: its length, or `None`
+/// if `data` is too short to tell.
+fn zlib_header(data: &[u8]) -> Result<Option<usize>, Error> {
+    let Some(&cmf) = data.first() else { return Ok(None) };
+    if cmf & 0x0f != 8 || cmf >> 4 > 7 {
+        return Err(Error::new(0, "not zlib data (bad header)"));
+    }
+    let Some(&flg) = data.get(1) else { return Ok(None) };
+    if !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+        return Err(Error::new(1, "not zlib data (header check failed)"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(Error::new(1, "zlib preset dictionaries are not supported"));
+    }
+    Ok(Some(2))
+}
+
+/// Parse a gzip member header at the start of `data`: its length, or
+/// `None` if `data` is too short to hold all of it.
+fn gzip_header(data: &[u8]) -> Result<Option<usize>, Error> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+    for (i, &magic) in [0x1f, 0x8b].iter().enumerate() {
+        match data.get(i) {
+            None => return Ok(None),
+            Some(&b) if b != magic => return Err(Error::new(i, "not gzip data (bad magic)")),
+            _ => {}
+        }
+    }
+    match data.get(2) {
+        None => return Ok(None),
+        Some(8) => {}
+        Some(_) => return Err(Error::new(2, "unsupported gzip compression method")),
+    }
+    let Some(&flags) = data.get(3) else { return Ok(None) };
+    if flags & 0xe0 != 0 {
+        return Err(Error::new(3, "reserved gzip flag bits set"));
+    }
+    let mut p = 10;
+    if flags & FEXTRA != 0 {
+        if data.len() < p + 2 {
+            return Ok(None);
+        }
+        p += 2 + u16::from_le_bytes([data[p], data[p + 1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            match data.get(p..).and_then(|rest| rest.iter().position(|&b| b == 0)) {
+                Some(nul) => p += nul + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        p += 2;
+    }
+    Ok(if data.len() < p { None } else { Some(p) })
+}
+
+/// Check a member trailer against the running checksum and size.
+fn check_trailer(format: Format, trailer: &[u8], check: u32, size: u32) -> Result<(), &'static str> {
+    match format {
+        Format::Raw => Ok(()),
+        Format::Zlib => {
+            let want = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+            if want != check { Err("Adler-32 checksum mismatch") } else { Ok(()) }
+        }
+        Format::Gzip => {
+            let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+            let isize = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+            if crc != check {
+                Err("CRC-32 checksum mismatch")
+            } else if isize != size {
+                Err("uncompressed size mismatch")
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Where a streaming decoder is within its input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+impl Stage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Header => "header",
+            Stage::Body => "body",
+            Stage::Trailer => "trailer",
+            Stage::Done => "done",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Stage> {
+        match name {
+            "header" => Some(Stage::Header),
+            "body" => Some(Stage::Body),
+            "trailer" => Some(Stage::Trailer),
+            "done" => Some(Stage::Done),
+            _ => None,
+        }
+    }
+}
+
+/// A resumable decoder. Feed it input in chunks of any size with
+/// [`Inflater::write`]; each call returns all the output the input so
+/// far decodes to.
+#[derive(Clone, Debug)]
+pub struct Inflater {
+    pub format: Format,
+    pub stage: Stage,
+    /// Input not yet consumed, starting at bit `bit` of its first byte.
+    /// Mid-block, it starts at the current block's header.
+    pub pending: Vec<u8>,
+    pub bit: usize,
+    /// Where to resume the current block (see [`Block::Partial`]).
+    pub resume: Option<usize>,
+    /// The last [`WINDOW`] bytes of output, for back-references.
+    pub window: Vec<u8>,
+    /// Running checksum and (mod 2^32) size of this member's output.
+    pub check: u32,
+    pub size: u32,
+    /// Input bytes consumed before `pending`, for error offsets.
+    pub consumed: usize,
+}
+
+impl Inflater {
+    pub fn new(format: Format) -> Inflater {
+        Inflater {
+            format,
+            stage: Stage::Header,
+            pending: Vec::new(),
+            bit: 0,
+            resume: None,
+            window: Vec::new(),
+            check: format.check_init(),
+            size: 0,
+            consumed: 0,
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pending.drain(..n);
+        self.consumed += n;
+    }
+
+    fn error(&self, offset: usize, message: impl Into<String>) -> Error {
+        Error::new(self.consumed + offset, message)
+    }
+
+    /// Consume `chunk` and return the output it completes. Raises a
+    /// `too_large` error rather than return more than `limit` bytes.
+    pub fn write(&mut self, chunk: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+        self.pending.extend_from_slice(chunk);
+        let mut out = Vec::new();
+        loop {
+            match self.stage {
+                Stage::Header => {
+                    let header = match self.format {
+                        Format::Raw => Ok(Some(0)),
+                        Format::Zlib => zlib_header(&self.pending),
+                        Format::Gzip => gzip_header(&self.pending),
+                    };
+                    match header.map_err(|e| self.error(e.offset, e.message))? {
+                        None => break,
+                        Some(n) => {
+                            self.advance(n);
+                            self.stage = Stage::Body;
+                            self.check = self.format.check_init();
+                            self.size = 0;
+                        }
+                    }
+                }
+                Stage::Body => {
+                    let mut buf = std::mem::take(&mut self.window);
+                    let history = buf.len();
+                    let room = history.saturating_add(limit.saturating_sub(out.len()));
+                    let step = inflate_block(&self.pending, self.bit, self.resume, &mut buf, room);
+                    let step = step.map_err(|e| Error { offset: self.consumed + e.offset, ..e })?;
+                    let produced = &buf[history..];
+                    self.check = self.format.update_check(self.check, produced);
+                    self.size = self.size.wrapping_add(produced.len() as u32);
+                    out.extend_from_slice(produced);
+                    if buf.len() > WINDOW {
+                        buf.drain(..buf.len() - WINDOW);
+                    }
+                    self.window = buf;
+                    let (next, last) = match step {
+                        Block::Partial { resume } => {
+                            self.resume = resume;
+                            break;
+                        }
+                        Block::Done { next, last } => (next, last),
+                    };
+                    self.resume = None;
+                    self.advance(next / 8);
+                    self.bit = next % 8;
+                    if last {
+                        if self.bit != 0 {
+                            self.advance(1);
+                            self.bit = 0;
+                        }
+                        self.window.clear();
+                        self.stage = Stage::Trailer;
+                    }
+                }
+                Stage::Trailer => {
+                    let need = self.format.trailer_len();
+                    if self.pending.len() < need {
+                        break;
+                    }
+                    check_trailer(self.format, &self.pending[..need], self.check, self.size)
+                        .map_err(|msg| self.error(0, msg))?;
+                    self.advance(need);
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => {
+                    if self.pending.is_empty() {
+                        break;
+                    }
+                    // gzip allows several members back to back.
+                    if self.format != Format::Gzip {
+                        return Err(self.error(0, "unexpected data after the end of the stream"));
+                    }
+                    self.stage = Stage::Header;
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Confirm the stream is complete: every block, and the trailer,
+    /// has arrived.
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.stage == Stage::Done {
+            Ok(())
+        } else {
+            Err(self.error(self.pending.len(), "unexpected end of compressed data"))
+        }
+    }
+}
+
+/// Decode a complete compressed stream in one call, raising a
+/// `too_large` error if it decodes to more than `limit` bytes.
+pub fn decompress(input: &[u8], format: Format, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut inflater = Inflater::new(format);
+    let out = inflater.write(input, limit)?;
+    inflater.finish()?;
+    Ok(out)
+}
+
+// ---------------------------------------------------------------------
+// Deflate
+// ---------------------------------------------------------------------
+
+/// An LSB-first bit writer.
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    n: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { out: Vec::new(), acc: 0, n: 0 }
+    }
+
+    fn put(&mut self, bits: u32, n: u32) {
+        self.acc |= (bits as u64) << self.n;
+        self.n += n;
+        while self.n >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n -= 8;
+        }
+    }
+
+    fn align(&mut self) {
+        if self.n > 0 {
+            self.out.push(self.acc as u8);
+            self.acc = 0;
+            self.n = 0;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Literal(u8),
+    Copy { len: u16, dist: u16 },
+}
+
+fn len_symbol(len: usize) -> (usize, u32) {
+    let s = LEN_BASE.partition_point(|&b| b as usize <= len) - 1;
+    (s, (len - LEN_BASE[s] as usize) as u32)
+}
+
+fn dist_symbol(dist: usize) -> (usize, u32) {
+    let s = DIST_BASE.partition_point(|&b| b as usize <= dist) - 1;
+    (s, (dist - DIST_BASE[s] as usize) as u32)
+}
+
+/// Match-finder effort for a compression level: (longest hash chain
+/// to walk, match length that ends the search early, lazy matching).
+fn effort(level: u32) -> (usize, usize, bool) {
+    match level {
+        1 => (4, 8, false),
+        2 => (8, 16, false),
+        3 => (16, 32, false),
+        4 => (16, 16, true),
+        5 => (32, 32, true),
+        6 => (128, 128, true),
+        7 => (256, 258, true),
+        8 => (1024, 258, true),
+        _ => (4096, 258, true),
+    }
+}
+
+const HASH_BITS: u32 = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const NONE: u32 = u32::MAX;
+
+fn hash3(buf: &[u8], i: usize) -> usize {
+    let v = (buf[i] as u32) << 16 | (buf[i + 1] as u32) << 8 | buf[i + 2] as u32;
+    (v.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Hash chains over `buf`: `head[h]` is the latest position with hash
+/// `h`, `prev[i]` the one before position `i`.
+struct Matcher<'a> {
+    buf: &'a [u8],
+    head: Vec<u32>,
+    prev: Vec<u32>,
+    max_chain: usize,
+    nice: usize,
+}
+
+impl Matcher<'_> {
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.buf.len() {
+            let h = hash3(self.buf, i);
+            self.prev[i] = self.head[h];
+            self.head[h] = i as u32;
+        }
+    }
+
+    /// The longest earlier match for position `i`: (length, distance).
+    fn longest(&self, i: usize) -> (usize, usize) {
+        let buf = self.buf;
+        if i + MIN_MATCH > buf.len() {
+            return (0, 0);
+        }
+        let limit = (buf.len() - i).min(MAX_MATCH);
+        let (mut best_len, mut best_dist) = (0, 0);
+        let mut cand = self.head[hash3(buf, i)];
+        let mut chain = self.max_chain;
+        while cand != NONE && chain > 0 {
+            let c = cand as usize;
+            if i - c > WINDOW {
+                break;
+            }
+            if buf[c + best_len.min(limit - 1)] == buf[i + best_len.min(limit - 1)] {
+                let len = buf[c..c + limit]
+                    .iter()
+                    .zip(&buf[i..i + limit])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - c;
+                    if len >= self.nice || len == limit {
+                        break;
+                    }
+                }
+            }
+            cand = self.prev[c];
+            chain -= 1;
+        }
+        if best_len < MIN_MATCH { (0, 0) } else { (best_len, best_dist) }
+    }
+}
+
+/// LZ77-parse `buf[start..]`; `buf[..start]` is history a match may
+/// reach back into.
+fn tokenize(buf: &[u8], start: usize, level: u32) -> Vec<Token> {
+    let (max_chain, nice, lazy) = effort(level);
+    let mut m = Matcher {
+        buf,
+        head: vec![NONE; 1 << HASH_BITS],
+        prev: vec![NONE; buf.len()],
+        max_chain,
+        nice,
+    };
+    for i in start.saturating_sub(WINDOW)..start {
+        m.insert(i);
+    }
+    let mut tokens = Vec::with_capacity(buf.len() - start);
+    let mut i = start;
+    while i < buf.len() {
+        let (len, dist) = m.longest(i);
+        m.insert(i);
+        if len == 0 {
+            tokens.push(Token::Literal(buf[i]));
+            i += 1;
+            continue;
+        }
+        if lazy && len < nice && m.longest(i + 1).0 > len {
+            // A longer match starts one byte later: emit a literal now.
+            tokens.push(Token::Literal(buf[i]));
+            i += 1;
+            continue;
+        }
+        tokens.push(Token::Copy { len: len as u16, dist: dist as u16 });
+        for j in i + 1..i + len {
+            m.insert(j);
+        }
+        i += len;
+    }
+    tokens
+}
+
+/// Code lengths (at most `limit` bits) for symbols with frequencies
+/// `freq`. At least two symbols must have a non-zero frequency.
+fn huffman_lengths(freq: &[u32], limit: usize) -> Vec<u8> {
+    let mut lengths = vec![0u8; freq.len()];
+    let mut syms: Vec<usize> = (0..freq.len()).filter(|&s| freq[s] > 0).collect();
+    // Build the tree: leaves are `0..n`, internal nodes follow.
+    let n = syms.len();
+    let mut parent = vec![0usize; 2 * n];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> =
+        syms.iter().enumerate().map(|(i, &s)| Reverse((freq[s] as u64, i))).collect();
+    let mut next = n;
+    while heap.len() > 1 {
+        let Reverse((fa, a)) = heap.pop().unwrap();
+        let Reverse((fb, b)) = heap.pop().unwrap();
+        parent[a] = next;
+        parent[b] = next;
+        heap.push(Reverse((fa + fb, next)));
+        next += 1;
+    }
+    let root = next - 1;
+    let mut num = vec![0usize; limit + 1];
+    for leaf in 0..n {
+        let (mut depth, mut node) = (0, leaf);
+        while node != root {
+            node = parent[node];
+            depth += 1;
+        }
+        num[depth.min(limit)] += 1;
+    }
+    // Clamping long codes to `limit` over-subscribes the code; push
+    // leaves down from shorter lengths until the Kraft sum is exact.
+    let mut total: usize = (1..=limit).map(|l| num[l] << (limit - l)).sum();
+    while total > 1 << limit {
+        num[limit] -= 1;
+        if let Some(l) = (1..limit).rev().find(|&l| num[l] > 0) {
+            num[l] -= 1;
+            num[l + 1] += 2;
+        }
+        total -= 1;
+    }
+    // Most frequent symbols get the shortest codes.
+    syms.sort_by_key(|&s| Reverse(freq[s]));
+    let mut it = syms.into_iter();
+    for (len, &count) in num.iter().enumerate().skip(1) {
+        for s in it.by_ref().take(count) {
+            lengths[s] = len as u8;
+        }
+    }
+    lengths
+}
+
+/// Canonical codes for `lengths`, bit-reversed for LSB-first output.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut count = [0u32; 16];
+    for &l in lengths {
+        count[l as usize] += 1;
+    }
+    count[0] = 0;
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for len in 1..16 {
+        code = (code + count[len - 1]) << 1;
+        next[len] = code;
+    }
+    lengths
+        .iter()
+        .map(|&l| {
+            if l == 0 {
+                return 0;
+            }
+            let c = next[l as usize];
+            next[l as usize] += 1;
+            reverse(c, l as u32)
+        })
+        .collect()
+}
+
+/// Make sure at least two symbols are used, so the code built from
+/// `freq` is complete (decoders reject most incomplete codes).
+fn ensure_two(freq: &mut [u32]) {
+    let mut used = freq.iter().filter(|&&f| f > 0).count();
+    for f in freq.iter_mut() {
+        if used >= 2 {
+            break;
+        }
+        if *f == 0 {
+            *f = 1;
+            used += 1;
+        }
+    }
+}
+
+/// Run-length encode a code-length sequence with symbols 16/17/18:
+/// (symbol, extra-bits value) pairs.
+fn rle_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let l = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&x| x == l).count();
+        let mut r = run;
+        if l == 0 {
+            while r >= 11 {
+                let k = r.min(138);
+                out.push((18, (k - 11) as u8));
+                r -= k;
+            }
+            if r >= 3 {
+                out.push((17, (r - 3) as u8));
+                r = 0;
+            }
+        } else {
+            out.push((l, 0));
+            r -= 1;
+            while r >= 3 {
+                let k = r.min(6);
+                out.push((16, (k - 3) as u8));
+                r -= k;
+            }
+        }
+        out.extend(std::iter::repeat_n((l, 0), r));
+        i += run;
+    }
+    out
+}
+
+/// A dynamic block's code tables and transmitted header.
+struct DynamicCodes {
+    lit: Vec<u8>,
+    dist: Vec<u8>,
+    hlit: usize,
+    hdist: usize,
+    cl: Vec<u8>,
+    hclen: usize,
+    rle: Vec<(u8, u8)>,
+}
+
+impl DynamicCodes {
+    fn new(lit_freq: &[u32], dist_freq: &[u32]) -> DynamicCodes {
+        let mut lf = lit_freq.to_vec();
+        let mut df = dist_freq.to_vec();
+        ensure_two(&mut lf);
+        ensure_two(&mut df);
+        let lit = huffman_lengths(&lf, 15);
+        let dist = huffman_lengths(&df, 15);
+        let hlit = 257.max(lit.iter().rposition(|&l| l != 0).map_or(0, |p| p + 1));
+        let hdist = 1.max(dist.iter().rposition(|&l| l != 0).map_or(0, |p| p + 1));
+        let all: Vec<u8> = lit[..hlit].iter().chain(&dist[..hdist]).copied().collect();
+        let rle = rle_lengths(&all);
+        let mut cl_freq = [0u32; 19];
+        for &(sym, _) in &rle {
+            cl_freq[sym as usize] += 1;
+        }
+        ensure_two(&mut cl_freq);
+        let cl = huffman_lengths(&cl_freq, 7);
+        let hclen = 4.max(CL_ORDER.iter().rposition(|&s| cl[s] != 0).map_or(0, |p| p + 1));
+        DynamicCodes { lit, dist, hlit, hdist, cl, hclen, rle }
+    }
+
+    fn header_bits(&self) -> usize {
+        let mut bits = 5 + 5 + 4 + 3 * self.hclen;
+        for &(sym, _) in &self.rle {
+            bits += self.cl[sym as usize] as usize + cl_extra(sym) as usize;
+        }
+        bits
+    }
+
+    fn write_header(&self, w: &mut BitWriter) {
+        w.put((self.hlit - 257) as u32, 5);
+        w.put((self.hdist - 1) as u32, 5);
+        w.put((self.hclen - 4) as u32, 4);
+        for &slot in &CL_ORDER[..self.hclen] {
+            w.put(self.cl[slot] as u32, 3);
+        }
+        let codes = canonical_codes(&self.cl);
+        for &(sym, extra) in &self.rle {
+            w.put(codes[sym as usize], self.cl[sym as usize] as u32);
+            w.put(extra as u32, cl_extra(sym));
+        }
+    }
+}
+
+fn cl_extra(sym: u8) -> u32 {
+    match sym {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// Bits for `tokens` (plus end-of-block) under the given code lengths,
+/// extra bits excluded.
+fn data_bits(lit_freq: &[u32], dist_freq: &[u32], lit: &[u8], dist: &[u8]) -> usize {
+    let l: usize = lit_freq.iter().zip(lit).map(|(&f, &n)| f as usize * n as usize).sum();
+    let d: usize = dist_freq.iter().zip(dist).map(|(&f, &n)| f as usize * n as usize).sum();
+    l + d
+}
+
+fn write_tokens(w: &mut BitWriter, tokens: &[Token], lit: &[u8], dist: &[u8]) {
+    let lit_codes = canonical_codes(lit);
+    let dist_codes = canonical_codes(dist);
+    for &t in tokens {
+        match t {
+            Token::Literal(b) => w.put(lit_codes[b as usize], lit[b as usize] as u32),
+            Token::Copy { len, dist: d } => {
+                let (s, extra) = len_symbol(len as usize);
+                w.put(lit_codes[257 + s], lit[257 + s] as u32);
+                w.put(extra, LEN_EXTRA[s] as u32);
+                let (ds, dextra) = dist_symbol(d as usize);
+                w.put(dist_codes[ds], dist[ds] as u32);
+                w.put(dextra, DIST_EXTRA[ds] as u32);
+            }
+        }
+    }
+    w.put(lit_codes[END_OF_BLOCK], lit[END_OF_BLOCK] as u32);
+}
+
+fn write_stored(w: &mut BitWriter, data: &[u8], last: bool) {
+    let mut chunks = data.chunks(u16::MAX as usize).peekable();
+    if chunks.peek().is_none() {
+        w.put(last as u32, 1);
+        w.put(0, 2);
+        w.align();
+        w.out.extend_from_slice(&[0, 0, 0xff, 0xff]);
+        return;
+    }
+    while let Some(chunk) = chunks.next() {
+        w.put((last && chunks.peek().is_none()) as u32, 1);
+        w.put(0, 2);
+        w.align();
+        let len = chunk.len() as u16;
+        w.out.extend_from_slice(&len.to_le_bytes());
+        w.out.extend_from_slice(&(!len).to_le_bytes());
+        w.out.extend_from_slice(chunk);
+    }
+}
+
+/// Encode one block of `tokens` (covering the raw bytes `raw`) as
+/// whichever of stored, fixed or dynamic Huffman is smallest.
+fn write_block(w: &mut BitWriter, tokens: &[Token], raw: &[u8], last: bool) {
+    let mut lit_freq = [0u32; 286];
+    let mut dist_freq = [0u32; 30];
+    let mut extra = 0usize;
+    for &t in tokens {
+        match t {
+            Token::Literal(b) => lit_freq[b as usize] += 1,
+            Token::Copy { len, dist } => {
+                let (s, _) = len_symbol(len as usize);
+                let (ds, _) = dist_symbol(dist as usize);
+                lit_freq[257 + s] += 1;
+                dist_freq[ds] += 1;
+                extra += LEN_EXTRA[s] as usize + DIST_EXTRA[ds] as usize;
+            }
+        }
+    }
+    lit_freq[END_OF_BLOCK] = 1;
+    let (fixed_lit, fixed_dist) = fixed_lengths();
+    let fixed = 3 + extra + data_bits(&lit_freq, &dist_freq, &fixed_lit, &fixed_dist);
+    let dynamic = DynamicCodes::new(&lit_freq, &dist_freq);
+    let dynamic_bits =
+        3 + extra + dynamic.header_bits() + data_bits(&lit_freq, &dist_freq, &dynamic.lit, &dynamic.dist);
+    let stored = (raw.len() + 5 * raw.len().div_ceil(u16::MAX as usize).max(1)) * 8 + 7;
+    if stored < fixed.min(dynamic_bits) {
+        write_stored(w, raw, last);
+    } else if fixed <= dynamic_bits {
+        w.put(last as u32, 1);
+        w.put(1, 2);
+        write_tokens(w, tokens, &fixed_lit, &fixed_dist);
+    } else {
+        w.put(last as u32, 1);
+        w.put(2, 2);
+        dynamic.write_header(w);
+        write_tokens(w, tokens, &dynamic.lit, &dynamic.dist);
+    }
+}
+
+/// Tokens per block: small enough that block-level code tables adapt
+/// to changing data, large enough that their headers stay cheap.
+const BLOCK_TOKENS: usize = 16 * 1024;
+
+/// Compress `data` into whole raw DEFLATE blocks; matches may reach
+/// back into `history`. The output ends on a byte boundary: with
+/// `last` the final block is marked final, otherwise an empty stored
+/// block (a sync flush) follows, so a decoder can emit everything.
+fn deflate_chunk(w: &mut BitWriter, history: &[u8], data: &[u8], level: u32, last: bool) {
+    if level == 0 {
+        write_stored(w, data, last);
+        w.align();
+        return;
+    }
+    if data.is_empty() {
+        if last {
+            // An empty final fixed block: just the end-of-block code.
+            w.put(1, 1);
+            w.put(1, 2);
+            w.put(0, 7);
+        } else {
+            write_stored(w, &[], false);
+        }
+        w.align();
+        return;
+    }
+    let history = &history[history.len().saturating_sub(WINDOW)..];
+    let mut buf = Vec::with_capacity(history.len() + data.len());
+    buf.extend_from_slice(history);
+    buf.extend_from_slice(data);
+    let tokens = tokenize(&buf, history.len(), level);
+    let mut raw_start = 0;
+    let blocks = tokens.len().div_ceil(BLOCK_TOKENS);
+    for (i, block) in tokens.chunks(BLOCK_TOKENS).enumerate() {
+        let covered: usize = block
+            .iter()
+            .map(|t| match t {
+                Token::Literal(_) => 1,
+                Token::Copy { len, .. } => *len as usize,
+            })
+            .sum();
+        let raw = &data[raw_start..raw_start + covered];
+        raw_start += covered;
+        write_block(w, block, raw, last && i + 1 == blocks);
+    }
+    if !last {
+        write_stored(w, &[], false);
+    }
+    w.align();
+}
+
+/// A resumable encoder. Each [`Deflater::write`] returns compressed
+/// bytes for everything written so far (the chunk ends in a sync
+/// flush); [`Deflater::finish`] ends the stream.
+#[derive(Clone, Debug)]
+pub struct Deflater {
+    pub format: Format,
+    /// 0 (store only) to 9 (smallest output).
+    pub level: u32,
+    /// The last [`WINDOW`] bytes of input, for matches across writes.
+    pub history: Vec<u8>,
+    pub check: u32,
+    pub size: u32,
+    /// Whether the header has been emitted.
+    pub started: bool,
+}
+
+impl Deflater {
+    pub fn new(format: Format, level: u32) -> Deflater {
+        Deflater {
+            format,
+            level: level.min(9),
+            history: Vec::new(),
+            check: format.check_init(),
+            size: 0,
+            started: false,
+        }
+    }
+
+    fn header(&mut self, w: &mut BitWriter) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        match self.format {
+            Format::Raw => {}
+            Format::Zlib => {
+                let cmf: u16 = 0x78;
+                let lvl: u16 = match self.level {
+                    0 | 1 => 0,
+                    2..=5 => 1,
+                    6 => 2,
+                    _ => 3,
+                };
+                let mut flg = lvl << 6;
+                flg += 31 - (cmf * 256 + flg) % 31;
+                w.out.extend_from_slice(&[cmf as u8, flg as u8]);
+            }
+            Format::Gzip => {
+                let xfl = match self.level {
+                    9 => 2,
+                    1 => 4,
+                    _ => 0,
+                };
+                // No mtime, unknown OS.
+                w.out.extend_from_slice(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, xfl, 255]);
+            }
+        }
+    }
+
+    fn consume(&mut self, data: &[u8]) {
+        self.check = self.format.update_check(self.check, data);
+        self.size = self.size.wrapping_add(data.len() as u32);
+        self.history.extend_from_slice(data);
+        if self.history.len() > WINDOW {
+            self.history.drain(..self.history.len() - WINDOW);
+        }
+    }
+
+    /// Compress `chunk`, returning the bytes that decode to it.
+    pub fn write(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut w = BitWriter::new();
+        self.header(&mut w);
+        if !chunk.is_empty() {
+            deflate_chunk(&mut w, &self.history, chunk, self.level, false);
+            self.consume(chunk);
+        }
+        w.out
+    }
+
+    /// Compress `chunk` as the end of the stream and append the
+    /// trailer.
+    pub fn finish(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut w = BitWriter::new();
+        self.header(&mut w);
+        deflate_chunk(&mut w, &self.history, chunk, self.level, true);
+        self.consume(chunk);
+        match self.format {
+            Format::Raw => {}
+            Format::Zlib => w.out.extend_from_slice(&self.check.to_be_bytes()),
+            Format::Gzip => {
+                w.out.extend_from_slice(&self.check.to_le_bytes());
+                w.out.extend_from_slice(&self.size.to_le_bytes());
+            }
+        }
+        w.out
+    }
+}
+
+/// Compress `data` as one complete stream.
+pub fn compress(data: &[u8], format: Format, level: u32) -> Vec<u8> {
+    Deflater::new(format, level).finish(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let mut v = Vec::new();
+        for i in 0..5000u32 {
+            v.extend_from_slice(format!("line {} of {}\n", i % 97, i / 13).as_bytes());
+        }
+        v
+    }
+
+    /// A deterministic pseudo-random byte stream (incompressible).
+    fn noise(n: usize) -> Vec<u8> {
+        let mut x: u32 = 0x1234_5678;
+        (0..n)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(1, b"Wikipedia"), 0x11e6_0398);
+        let split = crc32(crc32(0, b"1234"), b"56789");
+        assert_eq!(split, 0xcbf4_3926);
+    }
+
+    #[test]
+    fn round_trips_every_format_and_level() {
+        let inputs = [Vec::new(), b"a".to_vec(), sample(), noise(70_000)];
+        for format in [Format::Raw, Format::Zlib, Format::Gzip] {
+            for level in [0, 1, 6, 9] {
+                for data in &inputs {
+                    let packed = compress(data, format, level);
+                    assert_eq!(&decompress(&packed, format, usize::MAX).unwrap(), data, "{format:?} {level}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compresses_repetitive_input() {
+        let data = sample();
+        let packed = compress(&data, Format::Gzip, 6);
+        assert!(packed.len() * 5 < data.len(), "{} -> {}", data.len(), packed.len());
+    }
+
+    #[test]
+    fn decodes_known_streams() {
+        // `printf hello | gzip -n`
+        let gz = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x07, 0x00, 0x86, 0xa6, 0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(decompress(&gz, Format::Gzip, usize::MAX).unwrap(), b"hello");
+        // zlib.compress(b"hello")
+        let z = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15];
+        assert_eq!(decompress(&z, Format::Zlib, usize::MAX).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn gzip_members_concatenate() {
+        let mut both = compress(b"one ", Format::Gzip, 6);
+        both.extend(compress(b"two", Format::Gzip, 1));
+        assert_eq!(decompress(&both, Format::Gzip, usize::MAX).unwrap(), b"one two");
+    }
+
+    #[test]
+    fn streaming_matches_one_shot_at_any_split() {
+        let data = sample();
+        for format in [Format::Raw, Format::Zlib, Format::Gzip] {
+            let mut enc = Deflater::new(format, 6);
+            let mut packed = Vec::new();
+            for chunk in data.chunks(7000) {
+                packed.extend(enc.write(chunk));
+            }
+            packed.extend(enc.finish(&[]));
+            for size in [1, 3, 100, 4096] {
+                let mut dec = Inflater::new(format);
+                let mut out = Vec::new();
+                for chunk in packed.chunks(size) {
+                    out.extend(dec.write(chunk, usize::MAX).unwrap());
+                }
+                dec.finish().unwrap();
+                assert_eq!(out, data, "{format:?} split {size}");
+            }
+        }
+    }
+
+    #[test]
+    fn each_write_is_decodable_immediately() {
+        let mut enc = Deflater::new(Format::Zlib, 6);
+        let mut dec = Inflater::new(Format::Zlib);
+        for word in ["alpha ", "beta ", "alpha beta "] {
+            let out = dec.write(&enc.write(word.as_bytes()), usize::MAX).unwrap();
+            assert_eq!(out, word.as_bytes());
+        }
+    }
+
+    #[test]
+    fn output_limit_stops_a_bomb_early() {
+        let packed = compress(&vec![0u8; 1 << 20], Format::Gzip, 9);
+        assert!(packed.len() < 4096);
+        let e = decompress(&packed, Format::Gzip, 64 * 1024).unwrap_err();
+        assert!(e.too_large);
+        assert_eq!(decompress(&packed, Format::Gzip, 1 << 20).unwrap().len(), 1 << 20);
+        let mut dec = Inflater::new(Format::Gzip);
+        assert!(dec.write(&packed, 1000).unwrap_err().too_large);
+    }
+
+    #[test]
+    fn a_bad_resume_point_is_an_error_not_a_panic() {
+        let packed = compress(&noise(300), Format::Raw, 0);
+        for resume in [0, 7, 9999, usize::MAX] {
+            let mut out = Vec::new();
+            let _ = inflate_block(&packed[..100], 0, Some(resume), &mut out, usize::MAX);
+        }
+        let mut out = Vec::new();
+        let e = inflate_block(&packed, 0, Some(8), &mut out, usize::MAX).unwrap_err();
+        assert_eq!(e.message, "invalid resume point");
+    }
+
+    #[test]
+    fn rejects_corrupt_input_with_offsets() {
+        let mut packed = compress(&sample(), Format::Gzip, 6);
+        let e = decompress(&packed[..packed.len() - 3], Format::Gzip, usize::MAX).unwrap_err();
+        assert_eq!(e.message, "unexpected end of compressed data");
+        let n = packed.len();
+        packed[n - 8] ^= 1;
+        let e = decompress(&packed, Format::Gzip, usize::MAX).unwrap_err();
+        assert_eq!((e.offset, e.message.as_str()), (n - 8, "CRC-32 checksum mismatch"));
+        let e = decompress(b"\x1f\x8c", Format::Gzip, usize::MAX).unwrap_err();
+        assert_eq!(e.offset, 1);
+        let e = decompress(&[0x07], Format::Raw, usize::MAX).unwrap_err();
+        assert_eq!(e.message, "invalid block type");
+        let e = decompress(b"xyz", Format::Zlib, usize::MAX).unwrap_err();
+        assert_eq!((e.offset, e.message.as_str()), (1, "not zlib data (header check failed)"));
+    }
+
+    #[test]
+    fn length_limited_codes_stay_complete() {
+        // Fibonacci frequencies force a depth far beyond 15 bits.
+        let mut freq = vec![0u32; 30];
+        let (mut a, mut b) = (1u32, 1u32);
+        for f in freq.iter_mut() {
+            *f = a;
+            (a, b) = (b, a.saturating_add(b));
+        }
+        let lengths = huffman_lengths(&freq, 15);
+        assert!(lengths.iter().all(|&l| (1..=15).contains(&l)));
+        let kraft: f64 = lengths.iter().map(|&l| 0.5f64.powi(l as i32)).sum();
+        assert!((kraft - 1.0).abs() < 1e-12);
+        assert_eq!(Huffman::new(&lengths).left, 0);
+    }
+}
@@ -211,6 +211,9 @@ pub enum RuntimeErrorKind {
     /// A `Map`/`Set` key (or `m[k]` index) had an un-hashable type.
     InvalidKeyType(String),
     ImmutableTarget(String),
+    /// A mutation of a `Shared.of` value, which is frozen. Carries a
+    /// description of the attempted operation.
+    Immutable(String),
     ImportFailed(String, String),
     /// Integer arithmetic (`+ - *`, unary `-`) overflowed `i64`.
     Overflow,
@@ -227,6 +230,10 @@ pub enum RuntimeErrorKind {
     NotSendable(String),
     /// A `send` was attempted on a closed channel. v0.14.
     ChannelClosed,
+    /// A broadcast subscription fell behind its lag limit and lost
+    /// this many messages; the receive after it carries on from the
+    /// oldest message the subscription kept.
+    Lagged(u64),
     /// `cancel(handle)` requested cancellation of a parked green thread:
     /// raised at the coroutine's park call site when it next resumes,
     /// unwinding its body through the normal error path. Catchable
@@ -262,6 +269,7 @@ impl RuntimeErrorKind {
             RuntimeErrorKind::InvalidIndexType(_) => "invalid_index_type",
             RuntimeErrorKind::InvalidKeyType(_) => "invalid_key_type",
             RuntimeErrorKind::ImmutableTarget(_) => "immutable_target",
+            RuntimeErrorKind::Immutable(_) => "immutable",
             RuntimeErrorKind::ImportFailed(..) => "import_failed",
             RuntimeErrorKind::Overflow => "overflow",
             RuntimeErrorKind::StackOverflow => "stack_overflow",
@@ -269,6 +277,7 @@ impl RuntimeErrorKind {
             RuntimeErrorKind::NoMatch => "no_match",
             RuntimeErrorKind::NotSendable(_) => "not_sendable",
             RuntimeErrorKind::ChannelClosed => "channel_closed",
+            RuntimeErrorKind::Lagged(_) => "lagged",
             RuntimeErrorKind::Cancelled => "cancelled",
             RuntimeErrorKind::Raised(_) => "raised",
             // Never reified — absorbed by the host driver before any
@@ -294,6 +303,9 @@ impl fmt::Display for RuntimeError {
                 f, "invalid key type: {t} (Map/Set keys must be null, bool, int, or string)"
             ),
             RuntimeErrorKind::ImmutableTarget(t) => write!(f, "{t} is immutable"),
+            RuntimeErrorKind::Immutable(t) => write!(
+                f, "cannot {t}: the value is shared and immutable"
+            ),
             RuntimeErrorKind::ImportFailed(path, msg) => write!(
                 f, "import of {path:?} failed: {msg}"
             ),
@@ -307,6 +319,9 @@ impl fmt::Display for RuntimeError {
             RuntimeErrorKind::ChannelClosed => {
                 f.write_str("send on a closed channel")
             }
+            RuntimeErrorKind::Lagged(n) => write!(
+                f, "subscription lagged behind and lost {n} message(s)"
+            ),
             RuntimeErrorKind::Cancelled => {
                 f.write_str("green thread cancelled")
             }
@@ -31,7 +31,7 @@ use std::sync::Arc;
 
 use indexmap::{IndexMap, IndexSet};
 
-use crate::vm::local_channel::LocalChannel;
+use crate::vm::local_channel::{LocalChannel, LocalFlavour};
 use crate::vm::scheduler::{Deferred, GeneratorState, GreenHandle};
 use crate::vm::value::{Closure, IterState, MapKey, Upvalue, Value};
 
@@ -299,7 +299,9 @@ pub struct Heap {
 }
 
 impl Heap {
-    fn new() -> Self {
+    /// An empty heap. Besides the thread-local one every thread starts
+    /// with, the actor pool builds one per actor (see [`swap_heap`]).
+    pub(crate) fn new() -> Self {
         Heap {
             arrays: Arena::new(),
             objects: Arena::new(),
@@ -354,6 +356,16 @@ thread_local! {
     static HEAP: RefCell<Heap> = RefCell::new(Heap::new());
 }
 
+/// Exchange this thread's managed heap with `other`. The actor pool
+/// ([`crate::vm::actor_pool`]) runs many actors on one OS thread, each
+/// with a heap of its own: it swaps an actor's heap in before running
+/// it and back out when the actor parks, so every `alloc_*` and
+/// `GcRef::borrow` in between reaches that actor's heap. A `GcRef` must
+/// never be used across a swap — it indexes the heap it came from.
+pub(crate) fn swap_heap(other: &mut Heap) {
+    HEAP.with(|h| std::mem::swap(&mut *h.borrow_mut(), other));
+}
+
 /// Current heap counters.
 pub fn stats() -> HeapStats {
     HEAP.with(|h| h.borrow().stats())
@@ -584,12 +596,16 @@ impl Trace for Value {
             | Value::Range(_)
             | Value::NativeFn(_)
             | Value::BigInt(_)
-            // A channel / task / socket / file is `Arc`-backed, no
-            // `GcRef` — leaves.
+            // A channel / task / socket / file / store / shared / atomic /
+            // shared map value is `Arc`-backed, no `GcRef` — leaves.
             | Value::Channel(_)
             | Value::Task(_)
             | Value::Socket(_)
-            | Value::File(_) => {}
+            | Value::File(_)
+            | Value::Store(_)
+            | Value::Shared(_)
+            | Value::Atomic(_)
+            | Value::SharedMap(_) => {}
         }
     }
 }
@@ -639,12 +655,25 @@ impl Trace for Deferred {
 }
 
 /// An intra-actor channel roots every buffered, not-yet-received
-/// message.
+/// message; a broadcast or watch hub also roots its subscriptions and
+/// current value, and a subscription its hub.
 impl Trace for LocalChannel {
     fn trace(&self, m: &mut Marker) {
         for v in &self.queue {
             v.trace(m);
         }
+        match &self.flavour {
+            LocalFlavour::Hub { subscribers, current, .. } => {
+                for sub in subscribers {
+                    m.mark_local_channel(*sub);
+                }
+                if let Some(v) = current {
+                    v.trace(m);
+                }
+            }
+            LocalFlavour::Subscriber { hub, .. } => m.mark_local_channel(*hub),
+            LocalFlavour::Queue | LocalFlavour::Oneshot => {}
+        }
     }
 }
 
@@ -676,7 +705,9 @@ impl Trace for IterState {
             IterState::Map { map, .. } => m.mark_map(*map),
             IterState::Set { set, .. } => m.mark_set(*set),
             IterState::Bytes { bytes, .. } => m.mark_bytes(*bytes),
-            IterState::Range { .. } | IterState::String { .. } => {}
+            IterState::Range { .. }
+            | IterState::String { .. }
+            | IterState::Shared { .. } => {}
         }
     }
 }
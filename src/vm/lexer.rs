@@ -510,6 +510,7 @@ impl<'src> Lexer<'src> {
             "match" => Token::Match,
             "spawn" => Token::Spawn,
             "select" => Token::Select,
+            "receive" => Token::Receive,
             "parallel" => Token::Parallel,
             "go" => Token::Go,
             "yield" => Token::Yield,
@@ -10,10 +10,18 @@
 //!
 //! `send`/`close` are non-blocking; a `recv` on an empty channel
 //! cooperatively `yield`s the coroutine and retries — see
-//! `stdlib/LocalChannel.tg`.
+//! `stdlib/LocalChannel.tg`. A `select` arm parks instead, and the VM
+//! wakes it from the next `send` or `close`.
+//!
+//! The oneshot, broadcast and watch flavours of a cross-actor channel
+//! exist here too, with the same rules (see [`crate::vm::channel`]).
+//! The one difference is that a local hub holds its subscriptions
+//! strongly — the heap has no weak references — so a subscription
+//! lives as long as its hub unless it is closed.
 
 use std::collections::VecDeque;
 
+use crate::vm::gc::{GcRef, LocalChannelKind};
 use crate::vm::value::Value;
 
 /// A buffered, unbounded intra-actor channel. Messages move by value
@@ -24,10 +32,114 @@ pub struct LocalChannel {
     /// Once closed, `send` raises and `recv` drains then reports
     /// `${closed: true}`.
     pub(crate) closed: bool,
+    pub(crate) flavour: LocalFlavour,
+}
+
+/// Which kind of channel a [`LocalChannel`] is.
+pub(crate) enum LocalFlavour {
+    /// A queue: each message goes to exactly one receiver.
+    Queue,
+    /// A queue for one message: the first `send` closes it.
+    Oneshot,
+    /// The sending side of a broadcast or watch channel. Holds no
+    /// messages itself; each `send` is copied into every subscription.
+    Hub {
+        subscribers: Vec<GcRef<LocalChannelKind>>,
+        /// The lag limit a subscription gets when none is given.
+        lag: usize,
+        /// For a watch channel, its current value; `None` for a
+        /// broadcast.
+        current: Option<Value>,
+    },
+    /// A subscription to `hub`, keeping at most `limit` messages: a
+    /// new one pushes out the oldest, which a broadcast subscription
+    /// counts in `missed` until the next receive reports it.
+    Subscriber {
+        hub: GcRef<LocalChannelKind>,
+        limit: usize,
+        watch: bool,
+        missed: u64,
+    },
 }
 
 impl LocalChannel {
     pub fn new() -> Self {
-        LocalChannel { queue: VecDeque::new(), closed: false }
+        Self::with_flavour(LocalFlavour::Queue)
+    }
+
+    /// A oneshot channel: one message, then closed.
+    pub fn oneshot() -> Self {
+        Self::with_flavour(LocalFlavour::Oneshot)
+    }
+
+    /// A broadcast channel whose subscriptions keep up to `lag`
+    /// messages unless they ask for another limit.
+    pub fn broadcast(lag: usize) -> Self {
+        Self::with_flavour(LocalFlavour::Hub { subscribers: Vec::new(), lag, current: None })
+    }
+
+    /// A watch channel holding `initial`.
+    pub fn watch(initial: Value) -> Self {
+        Self::with_flavour(LocalFlavour::Hub {
+            subscribers: Vec::new(),
+            lag: 1,
+            current: Some(initial),
+        })
+    }
+
+    /// A subscription to `hub`; a watch subscription keeps only the
+    /// newest value.
+    pub(crate) fn subscription(hub: GcRef<LocalChannelKind>, limit: usize, watch: bool) -> Self {
+        Self::with_flavour(LocalFlavour::Subscriber {
+            hub,
+            limit: if watch { 1 } else { limit },
+            watch,
+            missed: 0,
+        })
+    }
+
+    fn with_flavour(flavour: LocalFlavour) -> Self {
+        LocalChannel { queue: VecDeque::new(), closed: false, flavour }
+    }
+
+    /// Is this the sending side of a broadcast or watch channel?
+    pub(crate) fn is_hub(&self) -> bool {
+        matches!(self.flavour, LocalFlavour::Hub { .. })
+    }
+
+    /// A hub's subscriptions; none for any other flavour.
+    pub(crate) fn subscribers(&self) -> &[GcRef<LocalChannelKind>] {
+        match &self.flavour {
+            LocalFlavour::Hub { subscribers, .. } => subscribers,
+            _ => &[],
+        }
+    }
+
+    /// The messages a broadcast subscription dropped since its last
+    /// receive, if any, resetting the count — reported as `lagged`.
+    pub(crate) fn take_missed(&mut self) -> Option<u64> {
+        match &mut self.flavour {
+            LocalFlavour::Subscriber { missed, .. } if *missed > 0 => {
+                Some(std::mem::take(missed))
+            }
+            _ => None,
+        }
+    }
+
+    /// Hand `msg` to this subscription, pushing out its oldest message
+    /// if it is at its limit. A no-op once closed.
+    pub(crate) fn offer(&mut self, msg: Value) {
+        if self.closed {
+            return;
+        }
+        if let LocalFlavour::Subscriber { limit, watch, missed, .. } = &mut self.flavour {
+            while self.queue.len() >= *limit {
+                self.queue.pop_front();
+                if !*watch {
+                    *missed += 1;
+                }
+            }
+        }
+        self.queue.push_back(msg);
     }
 }
@@ -0,0 +1,429 @@
+//! In-memory loopback network — the `Net` backend in simulation mode.
+//!
+//! Under [`crate::vm::sim`], `Net.listen` binds a port in a process-wide
+//! table instead of the OS, and `Net.connect` to that port — whatever
+//! the host name — pairs two in-memory [`Pipe`]s, one per direction,
+//! and queues the server end on the listener for `accept`. Nothing
+//! touches a real socket, so a test of a client and server finishes the
+//! same way on every run of a seed.
+//!
+//! A pipe never blocks its writer: a write appends to the buffer and
+//! returns at once. A read or accept that finds nothing is *parked*
+//! here, and every change that could let it finish — a write, a close,
+//! a new connection — re-drives the parked ops with the reactor's own
+//! state machine ([`reactor::advance`]), so the socket ops behave as
+//! they do on the reactor. A finished op hands its result to the
+//! callback it was submitted with: a mailbox post for a parked
+//! coroutine, or a channel send for the inline executor.
+//!
+//! `Net.set_timeout` runs on virtual time for a coroutine of a VM in
+//! the simulation: its op times out once the virtual clock passes its
+//! deadline, which the scheduler jumps to when nothing else can happen
+//! (see [`next_deadline`]). Any other op — a blocking call, or one from a
+//! spawned actor — can only be finished by another actor, so it times
+//! out in real time.
+//!
+//! Only TCP-style streams are simulated. UDP, TLS and Unix-domain
+//! sockets raise in simulation mode.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::vm::native_modules::net;
+use crate::vm::offload::{self, OffloadErr, OffloadResult};
+use crate::vm::reactor::{self, Advance};
+use crate::vm::sim;
+use crate::vm::socket::{NetError, ReactorOp, SocketHandle, SocketInner, SocketOp};
+
+/// The first port handed out for `listen(host, 0)` and for the client
+/// end of a connection — the start of the IANA dynamic range.
+const FIRST_EPHEMERAL: u16 = 49152;
+
+/// One direction of a connection: bytes written by one end, not yet
+/// read by the other.
+struct Pipe {
+    state: Mutex<PipeState>,
+}
+
+#[derive(Default)]
+struct PipeState {
+    data: VecDeque<u8>,
+    /// The writing end closed: once `data` drains, reads see
+    /// end-of-stream.
+    writer_closed: bool,
+    /// The reading end closed: writes fail with a broken pipe.
+    reader_closed: bool,
+}
+
+impl Pipe {
+    fn new() -> Arc<Self> {
+        Arc::new(Pipe { state: Mutex::new(PipeState::default()) })
+    }
+}
+
+/// One end of a loopback connection.
+pub(crate) struct Stream {
+    rx: Arc<Pipe>,
+    tx: Arc<Pipe>,
+    local: SocketAddr,
+    peer: SocketAddr,
+}
+
+impl Stream {
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    pub(crate) fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Read what the peer has written. `Ok(0)` is end-of-stream;
+    /// `WouldBlock` means nothing is buffered yet.
+    pub(crate) fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut st = self.rx.state.lock().unwrap();
+        if st.data.is_empty() {
+            return if st.writer_closed {
+                Ok(0)
+            } else {
+                Err(io::ErrorKind::WouldBlock.into())
+            };
+        }
+        let n = buf.len().min(st.data.len());
+        for (slot, byte) in buf.iter_mut().zip(st.data.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+
+    /// Append `buf` for the peer to read. Never blocks.
+    pub(crate) fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut st = self.tx.state.lock().unwrap();
+        if st.reader_closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        st.data.extend(buf);
+        Ok(buf.len())
+    }
+
+    /// Close both directions: the peer reads end-of-stream once it has
+    /// drained what was sent, and its writes fail.
+    pub(crate) fn close(&self) {
+        self.tx.state.lock().unwrap().writer_closed = true;
+        let mut rx = self.rx.state.lock().unwrap();
+        rx.reader_closed = true;
+        rx.data.clear();
+    }
+}
+
+impl Drop for Stream {
+    /// A dropped end closes like an explicit `close`, as an OS socket
+    /// does, so a peer reading to end-of-stream is not left hanging.
+    fn drop(&mut self) {
+        self.close();
+        // A drop can happen inside a re-drive (a finished op releasing
+        // its socket); that pass sees the close itself.
+        if let Ok(mut parked) = PARKED.try_lock() {
+            redrive(&mut parked);
+        }
+    }
+}
+
+/// A listening loopback socket: connections wait here for `accept`.
+pub(crate) struct Listener {
+    addr: SocketAddr,
+    backlog: Mutex<VecDeque<SocketHandle>>,
+}
+
+impl Listener {
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Take the next queued connection; `WouldBlock` when none is.
+    pub(crate) fn accept(&self) -> Result<SocketHandle, NetError> {
+        self.backlog
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| NetError::Io(io::ErrorKind::WouldBlock.into()))
+    }
+
+    /// Drop every connection that was never accepted, which its client
+    /// sees as end-of-stream. The port is free again once the socket is
+    /// marked closed: the table skips closed listeners.
+    pub(crate) fn close(&self) {
+        let pending: Vec<_> = self.backlog.lock().unwrap().drain(..).collect();
+        for conn in pending {
+            conn.close();
+        }
+    }
+}
+
+/// The bound ports, and the next ephemeral one to try.
+struct Network {
+    ports: HashMap<u16, Weak<SocketInner>>,
+    next_port: u16,
+}
+
+impl Network {
+    /// A free ephemeral port, counting up from [`FIRST_EPHEMERAL`] so
+    /// the ports a run sees are the same every time.
+    fn ephemeral(&mut self) -> u16 {
+        loop {
+            let port = self.next_port;
+            self.next_port = self.next_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL);
+            if !self.in_use(port) {
+                return port;
+            }
+        }
+    }
+
+    fn in_use(&self, port: u16) -> bool {
+        self.ports
+            .get(&port)
+            .and_then(Weak::upgrade)
+            .is_some_and(|s| !s.is_closed())
+    }
+}
+
+/// The process-wide port table.
+fn network() -> &'static Mutex<Network> {
+    static NETWORK: OnceLock<Mutex<Network>> = OnceLock::new();
+    NETWORK.get_or_init(|| {
+        Mutex::new(Network { ports: HashMap::new(), next_port: FIRST_EPHEMERAL })
+    })
+}
+
+/// A finished op's result goes here.
+type Done = Box<dyn FnOnce(OffloadResult) + Send>;
+
+/// An op waiting for a write, close or connection to let it finish.
+struct Parked {
+    id: u64,
+    rop: ReactorOp,
+    done: Done,
+    /// The virtual time, in seconds, it fails with `timeout` at, from the
+    /// socket's `Net.set_timeout` when it was submitted.
+    deadline: Option<f64>,
+}
+
+/// The ops waiting to finish.
+static PARKED: Mutex<Vec<Parked>> = Mutex::new(Vec::new());
+
+/// The id the next parked op gets.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Forget every bound port and parked op — a fresh simulation.
+pub(crate) fn reset() {
+    let mut net = network().lock().unwrap();
+    net.ports.clear();
+    net.next_port = FIRST_EPHEMERAL;
+    drop(net);
+    let stale = std::mem::take(&mut *PARKED.lock().unwrap());
+    drop(stale);
+}
+
+/// Bind a listener on `port` (`0` picks a free one); `make` wraps it in
+/// a socket, which the port table then refers to.
+pub(crate) fn listen(
+    port: u16,
+    make: impl FnOnce(Listener) -> SocketHandle,
+) -> Result<SocketHandle, NetError> {
+    let mut net = network().lock().unwrap();
+    let port = match port {
+        0 => net.ephemeral(),
+        p if net.in_use(p) => {
+            return Err(NetError::Io(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("port {p} is already bound"),
+            )));
+        }
+        p => p,
+    };
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let sock = make(Listener { addr, backlog: Mutex::new(VecDeque::new()) });
+    net.ports.insert(port, Arc::downgrade(&sock));
+    Ok(sock)
+}
+
+/// Connect to the listener on `port`: queue the server end on it and
+/// return the client end, both wrapped by `make`.
+pub(crate) fn connect(
+    port: u16,
+    make: impl Fn(Stream) -> SocketHandle,
+) -> Result<SocketHandle, NetError> {
+    let mut net = network().lock().unwrap();
+    let listener = net
+        .ports
+        .get(&port)
+        .and_then(Weak::upgrade)
+        .filter(|s| !s.is_closed())
+        .ok_or_else(|| {
+            NetError::Io(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("nothing is listening on port {port}"),
+            ))
+        })?;
+    let server_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, net.ephemeral()));
+    drop(net);
+    let (up, down) = (Pipe::new(), Pipe::new());
+    let client = make(Stream {
+        rx: down.clone(),
+        tx: up.clone(),
+        local: client_addr,
+        peer: server_addr,
+    });
+    let server = make(Stream { rx: up, tx: down, local: server_addr, peer: client_addr });
+    let l = listener.loopback_listener().expect("the port table holds listeners");
+    l.backlog.lock().unwrap().push_back(server);
+    kick();
+    Ok(client)
+}
+
+/// Run `rop` against its loopback socket. An op that can finish now
+/// calls `done` before this returns; one that cannot is parked until a
+/// change on another socket lets it, or until the socket's timeout —
+/// on the virtual clock if `sim_clock`, else in real time. Either way,
+/// whatever the op wrote may finish ops parked on the other end.
+pub(crate) fn submit(rop: ReactorOp, done: Done, sim_clock: bool) {
+    let label = rop.label;
+    let timeout = rop.socket.timeout().filter(|_| !matches!(rop.op, SocketOp::Readable));
+    let deadline = timeout
+        .filter(|_| sim_clock)
+        .map(|t| sim::now() + t.as_secs_f64());
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut parked = PARKED.lock().unwrap();
+    parked.push(Parked { id, rop, done, deadline });
+    redrive(&mut parked);
+    let waiting = parked.iter().any(|p| p.id == id);
+    drop(parked);
+    if let (Some(t), false, true) = (timeout, sim_clock, waiting) {
+        offload::run_after(t, Box::new(move || {
+            // Lost the race if it finished meanwhile.
+            if let Some(p) = unpark(id) {
+                (p.done)(Err(timed_out(label)));
+            }
+        }));
+    }
+}
+
+/// Run `rop` on the calling thread for the inline executor: another
+/// actor has to let it finish, so it waits in real time.
+pub(crate) fn run_inline(rop: ReactorOp) -> OffloadResult {
+    let label = rop.label;
+    let (tx, rx) = mpsc::channel();
+    let done = Box::new(move |result| {
+        let _ = tx.send(result);
+    });
+    submit(rop, done, false);
+    // Disconnected: dropped unfinished by a `reset`.
+    rx.recv()
+        .unwrap_or_else(|_| Err(net::offload_err(label, NetError::Closed)))
+}
+
+/// Take parked op `id` out unfinished, if it is still parked.
+fn unpark(id: u64) -> Option<Parked> {
+    let mut parked = PARKED.lock().unwrap();
+    let i = parked.iter().position(|p| p.id == id)?;
+    Some(parked.remove(i))
+}
+
+/// The error an op that ran past its socket's timeout fails with.
+fn timed_out(label: &'static str) -> OffloadErr {
+    let e = io::Error::new(io::ErrorKind::TimedOut, "operation timed out");
+    net::offload_err(label, NetError::Io(e))
+}
+
+/// Re-drive the parked ops after a write, close or new connection.
+pub(crate) fn kick() {
+    redrive(&mut PARKED.lock().unwrap());
+}
+
+/// Advance every parked op, finishing those that can, until a pass
+/// finishes none — one op's write can be what another was waiting for.
+fn redrive(parked: &mut Vec<Parked>) {
+    loop {
+        let mut progressed = false;
+        for mut p in std::mem::take(parked) {
+            match reactor::advance(&mut p.rop.op, &p.rop.socket, p.rop.label) {
+                Advance::Done(result) => {
+                    progressed = true;
+                    (p.done)(result);
+                }
+                Advance::Pending => parked.push(p),
+            }
+        }
+        if !progressed {
+            return;
+        }
+    }
+}
+
+/// The earliest virtual time a parked op times out at. A scheduler with
+/// nothing else to do jumps the clock there, as it does for a `wait`.
+pub(crate) fn next_deadline() -> Option<f64> {
+    PARKED
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|p| p.deadline)
+        .min_by(f64::total_cmp)
+}
+
+/// Fail every parked op whose deadline is at or before `now` with
+/// `timeout`. Called whenever the virtual clock moves.
+pub(crate) fn expire(now: f64) {
+    let mut parked = PARKED.lock().unwrap();
+    let (due, keep) = std::mem::take(&mut *parked)
+        .into_iter()
+        .partition(|p| p.deadline.is_some_and(|at| at <= now));
+    *parked = keep;
+    drop(parked);
+    for p in due {
+        (p.done)(Err(timed_out(p.rop.label)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (Stream, Stream) {
+        let (up, down) = (Pipe::new(), Pipe::new());
+        let a = SocketAddr::from((Ipv4Addr::LOCALHOST, 1));
+        let b = SocketAddr::from((Ipv4Addr::LOCALHOST, 2));
+        let client = Stream { rx: down.clone(), tx: up.clone(), local: a, peer: b };
+        let server = Stream { rx: up, tx: down, local: b, peer: a };
+        (client, server)
+    }
+
+    #[test]
+    fn read_waits_for_a_write_then_sees_end_of_stream() {
+        let (client, server) = pair();
+        let mut buf = [0u8; 8];
+        let err = server.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(client.write(b"hello").unwrap(), 5);
+        assert_eq!(server.read(&mut buf[..3]).unwrap(), 3);
+        assert_eq!(&buf[..3], b"hel");
+        client.close();
+        // What was sent before the close still arrives.
+        assert_eq!(server.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(server.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn write_to_a_closed_peer_is_a_broken_pipe() {
+        let (client, server) = pair();
+        drop(server);
+        let err = client.write(b"x").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
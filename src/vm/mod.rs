@@ -3,10 +3,16 @@
 //! Pipeline: source → [`lexer`] → [`token`]s → [`parser`] → [`ast`] →
 //! [`compiler`] → [`chunk`] of [`opcode`]s → [`vm`] → [`value`].
 
+/// The M:N actor runtime under `spawn`. Actors need OS threads, which
+/// the `wasm32` build has none of; `spawn` raises there instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod actor_pool;
 pub mod ast;
+pub mod atomic;
 pub mod channel;
 pub mod chunk;
 pub mod compiler;
+pub mod deflate;
 pub mod error;
 pub mod file_handle;
 pub mod fold;
@@ -14,10 +20,15 @@ pub mod gc;
 pub mod io_capture;
 pub mod lexer;
 pub mod local_channel;
+/// The in-memory `Net` backend of simulation mode; sockets only exist
+/// on native targets.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod loopback;
 pub mod native_modules;
 pub mod offload;
 pub mod opcode;
 pub mod parser;
+pub mod preempt;
 /// The async-IO reactor. The real readiness-based implementation builds
 /// on every native target — epoll / kqueue / IOCP-AFD behind the
 /// cross-platform `polling` crate. Only `wasm32` (no sockets) swaps in
@@ -30,6 +41,9 @@ pub mod reactor;
 pub mod reactor;
 pub mod rng;
 pub mod scheduler;
+pub mod shared;
+pub mod shared_map;
+pub mod sim;
 /// Network sockets. Built on every native target; only `wasm32` swaps
 /// in a type-only stub (the `Net` module is unregistered there, so it
 /// is never reached at runtime).
@@ -41,11 +55,15 @@ pub mod socket;
 pub mod source_map;
 pub mod source_stdlib;
 pub mod stdlib;
+pub mod store;
 pub mod task;
 pub mod token;
+pub mod trace;
 pub mod transfer;
 pub mod value;
 pub mod vm;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod x509;
 
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
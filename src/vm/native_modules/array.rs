@@ -14,6 +14,7 @@
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc::{self, ArrayKind, GcRef};
+use crate::vm::shared::{self, SharedNode};
 use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
@@ -41,6 +42,8 @@ fn expect_array(
 ) -> Result<GcRef<ArrayKind>, RuntimeError> {
     match v {
         Value::Array(a) => Ok(*a),
+        // Every entry here mutates its array argument.
+        Value::Shared(_) => Err(shared::immutable(&format!("call Array.{label}"), 0)),
         other => Err(err(format!(
             "Array.{label}: expected Array, got {}",
             other.type_name()
@@ -78,8 +81,11 @@ fn a_push(args: &[Value]) -> Result<Value, RuntimeError> {
 /// self-extend (`extend(a, a)`) doesn't double-borrow the cell.
 fn a_extend(args: &[Value]) -> Result<Value, RuntimeError> {
     let arr = expect_array(&args[0], "extend")?;
-    let other = expect_array(&args[1], "extend")?;
-    let items: Vec<Value> = other.borrow().clone();
+    // Only `arr` is mutated, so `other` may be a shared array.
+    let items: Vec<Value> = match &args[1] {
+        Value::Shared(n) if matches!(**n, SharedNode::Array(_)) => n.values(),
+        other => expect_array(other, "extend")?.borrow().clone(),
+    };
     arr.borrow_mut().extend(items);
     Ok(args[0].clone())
 }
@@ -0,0 +1,87 @@
+//! `Atomic` — integer counters shared between actors.
+//!
+//! `Atomic.new(n)` makes a counter outside every actor heap (see
+//! [`crate::vm::atomic`]); the handle crosses `spawn` and channels by
+//! clone, so every holder updates the one counter. Each function is a
+//! single atomic step: there is no lock to hold and nothing to wait on.
+
+use crate::vm::atomic::{AtomicHandle, AtomicInner};
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object};
+
+pub fn module() -> Value {
+    object(&[
+        ("new",   native("new",   Arity::Range(0, 1), a_new)),
+        ("load",  native("load",  Arity::Exact(1), a_load)),
+        ("store", native("store", Arity::Exact(2), a_store)),
+        ("add",   native("add",   Arity::Exact(2), a_add)),
+        ("cas",   native("cas",   Arity::Exact(3), a_cas)),
+    ])
+}
+
+/// Extract an atomic handle from an argument, or raise a type error.
+fn as_atomic<'a>(v: &'a Value, func: &str) -> Result<&'a AtomicHandle, RuntimeError> {
+    match v {
+        Value::Atomic(h) => Ok(h),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "Atomic.{func} expects an atomic, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+fn as_int(v: &Value, func: &str) -> Result<i64, RuntimeError> {
+    match v {
+        Value::Int(n) => Ok(*n),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "Atomic.{func} expects an int, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+/// `new(initial = 0)` — a fresh counter.
+fn a_new(args: &[Value]) -> Result<Value, RuntimeError> {
+    let initial = match args.first() {
+        Some(v) => as_int(v, "new")?,
+        None => 0,
+    };
+    Ok(Value::Atomic(AtomicInner::new(initial)))
+}
+
+/// `load(a)` — the current value.
+fn a_load(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Int(as_atomic(&args[0], "load")?.load()))
+}
+
+/// `store(a, n)` — overwrite the value. Returns `null`.
+fn a_store(args: &[Value]) -> Result<Value, RuntimeError> {
+    let a = as_atomic(&args[0], "store")?;
+    a.store(as_int(&args[1], "store")?);
+    Ok(Value::Null)
+}
+
+/// `add(a, delta)` — add `delta` (which may be negative) and return
+/// the new value. Raises `overflow` and leaves the counter alone if
+/// the sum does not fit an `Int`.
+fn a_add(args: &[Value]) -> Result<Value, RuntimeError> {
+    let a = as_atomic(&args[0], "add")?;
+    Ok(Value::Int(a.add(as_int(&args[1], "add")?)?))
+}
+
+/// `cas(a, expected, new)` — set the value to `new` if it is
+/// `expected`. Returns whether it did.
+fn a_cas(args: &[Value]) -> Result<Value, RuntimeError> {
+    let a = as_atomic(&args[0], "cas")?;
+    let expected = as_int(&args[1], "cas")?;
+    let new = as_int(&args[2], "cas")?;
+    Ok(Value::Bool(a.compare_and_set(expected, new)))
+}
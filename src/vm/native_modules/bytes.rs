@@ -3,16 +3,37 @@
 //! `Bytes` is a `Value` in its own right (a GC-managed `Vec<u8>`):
 //! indexable, `#`-length, `for`-iterable, sliceable with `b[start:]`,
 //! and concatenable with `+` / `+=`. This module supplies everything
-//! the operators cannot: construction, `String`/`[Int]`/hex/base64
-//! conversion, in-place growth, and a named family of fixed-width
-//! integer readers and writers for binary-protocol work.
+//! the operators cannot: construction, `String`/`[Int]`/hex/base64/
+//! base32 conversion, in-place growth, and a named family of
+//! fixed-width integer and varint readers and writers for
+//! binary-protocol work.
 //!
 //! Reading multi-byte integers uses self-documenting names —
 //! `read_u32_be(buf, offset)`, `write_i16_le(buf, offset, value)` — so a
 //! call site states its width and endianness without a magic argument.
+//! For whole records, `pack` / `unpack` take a compact format string
+//! (`'>I H 8s'`, or with named fields `'>magic:I ver:H'`) and a cursor
+//! (`${buf, pos}`) lets `read` / `write` walk a buffer without manual
+//! offset bookkeeping. Varints follow the same naming: `read_uvarint`
+//! / `push_uvarint` for LEB128 (protobuf `uint64`), `read_svarint` /
+//! `push_svarint` for the zigzag form (protobuf `sint64`).
+//!
+//! The text codecs (hex, base64, base64url, base32) and the UTF-16 /
+//! Latin-1 string encodings are hand-rolled — no external crate. A
+//! decoder that rejects its input raises a structured `decode_error`
+//! carrying the byte `offset` it failed at.
+
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use num_bigint::BigInt as BigIntData;
+use num_traits::ToPrimitive;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
-use crate::vm::gc::{self, ArrayKind, BytesKind, GcRef};
+use crate::vm::gc::{self, ArrayKind, BytesKind, GcReadGuard, GcRef, ObjectKind};
+use crate::vm::shared::{self, SharedHandle, SharedNode};
 use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
@@ -22,19 +43,24 @@ pub fn module() -> Value {
         // -- construction --
         ("new",         native("new",         Arity::Range(1, 2), b_new)),
         ("from_array",  native("from_array",  Arity::Exact(1), b_from_array)),
-        ("from_string", native("from_string", Arity::Exact(1), b_from_string)),
+        ("from_string", native("from_string", Arity::Range(1, 2), b_from_string)),
         ("from_hex",    native("from_hex",    Arity::Exact(1), b_from_hex)),
-        ("from_base64", native("from_base64", Arity::Exact(1), b_from_base64)),
+        ("from_base64", native("from_base64", Arity::Range(1, 2), b_from_base64)),
+        ("from_base64_url", native("from_base64_url", Arity::Range(1, 2), b_from_base64_url)),
+        ("from_base32", native("from_base32", Arity::Range(1, 2), b_from_base32)),
         // -- conversion --
         ("to_array",    native("to_array",    Arity::Exact(1), b_to_array)),
-        ("to_string",   native("to_string",   Arity::Exact(1), b_to_string)),
+        ("to_string",   native("to_string",   Arity::Range(1, 2), b_to_string)),
         ("to_hex",      native("to_hex",      Arity::Exact(1), b_to_hex)),
-        ("to_base64",   native("to_base64",   Arity::Exact(1), b_to_base64)),
+        ("to_base64",   native("to_base64",   Arity::Range(1, 2), b_to_base64)),
+        ("to_base64_url", native("to_base64_url", Arity::Range(1, 2), b_to_base64_url)),
+        ("to_base32",   native("to_base32",   Arity::Range(1, 2), b_to_base32)),
         // -- buffer ops --
         ("push",        native("push",        Arity::Exact(2), b_push)),
         ("extend",      native("extend",      Arity::Exact(2), b_extend)),
         ("slice",       native("slice",       Arity::Exact(3), b_slice)),
         ("concat",      native("concat",      Arity::Exact(2), b_concat)),
+        ("index_of",    native("index_of",    Arity::Range(2, 3), b_index_of)),
         // -- integer pack/unpack (named family) --
         ("read_u8",     native("read_u8",     Arity::Exact(2), read_u8)),
         ("read_i8",     native("read_i8",     Arity::Exact(2), read_i8)),
@@ -64,6 +90,18 @@ pub fn module() -> Value {
         ("write_u64_le", native("write_u64_le", Arity::Exact(3), write_u64_le)),
         ("write_i64_be", native("write_i64_be", Arity::Exact(3), write_i64_be)),
         ("write_i64_le", native("write_i64_le", Arity::Exact(3), write_i64_le)),
+        // -- format-string packing --
+        ("pack",        native("pack",        Arity::Exact(2), b_pack)),
+        ("unpack",      native("unpack",      Arity::Range(2, 3), b_unpack)),
+        ("size_of",     native("size_of",     Arity::Exact(1), b_size_of)),
+        ("cursor",      native("cursor",      Arity::Range(1, 2), b_cursor)),
+        ("read",        native("read",        Arity::Exact(2), b_read)),
+        ("write",       native("write",       Arity::Exact(3), b_write)),
+        // -- varints --
+        ("read_uvarint", native("read_uvarint", Arity::Exact(2), read_uvarint)),
+        ("read_svarint", native("read_svarint", Arity::Exact(2), read_svarint)),
+        ("push_uvarint", native("push_uvarint", Arity::Exact(2), push_uvarint)),
+        ("push_svarint", native("push_svarint", Arity::Exact(2), push_svarint)),
     ])
 }
 
@@ -76,20 +114,94 @@ fn err(msg: String) -> RuntimeError {
     RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
 }
 
-/// A catchable structured decode error — `${kind: 'decode', message}`.
-/// Mirrors the shape of a reified built-in error so `catch` code (and
-/// `Test.assert_raises(..., 'decode')`) can dispatch on `.kind`.
-fn decode_err(msg: String) -> RuntimeError {
+/// Where and why a codec rejected its input. `offset` is the byte
+/// position in the input (the source string for a text decoder, the
+/// buffer for a binary one) at which the problem was found.
+#[derive(Debug)]
+struct CodecError {
+    offset: usize,
+    message: String,
+}
+
+impl CodecError {
+    fn new(offset: usize, message: impl Into<String>) -> Self {
+        CodecError { offset, message: message.into() }
+    }
+}
+
+/// A catchable structured codec error — `${kind, message, offset}`,
+/// `kind` being `'decode_error'` or `'encode'`. Mirrors the shape of a
+/// reified built-in error so `catch` code (and
+/// `Test.assert_raises(..., 'decode_error')`) can dispatch on `.kind`.
+fn codec_err(kind: &str, label: &str, e: CodecError) -> RuntimeError {
     let obj = super::object(&[
-        ("kind", Value::Str("decode".into())),
-        ("message", Value::Str(msg.into())),
+        ("kind", Value::Str(kind.into())),
+        ("message", Value::Str(
+            format!("Bytes.{label}: {} at offset {}", e.message, e.offset).into(),
+        )),
+        ("offset", Value::Int(e.offset as i64)),
     ]);
     RuntimeError::new(RuntimeErrorKind::Raised(obj), 0)
 }
 
-fn expect_bytes(v: &Value, label: &str) -> Result<GcRef<BytesKind>, RuntimeError> {
+fn decode_err(label: &str, e: CodecError) -> RuntimeError {
+    codec_err("decode_error", label, e)
+}
+
+/// A `Bytes` argument an entry only reads: a heap buffer, or the
+/// bytes of a `Shared.of` value, which read the same way.
+enum BytesArg {
+    Heap(GcRef<BytesKind>),
+    Shared(SharedHandle),
+}
+
+impl BytesArg {
+    fn borrow(&self) -> BytesView<'_> {
+        match self {
+            BytesArg::Heap(b) => BytesView::Heap(b.borrow()),
+            BytesArg::Shared(node) => match &**node {
+                SharedNode::Bytes(b) => BytesView::Shared(b),
+                _ => unreachable!("only shared bytes become a BytesArg"),
+            },
+        }
+    }
+}
+
+/// The contents of a [`BytesArg`] while it is borrowed.
+enum BytesView<'a> {
+    Heap(GcReadGuard<Vec<u8>>),
+    Shared(&'a [u8]),
+}
+
+impl Deref for BytesView<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            BytesView::Heap(b) => b,
+            BytesView::Shared(b) => b,
+        }
+    }
+}
+
+fn expect_bytes(v: &Value, label: &str) -> Result<BytesArg, RuntimeError> {
+    match v {
+        Value::Bytes(b) => Ok(BytesArg::Heap(*b)),
+        Value::Shared(node) if matches!(**node, SharedNode::Bytes(_)) => {
+            Ok(BytesArg::Shared(node.clone()))
+        }
+        other => Err(err(format!(
+            "Bytes.{label}: expected Bytes, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// [`expect_bytes`] for an entry that writes to the buffer: shared
+/// bytes raise `immutable` rather than a type error.
+fn expect_bytes_mut(v: &Value, label: &str) -> Result<GcRef<BytesKind>, RuntimeError> {
     match v {
         Value::Bytes(b) => Ok(*b),
+        Value::Shared(_) => Err(shared::immutable(&format!("call Bytes.{label}"), 0)),
         other => Err(err(format!(
             "Bytes.{label}: expected Bytes, got {}",
             other.type_name()
@@ -127,6 +239,19 @@ fn expect_int(v: &Value, label: &str) -> Result<i64, RuntimeError> {
     }
 }
 
+/// The optional trailing `pad` flag of the base64 / base32 codecs —
+/// `true` unless explicitly passed as `false`.
+fn opt_pad(args: &[Value], idx: usize, label: &str) -> Result<bool, RuntimeError> {
+    match args.get(idx) {
+        None | Some(Value::Null) => Ok(true),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(other) => Err(err(format!(
+            "Bytes.{label}: pad flag must be a Bool, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
 /// An `Int` argument constrained to a single byte (0..=255).
 fn expect_byte(v: &Value, label: &str) -> Result<u8, RuntimeError> {
     match expect_int(v, label)? {
@@ -182,26 +307,49 @@ fn b_from_array(args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::Bytes(gc::alloc_bytes(out)))
 }
 
-/// `from_string(s)` — the UTF-8 encoding of `s`. Always succeeds.
+/// `from_string(s)` / `from_string(s, encoding)` — `s` encoded as
+/// UTF-8 (the default), UTF-16LE/BE or Latin-1. Only Latin-1 can fail:
+/// a character above U+00FF raises a structured `encode` error whose
+/// `offset` is that character's index in `s`.
 fn b_from_string(args: &[Value]) -> Result<Value, RuntimeError> {
     let s = expect_str(&args[0], "from_string")?;
-    Ok(Value::Bytes(gc::alloc_bytes(s.as_bytes().to_vec())))
+    let enc = opt_encoding(args, "from_string")?;
+    let bytes = encode_text(s, enc).map_err(|e| codec_err("encode", "from_string", e))?;
+    Ok(Value::Bytes(gc::alloc_bytes(bytes)))
 }
 
 /// `from_hex(s)` — decode a hex string. ASCII whitespace is ignored.
 fn b_from_hex(args: &[Value]) -> Result<Value, RuntimeError> {
     let s = expect_str(&args[0], "from_hex")?;
-    let bytes = hex_decode(s).map_err(|e| decode_err(format!("Bytes.from_hex: {e}")))?;
+    let bytes = hex_decode(s).map_err(|e| decode_err("from_hex", e))?;
     Ok(Value::Bytes(gc::alloc_bytes(bytes)))
 }
 
-/// `from_base64(s)` — decode a standard-alphabet base64 string.
-fn b_from_base64(args: &[Value]) -> Result<Value, RuntimeError> {
-    let s = expect_str(&args[0], "from_base64")?;
-    let bytes = base64_decode(s).map_err(|e| decode_err(format!("Bytes.from_base64: {e}")))?;
+/// Shared body of the `from_base64` / `from_base64_url` / `from_base32`
+/// entries: `(s)` or `(s, pad)`.
+fn from_radix(args: &[Value], label: &str, alpha: &Alphabet) -> Result<Value, RuntimeError> {
+    let s = expect_str(&args[0], label)?;
+    let pad = opt_pad(args, 1, label)?;
+    let bytes = radix_decode(s, alpha, pad).map_err(|e| decode_err(label, e))?;
     Ok(Value::Bytes(gc::alloc_bytes(bytes)))
 }
 
+/// `from_base64(s)` — decode a standard-alphabet base64 string. With
+/// `pad = false` the input must be unpadded.
+fn b_from_base64(args: &[Value]) -> Result<Value, RuntimeError> {
+    from_radix(args, "from_base64", &BASE64)
+}
+
+/// `from_base64_url(s)` — decode the URL-safe (`-` / `_`) alphabet.
+fn b_from_base64_url(args: &[Value]) -> Result<Value, RuntimeError> {
+    from_radix(args, "from_base64_url", &BASE64_URL)
+}
+
+/// `from_base32(s)` — decode RFC 4648 base32; letters in either case.
+fn b_from_base32(args: &[Value]) -> Result<Value, RuntimeError> {
+    from_radix(args, "from_base32", &BASE32)
+}
+
 // ---------------------------------------------------------------------
 // Conversion
 // ---------------------------------------------------------------------
@@ -213,18 +361,14 @@ fn b_to_array(args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::Array(gc::alloc_array(out)))
 }
 
-/// `to_string(b)` — decode the buffer as UTF-8. Raises a catchable
-/// `decode` error if the bytes are not valid UTF-8.
+/// `to_string(b)` / `to_string(b, encoding)` — decode the buffer as
+/// UTF-8 (the default), UTF-16LE/BE or Latin-1. Raises a catchable
+/// `decode_error` at the first malformed byte.
 fn b_to_string(args: &[Value]) -> Result<Value, RuntimeError> {
     let buf = expect_bytes(&args[0], "to_string")?;
-    let bytes = buf.borrow().clone();
-    match String::from_utf8(bytes) {
-        Ok(s) => Ok(Value::Str(s.into())),
-        Err(e) => Err(decode_err(format!(
-            "Bytes.to_string: invalid UTF-8 at byte {}",
-            e.utf8_error().valid_up_to()
-        ))),
-    }
+    let enc = opt_encoding(args, "to_string")?;
+    let s = decode_text(&buf.borrow(), enc).map_err(|e| decode_err("to_string", e))?;
+    Ok(Value::Str(s.into()))
 }
 
 /// `to_hex(b)` — lower-case hex, two digits per byte, no separators.
@@ -233,10 +377,29 @@ fn b_to_hex(args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::Str(hex_encode(&buf.borrow()).into()))
 }
 
-/// `to_base64(b)` — standard-alphabet base64 with `=` padding.
+/// Shared body of the `to_base64` / `to_base64_url` / `to_base32`
+/// entries: `(b)` or `(b, pad)`.
+fn to_radix(args: &[Value], label: &str, alpha: &Alphabet) -> Result<Value, RuntimeError> {
+    let buf = expect_bytes(&args[0], label)?;
+    let pad = opt_pad(args, 1, label)?;
+    Ok(Value::Str(radix_encode(&buf.borrow(), alpha, pad).into()))
+}
+
+/// `to_base64(b)` — standard-alphabet base64, `=`-padded unless `pad`
+/// is `false`.
 fn b_to_base64(args: &[Value]) -> Result<Value, RuntimeError> {
-    let buf = expect_bytes(&args[0], "to_base64")?;
-    Ok(Value::Str(base64_encode(&buf.borrow()).into()))
+    to_radix(args, "to_base64", &BASE64)
+}
+
+/// `to_base64_url(b)` — the URL-safe alphabet (`-` / `_`), as used by
+/// JWTs and data in query strings.
+fn b_to_base64_url(args: &[Value]) -> Result<Value, RuntimeError> {
+    to_radix(args, "to_base64_url", &BASE64_URL)
+}
+
+/// `to_base32(b)` — RFC 4648 base32, upper case.
+fn b_to_base32(args: &[Value]) -> Result<Value, RuntimeError> {
+    to_radix(args, "to_base32", &BASE32)
 }
 
 // ---------------------------------------------------------------------
@@ -245,7 +408,7 @@ fn b_to_base64(args: &[Value]) -> Result<Value, RuntimeError> {
 
 /// `push(b, byte)` — append one byte in place. Returns `b`.
 fn b_push(args: &[Value]) -> Result<Value, RuntimeError> {
-    let buf = expect_bytes(&args[0], "push")?;
+    let buf = expect_bytes_mut(&args[0], "push")?;
     let byte = expect_byte(&args[1], "push")?;
     buf.borrow_mut().push(byte);
     Ok(args[0].clone())
@@ -254,9 +417,9 @@ fn b_push(args: &[Value]) -> Result<Value, RuntimeError> {
 /// `extend(b, other)` — append every byte of `other` in place. Returns
 /// `b`. `other` is snapshotted first so `extend(b, b)` is safe.
 fn b_extend(args: &[Value]) -> Result<Value, RuntimeError> {
-    let buf = expect_bytes(&args[0], "extend")?;
+    let buf = expect_bytes_mut(&args[0], "extend")?;
     let other = expect_bytes(&args[1], "extend")?;
-    let items: Vec<u8> = other.borrow().clone();
+    let items: Vec<u8> = other.borrow().to_vec();
     buf.borrow_mut().extend(items);
     Ok(args[0].clone())
 }
@@ -277,11 +440,44 @@ fn b_slice(args: &[Value]) -> Result<Value, RuntimeError> {
 fn b_concat(args: &[Value]) -> Result<Value, RuntimeError> {
     let a = expect_bytes(&args[0], "concat")?;
     let b = expect_bytes(&args[1], "concat")?;
-    let mut out: Vec<u8> = a.borrow().clone();
+    let mut out: Vec<u8> = a.borrow().to_vec();
     out.extend(b.borrow().iter().copied());
     Ok(Value::Bytes(gc::alloc_bytes(out)))
 }
 
+/// `index_of(b, needle, start?)` — the offset of the first `needle`
+/// at or after `start`, or `-1`. `needle` is `Bytes`, a `String`
+/// (matched as its UTF-8 bytes), or a single byte `Int`; an empty
+/// needle matches at `start`. A negative `start` counts from the end.
+fn b_index_of(args: &[Value]) -> Result<Value, RuntimeError> {
+    let buf = expect_bytes(&args[0], "index_of")?;
+    let needle: Vec<u8> = match &args[1] {
+        Value::Bytes(_) | Value::Shared(_) => expect_bytes(&args[1], "index_of")?.borrow().to_vec(),
+        Value::Str(s) => s.as_bytes().to_vec(),
+        Value::Int(_) => vec![expect_byte(&args[1], "index_of")?],
+        other => {
+            return Err(err(format!(
+                "Bytes.index_of: expected Bytes, String or Int needle, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let src = buf.borrow();
+    let start = match args.get(2) {
+        None | Some(Value::Null) => 0,
+        Some(v) => resolve_clamped(expect_int(v, "index_of")?, src.len()),
+    };
+    let found = if needle.is_empty() {
+        Some(start)
+    } else {
+        src[start..]
+            .windows(needle.len())
+            .position(|w| w == needle.as_slice())
+            .map(|i| start + i)
+    };
+    Ok(Value::Int(found.map_or(-1, |i| i as i64)))
+}
+
 // ---------------------------------------------------------------------
 // Integer pack/unpack
 // ---------------------------------------------------------------------
@@ -340,7 +536,10 @@ fn read_int(
 }
 
 /// Write `value` as a `width`-byte integer at `offset`, in place.
-/// Returns the buffer. Raises if `value` does not fit the field.
+/// Returns the buffer. Raises if `value` does not fit the field. A
+/// `BigInt` is accepted too — for the 64-bit writers it is the only way
+/// to name an unsigned value in 2^63 .. 2^64-1 (there is no unsigned
+/// `Int`).
 fn write_int(
     args: &[Value],
     label: &str,
@@ -348,36 +547,49 @@ fn write_int(
     signed: bool,
     be: bool,
 ) -> Result<Value, RuntimeError> {
-    let buf = expect_bytes(&args[0], label)?;
+    let buf = expect_bytes_mut(&args[0], label)?;
     let offset = expect_int(&args[1], label)?;
-    let value = expect_int(&args[2], label)?;
+    let acc = int_bits(&args[2], width, signed).map_err(|m| err(format!("Bytes.{label}: {m}")))?;
+    write_raw(buf, &args[0], label, offset, width, be, acc)
+}
+
+/// The `width`-byte two's-complement bit pattern of an `Int` or
+/// `BigInt`, or why it does not fit a `signed` / unsigned field.
+fn int_bits(v: &Value, width: usize, signed: bool) -> Result<u64, String> {
     let bits = width * 8;
+    let n: i128 = match v {
+        Value::Int(n) => *n as i128,
+        // Anything past i128 is out of range for every field anyway.
+        Value::BigInt(b) => b.to_i128().unwrap_or(i128::MAX),
+        other => return Err(format!("expected Int, got {}", other.type_name())),
+    };
     if signed {
-        if bits < 64 {
-            let min = -(1i64 << (bits - 1));
-            let max = (1i64 << (bits - 1)) - 1;
-            if value < min || value > max {
-                return Err(err(format!(
-                    "Bytes.{label}: value {value} does not fit a signed {width}-byte field"
-                )));
-            }
+        let min = -(1i128 << (bits - 1));
+        let max = (1i128 << (bits - 1)) - 1;
+        if n < min || n > max {
+            return Err(format!("value {v} does not fit a signed {width}-byte field"));
         }
     } else {
-        if value < 0 {
-            return Err(err(format!(
-                "Bytes.{label}: value {value} is negative — use a signed writer"
-            )));
+        if n < 0 {
+            return Err(format!("value {v} is negative — use a signed field"));
         }
-        if bits < 64 {
-            let max = (1i64 << bits) - 1;
-            if value > max {
-                return Err(err(format!(
-                    "Bytes.{label}: value {value} does not fit an unsigned {width}-byte field"
-                )));
-            }
+        if n > (1i128 << bits) - 1 {
+            return Err(format!("value {v} does not fit an unsigned {width}-byte field"));
         }
     }
-    let acc = value as u64;
+    Ok(n as u64)
+}
+
+/// Store the low `width` bytes of `acc` at `offset`. Returns `value`.
+fn write_raw(
+    buf: GcRef<BytesKind>,
+    value: &Value,
+    label: &str,
+    offset: i64,
+    width: usize,
+    be: bool,
+    acc: u64,
+) -> Result<Value, RuntimeError> {
     let mut bytes = buf.borrow_mut();
     if offset < 0 {
         return Err(err(format!("Bytes.{label}: negative offset {offset}")));
@@ -394,7 +606,7 @@ fn write_int(
         bytes[off + i] = ((acc >> shift) & 0xff) as u8;
     }
     drop(bytes);
-    Ok(args[0].clone())
+    Ok(value.clone())
 }
 
 /// Generate the named `read_*` / `write_*` pair for one width/sign/
@@ -430,7 +642,700 @@ int_fns! {
 }
 
 // ---------------------------------------------------------------------
-// Hex / base64 codecs (hand-rolled — no external crate)
+// Format-string packing
+// ---------------------------------------------------------------------
+//
+// A format is an optional byte-order mark — `>` or `!` big-endian (the
+// default), `<` little-endian — then whitespace-separated fields, each
+// `[name:][count]code`:
+//
+//   x        pad byte (zero on pack, skipped on unpack; no value)
+//   ?        Bool, one byte
+//   b B      i8 / u8          h H   i16 / u16
+//   i I      i32 / u32        q Q   i64 / u64
+//   f d      Float as binary32 / binary64
+//   Ns       exactly N raw bytes, as `Bytes`
+//   p P V    UTF-8 String behind a u8 / u16 / u32 byte count
+//
+// A count repeats a numeric code (`3H`); for `s` it is the length and
+// for `x` the pad width. Name every value-producing field or none: a
+// named format unpacks to an Object (a counted field to an Array) and
+// packs from one; an unnamed format works with a flat Array.
+
+/// One field's wire type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Code {
+    Pad,
+    Bool,
+    Int { width: usize, signed: bool },
+    F32,
+    F64,
+    Fixed(usize),
+    /// Length-prefixed text; the prefix width in bytes.
+    Prefixed(usize),
+}
+
+impl Code {
+    /// The encoded width, or `None` for a length-prefixed string.
+    fn width(self) -> Option<usize> {
+        match self {
+            Code::Pad | Code::Bool => Some(1),
+            Code::Int { width, .. } => Some(width),
+            Code::F32 => Some(4),
+            Code::F64 => Some(8),
+            Code::Fixed(n) => Some(n),
+            Code::Prefixed(_) => None,
+        }
+    }
+}
+
+struct Field {
+    name: Option<Arc<str>>,
+    /// An explicit repeat count. `s` and `x` fold theirs into the code
+    /// and the pad width respectively, so for them this is `None` /
+    /// the width.
+    count: Option<usize>,
+    code: Code,
+}
+
+struct Format {
+    be: bool,
+    fields: Vec<Field>,
+    named: bool,
+}
+
+/// The most bytes of padding and fixed-width strings a format may lay
+/// out. `pack` writes them byte for byte, so a width like `99999999x`
+/// must fail in the parser rather than in the allocator.
+const MAX_LAID_OUT: usize = 64 * 1024 * 1024;
+
+fn parse_format(fmt: &str, label: &str) -> Result<Format, RuntimeError> {
+    let bad = |msg: &str| err(format!("Bytes.{label}: bad format {fmt:?}: {msg}"));
+    let chars: Vec<char> = fmt.chars().collect();
+    let skip_ws = |mut i: usize| {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = skip_ws(0);
+    let mut be = true;
+    match chars.get(i) {
+        Some('<') => { be = false; i += 1; }
+        Some('>') | Some('!') => i += 1,
+        _ => {}
+    }
+    let mut fields = Vec::new();
+    let mut laid_out = 0usize;
+    loop {
+        i = skip_ws(i);
+        if i == chars.len() {
+            break;
+        }
+        // An identifier only names the field if a `:` follows it;
+        // otherwise it is a run of type codes (`BHI`) and is re-read.
+        let mut name = None;
+        if chars[i].is_ascii_alphabetic() || chars[i] == '_' {
+            let mut j = i;
+            while j < chars.len() && (chars[j].is_ascii_alphanumeric() || chars[j] == '_') {
+                j += 1;
+            }
+            if chars.get(j) == Some(&':') {
+                name = Some(Arc::from(chars[i..j].iter().collect::<String>()));
+                i = j + 1;
+            }
+        }
+        let digits = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let count = if i > digits {
+            let text: String = chars[digits..i].iter().collect();
+            Some(text.parse::<usize>().map_err(|_| bad("count out of range"))?)
+        } else {
+            None
+        };
+        let code = match chars.get(i) {
+            None => return Err(bad("missing type code")),
+            Some('x') => Code::Pad,
+            Some('?') => Code::Bool,
+            Some('b') => Code::Int { width: 1, signed: true },
+            Some('B') => Code::Int { width: 1, signed: false },
+            Some('h') => Code::Int { width: 2, signed: true },
+            Some('H') => Code::Int { width: 2, signed: false },
+            Some('i') => Code::Int { width: 4, signed: true },
+            Some('I') => Code::Int { width: 4, signed: false },
+            Some('q') => Code::Int { width: 8, signed: true },
+            Some('Q') => Code::Int { width: 8, signed: false },
+            Some('f') => Code::F32,
+            Some('d') => Code::F64,
+            Some('s') => Code::Fixed(count.unwrap_or(1)),
+            Some('p') => Code::Prefixed(1),
+            Some('P') => Code::Prefixed(2),
+            Some('V') => Code::Prefixed(4),
+            Some(c) => return Err(bad(&format!("unknown type code '{c}'"))),
+        };
+        i += 1;
+        let width = match code {
+            Code::Pad => count.unwrap_or(1),
+            Code::Fixed(n) => n,
+            _ => 0,
+        };
+        laid_out = laid_out
+            .checked_add(width)
+            .filter(|&n| n <= MAX_LAID_OUT)
+            .ok_or_else(|| bad("padding and fixed strings exceed 64 MiB"))?;
+        let count = match code {
+            Code::Fixed(_) => None,
+            Code::Prefixed(_) if count.is_some() => {
+                return Err(bad("a length-prefixed string takes no count"));
+            }
+            Code::Pad if name.is_some() => return Err(bad("padding cannot be named")),
+            _ => count,
+        };
+        fields.push(Field { name, count, code });
+    }
+    let valued = fields.iter().filter(|f| f.code != Code::Pad).count();
+    let named = fields.iter().filter(|f| f.name.is_some()).count();
+    if named != 0 && named != valued {
+        return Err(bad("name every field or none"));
+    }
+    Ok(Format { be, fields, named: named > 0 })
+}
+
+/// `data[pos..pos + n]`, or a `decode_error` if the buffer ends first.
+fn take<'a>(data: &'a [u8], pos: usize, n: usize, label: &str) -> Result<&'a [u8], RuntimeError> {
+    pos.checked_add(n)
+        .and_then(|end| data.get(pos..end))
+        .ok_or_else(|| decode_err(label, CodecError::new(
+            pos,
+            format!("field needs {n} bytes but the buffer has {}", data.len().saturating_sub(pos)),
+        )))
+}
+
+fn load(bytes: &[u8], be: bool) -> u64 {
+    let fold = |acc: u64, &b: &u8| (acc << 8) | b as u64;
+    if be { bytes.iter().fold(0, fold) } else { bytes.iter().rev().fold(0, fold) }
+}
+
+fn store(out: &mut Vec<u8>, raw: u64, width: usize, be: bool) {
+    for i in 0..width {
+        let shift = if be { (width - 1 - i) * 8 } else { i * 8 };
+        out.push((raw >> shift) as u8);
+    }
+}
+
+/// Decode one value of `code` at `pos`; returns it and the next offset.
+fn unpack_one(
+    code: Code,
+    be: bool,
+    data: &[u8],
+    pos: usize,
+    label: &str,
+) -> Result<(Value, usize), RuntimeError> {
+    let width = code.width().unwrap_or(0);
+    let value = match code {
+        Code::Pad => unreachable!("padding produces no value"),
+        Code::Bool => Value::Bool(take(data, pos, 1, label)?[0] != 0),
+        Code::Int { width, signed } => {
+            let raw = load(take(data, pos, width, label)?, be);
+            let bits = width * 8;
+            if signed {
+                let shift = 64 - bits;
+                Value::Int(((raw << shift) as i64) >> shift)
+            } else if raw > i64::MAX as u64 {
+                // A `Q` above the Int range comes back as a BigInt, so
+                // `pack` / `unpack` round-trip the full u64 range.
+                Value::BigInt(Rc::new(BigIntData::from(raw)))
+            } else {
+                Value::Int(raw as i64)
+            }
+        }
+        Code::F32 => Value::Float(f32::from_bits(load(take(data, pos, 4, label)?, be) as u32) as f64),
+        Code::F64 => Value::Float(f64::from_bits(load(take(data, pos, 8, label)?, be))),
+        Code::Fixed(n) => Value::Bytes(gc::alloc_bytes(take(data, pos, n, label)?.to_vec())),
+        Code::Prefixed(w) => {
+            let len = load(take(data, pos, w, label)?, be) as usize;
+            let body = take(data, pos + w, len, label)?;
+            let text = std::str::from_utf8(body).map_err(|e| {
+                decode_err(label, CodecError::new(pos + w + e.valid_up_to(), "invalid UTF-8"))
+            })?;
+            return Ok((Value::Str(text.into()), pos + w + len));
+        }
+    };
+    Ok((value, pos + width))
+}
+
+fn unpack_at(
+    f: &Format,
+    data: &[u8],
+    mut pos: usize,
+    label: &str,
+) -> Result<(Value, usize), RuntimeError> {
+    let mut list = Vec::new();
+    let mut obj: IndexMap<Arc<str>, Value> = IndexMap::new();
+    for field in &f.fields {
+        let n = field.count.unwrap_or(1);
+        if field.code == Code::Pad {
+            take(data, pos, n, label)?;
+            pos += n;
+            continue;
+        }
+        // Every repeat of a fixed-width code must fit the buffer, so a
+        // huge count fails as a short read before anything is allocated.
+        if let Some(width) = field.code.width() {
+            match width.checked_mul(n) {
+                Some(need) => take(data, pos, need, label)?,
+                None => return Err(decode_err(label, CodecError::new(
+                    pos,
+                    format!("a count of {n} does not fit the buffer"),
+                ))),
+            };
+        }
+        let mut vals = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (v, next) = unpack_one(field.code, f.be, data, pos, label)?;
+            vals.push(v);
+            pos = next;
+        }
+        match &field.name {
+            Some(name) if field.count.is_some() => {
+                obj.insert(name.clone(), Value::Array(gc::alloc_array(vals)));
+            }
+            Some(name) => {
+                obj.insert(name.clone(), vals.pop().unwrap_or(Value::Null));
+            }
+            None => list.extend(vals),
+        }
+    }
+    let value = if f.named {
+        Value::Object(gc::alloc_object(obj))
+    } else {
+        Value::Array(gc::alloc_array(list))
+    };
+    Ok((value, pos))
+}
+
+/// Encode one value of `code` onto `out`. `what` names the value in
+/// errors (a field name or a position).
+fn pack_one(
+    code: Code,
+    be: bool,
+    v: &Value,
+    out: &mut Vec<u8>,
+    what: &str,
+) -> Result<(), String> {
+    let float = |v: &Value| match v {
+        Value::Float(x) => Ok(*x),
+        Value::Int(n) => Ok(*n as f64),
+        other => Err(format!("{what}: expected Float, got {}", other.type_name())),
+    };
+    let raw_bytes = |v: &Value| match v {
+        Value::Bytes(b) => Ok(b.borrow().clone()),
+        Value::Str(s) => Ok(s.as_bytes().to_vec()),
+        other => Err(format!("{what}: expected Bytes or String, got {}", other.type_name())),
+    };
+    match code {
+        Code::Pad => unreachable!("padding consumes no value"),
+        Code::Bool => match v {
+            Value::Bool(b) => out.push(*b as u8),
+            other => return Err(format!("{what}: expected Bool, got {}", other.type_name())),
+        },
+        Code::Int { width, signed } => {
+            let raw = int_bits(v, width, signed).map_err(|m| format!("{what}: {m}"))?;
+            store(out, raw, width, be);
+        }
+        Code::F32 => store(out, (float(v)? as f32).to_bits() as u64, 4, be),
+        Code::F64 => store(out, float(v)?.to_bits(), 8, be),
+        Code::Fixed(n) => {
+            let data = raw_bytes(v)?;
+            if data.len() > n {
+                return Err(format!("{what}: {} bytes do not fit a {n}-byte field", data.len()));
+            }
+            let end = out
+                .len()
+                .checked_add(n)
+                .ok_or_else(|| format!("{what}: the record is too large"))?;
+            out.extend_from_slice(&data);
+            out.resize(end, 0);
+        }
+        Code::Prefixed(w) => {
+            let data = raw_bytes(v)?;
+            if (data.len() as u64) >> (w * 8) != 0 {
+                return Err(format!(
+                    "{what}: {} bytes do not fit a {w}-byte length prefix",
+                    data.len()
+                ));
+            }
+            store(out, data.len() as u64, w, be);
+            out.extend_from_slice(&data);
+        }
+    }
+    Ok(())
+}
+
+/// Append `n` zero bytes of padding to `out`.
+fn pad(out: &mut Vec<u8>, n: usize) -> Result<(), String> {
+    let end = out.len().checked_add(n).ok_or_else(|| "the record is too large".to_string())?;
+    out.resize(end, 0);
+    Ok(())
+}
+
+fn pack_values(f: &Format, values: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    let fail = |m: String| err(format!("Bytes.{label}: {m}"));
+    let mut out = Vec::new();
+    if f.named {
+        let Value::Object(o) = values else {
+            return Err(fail(format!(
+                "a named format packs an Object, got {}",
+                values.type_name()
+            )));
+        };
+        let src = o.borrow().clone();
+        for field in &f.fields {
+            let Some(name) = &field.name else {
+                pad(&mut out, field.count.unwrap_or(1)).map_err(fail)?;
+                continue;
+            };
+            let v = src.get(name).ok_or_else(|| fail(format!("missing field '{name}'")))?;
+            match field.count {
+                None => pack_one(field.code, f.be, v, &mut out, name).map_err(fail)?,
+                Some(n) => {
+                    let items = match v {
+                        Value::Array(a) => a.borrow().clone(),
+                        other => return Err(fail(format!(
+                            "{name}: expected an Array of {n}, got {}",
+                            other.type_name()
+                        ))),
+                    };
+                    if items.len() != n {
+                        return Err(fail(format!(
+                            "{name}: expected {n} values, got {}",
+                            items.len()
+                        )));
+                    }
+                    for (i, item) in items.iter().enumerate() {
+                        pack_one(field.code, f.be, item, &mut out, &format!("{name}[{i}]"))
+                            .map_err(fail)?;
+                    }
+                }
+            }
+        }
+        return Ok(out);
+    }
+    let items = match values {
+        Value::Array(a) => a.borrow().clone(),
+        other => return Err(fail(format!("expected Array, got {}", other.type_name()))),
+    };
+    let wanted = f
+        .fields
+        .iter()
+        .filter(|fl| fl.code != Code::Pad)
+        .try_fold(0usize, |acc, fl| acc.checked_add(fl.count.unwrap_or(1)))
+        .ok_or_else(|| fail("format counts overflow".to_string()))?;
+    if items.len() != wanted {
+        return Err(fail(format!(
+            "format takes {wanted} values, got {}",
+            items.len()
+        )));
+    }
+    let mut next = items.iter().enumerate();
+    for field in &f.fields {
+        let n = field.count.unwrap_or(1);
+        if field.code == Code::Pad {
+            pad(&mut out, n).map_err(fail)?;
+            continue;
+        }
+        for (i, item) in next.by_ref().take(n) {
+            pack_one(field.code, f.be, item, &mut out, &format!("value {i}")).map_err(fail)?;
+        }
+    }
+    Ok(out)
+}
+
+/// `pack(fmt, values)` — a new buffer holding `values` (an Array, or an
+/// Object for a named format) laid out by `fmt`.
+fn b_pack(args: &[Value]) -> Result<Value, RuntimeError> {
+    let f = parse_format(expect_str(&args[0], "pack")?, "pack")?;
+    Ok(Value::Bytes(gc::alloc_bytes(pack_values(&f, &args[1], "pack")?)))
+}
+
+/// `unpack(fmt, b)` / `unpack(fmt, b, offset)` — decode one record.
+/// Bytes after the record are ignored.
+fn b_unpack(args: &[Value]) -> Result<Value, RuntimeError> {
+    let f = parse_format(expect_str(&args[0], "unpack")?, "unpack")?;
+    let buf = expect_bytes(&args[1], "unpack")?;
+    let data = buf.borrow();
+    let offset = match args.get(2) {
+        None => 0,
+        Some(v) => checked_offset(expect_int(v, "unpack")?, data.len(), "unpack")?,
+    };
+    Ok(unpack_at(&f, &data, offset, "unpack")?.0)
+}
+
+/// `size_of(fmt)` — the encoded byte length of a fixed-size format.
+/// Raises for a format with a length-prefixed string.
+fn b_size_of(args: &[Value]) -> Result<Value, RuntimeError> {
+    let f = parse_format(expect_str(&args[0], "size_of")?, "size_of")?;
+    let mut total = 0usize;
+    for field in &f.fields {
+        let width = field.code.width().ok_or_else(|| {
+            err("Bytes.size_of: a length-prefixed string has no fixed size".to_string())
+        })?;
+        total = width
+            .checked_mul(field.count.unwrap_or(1))
+            .and_then(|n| total.checked_add(n))
+            .filter(|&t| t <= i64::MAX as usize)
+            .ok_or_else(|| err("Bytes.size_of: the format's size overflows an Int".to_string()))?;
+    }
+    Ok(Value::Int(total as i64))
+}
+
+fn checked_offset(offset: i64, len: usize, label: &str) -> Result<usize, RuntimeError> {
+    if offset < 0 || offset as usize > len {
+        return Err(err(format!(
+            "Bytes.{label}: offset {offset} out of bounds (buffer length {len})"
+        )));
+    }
+    Ok(offset as usize)
+}
+
+// ---------------------------------------------------------------------
+// Cursors
+// ---------------------------------------------------------------------
+
+/// `cursor(b)` / `cursor(b, pos)` — a plain `${buf, pos}` Object.
+/// `read` / `write` advance `pos`; assigning it seeks.
+fn b_cursor(args: &[Value]) -> Result<Value, RuntimeError> {
+    let buf = expect_bytes(&args[0], "cursor")?;
+    let pos = match args.get(1) {
+        None => 0,
+        Some(v) => checked_offset(expect_int(v, "cursor")?, buf.borrow().len(), "cursor")?,
+    };
+    Ok(object(&[("buf", args[0].clone()), ("pos", Value::Int(pos as i64))]))
+}
+
+fn cursor_parts(
+    v: &Value,
+    label: &str,
+) -> Result<(GcRef<ObjectKind>, BytesArg, usize), RuntimeError> {
+    let bad = || err(format!("Bytes.{label}: expected a cursor (${{buf, pos}})"));
+    let Value::Object(c) = v else { return Err(bad()) };
+    let (buf, pos) = {
+        let fields = c.borrow();
+        match (fields.get("buf"), fields.get("pos")) {
+            (Some(b), Some(Value::Int(p))) => (expect_bytes(b, label).map_err(|_| bad())?, *p),
+            _ => return Err(bad()),
+        }
+    };
+    let pos = checked_offset(pos, buf.borrow().len(), label)?;
+    Ok((*c, buf, pos))
+}
+
+/// `read(cursor, fmt)` — unpack one record at the cursor and advance.
+fn b_read(args: &[Value]) -> Result<Value, RuntimeError> {
+    let (c, buf, pos) = cursor_parts(&args[0], "read")?;
+    let f = parse_format(expect_str(&args[1], "read")?, "read")?;
+    let (value, next) = unpack_at(&f, &buf.borrow(), pos, "read")?;
+    c.borrow_mut().insert(Arc::from("pos"), Value::Int(next as i64));
+    Ok(value)
+}
+
+/// `write(cursor, fmt, values)` — pack at the cursor, overwriting in
+/// place and growing the buffer past its end, then advance. Returns the
+/// cursor.
+fn b_write(args: &[Value]) -> Result<Value, RuntimeError> {
+    let (c, buf, pos) = cursor_parts(&args[0], "write")?;
+    let BytesArg::Heap(buf) = buf else {
+        return Err(shared::immutable("call Bytes.write", 0));
+    };
+    let f = parse_format(expect_str(&args[1], "write")?, "write")?;
+    let packed = pack_values(&f, &args[2], "write")?;
+    let end = pos + packed.len();
+    {
+        let mut bytes = buf.borrow_mut();
+        if end > bytes.len() {
+            bytes.resize(end, 0);
+        }
+        bytes[pos..end].copy_from_slice(&packed);
+    }
+    c.borrow_mut().insert(Arc::from("pos"), Value::Int(end as i64));
+    Ok(args[0].clone())
+}
+
+// ---------------------------------------------------------------------
+// Varints
+// ---------------------------------------------------------------------
+
+/// Decode a LEB128 varint at `offset`: 7 bits per byte, least
+/// significant group first, high bit set on every byte but the last.
+/// Returns the raw `u64` and the offset just past it.
+fn uvarint_at(args: &[Value], label: &str) -> Result<(u64, usize), RuntimeError> {
+    let buf = expect_bytes(&args[0], label)?;
+    let offset = expect_int(&args[1], label)?;
+    let bytes = buf.borrow();
+    if offset < 0 || offset as usize > bytes.len() {
+        return Err(err(format!(
+            "Bytes.{label}: offset {offset} out of bounds (buffer length {})",
+            bytes.len()
+        )));
+    }
+    let start = offset as usize;
+    let mut acc: u64 = 0;
+    for (i, &b) in bytes[start..].iter().enumerate() {
+        let shift = 7 * i as u32;
+        // A u64 needs at most ten groups, and the tenth may only carry
+        // the single top bit.
+        if shift == 63 && b > 1 || shift > 63 {
+            return Err(RuntimeError::new(RuntimeErrorKind::Overflow, 0));
+        }
+        acc |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok((acc, start + i + 1));
+        }
+    }
+    Err(decode_err(label, CodecError::new(bytes.len(), "truncated varint")))
+}
+
+/// `read_uvarint(b, offset)` — `[value, next_offset]` for the unsigned
+/// varint at `offset`. A value above the signed 64-bit range raises
+/// `overflow`, like `read_u64_*`.
+fn read_uvarint(args: &[Value]) -> Result<Value, RuntimeError> {
+    let (raw, next) = uvarint_at(args, "read_uvarint")?;
+    if raw > i64::MAX as u64 {
+        return Err(RuntimeError::new(RuntimeErrorKind::Overflow, 0));
+    }
+    Ok(pair(raw as i64, next))
+}
+
+/// `read_svarint(b, offset)` — `[value, next_offset]` for the zigzag
+/// varint at `offset`. Every `Int` is representable.
+fn read_svarint(args: &[Value]) -> Result<Value, RuntimeError> {
+    let (raw, next) = uvarint_at(args, "read_svarint")?;
+    let value = (raw >> 1) as i64 ^ -((raw & 1) as i64);
+    Ok(pair(value, next))
+}
+
+fn pair(value: i64, next: usize) -> Value {
+    Value::Array(gc::alloc_array(vec![Value::Int(value), Value::Int(next as i64)]))
+}
+
+fn push_varint_raw(buf: GcRef<BytesKind>, mut raw: u64) {
+    let mut bytes = buf.borrow_mut();
+    while raw >= 0x80 {
+        bytes.push((raw as u8 & 0x7f) | 0x80);
+        raw >>= 7;
+    }
+    bytes.push(raw as u8);
+}
+
+/// `push_uvarint(b, value)` — append `value` (non-negative) as an
+/// unsigned varint, in place. Returns `b`.
+fn push_uvarint(args: &[Value]) -> Result<Value, RuntimeError> {
+    let buf = expect_bytes_mut(&args[0], "push_uvarint")?;
+    let value = expect_int(&args[1], "push_uvarint")?;
+    if value < 0 {
+        return Err(err(format!(
+            "Bytes.push_uvarint: value {value} is negative — use push_svarint"
+        )));
+    }
+    push_varint_raw(buf, value as u64);
+    Ok(args[0].clone())
+}
+
+/// `push_svarint(b, value)` — append `value` zigzag-encoded (small
+/// magnitudes of either sign stay short), in place. Returns `b`.
+fn push_svarint(args: &[Value]) -> Result<Value, RuntimeError> {
+    let buf = expect_bytes_mut(&args[0], "push_svarint")?;
+    let value = expect_int(&args[1], "push_svarint")?;
+    push_varint_raw(buf, ((value << 1) ^ (value >> 63)) as u64);
+    Ok(args[0].clone())
+}
+
+// ---------------------------------------------------------------------
+// Text encodings
+// ---------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+/// The optional `encoding` name at `args[1]`, case-insensitive.
+fn opt_encoding(args: &[Value], label: &str) -> Result<Encoding, RuntimeError> {
+    let name = match args.get(1) {
+        None | Some(Value::Null) => return Ok(Encoding::Utf8),
+        Some(v) => expect_str(v, label)?,
+    };
+    match name.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => Ok(Encoding::Utf8),
+        "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+        "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+        "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+        _ => Err(err(format!(
+            "Bytes.{label}: unknown encoding '{name}' (expected utf-8, utf-16le, utf-16be or latin-1)"
+        ))),
+    }
+}
+
+fn encode_text(s: &str, enc: Encoding) -> Result<Vec<u8>, CodecError> {
+    Ok(match enc {
+        Encoding::Utf8 => s.as_bytes().to_vec(),
+        Encoding::Utf16Le => s.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        Encoding::Utf16Be => s.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        Encoding::Latin1 => {
+            let mut out = Vec::with_capacity(s.len());
+            for (i, c) in s.chars().enumerate() {
+                if c as u32 > 0xff {
+                    return Err(CodecError::new(
+                        i,
+                        format!("character {c:?} is not representable in Latin-1"),
+                    ));
+                }
+                out.push(c as u8);
+            }
+            out
+        }
+    })
+}
+
+fn decode_text(data: &[u8], enc: Encoding) -> Result<String, CodecError> {
+    match enc {
+        Encoding::Utf8 => std::str::from_utf8(data)
+            .map(str::to_string)
+            .map_err(|e| CodecError::new(e.valid_up_to(), "invalid UTF-8")),
+        Encoding::Latin1 => Ok(data.iter().map(|&b| b as char).collect()),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !data.len().is_multiple_of(2) {
+                return Err(CodecError::new(data.len() - 1, "odd byte count for UTF-16"));
+            }
+            let be = enc == Encoding::Utf16Be;
+            let units = data.chunks(2).map(|p| {
+                if be { u16::from_be_bytes([p[0], p[1]]) } else { u16::from_le_bytes([p[0], p[1]]) }
+            });
+            let mut out = String::with_capacity(data.len() / 2);
+            // `decode_utf16` yields one item per scalar value; track the
+            // byte position so an unpaired surrogate reports its offset.
+            let mut pos = 0;
+            for r in char::decode_utf16(units) {
+                match r {
+                    Ok(c) => {
+                        pos += c.len_utf16() * 2;
+                        out.push(c);
+                    }
+                    Err(_) => return Err(CodecError::new(pos, "unpaired UTF-16 surrogate")),
+                }
+            }
+            Ok(out)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------
+// Hex / base64 / base32 codecs (hand-rolled — no external crate)
 // ---------------------------------------------------------------------
 
 fn hex_encode(data: &[u8]) -> String {
@@ -443,7 +1348,7 @@ fn hex_encode(data: &[u8]) -> String {
     s
 }
 
-fn hex_decode(s: &str) -> Result<Vec<u8>, String> {
+fn hex_decode(s: &str) -> Result<Vec<u8>, CodecError> {
     fn nibble(c: u8) -> Option<u8> {
         match c {
             b'0'..=b'9' => Some(c - b'0'),
@@ -452,90 +1357,135 @@ fn hex_decode(s: &str) -> Result<Vec<u8>, String> {
             _ => None,
         }
     }
-    let cleaned: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
-    if cleaned.len() % 2 != 0 {
-        return Err(format!("odd number of hex digits ({})", cleaned.len()));
+    let cleaned: Vec<(usize, u8)> =
+        s.bytes().enumerate().filter(|(_, b)| !b.is_ascii_whitespace()).collect();
+    if !cleaned.len().is_multiple_of(2) {
+        return Err(CodecError::new(
+            s.len(),
+            format!("odd number of hex digits ({})", cleaned.len()),
+        ));
     }
+    let digit = |(i, c): (usize, u8)| {
+        nibble(c).ok_or_else(|| CodecError::new(i, format!("invalid hex digit {:?}", c as char)))
+    };
     let mut out = Vec::with_capacity(cleaned.len() / 2);
     for pair in cleaned.chunks(2) {
-        let hi = nibble(pair[0])
-            .ok_or_else(|| format!("invalid hex digit {:?}", pair[0] as char))?;
-        let lo = nibble(pair[1])
-            .ok_or_else(|| format!("invalid hex digit {:?}", pair[1] as char))?;
-        out.push((hi << 4) | lo);
+        out.push((digit(pair[0])? << 4) | digit(pair[1])?);
     }
     Ok(out)
 }
 
-const B64: &[u8; 64] =
-    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// A power-of-two radix text codec: `symbols` maps each `bits`-wide
+/// group to a character; padded output is `=`-filled to a multiple of
+/// `group` characters. `fold_case` makes decoding case-insensitive.
+struct Alphabet {
+    symbols: &'static [u8],
+    bits: u32,
+    group: usize,
+    fold_case: bool,
+}
 
-fn base64_encode(data: &[u8]) -> String {
-    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
-    for chunk in data.chunks(3) {
-        let b0 = chunk[0];
-        let b1 = chunk.get(1).copied().unwrap_or(0);
-        let b2 = chunk.get(2).copied().unwrap_or(0);
-        out.push(B64[(b0 >> 2) as usize] as char);
-        out.push(B64[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
-        out.push(if chunk.len() > 1 {
-            B64[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
-        } else {
-            '='
-        });
-        out.push(if chunk.len() > 2 {
-            B64[(b2 & 0x3f) as usize] as char
-        } else {
-            '='
-        });
+const BASE64: Alphabet = Alphabet {
+    symbols: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+    bits: 6,
+    group: 4,
+    fold_case: false,
+};
+
+const BASE64_URL: Alphabet = Alphabet {
+    symbols: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+    bits: 6,
+    group: 4,
+    fold_case: false,
+};
+
+const BASE32: Alphabet = Alphabet {
+    symbols: b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567",
+    bits: 5,
+    group: 8,
+    fold_case: true,
+};
+
+impl Alphabet {
+    fn value(&self, c: u8) -> Option<u32> {
+        let c = if self.fold_case { c.to_ascii_uppercase() } else { c };
+        self.symbols.iter().position(|&s| s == c).map(|p| p as u32)
+    }
+
+    /// Whether a trailing group of `n` symbols can end a valid encoding:
+    /// the bits left over after the last whole byte must be fewer than
+    /// one symbol's worth (base64 rejects 1; base32 rejects 1, 3 and 6).
+    fn valid_tail(&self, n: usize) -> bool {
+        (n as u32 * self.bits) % 8 < self.bits
     }
-    out
 }
 
-fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
-    fn value(c: u8) -> Option<u8> {
-        match c {
-            b'A'..=b'Z' => Some(c - b'A'),
-            b'a'..=b'z' => Some(c - b'a' + 26),
-            b'0'..=b'9' => Some(c - b'0' + 52),
-            b'+' => Some(62),
-            b'/' => Some(63),
-            _ => None,
+fn radix_encode(data: &[u8], alpha: &Alphabet, pad: bool) -> String {
+    let bits = alpha.bits;
+    let mask = (1u32 << bits) - 1;
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(bits as usize) + alpha.group);
+    let mut acc: u32 = 0;
+    let mut held: u32 = 0;
+    for &b in data {
+        acc = (acc << 8) | b as u32;
+        held += 8;
+        while held >= bits {
+            held -= bits;
+            out.push(alpha.symbols[((acc >> held) & mask) as usize] as char);
         }
+        acc &= (1 << held) - 1;
     }
-    let cleaned: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
-    if cleaned.len() % 4 != 0 {
-        return Err(format!(
-            "length {} is not a multiple of 4",
-            cleaned.len()
-        ));
+    if held > 0 {
+        out.push(alpha.symbols[((acc << (bits - held)) & mask) as usize] as char);
     }
-    let chunks = cleaned.len() / 4;
-    let mut out = Vec::with_capacity(chunks * 3);
-    for (ci, chunk) in cleaned.chunks(4).enumerate() {
-        let pad = chunk.iter().filter(|&&c| c == b'=').count();
-        if pad > 0 && ci + 1 != chunks {
-            return Err("'=' padding before the final group".to_string());
+    if pad {
+        while !out.len().is_multiple_of(alpha.group) {
+            out.push('=');
         }
-        if pad > 2 || chunk[0] == b'=' || chunk[1] == b'=' {
-            return Err("misplaced '=' padding".to_string());
-        }
-        if chunk[2] == b'=' && chunk[3] != b'=' {
-            return Err("misplaced '=' padding".to_string());
-        }
-        let mut q = [0u8; 4];
-        for (i, &c) in chunk.iter().enumerate() {
-            if c != b'=' {
-                q[i] = value(c)
-                    .ok_or_else(|| format!("invalid base64 character {:?}", c as char))?;
-            }
+    }
+    out
+}
+
+fn radix_decode(s: &str, alpha: &Alphabet, pad: bool) -> Result<Vec<u8>, CodecError> {
+    let cleaned: Vec<(usize, u8)> =
+        s.bytes().enumerate().filter(|(_, b)| !b.is_ascii_whitespace()).collect();
+    // Split off trailing `=` padding; any `=` left in the body is misplaced.
+    let body_len = cleaned.iter().rposition(|&(_, c)| c != b'=').map_or(0, |p| p + 1);
+    let (body, padding) = cleaned.split_at(body_len);
+    if let Some(&(i, _)) = body.iter().find(|&&(_, c)| c == b'=') {
+        return Err(CodecError::new(i, "misplaced '=' padding"));
+    }
+    let tail = body.len() % alpha.group;
+    if pad {
+        if !cleaned.len().is_multiple_of(alpha.group) {
+            return Err(CodecError::new(
+                s.len(),
+                format!("length {} is not a multiple of {}", cleaned.len(), alpha.group),
+            ));
         }
-        out.push((q[0] << 2) | (q[1] >> 4));
-        if pad < 2 {
-            out.push((q[1] << 4) | (q[2] >> 2));
+        if !padding.is_empty() && tail == 0 {
+            return Err(CodecError::new(padding[0].0, "misplaced '=' padding"));
         }
-        if pad < 1 {
-            out.push((q[2] << 6) | q[3]);
+    } else if let Some(&(i, _)) = padding.first() {
+        return Err(CodecError::new(i, "unexpected '=' padding"));
+    }
+    if !alpha.valid_tail(tail) {
+        let at = body.get(body.len() - tail).map_or(s.len(), |&(i, _)| i);
+        return Err(CodecError::new(at, "truncated final group"));
+    }
+    let mut out = Vec::with_capacity(body.len() * alpha.bits as usize / 8);
+    let mut acc: u32 = 0;
+    let mut held: u32 = 0;
+    for &(i, c) in body {
+        let v = alpha
+            .value(c)
+            .ok_or_else(|| CodecError::new(i, format!("invalid character {:?}", c as char)))?;
+        acc = (acc << alpha.bits) | v;
+        held += alpha.bits;
+        if held >= 8 {
+            held -= 8;
+            out.push((acc >> held) as u8);
+            acc &= (1 << held) - 1;
         }
     }
     Ok(out)
@@ -573,13 +1523,107 @@ mod tests {
     #[test]
     fn base64_roundtrip() {
         for s in ["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
-            let enc = base64_encode(s.as_bytes());
-            assert_eq!(base64_decode(&enc).unwrap(), s.as_bytes());
+            let enc = radix_encode(s.as_bytes(), &BASE64, true);
+            assert_eq!(radix_decode(&enc, &BASE64, true).unwrap(), s.as_bytes());
+            let bare = radix_encode(s.as_bytes(), &BASE64, false);
+            assert_eq!(radix_decode(&bare, &BASE64, false).unwrap(), s.as_bytes());
+        }
+        assert_eq!(radix_encode(b"foobar", &BASE64, true), "Zm9vYmFy");
+        assert_eq!(radix_decode("Zm9vYmFy", &BASE64, true).unwrap(), b"foobar");
+        assert!(radix_decode("abc", &BASE64, true).is_err()); // not a multiple of 4
+        assert!(radix_decode("****", &BASE64, true).is_err()); // bad chars
+        assert!(radix_decode("Zg==", &BASE64, false).is_err()); // padding when unpadded
+        assert!(radix_decode("Z", &BASE64, false).is_err()); // one dangling symbol
+    }
+
+    #[test]
+    fn base64_url_alphabet() {
+        assert_eq!(radix_encode(&[0xfb, 0xff], &BASE64, true), "+/8=");
+        assert_eq!(radix_encode(&[0xfb, 0xff], &BASE64_URL, false), "-_8");
+        assert_eq!(radix_decode("-_8", &BASE64_URL, false).unwrap(), vec![0xfb, 0xff]);
+    }
+
+    #[test]
+    fn base32_rfc4648_vectors() {
+        let cases = [
+            ("", ""), ("f", "MY======"), ("fo", "MZXQ===="), ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="), ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI======"),
+        ];
+        for (plain, enc) in cases {
+            assert_eq!(radix_encode(plain.as_bytes(), &BASE32, true), enc);
+            assert_eq!(radix_decode(enc, &BASE32, true).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(radix_decode("mzxw6===", &BASE32, true).unwrap(), b"foo");
+        assert!(radix_decode("MZX=====", &BASE32, true).is_err()); // 3-symbol tail
+    }
+
+    #[test]
+    fn decode_errors_carry_the_offset() {
+        assert_eq!(radix_decode("Zm9v*mFy", &BASE64, true).unwrap_err().offset, 4);
+        assert_eq!(hex_decode("de ag").unwrap_err().offset, 4);
+        assert_eq!(decode_text(&[0x68, 0xff], Encoding::Utf8).unwrap_err().offset, 1);
+        // a lone high surrogate (0xd800) after one good unit
+        let e = decode_text(&[0x41, 0x00, 0x00, 0xd8], Encoding::Utf16Le).unwrap_err();
+        assert_eq!(e.offset, 2);
+    }
+
+    #[test]
+    fn text_encodings_roundtrip() {
+        for enc in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            let data = encode_text("h\u{e9}\u{1f600}", enc).unwrap();
+            assert_eq!(decode_text(&data, enc).unwrap(), "h\u{e9}\u{1f600}");
+        }
+        assert_eq!(encode_text("hi", Encoding::Utf16Be).unwrap(), vec![0, b'h', 0, b'i']);
+        assert_eq!(encode_text("\u{e9}", Encoding::Latin1).unwrap(), vec![0xe9]);
+        assert_eq!(decode_text(&[0xe9], Encoding::Latin1).unwrap(), "\u{e9}");
+        assert_eq!(encode_text("a\u{20ac}", Encoding::Latin1).unwrap_err().offset, 1);
+    }
+
+    fn as_pair(v: &Value) -> (i64, i64) {
+        match v {
+            Value::Array(a) => match a.borrow().as_slice() {
+                [Value::Int(x), Value::Int(n)] => (*x, *n),
+                other => panic!("expected [Int, Int], got {other:?}"),
+            },
+            _ => panic!("expected Array, got {v:?}"),
         }
-        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
-        assert_eq!(base64_decode("Zm9vYmFy").unwrap(), b"foobar");
-        assert!(base64_decode("abc").is_err()); // not a multiple of 4
-        assert!(base64_decode("****").is_err()); // bad chars
+    }
+
+    #[test]
+    fn uvarint_roundtrip() {
+        for (n, len) in [(0i64, 1), (127, 1), (128, 2), (300, 2), (i64::MAX, 9)] {
+            let buf = bytes(vec![]);
+            push_uvarint(&[buf.clone(), Value::Int(n)]).unwrap();
+            assert_eq!(as_bytes(&buf).len(), len);
+            assert_eq!(as_pair(&read_uvarint(&[buf, Value::Int(0)]).unwrap()), (n, len as i64));
+        }
+        // protobuf's canonical example: 300 = ac 02
+        let buf = bytes(vec![0xac, 0x02]);
+        assert_eq!(as_pair(&read_uvarint(&[buf, Value::Int(0)]).unwrap()), (300, 2));
+    }
+
+    #[test]
+    fn svarint_zigzag() {
+        for (n, first) in [(0i64, 0u8), (-1, 1), (1, 2), (-2, 3)] {
+            let buf = bytes(vec![]);
+            push_svarint(&[buf.clone(), Value::Int(n)]).unwrap();
+            assert_eq!(as_bytes(&buf), vec![first]);
+        }
+        for n in [i64::MIN, i64::MAX, -300] {
+            let buf = bytes(vec![]);
+            push_svarint(&[buf.clone(), Value::Int(n)]).unwrap();
+            assert_eq!(as_pair(&read_svarint(&[buf, Value::Int(0)]).unwrap()).0, n);
+        }
+    }
+
+    #[test]
+    fn varint_malformed_input() {
+        let truncated = bytes(vec![0x80, 0x80]);
+        assert!(read_uvarint(&[truncated, Value::Int(0)]).is_err());
+        let too_big = bytes(vec![0xff; 9].into_iter().chain([0x01]).collect());
+        let e = read_uvarint(&[too_big, Value::Int(0)]).unwrap_err();
+        assert!(matches!(e.kind, RuntimeErrorKind::Overflow));
+        assert!(push_uvarint(&[bytes(vec![]), Value::Int(-1)]).is_err());
     }
 
     #[test]
@@ -632,4 +1676,75 @@ mod tests {
         assert!(write_u16_be(&[buf.clone(), Value::Int(0), Value::Int(-1)]).is_err());
         assert!(write_i8(&[buf, Value::Int(0), Value::Int(200)]).is_err());
     }
+
+    #[test]
+    fn write_u64_accepts_bigint_above_int_max() {
+        let buf = bytes(vec![0; 8]);
+        let big = Value::BigInt(Rc::new(BigIntData::from(u64::MAX)));
+        write_u64_be(&[buf.clone(), Value::Int(0), big.clone()]).unwrap();
+        assert_eq!(as_bytes(&buf), vec![0xff; 8]);
+        // out of range for the signed writer, and past 2^64 for both
+        assert!(write_i64_be(&[buf.clone(), Value::Int(0), big]).is_err());
+        let huge = Value::BigInt(Rc::new(BigIntData::from(u64::MAX) + 1u8));
+        assert!(write_u64_le(&[buf, Value::Int(0), huge]).is_err());
+    }
+
+    fn s(text: &str) -> Value {
+        Value::Str(text.into())
+    }
+
+    #[test]
+    fn format_parsing() {
+        let f = parse_format("<name:8s id:I 2x tags:3H", "t").unwrap();
+        assert!(!f.be && f.named);
+        assert_eq!(f.fields.len(), 4);
+        assert_eq!(f.fields[0].code, Code::Fixed(8));
+        assert_eq!(f.fields[2].count, Some(2));
+        assert_eq!(f.fields[3].count, Some(3));
+        // a run of bare codes is several fields, not a name
+        assert_eq!(parse_format("BHI", "t").unwrap().fields.len(), 3);
+        assert!(parse_format("a:B H", "t").is_err()); // mixed naming
+        assert!(parse_format("3p", "t").is_err()); // counted prefix string
+        assert!(parse_format("Z", "t").is_err()); // unknown code
+        assert!(parse_format("x:x", "t").is_err()); // named padding
+    }
+
+    #[test]
+    fn pack_matches_hand_layout() {
+        let f = parse_format(">H b 2x 2s p", "t").unwrap();
+        let vals = Value::Array(gc::alloc_array(vec![
+            Value::Int(0x0102), Value::Int(-1), s("a"), s("hi"),
+        ]));
+        let out = pack_values(&f, &vals, "t").unwrap();
+        assert_eq!(out, vec![1, 2, 0xff, 0, 0, b'a', 0, 2, b'h', b'i']);
+        let (back, end) = unpack_at(&f, &out, 0, "t").unwrap();
+        assert_eq!(end, out.len());
+        match back {
+            Value::Array(a) => {
+                let a = a.borrow();
+                assert_eq!(a[0], Value::Int(0x0102));
+                assert_eq!(a[1], Value::Int(-1));
+                assert_eq!(as_bytes(&a[2]), vec![b'a', 0]);
+                assert_eq!(a[3], s("hi"));
+            }
+            other => panic!("expected Array, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unpack_q_above_int_max_is_bigint() {
+        let f = parse_format("<Q", "t").unwrap();
+        let (v, _) = unpack_at(&f, &[0xff; 8], 0, "t").unwrap();
+        match v {
+            Value::Array(a) => assert!(matches!(a.borrow()[0], Value::BigInt(_))),
+            other => panic!("expected Array, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unpack_truncated_is_a_decode_error() {
+        let f = parse_format("I", "t").unwrap();
+        let e = unpack_at(&f, &[0, 0], 0, "t").unwrap_err();
+        assert!(matches!(e.kind, RuntimeErrorKind::Raised(Value::Object(_))));
+    }
 }
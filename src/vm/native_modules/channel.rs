@@ -6,6 +6,12 @@
 //! caller's heap. `recv`/`try_recv` return an object the caller
 //! pattern-matches: `${value: v}`, `${closed: true}`, or — `try_recv`
 //! only — `${empty: true}`.
+//!
+//! `oneshot`, `broadcast` and `watch` make the other channel flavours
+//! (see [`crate::vm::channel`]); `subscribe` makes a receiving handle
+//! for a broadcast or watch channel, and `current` reads a watch
+//! channel's value. Every flavour is the same `channel` value, so
+//! `send`, `recv`, `try_recv` and `close` serve them all.
 
 use std::sync::Arc;
 
@@ -14,19 +20,25 @@ use indexmap::IndexMap;
 use crate::vm::channel::{ChannelHandle, ChannelInner, RecvOutcome};
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc;
-use crate::vm::offload::{BlockingJob, OffloadOk};
+use crate::vm::offload::{HandoffOp, OffloadOk, Waiter};
 use crate::vm::transfer::{decode, encode};
 use crate::vm::value::{Arity, Value};
 
-use super::{native, native_blocking, object};
+use super::{native, native_handoff, object};
 
 pub fn module() -> Value {
     object(&[
         ("new", native("new", Arity::Exact(1), c_new)),
-        // `send` blocks on a bounded channel's backpressure, `recv`
-        // blocks for a message — both offload inside a green thread.
-        ("send", native_blocking("send", Arity::Exact(2), c_send)),
-        ("recv", native_blocking("recv", Arity::Exact(1), c_recv)),
+        ("oneshot", native("oneshot", Arity::Exact(0), c_oneshot)),
+        ("broadcast", native("broadcast", Arity::Exact(1), c_broadcast)),
+        ("watch", native("watch", Arity::Exact(1), c_watch)),
+        ("subscribe", native("subscribe", Arity::Exact(2), c_subscribe)),
+        ("current", native("current", Arity::Exact(1), c_current)),
+        // `send` waits on a bounded channel's backpressure, `recv`
+        // waits for a message — both park the caller, and the other
+        // end of the channel hands over the result.
+        ("send", native_handoff("send", Arity::Exact(2), c_send)),
+        ("recv", native_handoff("recv", Arity::Exact(1), c_recv)),
         ("try_recv", native("try_recv", Arity::Exact(1), c_try_recv)),
         ("close", native("close", Arity::Exact(1), c_close)),
     ])
@@ -36,6 +48,11 @@ fn raise(msg: &str) -> RuntimeError {
     RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
 }
 
+/// Raise a `type_mismatch`: a channel of the wrong flavour.
+fn mismatch(msg: &str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::TypeMismatch(msg.to_string()), 0)
+}
+
 /// Extract a channel handle from an argument, or raise a type error.
 fn as_channel(v: &Value) -> Result<&ChannelHandle, RuntimeError> {
     match v {
@@ -50,6 +67,44 @@ fn as_channel(v: &Value) -> Result<&ChannelHandle, RuntimeError> {
     }
 }
 
+/// Extract a channel that can be received from — anything but the
+/// sending side of a broadcast or watch channel.
+fn as_receivable(v: &Value) -> Result<&ChannelHandle, RuntimeError> {
+    let ch = as_channel(v)?;
+    if ch.is_hub() {
+        return Err(mismatch(
+            "cannot receive from a broadcast or watch channel; \
+             receive from a subscription to it",
+        ));
+    }
+    Ok(ch)
+}
+
+/// A lag limit argument: a positive integer, or `null` for `default`.
+fn lag_limit(v: &Value, default: Option<usize>) -> Result<Option<usize>, RuntimeError> {
+    match v {
+        Value::Null => Ok(default),
+        Value::Int(n) if *n > 0 => Ok(Some(*n as usize)),
+        Value::Int(_) => Err(raise("Channel lag limit must be a positive integer")),
+        other => Err(raise(&format!(
+            "Channel lag limit must be an integer or null, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// The value of a receive: `${value: v}`, `${closed: true}`, or a
+/// `lagged` raise.
+fn received(outcome: RecvOutcome) -> Result<Value, RuntimeError> {
+    Ok(match outcome {
+        RecvOutcome::Message(t) => tagged("value", decode(t)),
+        RecvOutcome::Closed => tagged("closed", Value::Bool(true)),
+        RecvOutcome::Lagged(n) => {
+            return Err(RuntimeError::new(RuntimeErrorKind::Lagged(n), 0));
+        }
+    })
+}
+
 /// Build a single-field result object (`${key: val}`).
 fn tagged(key: &str, val: Value) -> Value {
     let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(1);
@@ -76,38 +131,86 @@ fn c_new(args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::Channel(ChannelInner::new(capacity)))
 }
 
+/// `oneshot()` — a channel for a single message: the first `send`
+/// delivers it and closes the channel, so a second raises
+/// `channel_closed`.
+fn c_oneshot(_args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Channel(ChannelInner::oneshot()))
+}
+
+/// `broadcast(lag)` — a broadcast channel whose subscriptions keep up
+/// to `lag` unreceived messages by default (the `Channel.tg` wrapper
+/// defaults it to 16).
+fn c_broadcast(args: &[Value]) -> Result<Value, RuntimeError> {
+    let lag = lag_limit(&args[0], None)?
+        .ok_or_else(|| raise("broadcast needs a lag limit"))?;
+    Ok(Value::Channel(ChannelInner::broadcast(lag)))
+}
+
+/// `watch(initial)` — a watch channel holding `initial`. Raises
+/// `not_sendable`/`cycle` as `send` does.
+fn c_watch(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Channel(ChannelInner::watch(encode(&args[0])?)))
+}
+
+/// `subscribe(channel, lag)` — a receiving handle for a broadcast or
+/// watch channel. `lag` (`null` for the channel's own) limits how far a
+/// broadcast subscription may fall behind; a watch subscription keeps
+/// only the newest value and ignores it.
+fn c_subscribe(args: &[Value]) -> Result<Value, RuntimeError> {
+    let ch = as_channel(&args[0])?;
+    let lag = lag_limit(&args[1], None)?;
+    ch.subscribe(lag)
+        .map(Value::Channel)
+        .ok_or_else(|| mismatch("subscribe needs a broadcast or watch channel"))
+}
+
+/// `current(channel)` — a watch channel's value, read from the channel
+/// or a subscription to it, without waiting.
+fn c_current(args: &[Value]) -> Result<Value, RuntimeError> {
+    let ch = as_channel(&args[0])?;
+    ch.current()
+        .map(decode)
+        .ok_or_else(|| mismatch("current needs a watch channel or a subscription to one"))
+}
+
 /// `send(channel, message)` — transfer-encodes and enqueues `message`.
 /// Raises `not_sendable`/`cycle` for an un-sendable value, or
 /// `channel_closed` if the channel is closed. Returns `null`. A
-/// blocking native — a bounded channel's `send` waits while the
-/// buffer is full. `message` is transfer-encoded here, on the actor
-/// thread (it needs the heap); only the encoded `Transfer` crosses to
-/// the worker.
-fn c_send(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+/// handoff native — a bounded channel's `send` waits while the buffer
+/// is full, until a receiver frees a slot. `message` is
+/// transfer-encoded here, on the actor thread (it needs the heap); only
+/// the encoded `Transfer` is handed on.
+fn c_send(args: &[Value]) -> Result<HandoffOp, RuntimeError> {
     let ch = as_channel(&args[0])?.clone();
+    if ch.is_subscription() {
+        return Err(mismatch(
+            "cannot send on a subscription; send on its broadcast or watch channel",
+        ));
+    }
     let msg = encode(&args[1])?;
-    Ok(Box::new(move || {
-        let outcome = ch.send(msg);
-        OffloadOk::deferred(move || match outcome {
-            Ok(()) => Ok(Value::Null),
-            Err(()) => {
-                Err(RuntimeError::new(RuntimeErrorKind::ChannelClosed, 0))
-            }
-        })
+    Ok(Box::new(move |w: Waiter| {
+        ch.send_then(msg, Box::new(move |outcome| {
+            w.complete(OffloadOk::deferred(move || match outcome {
+                Ok(()) => Ok(Value::Null),
+                Err(()) => {
+                    Err(RuntimeError::new(RuntimeErrorKind::ChannelClosed, 0))
+                }
+            }))
+        }))
     }))
 }
 
-/// `recv(channel)` — blocks for a message. Returns `${value: v}`, or
-/// `${closed: true}` once the channel is closed and drained. A
-/// blocking native; the received `Transfer` is decoded into the
-/// caller's heap back on the actor thread.
-fn c_recv(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
-    let ch = as_channel(&args[0])?.clone();
-    Ok(Box::new(move || {
-        let outcome = ch.recv();
-        OffloadOk::deferred(move || Ok(match outcome {
-            RecvOutcome::Message(t) => tagged("value", decode(t)),
-            RecvOutcome::Closed => tagged("closed", Value::Bool(true)),
+/// `recv(channel)` — waits for a message. Returns `${value: v}`, or
+/// `${closed: true}` once the channel is closed and drained, and raises
+/// `lagged` for a broadcast subscription that fell behind. A handoff
+/// native: the sender passes the `Transfer` to the parked receiver,
+/// which decodes it into its own heap back on the actor thread.
+fn c_recv(args: &[Value]) -> Result<HandoffOp, RuntimeError> {
+    let ch = as_receivable(&args[0])?.clone();
+    Ok(Box::new(move |w: Waiter| {
+        ch.recv_then(Box::new(move |outcome| {
+            w.complete(OffloadOk::deferred(move || received(outcome)))
         }))
     }))
 }
@@ -115,12 +218,11 @@ fn c_recv(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
 /// `try_recv(channel)` — never blocks. Returns `${value: v}`,
 /// `${closed: true}`, or `${empty: true}` if nothing is ready.
 fn c_try_recv(args: &[Value]) -> Result<Value, RuntimeError> {
-    let ch = as_channel(&args[0])?;
-    Ok(match ch.try_recv() {
-        Some(RecvOutcome::Message(t)) => tagged("value", decode(t)),
-        Some(RecvOutcome::Closed) => tagged("closed", Value::Bool(true)),
-        None => tagged("empty", Value::Bool(true)),
-    })
+    let ch = as_receivable(&args[0])?;
+    match ch.try_recv() {
+        Some(outcome) => received(outcome),
+        None => Ok(tagged("empty", Value::Bool(true))),
+    }
 }
 
 /// `close(channel)` — closes the channel, waking blocked actors.
@@ -0,0 +1,437 @@
+//! `import 'Compress'` — deflate, zlib and gzip over `Bytes`.
+//!
+//! One-shot `compress(data, format, level?)` / `decompress(data,
+//! format, opts?)`, and streaming codecs: `encoder(format, level?)` and
+//! `decoder(format)` return a stream Object that `write(stream, chunk)`
+//! feeds and `finish(stream)` ends, each call returning the output
+//! bytes it produced. The codec itself is [`crate::vm::deflate`].
+//!
+//! Decoding is bounded: `decompress` stops at `max_output` bytes
+//! ([`DEFAULT_MAX_OUTPUT`] unless the options say otherwise) and a
+//! decoder's `write` takes an optional per-call limit, so a small
+//! "compression bomb" raises `${kind: 'too_large', message, limit}`
+//! instead of exhausting memory.
+//!
+//! A stream is an ordinary Object. Its public fields are `format`,
+//! `mode` (`'encode'` / `'decode'`) and, for an encoder, `level`; the
+//! codec's resumable state (unconsumed input, the back-reference
+//! window, running checksum) lives in underscore-prefixed fields that
+//! `write` reads and replaces. Being plain data, a stream needs no
+//! cleanup and is collected like any other value; each field is
+//! range-checked on the way back in, so a stream whose state was
+//! tampered with raises rather than upsetting the codec.
+//!
+//! A decoder that rejects its input raises a structured `decode`
+//! error — `${kind, message, offset}` — the same shape `Bytes` uses.
+
+use std::sync::Arc;
+
+use crate::vm::deflate::{self, Deflater, Format, Inflater, Stage};
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc::{self, GcRef, ObjectKind};
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object};
+
+/// The level used when none is given — zlib's default trade-off.
+const DEFAULT_LEVEL: i64 = 6;
+
+/// The most bytes `decompress` produces when no `max_output` is given.
+pub const DEFAULT_MAX_OUTPUT: usize = 256 * 1024 * 1024;
+
+pub fn module() -> Value {
+    object(&[
+        ("compress",   native("compress",   Arity::Range(2, 3), c_compress)),
+        ("decompress", native("decompress", Arity::Range(2, 3), c_decompress)),
+        ("encoder",    native("encoder",    Arity::Range(1, 2), c_encoder)),
+        ("decoder",    native("decoder",    Arity::Exact(1), c_decoder)),
+        ("write",      native("write",      Arity::Range(2, 3), c_write)),
+        ("finish",     native("finish",     Arity::Exact(1), c_finish)),
+        ("crc32",      native("crc32",      Arity::Range(1, 2), c_crc32)),
+        ("adler32",    native("adler32",    Arity::Range(1, 2), c_adler32)),
+    ])
+}
+
+// ---------------------------------------------------------------------
+// Argument helpers
+// ---------------------------------------------------------------------
+
+/// A catchable, string-valued error. The VM backfills the call line.
+fn err(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+/// A catchable structured `decode_error`, as `Bytes` raises, or a
+/// `too_large` error when the output limit stopped the decoder.
+fn decode_err(label: &str, e: deflate::Error, limit: usize) -> RuntimeError {
+    if e.too_large {
+        let obj = object(&[
+            ("kind", Value::Str("too_large".into())),
+            ("message", Value::Str(
+                format!("Compress.{label}: output exceeds {limit} bytes").into(),
+            )),
+            ("limit", Value::Int(limit as i64)),
+        ]);
+        return RuntimeError::new(RuntimeErrorKind::Raised(obj), 0);
+    }
+    let obj = object(&[
+        ("kind", Value::Str("decode_error".into())),
+        ("message", Value::Str(
+            format!("Compress.{label}: {} at offset {}", e.message, e.offset).into(),
+        )),
+        ("offset", Value::Int(e.offset as i64)),
+    ]);
+    RuntimeError::new(RuntimeErrorKind::Raised(obj), 0)
+}
+
+/// The input of an encoder: `Bytes`, or a `String` taken as UTF-8.
+fn input(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    match v {
+        Value::Bytes(b) => Ok(b.borrow().clone()),
+        Value::Str(s) => Ok(s.as_bytes().to_vec()),
+        other => Err(err(format!(
+            "Compress.{label}: expected Bytes or String, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn expect_bytes(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    match v {
+        Value::Bytes(b) => Ok(b.borrow().clone()),
+        other => Err(err(format!(
+            "Compress.{label}: expected Bytes, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn expect_format(v: &Value, label: &str) -> Result<Format, RuntimeError> {
+    match v {
+        Value::Str(s) => Format::parse(s).ok_or_else(|| {
+            err(format!(
+                "Compress.{label}: unknown format '{s}' (expected 'deflate', 'zlib' or 'gzip')"
+            ))
+        }),
+        other => Err(err(format!(
+            "Compress.{label}: format must be a String, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// The optional compression level: 0 (store) to 9 (smallest).
+fn opt_level(args: &[Value], idx: usize, label: &str) -> Result<u32, RuntimeError> {
+    match args.get(idx) {
+        None | Some(Value::Null) => Ok(DEFAULT_LEVEL as u32),
+        Some(Value::Int(n)) if (0..=9).contains(n) => Ok(*n as u32),
+        Some(Value::Int(n)) => Err(err(format!(
+            "Compress.{label}: level {n} out of range 0..=9"
+        ))),
+        Some(other) => Err(err(format!(
+            "Compress.{label}: level must be an Int, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// An output limit in bytes: a non-negative Int, or `null` for
+/// `default`.
+fn limit_value(v: Option<&Value>, default: usize, label: &str) -> Result<usize, RuntimeError> {
+    match v {
+        None | Some(Value::Null) => Ok(default),
+        Some(Value::Int(n)) if *n >= 0 => Ok(*n as usize),
+        Some(Value::Int(n)) => Err(err(format!(
+            "Compress.{label}: max_output must not be negative, got {n}"
+        ))),
+        Some(other) => Err(err(format!(
+            "Compress.{label}: max_output must be an Int, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// `decompress`'s options object: `${max_output}`.
+fn opt_max_output(args: &[Value], idx: usize, label: &str) -> Result<usize, RuntimeError> {
+    match args.get(idx) {
+        None | Some(Value::Null) => Ok(DEFAULT_MAX_OUTPUT),
+        Some(Value::Object(o)) => {
+            let v = o.borrow().get("max_output").cloned();
+            limit_value(v.as_ref(), DEFAULT_MAX_OUTPUT, label)
+        }
+        Some(other) => Err(err(format!(
+            "Compress.{label}: options must be an Object, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn bytes(data: Vec<u8>) -> Value {
+    Value::Bytes(gc::alloc_bytes(data))
+}
+
+// ---------------------------------------------------------------------
+// One-shot
+// ---------------------------------------------------------------------
+
+/// `compress(data, format, level?)` — one complete stream.
+fn c_compress(args: &[Value]) -> Result<Value, RuntimeError> {
+    let data = input(&args[0], "compress")?;
+    let format = expect_format(&args[1], "compress")?;
+    let level = opt_level(args, 2, "compress")?;
+    Ok(bytes(deflate::compress(&data, format, level)))
+}
+
+/// `decompress(data, format, opts?)` — decode one complete stream
+/// (for gzip, every concatenated member), up to `opts.max_output`
+/// bytes.
+fn c_decompress(args: &[Value]) -> Result<Value, RuntimeError> {
+    let data = expect_bytes(&args[0], "decompress")?;
+    let format = expect_format(&args[1], "decompress")?;
+    let limit = opt_max_output(args, 2, "decompress")?;
+    deflate::decompress(&data, format, limit)
+        .map(bytes)
+        .map_err(|e| decode_err("decompress", e, limit))
+}
+
+// ---------------------------------------------------------------------
+// Streams
+// ---------------------------------------------------------------------
+
+/// `encoder(format, level?)` — a stream that compresses what is
+/// written to it.
+fn c_encoder(args: &[Value]) -> Result<Value, RuntimeError> {
+    let format = expect_format(&args[0], "encoder")?;
+    let level = opt_level(args, 1, "encoder")?;
+    let stream = object(&[
+        ("format", Value::Str(format.name().into())),
+        ("mode", Value::Str("encode".into())),
+        ("level", Value::Int(level as i64)),
+    ]);
+    let Value::Object(obj) = stream else { unreachable!() };
+    store_deflater(obj, &Deflater::new(format, level), false);
+    Ok(stream)
+}
+
+/// `decoder(format)` — a stream that decompresses what is written to
+/// it.
+fn c_decoder(args: &[Value]) -> Result<Value, RuntimeError> {
+    let format = expect_format(&args[0], "decoder")?;
+    let stream = object(&[
+        ("format", Value::Str(format.name().into())),
+        ("mode", Value::Str("decode".into())),
+    ]);
+    let Value::Object(obj) = stream else { unreachable!() };
+    store_inflater(obj, &Inflater::new(format));
+    Ok(stream)
+}
+
+/// A stream argument, split by direction.
+enum Stream {
+    Encode(GcRef<ObjectKind>, Deflater, bool),
+    Decode(GcRef<ObjectKind>, Inflater),
+}
+
+fn field_bytes(obj: GcRef<ObjectKind>, key: &str) -> Option<Vec<u8>> {
+    match obj.borrow().get(key) {
+        Some(Value::Bytes(b)) => Some(b.borrow().clone()),
+        _ => None,
+    }
+}
+
+fn field_int(obj: GcRef<ObjectKind>, key: &str) -> Option<i64> {
+    match obj.borrow().get(key) {
+        Some(Value::Int(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn field_str(obj: GcRef<ObjectKind>, key: &str) -> Option<Arc<str>> {
+    match obj.borrow().get(key) {
+        Some(Value::Str(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn set(obj: GcRef<ObjectKind>, key: &str, v: Value) {
+    obj.borrow_mut().insert(Arc::from(key), v);
+}
+
+fn load_stream(v: &Value, label: &str) -> Result<Stream, RuntimeError> {
+    let bad = || {
+        err(format!(
+            "Compress.{label}: expected a stream from Compress.encoder or Compress.decoder"
+        ))
+    };
+    let corrupt = |field: &str| {
+        err(format!("Compress.{label}: stream state '{field}' is invalid"))
+    };
+    let Value::Object(obj) = v else { return Err(bad()) };
+    let obj = *obj;
+    let format = field_str(obj, "format")
+        .and_then(|s| Format::parse(&s))
+        .ok_or_else(bad)?;
+    let mode = field_str(obj, "mode").ok_or_else(bad)?;
+    let word = |field: &str| {
+        field_int(obj, field)
+            .filter(|n| (0..=u32::MAX as i64).contains(n))
+            .map(|n| n as u32)
+            .ok_or_else(|| corrupt(field))
+    };
+    let check = word("_check")?;
+    let size = word("_size")?;
+    let window = |field: &str| {
+        field_bytes(obj, field)
+            .filter(|b| b.len() <= deflate::WINDOW)
+            .ok_or_else(|| corrupt(field))
+    };
+    match &*mode {
+        "encode" => {
+            let level = field_int(obj, "level").filter(|n| (0..=9).contains(n)).ok_or_else(bad)?;
+            let done = matches!(obj.borrow().get("_done"), Some(Value::Bool(true)));
+            let started = matches!(obj.borrow().get("_started"), Some(Value::Bool(true)));
+            let d = Deflater {
+                format,
+                level: level as u32,
+                history: window("_history")?,
+                check,
+                size,
+                started,
+            };
+            Ok(Stream::Encode(obj, d, done))
+        }
+        "decode" => {
+            let stage = field_str(obj, "_stage")
+                .and_then(|s| Stage::parse(&s))
+                .ok_or_else(|| corrupt("_stage"))?;
+            let pending = field_bytes(obj, "_pending").ok_or_else(|| corrupt("_pending"))?;
+            // A bit offset or resume point must fall inside the pending
+            // input, and only a block in progress has a resume point.
+            let bits = pending.len() * 8;
+            let bit = field_int(obj, "_bit")
+                .filter(|&n| (0..8).contains(&n) && (n == 0 || (n as usize) < bits))
+                .ok_or_else(|| corrupt("_bit"))? as usize;
+            let resume = match obj.borrow().get("_resume") {
+                Some(Value::Null) | None => None,
+                Some(Value::Int(n)) if stage == Stage::Body && (bit as i64..=bits as i64).contains(n) => {
+                    Some(*n as usize)
+                }
+                Some(_) => return Err(corrupt("_resume")),
+            };
+            let consumed = field_int(obj, "_consumed")
+                .filter(|n| *n >= 0)
+                .ok_or_else(|| corrupt("_consumed"))? as usize;
+            let i = Inflater {
+                format,
+                stage,
+                pending,
+                bit,
+                resume,
+                window: window("_window")?,
+                check,
+                size,
+                consumed,
+            };
+            Ok(Stream::Decode(obj, i))
+        }
+        _ => Err(bad()),
+    }
+}
+
+fn store_deflater(obj: GcRef<ObjectKind>, d: &Deflater, done: bool) {
+    set(obj, "_history", bytes(d.history.clone()));
+    set(obj, "_check", Value::Int(d.check as i64));
+    set(obj, "_size", Value::Int(d.size as i64));
+    set(obj, "_started", Value::Bool(d.started));
+    set(obj, "_done", Value::Bool(done));
+}
+
+fn store_inflater(obj: GcRef<ObjectKind>, i: &Inflater) {
+    set(obj, "_stage", Value::Str(i.stage.name().into()));
+    set(obj, "_pending", bytes(i.pending.clone()));
+    set(obj, "_bit", Value::Int(i.bit as i64));
+    set(obj, "_resume", i.resume.map_or(Value::Null, |n| Value::Int(n as i64)));
+    set(obj, "_window", bytes(i.window.clone()));
+    set(obj, "_check", Value::Int(i.check as i64));
+    set(obj, "_size", Value::Int(i.size as i64));
+    set(obj, "_consumed", Value::Int(i.consumed as i64));
+}
+
+/// `write(stream, chunk, max_output?)` — feed `chunk`; returns the
+/// output it produced (possibly empty). An encoder flushes on every
+/// write, so its output so far always decodes to everything written.
+/// A decoder raises `too_large`, leaving the stream as it was, rather
+/// than return more than `max_output` bytes from this call.
+fn c_write(args: &[Value]) -> Result<Value, RuntimeError> {
+    match load_stream(&args[0], "write")? {
+        Stream::Encode(obj, mut d, done) => {
+            if done {
+                return Err(err("Compress.write: stream already finished".into()));
+            }
+            if !matches!(args.get(2), None | Some(Value::Null)) {
+                return Err(err("Compress.write: max_output applies only to a decoder".into()));
+            }
+            let out = d.write(&input(&args[1], "write")?);
+            store_deflater(obj, &d, false);
+            Ok(bytes(out))
+        }
+        Stream::Decode(obj, mut i) => {
+            let limit = limit_value(args.get(2), usize::MAX, "write")?;
+            let out = i.write(&expect_bytes(&args[1], "write")?, limit);
+            let out = out.map_err(|e| decode_err("write", e, limit))?;
+            store_inflater(obj, &i);
+            Ok(bytes(out))
+        }
+    }
+}
+
+/// `finish(stream)` — end the stream. An encoder returns its final
+/// bytes (last block and trailer); a decoder returns empty `Bytes`
+/// and raises if the input stopped short of the end of the stream.
+fn c_finish(args: &[Value]) -> Result<Value, RuntimeError> {
+    match load_stream(&args[0], "finish")? {
+        Stream::Encode(obj, mut d, done) => {
+            if done {
+                return Err(err("Compress.finish: stream already finished".into()));
+            }
+            let out = d.finish(&[]);
+            store_deflater(obj, &d, true);
+            Ok(bytes(out))
+        }
+        Stream::Decode(_, i) => {
+            i.finish().map_err(|e| decode_err("finish", e, 0))?;
+            Ok(bytes(Vec::new()))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------
+// Checksums
+// ---------------------------------------------------------------------
+
+/// The optional running value a checksum continues from.
+fn opt_seed(args: &[Value], label: &str, init: u32) -> Result<u32, RuntimeError> {
+    match args.get(1) {
+        None | Some(Value::Null) => Ok(init),
+        Some(Value::Int(n)) if (0..=u32::MAX as i64).contains(n) => Ok(*n as u32),
+        Some(other) => Err(err(format!(
+            "Compress.{label}: running value must be an Int in 0..=0xffffffff, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// `crc32(data, crc?)` — CRC-32 of `data`, continuing from `crc`.
+fn c_crc32(args: &[Value]) -> Result<Value, RuntimeError> {
+    let data = input(&args[0], "crc32")?;
+    let seed = opt_seed(args, "crc32", 0)?;
+    Ok(Value::Int(deflate::crc32(seed, &data) as i64))
+}
+
+/// `adler32(data, adler?)` — Adler-32 of `data`, continuing from
+/// `adler`.
+fn c_adler32(args: &[Value]) -> Result<Value, RuntimeError> {
+    let data = input(&args[0], "adler32")?;
+    let seed = opt_seed(args, "adler32", 1)?;
+    Ok(Value::Int(deflate::adler32(seed, &data) as i64))
+}
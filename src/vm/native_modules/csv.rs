@@ -0,0 +1,758 @@
+//! `import '_NativeCsv'` — the RFC 4180 parser and formatter behind the
+//! source `Csv` module (`stdlib/Csv.tg`).
+//!
+//! Parsing is incremental: `parser(opts)` validates the options into a
+//! parser Object, and `feed(parser, chunk, last)` appends `chunk` to the
+//! parser's pending bytes and returns every record that is now
+//! complete — a record split across chunks (even mid-character) waits
+//! in the parser for the rest. `Csv.parse` is a single `feed` with
+//! `last` set; `Csv.reader` feeds file reads one at a time from a
+//! generator, so a file is never held in memory whole.
+//!
+//! Formatting mirrors it: `formatter(opts)` builds a formatter Object
+//! and `format(formatter, rows)` renders rows, writing the header line
+//! before the first Object row. Fields are quoted only when they must
+//! be.
+//!
+//! Parser and formatter state live in the Object (public option fields
+//! plus underscore-prefixed working fields), as `Compress` streams do.
+//! Malformed input raises `${kind: 'parse_error', message, line,
+//! column}`, the message carrying the same "at line L, column C" suffix
+//! `JSON.parse` uses.
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc::{self, GcRef, ObjectKind};
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object, parse_error};
+
+pub fn module() -> Value {
+    object(&[
+        ("parser",    native("parser",    Arity::Range(0, 1), parser)),
+        ("feed",      native("feed",      Arity::Exact(3), feed)),
+        ("formatter", native("formatter", Arity::Range(0, 1), formatter)),
+        ("format",    native("format",    Arity::Exact(2), format)),
+    ])
+}
+
+/// A catchable, string-valued error. The VM backfills the call line.
+fn raise(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+/// A catchable `parse_error` at a 1-based line and column.
+fn csv_error(msg: &str, line: usize, column: usize) -> RuntimeError {
+    parse_error(format!("Csv.parse: {msg} at line {line}, column {column}"), line, column)
+}
+
+// ---------------------------------------------------------------------
+// Options
+// ---------------------------------------------------------------------
+
+/// The dialect both directions share. Delimiter, quote and escape are
+/// single ASCII characters; `quote: null` turns quoting off.
+struct Dialect {
+    delimiter: u8,
+    quote: Option<u8>,
+    /// `None`: a quote inside a quoted field is doubled (RFC 4180).
+    escape: Option<u8>,
+}
+
+fn get(opts: Option<GcRef<ObjectKind>>, key: &str) -> Option<Value> {
+    opts.and_then(|o| o.borrow().get(key).cloned())
+}
+
+fn opts_arg(args: &[Value], label: &str) -> Result<Option<GcRef<ObjectKind>>, RuntimeError> {
+    match args.first() {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Object(o)) => Ok(Some(*o)),
+        Some(other) => Err(raise(format!(
+            "Csv.{label}: options must be an Object, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// A single-ASCII-character option; `Ok(None)` when set to `null` and
+/// `nullable`.
+fn char_opt(
+    opts: Option<GcRef<ObjectKind>>,
+    key: &str,
+    default: Option<u8>,
+    nullable: bool,
+    label: &str,
+) -> Result<Option<u8>, RuntimeError> {
+    match opts.and_then(|o| o.borrow().get(key).cloned()) {
+        None => Ok(default),
+        Some(Value::Null) if nullable => Ok(None),
+        Some(Value::Str(s)) if s.len() == 1 && s.is_ascii() && !matches!(&*s, "\r" | "\n") => {
+            Ok(Some(s.as_bytes()[0]))
+        }
+        Some(other) => Err(raise(format!(
+            "Csv.{label}: `{key}` must be a single ASCII character{}, got {}",
+            if nullable { " or null" } else { "" },
+            match other {
+                Value::Str(s) => format!("'{s}'"),
+                v => v.type_name().to_string(),
+            }
+        ))),
+    }
+}
+
+fn bool_opt(
+    opts: Option<GcRef<ObjectKind>>,
+    key: &str,
+    default: bool,
+    label: &str,
+) -> Result<bool, RuntimeError> {
+    match get(opts, key) {
+        None | Some(Value::Null) => Ok(default),
+        Some(Value::Bool(b)) => Ok(b),
+        Some(other) => Err(raise(format!(
+            "Csv.{label}: `{key}` must be a Bool, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// The `columns` option: an Array of Strings, or null.
+fn columns_opt(opts: Option<GcRef<ObjectKind>>, label: &str) -> Result<Value, RuntimeError> {
+    match get(opts, "columns") {
+        None | Some(Value::Null) => Ok(Value::Null),
+        Some(Value::Array(a)) => {
+            if a.borrow().iter().any(|v| !matches!(v, Value::Str(_))) {
+                return Err(raise(format!("Csv.{label}: `columns` must be an Array of Strings")));
+            }
+            Ok(Value::Array(gc::alloc_array(a.borrow().clone())))
+        }
+        Some(other) => Err(raise(format!(
+            "Csv.{label}: `columns` must be an Array of Strings, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn dialect(opts: Option<GcRef<ObjectKind>>, label: &str) -> Result<Dialect, RuntimeError> {
+    let delimiter = char_opt(opts, "delimiter", Some(b','), false, label)?.unwrap_or(b',');
+    let quote = char_opt(opts, "quote", Some(b'"'), true, label)?;
+    let escape = char_opt(opts, "escape", None, true, label)?;
+    if Some(delimiter) == quote {
+        return Err(raise(format!("Csv.{label}: `delimiter` and `quote` must differ")));
+    }
+    // An escape equal to the quote is the RFC doubling rule.
+    let escape = escape.filter(|&e| Some(e) != quote);
+    Ok(Dialect { delimiter, quote, escape })
+}
+
+fn char_value(c: Option<u8>) -> Value {
+    c.map_or(Value::Null, |c| Value::Str((c as char).to_string().into()))
+}
+
+fn load_dialect(obj: GcRef<ObjectKind>, label: &str) -> Result<Dialect, RuntimeError> {
+    dialect(Some(obj), label)
+}
+
+fn set(obj: GcRef<ObjectKind>, key: &str, v: Value) {
+    obj.borrow_mut().insert(Arc::from(key), v);
+}
+
+fn expect_state(v: &Value, mode: &str, label: &str) -> Result<GcRef<ObjectKind>, RuntimeError> {
+    match v {
+        Value::Object(o)
+            if matches!(o.borrow().get("_mode"), Some(Value::Str(m)) if &**m == mode) =>
+        {
+            Ok(*o)
+        }
+        _ => Err(raise(format!("Csv.{label}: expected a Csv {mode}"))),
+    }
+}
+
+// ---------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------
+
+/// `parser(opts?)` — validate options into a parser Object:
+/// `${delimiter, quote, escape, header, types, columns, ...}`.
+fn parser(args: &[Value]) -> Result<Value, RuntimeError> {
+    let opts = opts_arg(args, "parser")?;
+    let d = dialect(opts, "parser")?;
+    let columns = columns_opt(opts, "parser")?;
+    let header = bool_opt(opts, "header", columns == Value::Null, "parser")?;
+    let types = bool_opt(opts, "types", false, "parser")?;
+    Ok(object(&[
+        ("delimiter", char_value(Some(d.delimiter))),
+        ("quote", char_value(d.quote)),
+        ("escape", char_value(d.escape)),
+        ("header", Value::Bool(header)),
+        ("types", Value::Bool(types)),
+        ("columns", columns),
+        ("_mode", Value::Str("parser".into())),
+        ("_pending", Value::Bytes(gc::alloc_bytes(Vec::new()))),
+        ("_line", Value::Int(1)),
+        ("_started", Value::Bool(false)),
+    ]))
+}
+
+/// One parsed field: its raw bytes, and whether it was quoted (a
+/// quoted field is never type-sniffed).
+struct Field {
+    text: Vec<u8>,
+    quoted: bool,
+}
+
+/// A parse failure at byte `pos` of the scanned buffer.
+struct ScanError {
+    pos: usize,
+    message: &'static str,
+}
+
+/// Scan one record starting at `data[start]`. Returns its fields and
+/// the offset just past its line ending, or `None` if `data` ends
+/// before the record does and more input may follow (`!last`).
+fn scan_record(
+    data: &[u8],
+    start: usize,
+    d: &Dialect,
+    last: bool,
+) -> Result<Option<(Vec<Field>, usize)>, ScanError> {
+    let len = data.len();
+    let mut i = start;
+    let mut fields = Vec::new();
+    loop {
+        let mut text = Vec::new();
+        let quoted = d.quote.is_some() && i < len && Some(data[i]) == d.quote;
+        if quoted {
+            let open = i;
+            i += 1;
+            loop {
+                if i >= len {
+                    if last {
+                        return Err(ScanError { pos: open, message: "unterminated quoted field" });
+                    }
+                    return Ok(None);
+                }
+                let c = data[i];
+                if d.escape == Some(c) {
+                    if i + 1 >= len {
+                        if last {
+                            return Err(ScanError { pos: i, message: "escape character at end of input" });
+                        }
+                        return Ok(None);
+                    }
+                    text.push(data[i + 1]);
+                    i += 2;
+                } else if d.quote == Some(c) {
+                    if d.escape.is_none() && i + 1 < len && data[i + 1] == c {
+                        text.push(c);
+                        i += 2;
+                    } else if d.escape.is_none() && i + 1 >= len && !last {
+                        // Could be the first half of a doubled quote.
+                        return Ok(None);
+                    } else {
+                        i += 1;
+                        break;
+                    }
+                } else {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+            if i < len && data[i] != d.delimiter && data[i] != b'\r' && data[i] != b'\n' {
+                return Err(ScanError { pos: i, message: "unexpected character after closing quote" });
+            }
+        } else {
+            let end = data[i..]
+                .iter()
+                .position(|&c| c == d.delimiter || c == b'\n' || c == b'\r')
+                .map_or(len, |p| i + p);
+            text.extend_from_slice(&data[i..end]);
+            i = end;
+        }
+        fields.push(Field { text, quoted });
+        if i >= len {
+            return Ok(if last { Some((fields, i)) } else { None });
+        }
+        match data[i] {
+            b'\n' => return Ok(Some((fields, i + 1))),
+            b'\r' => {
+                if i + 1 >= len && !last {
+                    return Ok(None);
+                }
+                let next = if data.get(i + 1) == Some(&b'\n') { i + 2 } else { i + 1 };
+                return Ok(Some((fields, next)));
+            }
+            _ => i += 1, // the delimiter: another field follows
+        }
+    }
+}
+
+/// A field as a tigr value: text, or — with type sniffing on and the
+/// field unquoted — `null`, a Bool, an Int or a Float where it reads as
+/// one.
+fn field_value(text: String, quoted: bool, types: bool) -> Value {
+    if !types || quoted {
+        return Value::Str(text.into());
+    }
+    match text.as_str() {
+        "" => return Value::Null,
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Some(v) = sniff_number(&text) {
+        return v;
+    }
+    Value::Str(text.into())
+}
+
+/// An Int or Float spelled plainly: optional sign, digits, optional
+/// fraction and exponent. A leading zero on a multi-digit integer part
+/// (`007`, `0123.5`) keeps the field text — it is an identifier, not a
+/// number.
+fn sniff_number(s: &str) -> Option<Value> {
+    let b = s.as_bytes();
+    let mut i = usize::from(matches!(b.first(), Some(b'+' | b'-')));
+    let int_start = i;
+    while i < b.len() && b[i].is_ascii_digit() {
+        i += 1;
+    }
+    let int_digits = i - int_start;
+    if int_digits > 1 && b[int_start] == b'0' {
+        return None;
+    }
+    let mut is_float = false;
+    let mut frac_digits = 0;
+    if i < b.len() && b[i] == b'.' {
+        is_float = true;
+        i += 1;
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+            frac_digits += 1;
+        }
+    }
+    if int_digits + frac_digits == 0 {
+        return None;
+    }
+    if i < b.len() && (b[i] == b'e' || b[i] == b'E') {
+        is_float = true;
+        i += 1;
+        if i < b.len() && (b[i] == b'+' || b[i] == b'-') {
+            i += 1;
+        }
+        let exp_start = i;
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i == exp_start {
+            return None;
+        }
+    }
+    if i != b.len() {
+        return None;
+    }
+    if !is_float {
+        if let Ok(n) = s.parse::<i64>() {
+            return Some(Value::Int(n));
+        }
+    }
+    s.parse::<f64>().ok().map(Value::Float)
+}
+
+/// 1-based line and column (in characters) of byte `pos`, given that
+/// `data[start..]` begins at the start of line `line`.
+fn locate(data: &[u8], start: usize, pos: usize, line: usize) -> (usize, usize) {
+    let before = &data[start..pos.min(data.len())];
+    let newlines = before.iter().filter(|&&c| c == b'\n').count();
+    let line_start = before.iter().rposition(|&c| c == b'\n').map_or(0, |p| p + 1);
+    let column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+    (line + newlines, column)
+}
+
+/// `feed(parser, chunk, last)` — append `chunk` (Bytes or String) and
+/// return the records it completes: Arrays, or Objects keyed by the
+/// header / `columns`. With `last`, whatever remains is the final
+/// record.
+fn feed(args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = expect_state(&args[0], "parser", "feed")?;
+    let d = load_dialect(p, "feed")?;
+    let last = matches!(args[2], Value::Bool(true));
+    let (types, header) = {
+        let o = p.borrow();
+        (
+            matches!(o.get("types"), Some(Value::Bool(true))),
+            matches!(o.get("header"), Some(Value::Bool(true))),
+        )
+    };
+    let mut data = match get(Some(p), "_pending") {
+        Some(Value::Bytes(b)) => b.borrow().clone(),
+        _ => return Err(raise("Csv.feed: expected a Csv parser".into())),
+    };
+    match &args[1] {
+        Value::Bytes(b) => data.extend_from_slice(&b.borrow()),
+        Value::Str(s) => data.extend_from_slice(s.as_bytes()),
+        other => {
+            return Err(raise(format!(
+                "Csv.feed: expected Bytes or String, got {}",
+                other.type_name()
+            )))
+        }
+    }
+    let mut line = match get(Some(p), "_line") {
+        Some(Value::Int(n)) => n as usize,
+        _ => 1,
+    };
+    let mut pos = 0;
+    // A UTF-8 byte-order mark (as spreadsheet exports write) is not data.
+    if !matches!(get(Some(p), "_started"), Some(Value::Bool(true))) {
+        if data.len() < 3 && !last && b"\xEF\xBB\xBF".starts_with(&data) {
+            set(p, "_pending", Value::Bytes(gc::alloc_bytes(data)));
+            return Ok(Value::Array(gc::alloc_array(Vec::new())));
+        }
+        if data.starts_with(b"\xEF\xBB\xBF") {
+            pos = 3;
+        }
+        set(p, "_started", Value::Bool(true));
+    }
+    let mut columns: Option<Vec<Arc<str>>> = match get(Some(p), "columns") {
+        Some(Value::Array(a)) => Some(
+            a.borrow()
+                .iter()
+                .map(|v| match v {
+                    Value::Str(s) => s.clone(),
+                    _ => Arc::from(""),
+                })
+                .collect(),
+        ),
+        _ => None,
+    };
+    // With explicit `columns`, `header` means "skip the file's own".
+    let mut skip_header = header && columns.is_some() && line == 1;
+    let mut rows = Vec::new();
+    while pos < data.len() {
+        let scanned = scan_record(&data, pos, &d, last).map_err(|e| {
+            let (l, c) = locate(&data, pos, e.pos, line);
+            csv_error(e.message, l, c)
+        })?;
+        let Some((fields, next)) = scanned else { break };
+        let record_line = line;
+        line += data[pos..next].iter().filter(|&&c| c == b'\n').count();
+        // A lone `\r` ends a record too; count it as a line.
+        if data[..next].last() == Some(&b'\r') {
+            line += 1;
+        }
+        let start = pos;
+        pos = next;
+        // A blank line is not a record.
+        if fields.len() == 1 && !fields[0].quoted && fields[0].text.is_empty() {
+            continue;
+        }
+        let mut values = Vec::with_capacity(fields.len());
+        for f in fields {
+            let text = String::from_utf8(f.text).map_err(|_| {
+                let (l, c) = locate(&data, start, start, record_line);
+                csv_error("invalid UTF-8 in record", l, c)
+            })?;
+            values.push((text, f.quoted));
+        }
+        if skip_header {
+            skip_header = false;
+            continue;
+        }
+        if header && columns.is_none() {
+            let names: Vec<Arc<str>> = values.into_iter().map(|(t, _)| Arc::from(t)).collect();
+            let arr = names.iter().map(|s| Value::Str(s.clone())).collect();
+            set(p, "columns", Value::Array(gc::alloc_array(arr)));
+            columns = Some(names);
+            continue;
+        }
+        let row = match &columns {
+            None => {
+                let arr = values.into_iter().map(|(t, q)| field_value(t, q, types)).collect();
+                Value::Array(gc::alloc_array(arr))
+            }
+            Some(names) => {
+                if values.len() > names.len() {
+                    let (l, _) = locate(&data, start, start, record_line);
+                    return Err(csv_error(
+                        &format!(
+                            "record has {} fields but there are {} columns",
+                            values.len(),
+                            names.len()
+                        ),
+                        l,
+                        1,
+                    ));
+                }
+                let mut map: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(names.len());
+                let mut it = values.into_iter();
+                for name in names {
+                    let v = it.next().map_or(Value::Null, |(t, q)| field_value(t, q, types));
+                    map.insert(name.clone(), v);
+                }
+                Value::Object(gc::alloc_object(map))
+            }
+        };
+        rows.push(row);
+    }
+    data.drain(..pos);
+    set(p, "_pending", Value::Bytes(gc::alloc_bytes(data)));
+    set(p, "_line", Value::Int(line as i64));
+    Ok(Value::Array(gc::alloc_array(rows)))
+}
+
+// ---------------------------------------------------------------------
+// Formatting
+// ---------------------------------------------------------------------
+
+/// `formatter(opts?)` — validate options into a formatter Object:
+/// `${delimiter, quote, escape, newline, header, columns, ...}`.
+fn formatter(args: &[Value]) -> Result<Value, RuntimeError> {
+    let opts = opts_arg(args, "formatter")?;
+    let d = dialect(opts, "formatter")?;
+    let columns = columns_opt(opts, "formatter")?;
+    let header = bool_opt(opts, "header", true, "formatter")?;
+    let newline = match get(opts, "newline") {
+        None | Some(Value::Null) => Value::Str("\r\n".into()),
+        Some(Value::Str(s)) if matches!(&*s, "\r\n" | "\n" | "\r") => Value::Str(s),
+        Some(_) => {
+            return Err(raise(
+                "Csv.formatter: `newline` must be '\\r\\n', '\\n' or '\\r'".into(),
+            ))
+        }
+    };
+    Ok(object(&[
+        ("delimiter", char_value(Some(d.delimiter))),
+        ("quote", char_value(d.quote)),
+        ("escape", char_value(d.escape)),
+        ("newline", newline),
+        ("header", Value::Bool(header)),
+        ("columns", columns),
+        ("_mode", Value::Str("formatter".into())),
+        ("_wrote_header", Value::Bool(false)),
+    ]))
+}
+
+/// Append one field, quoted only if it holds the delimiter, the quote,
+/// the escape, a line break, or leading/trailing whitespace a reader
+/// might trim.
+fn write_field(out: &mut String, s: &str, d: &Dialect) -> Result<(), RuntimeError> {
+    let special = |c: char| {
+        c == d.delimiter as char
+            || c == '\r'
+            || c == '\n'
+            || d.quote == Some(c as u8) && c.is_ascii()
+            || d.escape == Some(c as u8) && c.is_ascii()
+    };
+    let padded = s.starts_with([' ', '\t']) || s.ends_with([' ', '\t']);
+    if !s.chars().any(special) && !padded {
+        out.push_str(s);
+        return Ok(());
+    }
+    let Some(q) = d.quote else {
+        return Err(raise(format!(
+            "Csv.format: field {s:?} needs quoting but `quote` is null"
+        )));
+    };
+    let q = q as char;
+    out.push(q);
+    for c in s.chars() {
+        if c == q {
+            out.push(d.escape.map_or(q, |e| e as char));
+        } else if d.escape == Some(c as u8) && c.is_ascii() {
+            out.push(c);
+        }
+        out.push(c);
+    }
+    out.push(q);
+    Ok(())
+}
+
+fn field_text(v: &Value) -> Result<String, RuntimeError> {
+    match v {
+        Value::Null => Ok(String::new()),
+        Value::Str(s) => Ok(s.to_string()),
+        Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::BigInt(_) => Ok(v.to_string()),
+        other => Err(raise(format!(
+            "Csv.format: a field must be a String, number, Bool or null, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn write_record(out: &mut String, fields: &[String], d: &Dialect, newline: &str) -> Result<(), RuntimeError> {
+    for (i, f) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(d.delimiter as char);
+        }
+        write_field(out, f, d)?;
+    }
+    // A single empty field would read back as a blank line.
+    if fields.len() == 1 && fields[0].is_empty() {
+        if let Some(q) = d.quote {
+            out.push(q as char);
+            out.push(q as char);
+        }
+    }
+    out.push_str(newline);
+    Ok(())
+}
+
+/// `format(formatter, rows)` — render an Array of rows (each an Array
+/// of fields, or an Object keyed by column) as CSV text. Before the
+/// first Object row, the header line is written (unless `header` is
+/// false) from `columns`, or else that row's keys.
+fn format(args: &[Value]) -> Result<Value, RuntimeError> {
+    let f = expect_state(&args[0], "formatter", "format")?;
+    let d = load_dialect(f, "format")?;
+    let newline = match get(Some(f), "newline") {
+        Some(Value::Str(s)) => s,
+        _ => Arc::from("\r\n"),
+    };
+    let header = matches!(get(Some(f), "header"), Some(Value::Bool(true)));
+    let rows = match &args[1] {
+        Value::Array(a) => a.borrow().clone(),
+        other => {
+            return Err(raise(format!(
+                "Csv.format: expected an Array of rows, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let mut out = String::new();
+    for row in &rows {
+        match row {
+            Value::Array(a) => {
+                let fields = a.borrow().iter().map(field_text).collect::<Result<Vec<_>, _>>()?;
+                write_record(&mut out, &fields, &d, &newline)?;
+            }
+            Value::Object(o) => {
+                let columns: Vec<Arc<str>> = match get(Some(f), "columns") {
+                    Some(Value::Array(c)) => c
+                        .borrow()
+                        .iter()
+                        .filter_map(|v| match v {
+                            Value::Str(s) => Some(s.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => {
+                        let keys: Vec<Arc<str>> = o.borrow().keys().cloned().collect();
+                        let arr = keys.iter().map(|k| Value::Str(k.clone())).collect();
+                        set(f, "columns", Value::Array(gc::alloc_array(arr)));
+                        keys
+                    }
+                };
+                if header && !matches!(get(Some(f), "_wrote_header"), Some(Value::Bool(true))) {
+                    let names: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                    write_record(&mut out, &names, &d, &newline)?;
+                    set(f, "_wrote_header", Value::Bool(true));
+                }
+                let obj = o.borrow();
+                if let Some(extra) = obj.keys().find(|k| !columns.contains(k)) {
+                    return Err(raise(format!(
+                        "Csv.format: row has key '{extra}' that is not a column"
+                    )));
+                }
+                let fields = columns
+                    .iter()
+                    .map(|c| field_text(obj.get(c).unwrap_or(&Value::Null)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                write_record(&mut out, &fields, &d, &newline)?;
+            }
+            other => {
+                return Err(raise(format!(
+                    "Csv.format: a row must be an Array or Object, got {}",
+                    other.type_name()
+                )))
+            }
+        }
+    }
+    Ok(Value::Str(out.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc() -> Dialect {
+        Dialect { delimiter: b',', quote: Some(b'"'), escape: None }
+    }
+
+    fn texts(data: &[u8], last: bool) -> Option<(Vec<String>, usize)> {
+        scan_record(data, 0, &rfc(), last)
+            .ok()
+            .flatten()
+            .map(|(f, n)| (f.into_iter().map(|f| String::from_utf8(f.text).unwrap()).collect(), n))
+    }
+
+    #[test]
+    fn scans_quoted_fields_with_delimiters_newlines_and_quotes() {
+        let (fields, next) = texts(b"a,\"b,c\",\"d\ne\",\"f\"\"g\"\r\nrest", false).unwrap();
+        assert_eq!(fields, ["a", "b,c", "d\ne", "f\"g"]);
+        assert_eq!(next, 22);
+    }
+
+    #[test]
+    fn an_incomplete_record_waits_for_more_input() {
+        assert!(texts(b"a,\"b", false).is_none());
+        assert!(texts(b"a,b", false).is_none());
+        assert!(texts(b"a,\"b\"", false).is_none(), "a trailing quote may be doubled");
+        assert!(texts(b"a,b\r", false).is_none(), "CR may start a CRLF");
+        assert_eq!(texts(b"a,b", true).unwrap().0, ["a", "b"]);
+    }
+
+    #[test]
+    fn reports_malformed_quoting() {
+        let e = scan_record(b"\"ab\"x", 0, &rfc(), true).err().unwrap();
+        assert_eq!((e.pos, e.message), (4, "unexpected character after closing quote"));
+        let e = scan_record(b"x,\"ab", 0, &rfc(), true).err().unwrap();
+        assert_eq!((e.pos, e.message), (2, "unterminated quoted field"));
+    }
+
+    #[test]
+    fn an_escape_character_replaces_doubling() {
+        let d = Dialect { delimiter: b';', quote: Some(b'\''), escape: Some(b'\\') };
+        let (fields, _) = scan_record(b"'it\\'s';x\n", 0, &d, true).ok().flatten().unwrap();
+        assert_eq!(fields[0].text, b"it's");
+        let mut out = String::new();
+        write_field(&mut out, "it's", &d).unwrap();
+        assert_eq!(out, "'it\\'s'");
+    }
+
+    #[test]
+    fn sniffs_plain_numbers_only() {
+        assert_eq!(sniff_number("42"), Some(Value::Int(42)));
+        assert_eq!(sniff_number("-0.5"), Some(Value::Float(-0.5)));
+        assert_eq!(sniff_number("1e3"), Some(Value::Float(1000.0)));
+        assert_eq!(sniff_number("0"), Some(Value::Int(0)));
+        for text in ["007", "1.2.3", "e5", ".", "-", "12a", "0x10", "1e"] {
+            assert_eq!(sniff_number(text), None, "{text}");
+        }
+        assert!(matches!(sniff_number("99999999999999999999"), Some(Value::Float(_))));
+    }
+
+    #[test]
+    fn quotes_only_when_needed() {
+        let d = rfc();
+        let quoted = |s: &str| {
+            let mut out = String::new();
+            write_field(&mut out, s, &d).unwrap();
+            out
+        };
+        assert_eq!(quoted("plain"), "plain");
+        assert_eq!(quoted("a,b"), "\"a,b\"");
+        assert_eq!(quoted("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quoted("two\nlines"), "\"two\nlines\"");
+        assert_eq!(quoted(" padded"), "\" padded\"");
+    }
+
+    #[test]
+    fn locates_errors_by_line_and_character_column() {
+        let data = "a\nxé,\"b".as_bytes();
+        assert_eq!(locate(data, 0, data.len() - 2, 4), (5, 4));
+    }
+}
@@ -17,7 +17,7 @@ use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
 
-const MS_PER_DAY: i64 = 86_400_000;
+pub(crate) const MS_PER_DAY: i64 = 86_400_000;
 
 pub fn module() -> Value {
     object(&[
@@ -35,7 +35,7 @@ fn raise(msg: String) -> RuntimeError {
 
 // ---- Hinnant civil <-> days-since-epoch ----
 
-fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+pub(crate) fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
     let y = if m <= 2 { y - 1 } else { y };
     let era = (if y >= 0 { y } else { y - 399 }) / 400;
     let yoe = y - era * 400;
@@ -45,7 +45,7 @@ fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
     era * 146097 + doe - 719468
 }
 
-fn civil_from_days(z: i64) -> (i64, i64, i64) {
+pub(crate) fn civil_from_days(z: i64) -> (i64, i64, i64) {
     let z = z + 719468;
     let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
     let doe = z - era * 146097;
@@ -103,6 +103,10 @@ fn parts_object(p: &Parts) -> Value {
 
 #[cfg(not(target_arch = "wasm32"))]
 fn now(_args: &[Value]) -> Result<Value, RuntimeError> {
+    // Simulation mode reads the virtual clock (see `crate::vm::sim`).
+    if crate::vm::sim::clock_here() {
+        return Ok(parts_object(&parts_from_ms(crate::vm::sim::unix_nanos() / 1_000_000)));
+    }
     let d = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map_err(|e| raise(format!("DateTime.now: {e}")))?;
@@ -156,9 +160,13 @@ fn to_ms(args: &[Value]) -> Result<Value, RuntimeError> {
     let minute = field(&map, "minute", 0)?;
     let second = field(&map, "second", 0)?;
     let ms = field(&map, "ms", 0)?;
+    // Minutes east of UTC the other fields are local to, as a TOML
+    // offset date-time carries.
+    let offset = field(&map, "offset", 0)?;
     let days = days_from_civil(year, month, day);
     Ok(Value::Int(
-        days * MS_PER_DAY + hour * 3_600_000 + minute * 60_000 + second * 1000 + ms,
+        days * MS_PER_DAY + hour * 3_600_000 + minute * 60_000 + second * 1000 + ms
+            - offset * 60_000,
     ))
 }
 
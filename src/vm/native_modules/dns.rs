@@ -0,0 +1,53 @@
+//! `import '_NativeDns'` — the system-resolver backend for the source
+//! `Dns` module (`stdlib/Dns.tg`).
+//!
+//! Only `lookup` lives here: it asks the operating system's resolver
+//! (`getaddrinfo`, so `/etc/hosts`, NSS and search domains all apply)
+//! for every address of a host. It waits on the worker pool, since the
+//! system resolver has no async form. The stub resolver that queries a
+//! DNS server for individual record types is pure tigr, over `Net`.
+
+use std::net::{IpAddr, ToSocketAddrs};
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::offload::{BlockingJob, OffloadErr, OffloadOk};
+use crate::vm::value::{Arity, Value};
+
+use super::{native_blocking, object};
+
+pub fn module() -> Value {
+    object(&[("lookup", native_blocking("lookup", Arity::Exact(1), lookup))])
+}
+
+/// `lookup(host)` — every IPv4 and IPv6 address the system resolver
+/// knows for `host`, as Strings in the resolver's preference order,
+/// duplicates dropped. An IP literal resolves to itself. Raises
+/// `${kind: 'dns'}` when the name does not resolve.
+fn lookup(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let host = match &args[0] {
+        Value::Str(s) => s.to_string(),
+        other => {
+            let msg = format!("Dns.lookup: expected String, got {}", other.type_name());
+            return Err(RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0));
+        }
+    };
+    Ok(Box::new(move || {
+        let fail = |message: String| OffloadErr {
+            kind: Some("dns".to_string()),
+            message: format!("Dns.lookup: {host}: {message}"),
+        };
+        // A bracketed IPv6 literal, as in a URL, names the same address.
+        let name = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(&host);
+        let addrs = (name, 0).to_socket_addrs().map_err(|e| fail(e.to_string()))?;
+        let mut seen: Vec<IpAddr> = Vec::new();
+        for addr in addrs {
+            if !seen.contains(&addr.ip()) {
+                seen.push(addr.ip());
+            }
+        }
+        if seen.is_empty() {
+            return Err(fail("no address resolved".to_string()));
+        }
+        Ok(OffloadOk::StrList(seen.iter().map(IpAddr::to_string).collect()))
+    }))
+}
@@ -17,6 +17,7 @@ use indexmap::IndexMap;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc::{self, ArrayKind, GcRef, ObjectKind};
+use crate::vm::shared::SharedNode;
 use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
@@ -44,7 +45,19 @@ fn parse(args: &[Value]) -> Result<Value, RuntimeError> {
             )));
         }
     };
-    let mut p = Parser::new(&s);
+    parse_text(&s, false)
+}
+
+/// Parse JSON text that [`encode`] wrote, for a native that keeps
+/// values as JSON (`Store`). Unlike `JSON.parse`, an integer literal
+/// that fits comes back as `Int`, so `Int`s round-trip — `encode`
+/// gives every `Float` a `.` or exponent.
+pub(crate) fn decode(s: &str) -> Result<Value, RuntimeError> {
+    parse_text(s, true)
+}
+
+fn parse_text(s: &str, ints: bool) -> Result<Value, RuntimeError> {
+    let mut p = Parser::new(s, ints);
     p.skip_ws();
     let value = p.parse_value()?;
     p.skip_ws();
@@ -59,11 +72,13 @@ struct Parser<'a> {
     pos: usize,
     line: u32,
     col: u32,
+    /// Parse integer literals as `Int` (see [`decode`]).
+    ints: bool,
 }
 
 impl<'a> Parser<'a> {
-    fn new(s: &'a str) -> Self {
-        Parser { bytes: s.as_bytes(), pos: 0, line: 1, col: 1 }
+    fn new(s: &'a str, ints: bool) -> Self {
+        Parser { bytes: s.as_bytes(), pos: 0, line: 1, col: 1, ints }
     }
 
     fn err(&self, msg: &str) -> RuntimeError {
@@ -298,6 +313,11 @@ impl<'a> Parser<'a> {
         }
         let lex = std::str::from_utf8(&self.bytes[start..self.pos])
             .expect("ASCII number lex");
+        if self.ints && !lex.contains(['.', 'e', 'E']) {
+            if let Ok(n) = lex.parse::<i64>() {
+                return Ok(Value::Int(n));
+            }
+        }
         lex.parse::<f64>()
             .map(Value::Float)
             .map_err(|_| self.err(&format!("invalid number {lex:?}")))
@@ -380,10 +400,19 @@ fn stringify(args: &[Value]) -> Result<Value, RuntimeError> {
     // identified by its handle within its own kind's set.
     let mut seen_a: Vec<GcRef<ArrayKind>> = Vec::new();
     let mut seen_o: Vec<GcRef<ObjectKind>> = Vec::new();
-    write_value(&mut out, &args[0], indent.as_deref(), 0, &mut seen_a, &mut seen_o)?;
+    write_value(&mut out, &args[0], indent.as_deref(), 0, &mut seen_a, &mut seen_o, "JSON.stringify")?;
     Ok(Value::Str(out.into()))
 }
 
+/// Compact JSON text for `v`, as `JSON.stringify(v)` writes it, for a
+/// native that keeps values as JSON (`Store`). `label` names the entry
+/// in the "cannot serialize" error.
+pub(crate) fn encode(v: &Value, label: &str) -> Result<String, RuntimeError> {
+    let mut out = String::new();
+    write_value(&mut out, v, None, 0, &mut Vec::new(), &mut Vec::new(), label)?;
+    Ok(out)
+}
+
 /// Raise the catchable `cycle` error. Line `0` — the VM stamps the
 /// `JSON.stringify` call-site line for native errors.
 fn cycle_err() -> RuntimeError {
@@ -397,6 +426,7 @@ fn write_value(
     depth: usize,
     seen_a: &mut Vec<GcRef<ArrayKind>>,
     seen_o: &mut Vec<GcRef<ObjectKind>>,
+    label: &str,
 ) -> Result<(), RuntimeError> {
     match v {
         Value::Null => out.push_str("null"),
@@ -422,7 +452,7 @@ fn write_value(
                     out.push(',');
                 }
                 write_indent(out, indent, depth + 1);
-                write_value(out, item, indent, depth + 1, seen_a, seen_o)?;
+                write_value(out, item, indent, depth + 1, seen_a, seen_o, label)?;
             }
             write_indent(out, indent, depth);
             out.push(']');
@@ -449,12 +479,18 @@ fn write_value(
                 if indent.is_some() {
                     out.push(' ');
                 }
-                write_value(out, val, indent, depth + 1, seen_a, seen_o)?;
+                write_value(out, val, indent, depth + 1, seen_a, seen_o, label)?;
             }
             write_indent(out, indent, depth);
             out.push('}');
             seen_o.pop();
         }
+        // A shared array or object serializes as its thawed copy would.
+        Value::Shared(node)
+            if matches!(**node, SharedNode::Array(_) | SharedNode::Object(_)) =>
+        {
+            write_value(out, &node.thaw(), indent, depth, seen_a, seen_o, label)?;
+        }
         // Non-serializable value types — raise so the caller can `try`.
         Value::Function(_)
         | Value::NativeFn(_)
@@ -468,12 +504,16 @@ fn write_value(
         | Value::Task(_)
         | Value::Socket(_)
         | Value::File(_)
+        | Value::Store(_)
         | Value::Generator(_)
         | Value::GreenHandle(_)
         | Value::LocalChannel(_)
-        | Value::Deferred(_) => {
+        | Value::Deferred(_)
+        | Value::Shared(_)
+        | Value::Atomic(_)
+        | Value::SharedMap(_) => {
             return Err(raise(format!(
-                "JSON.stringify: cannot serialize {}",
+                "{label}: cannot serialize {}",
                 v.type_name()
             )));
         }
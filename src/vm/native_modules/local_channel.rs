@@ -9,6 +9,9 @@
 //! `close` never suspend. The blocking `recv` is built in
 //! `LocalChannel.tg` by `yield`-looping on `try_recv` — cooperative
 //! waiting belongs in tigr, where `yield` exists.
+//!
+//! `oneshot`, `broadcast`, `watch`, `subscribe` and `current` mirror
+//! the `_NativeChannel` functions of the same names, on local channels.
 
 use std::sync::Arc;
 
@@ -16,7 +19,7 @@ use indexmap::IndexMap;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc::{self, GcRef, LocalChannelKind};
-use crate::vm::local_channel::LocalChannel;
+use crate::vm::local_channel::{LocalChannel, LocalFlavour};
 use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
@@ -24,6 +27,11 @@ use super::{native, object};
 pub fn module() -> Value {
     object(&[
         ("new", native("new", Arity::Exact(0), lc_new)),
+        ("oneshot", native("oneshot", Arity::Exact(0), lc_oneshot)),
+        ("broadcast", native("broadcast", Arity::Exact(1), lc_broadcast)),
+        ("watch", native("watch", Arity::Exact(1), lc_watch)),
+        ("subscribe", native("subscribe", Arity::Exact(2), lc_subscribe)),
+        ("current", native("current", Arity::Exact(1), lc_current)),
         ("send", native("send", Arity::Exact(2), lc_send)),
         ("try_recv", native("try_recv", Arity::Exact(1), lc_try_recv)),
         ("close", native("close", Arity::Exact(1), lc_close)),
@@ -45,6 +53,28 @@ fn as_local_channel(v: &Value) -> Result<GcRef<LocalChannelKind>, RuntimeError>
     }
 }
 
+/// Raise a `type_mismatch` with `msg`.
+fn mismatch(msg: &str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::TypeMismatch(msg.to_string()), 0)
+}
+
+fn raise(msg: &str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+/// A lag limit argument: a positive integer, or `null` for none.
+fn lag_limit(v: &Value) -> Result<Option<usize>, RuntimeError> {
+    match v {
+        Value::Null => Ok(None),
+        Value::Int(n) if *n > 0 => Ok(Some(*n as usize)),
+        Value::Int(_) => Err(raise("LocalChannel lag limit must be a positive integer")),
+        other => Err(raise(&format!(
+            "LocalChannel lag limit must be an integer or null, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
 /// Build a single-field result object (`${key: val}`).
 fn tagged(key: &str, val: Value) -> Value {
     let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(1);
@@ -57,7 +87,62 @@ fn lc_new(_args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::LocalChannel(gc::alloc_local_channel(LocalChannel::new())))
 }
 
+/// `oneshot()` — a local channel for one message: the first `send`
+/// delivers it and closes the channel.
+fn lc_oneshot(_args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::LocalChannel(gc::alloc_local_channel(LocalChannel::oneshot())))
+}
+
+/// `broadcast(lag)` — a local broadcast channel whose subscriptions
+/// keep up to `lag` unreceived messages by default.
+fn lc_broadcast(args: &[Value]) -> Result<Value, RuntimeError> {
+    let lag = lag_limit(&args[0])?.ok_or_else(|| raise("broadcast needs a lag limit"))?;
+    Ok(Value::LocalChannel(gc::alloc_local_channel(LocalChannel::broadcast(lag))))
+}
+
+/// `watch(initial)` — a local watch channel holding `initial`.
+fn lc_watch(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::LocalChannel(gc::alloc_local_channel(LocalChannel::watch(args[0].clone()))))
+}
+
+/// `subscribe(channel, lag)` — a receiving handle for a local broadcast
+/// or watch channel; `lag` (`null` for the channel's own) limits how
+/// far a broadcast subscription may fall behind. A subscription to a
+/// closed channel starts closed.
+fn lc_subscribe(args: &[Value]) -> Result<Value, RuntimeError> {
+    let ch = as_local_channel(&args[0])?;
+    let lag = lag_limit(&args[1])?;
+    let mut c = ch.borrow_mut();
+    let closed = c.closed;
+    let LocalFlavour::Hub { subscribers, lag: default, current } = &mut c.flavour else {
+        return Err(mismatch("subscribe needs a broadcast or watch channel"));
+    };
+    let mut sub = LocalChannel::subscription(ch, lag.unwrap_or(*default), current.is_some());
+    sub.closed = closed;
+    let sub = gc::alloc_local_channel(sub);
+    if !closed {
+        subscribers.push(sub);
+    }
+    Ok(Value::LocalChannel(sub))
+}
+
+/// `current(channel)` — a local watch channel's value, read from the
+/// channel or a subscription to it.
+fn lc_current(args: &[Value]) -> Result<Value, RuntimeError> {
+    let ch = as_local_channel(&args[0])?;
+    let hub = match &ch.borrow().flavour {
+        LocalFlavour::Subscriber { hub, .. } => *hub,
+        _ => ch,
+    };
+    let c = hub.borrow();
+    match &c.flavour {
+        LocalFlavour::Hub { current: Some(v), .. } => Ok(v.clone()),
+        _ => Err(mismatch("current needs a watch channel or a subscription to one")),
+    }
+}
+
 /// `send(channel, message)` — enqueues `message` by value (no copy).
+/// On a broadcast or watch channel, hands it to every subscription.
 /// Raises `channel_closed` on a closed channel. Returns `null`.
 fn lc_send(args: &[Value]) -> Result<Value, RuntimeError> {
     let ch = as_local_channel(&args[0])?;
@@ -65,16 +150,47 @@ fn lc_send(args: &[Value]) -> Result<Value, RuntimeError> {
     if c.closed {
         return Err(RuntimeError::new(RuntimeErrorKind::ChannelClosed, 0));
     }
-    c.queue.push_back(args[1].clone());
+    let msg = args[1].clone();
+    match &mut c.flavour {
+        LocalFlavour::Queue => c.queue.push_back(msg),
+        LocalFlavour::Oneshot => {
+            c.queue.push_back(msg);
+            c.closed = true;
+        }
+        LocalFlavour::Hub { subscribers, current, .. } => {
+            subscribers.retain(|sub| !sub.borrow().closed);
+            for sub in subscribers.iter() {
+                sub.borrow_mut().offer(msg.clone());
+            }
+            if current.is_some() {
+                *current = Some(msg);
+            }
+        }
+        LocalFlavour::Subscriber { .. } => {
+            return Err(mismatch(
+                "cannot send on a subscription; send on its broadcast or watch channel",
+            ));
+        }
+    }
     Ok(Value::Null)
 }
 
 /// `try_recv(channel)` — never blocks. Returns `${value: v}`,
 /// `${closed: true}`, or `${empty: true}` if nothing is ready. The
 /// `LocalChannel.tg` `recv` wrapper `yield`-loops on the `empty` case.
+/// Raises `lagged` for a broadcast subscription that fell behind.
 fn lc_try_recv(args: &[Value]) -> Result<Value, RuntimeError> {
     let ch = as_local_channel(&args[0])?;
     let mut c = ch.borrow_mut();
+    if c.is_hub() {
+        return Err(mismatch(
+            "cannot receive from a broadcast or watch channel; \
+             receive from a subscription to it",
+        ));
+    }
+    if let Some(n) = c.take_missed() {
+        return Err(RuntimeError::new(RuntimeErrorKind::Lagged(n), 0));
+    }
     if let Some(msg) = c.queue.pop_front() {
         Ok(tagged("value", msg))
     } else if c.closed {
@@ -85,10 +201,17 @@ fn lc_try_recv(args: &[Value]) -> Result<Value, RuntimeError> {
 }
 
 /// `close(channel)` — marks the channel closed: `send` then raises and
-/// `recv` drains the buffer, then reports `${closed: true}`. Returns
+/// `recv` drains the buffer, then reports `${closed: true}`. Closing a
+/// broadcast or watch channel closes its subscriptions too. Returns
 /// `null`.
 fn lc_close(args: &[Value]) -> Result<Value, RuntimeError> {
     let ch = as_local_channel(&args[0])?;
-    ch.borrow_mut().closed = true;
+    let mut c = ch.borrow_mut();
+    c.closed = true;
+    if let LocalFlavour::Hub { subscribers, .. } = &mut c.flavour {
+        for sub in std::mem::take(subscribers) {
+            sub.borrow_mut().closed = true;
+        }
+    }
     Ok(Value::Null)
 }
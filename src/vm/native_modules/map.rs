@@ -10,6 +10,7 @@ use indexmap::IndexMap;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc::{self, GcRef, MapKind};
+use crate::vm::shared::{self, SharedHandle, SharedItem, SharedNode};
 use crate::vm::value::{Arity, MapKey, Value};
 
 use super::{native, object};
@@ -33,12 +34,77 @@ fn err(msg: String) -> RuntimeError {
     RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
 }
 
-fn expect_map(
+/// A `Map` argument an entry only reads: a heap map, or a `Shared.of`
+/// map, whose nested collections read as further shared values.
+enum MapArg {
+    Heap(GcRef<MapKind>),
+    Shared(SharedHandle),
+}
+
+impl MapArg {
+    fn shared(node: &SharedNode) -> &IndexMap<MapKey, SharedItem> {
+        match node {
+            SharedNode::Map(m) => m,
+            _ => unreachable!("only a shared map becomes a MapArg"),
+        }
+    }
+
+    fn get(&self, key: &MapKey) -> Option<Value> {
+        match self {
+            MapArg::Heap(m) => m.borrow().get(key).cloned(),
+            MapArg::Shared(n) => Self::shared(n).get(key).map(SharedItem::to_value),
+        }
+    }
+
+    fn contains(&self, key: &MapKey) -> bool {
+        match self {
+            MapArg::Heap(m) => m.borrow().contains_key(key),
+            MapArg::Shared(n) => Self::shared(n).contains_key(key),
+        }
+    }
+
+    /// Every entry, in insertion order.
+    fn pairs(&self) -> Vec<(Value, Value)> {
+        match self {
+            MapArg::Heap(m) => m
+                .borrow()
+                .iter()
+                .map(|(k, v)| (Value::from(k.clone()), v.clone()))
+                .collect(),
+            MapArg::Shared(n) => Self::shared(n)
+                .iter()
+                .map(|(k, v)| (Value::from(k.clone()), v.to_value()))
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            MapArg::Heap(m) => m.borrow().len(),
+            MapArg::Shared(n) => n.len(),
+        }
+    }
+}
+
+fn expect_map(v: &Value, label: &str) -> Result<MapArg, RuntimeError> {
+    match v {
+        Value::Map(m) => Ok(MapArg::Heap(*m)),
+        Value::Shared(n) if matches!(**n, SharedNode::Map(_)) => Ok(MapArg::Shared(n.clone())),
+        other => Err(err(format!(
+            "Map.{label}: expected Map, got {}", other.type_name()
+        ))),
+    }
+}
+
+/// [`expect_map`] for an entry that mutates the map: a shared map
+/// raises `immutable` rather than a type error.
+fn expect_map_mut(
     v: &Value,
     label: &str,
 ) -> Result<GcRef<MapKind>, RuntimeError> {
     match v {
         Value::Map(m) => Ok(*m),
+        Value::Shared(_) => Err(shared::immutable(&format!("call Map.{label}"), 0)),
         other => Err(err(format!(
             "Map.{label}: expected Map, got {}", other.type_name()
         ))),
@@ -92,13 +158,13 @@ fn m_new(args: &[Value]) -> Result<Value, RuntimeError> {
 fn m_get(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "get")?;
     let key = MapKey::from_value(&args[1], 0)?;
-    let value = map.borrow().get(&key).cloned().unwrap_or(Value::Null);
+    let value = map.get(&key).unwrap_or(Value::Null);
     Ok(value)
 }
 
 /// `set(m, key, value)` → inserts in place, returns `m`.
 fn m_set(args: &[Value]) -> Result<Value, RuntimeError> {
-    let map = expect_map(&args[0], "set")?;
+    let map = expect_map_mut(&args[0], "set")?;
     let key = MapKey::from_value(&args[1], 0)?;
     map.borrow_mut().insert(key, args[2].clone());
     Ok(args[0].clone())
@@ -108,14 +174,14 @@ fn m_set(args: &[Value]) -> Result<Value, RuntimeError> {
 fn m_has(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "has")?;
     let key = MapKey::from_value(&args[1], 0)?;
-    let present = map.borrow().contains_key(&key);
+    let present = map.contains(&key);
     Ok(Value::Bool(present))
 }
 
 /// `delete(m, key)` → removes the key, returns `true` if it was
 /// present. `shift_remove` preserves the insertion order of the rest.
 fn m_delete(args: &[Value]) -> Result<Value, RuntimeError> {
-    let map = expect_map(&args[0], "delete")?;
+    let map = expect_map_mut(&args[0], "delete")?;
     let key = MapKey::from_value(&args[1], 0)?;
     let removed = map.borrow_mut().shift_remove(&key).is_some();
     Ok(Value::Bool(removed))
@@ -128,38 +194,34 @@ fn array(items: Vec<Value>) -> Value {
 /// `keys(m)` → Array of keys in insertion order.
 fn m_keys(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "keys")?;
-    let keys = map.borrow().keys().map(|k| Value::from(k.clone())).collect();
+    let keys = map.pairs().into_iter().map(|(k, _)| k).collect();
     Ok(array(keys))
 }
 
 /// `values(m)` → Array of values in insertion order.
 fn m_values(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "values")?;
-    let values = map.borrow().values().cloned().collect();
+    let values = map.pairs().into_iter().map(|(_, v)| v).collect();
     Ok(array(values))
 }
 
 /// `entries(m)` → Array of `[key, value]` pairs in insertion order.
 fn m_entries(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "entries")?;
-    let entries = map
-        .borrow()
-        .iter()
-        .map(|(k, v)| array(vec![Value::from(k.clone()), v.clone()]))
-        .collect();
+    let entries = map.pairs().into_iter().map(|(k, v)| array(vec![k, v])).collect();
     Ok(array(entries))
 }
 
 /// `size(m)` → entry count.
 fn m_size(args: &[Value]) -> Result<Value, RuntimeError> {
     let map = expect_map(&args[0], "size")?;
-    let n = map.borrow().len() as i64;
+    let n = map.len() as i64;
     Ok(Value::Int(n))
 }
 
 /// `clear(m)` → empties `m` in place, returns `m`.
 fn m_clear(args: &[Value]) -> Result<Value, RuntimeError> {
-    let map = expect_map(&args[0], "clear")?;
+    let map = expect_map_mut(&args[0], "clear")?;
     map.borrow_mut().clear();
     Ok(args[0].clone())
 }
@@ -8,11 +8,18 @@
 //! `Vm` run.
 
 pub mod array;
+pub mod atomic;
 pub mod bigint;
 pub mod bytes;
 pub mod channel;
+pub mod compress;
+pub mod csv;
 pub mod datetime;
 pub mod deferred;
+// The system-resolver backend for the source `Dns` module; like `Net`
+// it needs real sockets, so `wasm32` goes without.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dns;
 pub mod io;
 pub mod json;
 pub mod local_channel;
@@ -27,10 +34,19 @@ pub mod object;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod os;
 pub mod path;
+// The hashing backend for the source `Postgres` module: `ring`, like
+// the TLS stack it comes with, is native-only.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod postgres;
 pub mod random;
 pub mod set;
+pub mod shared;
+pub mod shared_map;
+pub mod store;
 pub mod string;
 pub mod time;
+pub mod toml;
+pub mod yaml;
 // The browser `WebSocket` backend for `import 'WS'`. Built only for a
 // plain-wasm host (purr): on `wasm32` there is no `Net` for the
 // pure-tigr `WS.tg` to use, so the same `WS` API is served by this
@@ -44,9 +60,9 @@ use std::sync::Arc;
 
 use indexmap::IndexMap;
 
-use crate::vm::error::RuntimeError;
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc;
-use crate::vm::offload::BlockingJob;
+use crate::vm::offload::{BlockingJob, HandoffOp};
 use crate::vm::socket::ReactorOp;
 use crate::vm::value::{Arity, NativeFn, NativeKind, Value, WaitKind};
 
@@ -61,7 +77,8 @@ use crate::vm::value::{Arity, NativeFn, NativeKind, Value, WaitKind};
 pub fn names() -> &'static [&'static str] {
     &[
         "IO", "Path", "Time", "DateTime", "JSON", "Random", "Bytes",
-        "BigInt", "Os", "Net", "Deferred",
+        "BigInt", "Os", "Net", "Deferred", "Compress", "Toml", "Yaml",
+        "Shared", "Atomic",
     ]
 }
 
@@ -78,10 +95,21 @@ pub fn resolve(name: &str) -> Option<Value> {
         "Random" => Some(random::module()),
         "Bytes" => Some(bytes::module()),
         "BigInt" => Some(bigint::module()),
+        // Pure computation over `Bytes` — no IO — so every target has it.
+        "Compress" => Some(compress::module()),
+        "Toml" => Some(toml::module()),
+        "Yaml" => Some(yaml::module()),
         // First-class deferred results. Pure VM machinery (GC + the
         // cooperative scheduler), so available on every target including
         // `wasm32` — no threads, sockets or processes involved.
         "Deferred" => Some(deferred::module()),
+        // Frozen, `Arc`-backed collections. Plain data with no threads
+        // of its own, so every target has it.
+        "Shared" => Some(shared::module()),
+        // Counters and maps shared between actors. Plain `std::sync`
+        // atomics and a mutex, which every target has.
+        "Atomic" => Some(atomic::module()),
+        "_NativeSharedMap" => Some(shared_map::module()),
         // `Os` (processes/env) and the cross-actor `_NativeChannel` use
         // `std::process` and OS threads — portable to every native
         // target, so they stay enabled on Windows. Only `wasm32` (no
@@ -96,6 +124,10 @@ pub fn resolve(name: &str) -> Option<Value> {
         // panicking.
         #[cfg(not(target_arch = "wasm32"))]
         "Net" => Some(net::module()),
+        #[cfg(not(target_arch = "wasm32"))]
+        "_NativeDns" => Some(dns::module()),
+        #[cfg(not(target_arch = "wasm32"))]
+        "_NativePostgres" => Some(postgres::module()),
         // `WS` on a plain-wasm host: no `Net` exists for the source
         // `WS.tg`, so the browser-`WebSocket` backend serves the same
         // API. On native, `WS` resolves earlier via `source_stdlib`
@@ -107,6 +139,8 @@ pub fn resolve(name: &str) -> Option<Value> {
         // them directly if it wants the raw primitives.
         "_NativeLocalChannel" => Some(local_channel::module()),
         "_NativeArray" => Some(array::module()),
+        "_NativeCsv" => Some(csv::module()),
+        "_NativeStore" => Some(store::module()),
         "_NativeMap" => Some(map::module()),
         "_NativeMath" => Some(math::module()),
         "_NativeObject" => Some(object::module()),
@@ -147,6 +181,24 @@ pub fn native_blocking(
     }))
 }
 
+/// Build a `Value::NativeFn` for a *handoff* entry — a cross-actor
+/// wait (a channel `send`/`recv`) that parks the caller and is
+/// completed by whichever thread makes the result available, rather
+/// than by a worker sleeping on it. `func` runs on the actor thread to
+/// validate arguments and build the [`HandoffOp`]. See
+/// [`crate::vm::offload::Waiter`].
+pub fn native_handoff(
+    name: &'static str,
+    arity: Arity,
+    func: fn(&[Value]) -> Result<HandoffOp, RuntimeError>,
+) -> Value {
+    Value::NativeFn(Rc::new(NativeFn {
+        name,
+        arity,
+        kind: NativeKind::Handoff(func),
+    }))
+}
+
 /// Build a `Value::NativeFn` for a steady-state *socket* entry — a
 /// `Net` stream / datagram call the VM drives on the async-IO reactor
 /// instead of a worker thread (see [`crate::vm::reactor`]). `func` runs
@@ -201,6 +253,31 @@ pub fn object(entries: &[(&'static str, Value)]) -> Value {
     Value::Object(gc::alloc_object(m))
 }
 
+/// A catchable structured `parse_error` for the text-format parsers
+/// (`Csv`, `Toml`, `Yaml`): `${kind: 'parse_error', message, line,
+/// column}`. `message` is already prefixed with the failing entry
+/// (`"Toml.parse: ..."`) and ends in the same "at line L, column C"
+/// that `JSON.parse` messages do.
+pub fn parse_error(message: String, line: usize, column: usize) -> RuntimeError {
+    let obj = object(&[
+        ("kind", Value::Str("parse_error".into())),
+        ("message", Value::Str(message.into())),
+        ("line", Value::Int(line as i64)),
+        ("column", Value::Int(column as i64)),
+    ]);
+    RuntimeError::new(RuntimeErrorKind::Raised(obj), 0)
+}
+
+/// The 1-based line and column (counted in characters) of byte `pos`
+/// in `src`, for a parser's error position.
+pub fn line_col(src: &[u8], pos: usize) -> (usize, usize) {
+    let before = &src[..pos.min(src.len())];
+    let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+    let start = before.iter().rposition(|&c| c == b'\n').map_or(0, |p| p + 1);
+    let column = String::from_utf8_lossy(&before[start..]).chars().count() + 1;
+    (line, column)
+}
+
 /// Build a `Value::Bytes` from an owned byte block, the byte-buffer
 /// counterpart of [`object`]. A host native that produces raw bytes (a
 /// pixel buffer's RGBA, a decoded blob) returns them with this; read them
@@ -1,4 +1,5 @@
-//! `import 'Net'` — TCP / UDP / TLS networking (v0.15).
+//! `import 'Net'` — TCP / UDP / TLS networking (v0.15), plus Unix
+//! domain sockets and socket options.
 //!
 //! A socket is a first-class `Value` (`Value::Socket`): `Arc`-backed
 //! and `Send`, so it crosses an actor boundary. The idiom is one actor
@@ -13,15 +14,19 @@
 //!
 //! Failures raise a catchable structured error `${kind, message}`;
 //! `kind` is one of `timeout`, `closed`, `eof`, `refused`, `dns`,
-//! `tls`, `addr_in_use`, `decode`, or `io`.
+//! `tls`, `addr_in_use`, `decode`, `too_large`, or `io`.
 
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::time::Duration;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::offload::{BlockingJob, OffloadErr, OffloadOk, OffloadResult};
-use crate::vm::socket::{self, NetError, ReactorOp, SocketHandle, SocketOp};
+use crate::vm::gc;
+use crate::vm::socket::{
+    self, Addr, ClientAuth, NetError, ReactorOp, SockOpt, SocketHandle, SocketOp,
+    TlsClientOpts, TlsInfo, TlsServerOpts, TlsVersion,
+};
 use crate::vm::value::{Arity, Value};
 
 use super::{native, native_blocking, native_socket, object};
@@ -31,18 +36,26 @@ pub fn module() -> Value {
         // -- TCP --  (`listen` binds without waiting. `accept` is
         // driven on the async-IO reactor inside a green thread;
         // `connect` waits on the worker pool — DNS has no async form.)
-        ("listen",      native("listen",      Arity::Exact(2), n_listen)),
+        ("listen",      native("listen",      Arity::Range(2, 3), n_listen)),
         ("accept",      native_socket("accept",    Arity::Exact(1), n_accept)),
         ("connect",     native_blocking("connect", Arity::Exact(2), n_connect)),
         // -- TLS --  (`listen_tls` binds without waiting — the PEM
         // parse is CPU-only; `connect_tls` waits on the worker pool.)
-        ("listen_tls",  native("listen_tls",  Arity::Exact(4), n_listen_tls)),
+        ("listen_tls",  native("listen_tls",  Arity::Range(3, 4), n_listen_tls)),
         ("connect_tls", native_blocking("connect_tls", Arity::Range(2, 3), n_connect_tls)),
+        ("start_tls",   native_blocking("start_tls", Arity::Range(2, 3), n_start_tls)),
+        // Finishes a lazy server handshake first — reactor-driven.
+        ("tls_info",    native_socket("tls_info", Arity::Exact(1), n_tls_info)),
         // -- UDP --  (`recv_from` waits for a datagram — reactor-driven;
         // `send_to` resolves its target with DNS, so it stays pooled.)
-        ("bind",        native("bind",        Arity::Exact(2), n_bind)),
-        ("send_to",     native_blocking("send_to", Arity::Exact(4), n_send_to)),
+        ("bind",        native("bind",        Arity::Range(2, 3), n_bind)),
+        ("send_to",     native_blocking("send_to", Arity::Range(3, 4), n_send_to)),
         ("recv_from",   native_socket("recv_from", Arity::Exact(2), n_recv_from)),
+        // -- Unix domain --  (`listen_unix` binds without waiting; a
+        // connect can wait on a full backlog, so it is pooled. The
+        // sockets then run on the reactor like their TCP / UDP twins.)
+        ("listen_unix",  native("listen_unix", Arity::Range(1, 2), n_listen_unix)),
+        ("connect_unix", native_blocking("connect_unix", Arity::Range(1, 2), n_connect_unix)),
         // -- stream I/O --  (steady-state socket reads / writes — driven
         // on the async-IO reactor inside a green thread; see
         // `crate::vm::reactor`.)
@@ -52,13 +65,14 @@ pub fn module() -> Value {
         ("read_available", native("read_available", Arity::Exact(2), n_read_available)),
         ("write",       native_socket("write",      Arity::Exact(2), n_write)),
         ("read_exact",  native_socket("read_exact", Arity::Exact(2), n_read_exact)),
-        ("read_line",   native_socket("read_line",  Arity::Exact(1), n_read_line)),
-        ("read_until",  native_socket("read_until", Arity::Exact(2), n_read_until)),
+        ("read_line",   native_socket("read_line",  Arity::Range(1, 2), n_read_line)),
+        ("read_until",  native_socket("read_until", Arity::Range(2, 3), n_read_until)),
         ("read_all",    native_socket("read_all",   Arity::Exact(1), n_read_all)),
         // -- addressing & lifecycle (all non-waiting — stay inline) --
         ("local_addr",  native("local_addr",  Arity::Exact(1), n_local_addr)),
         ("peer_addr",   native("peer_addr",   Arity::Exact(1), n_peer_addr)),
         ("set_timeout", native("set_timeout", Arity::Exact(2), n_set_timeout)),
+        ("set_option",  native("set_option",  Arity::Exact(3), n_set_option)),
         ("close",       native("close",       Arity::Exact(1), n_close)),
     ])
 }
@@ -94,8 +108,17 @@ fn classify(label: &str, e: NetError) -> (Option<&'static str>, String) {
         NetError::WrongKind(msg) => (None, format!("Net.{label}: {msg}")),
         NetError::Dns(msg) => (Some("dns"), format!("Net.{label}: {msg}")),
         NetError::Tls(msg) => (Some("tls"), format!("Net.{label}: {msg}")),
+        NetError::TooLong(max) => (
+            Some("too_large"),
+            format!("Net.{label}: no delimiter within {max} bytes"),
+        ),
         NetError::Io(io_err) => {
+            // A TLS alert or protocol failure mid-stream — e.g. a server
+            // refusing a missing client certificate — reaches us as the
+            // `rustls::Error` the record layer wrapped in an I/O error.
+            let tls = io_err.get_ref().is_some_and(|e| e.is::<rustls::Error>());
             let kind = match io_err.kind() {
+                _ if tls => "tls",
                 io::ErrorKind::ConnectionRefused => "refused",
                 io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => "timeout",
                 io::ErrorKind::AddrInUse => "addr_in_use",
@@ -249,25 +272,171 @@ fn expect_bytes(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
     }
 }
 
-/// Build a `${host, port}` address object.
-fn addr_object(addr: SocketAddr) -> Value {
-    object(&[
-        ("host", Value::Str(addr.ip().to_string().into())),
-        ("port", Value::Int(addr.port() as i64)),
-    ])
+fn expect_bool(v: &Value, label: &str, name: &str) -> Result<bool, RuntimeError> {
+    match v {
+        Value::Bool(b) => Ok(*b),
+        other => Err(err(format!(
+            "Net.{label}: '{name}' expects a Bool, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Build a `${host, port}` address object, or `${path}` for a Unix
+/// domain socket (`path` is `null` for an unnamed one).
+fn addr_object(addr: Addr) -> Value {
+    match addr {
+        Addr::Inet(addr) => object(&[
+            ("host", Value::Str(addr.ip().to_string().into())),
+            ("port", Value::Int(addr.port() as i64)),
+        ]),
+        #[cfg(unix)]
+        Addr::Unix(path) => object(&[(
+            "path",
+            path.map_or(Value::Null, |p| Value::Str(p.into())),
+        )]),
+    }
+}
+
+/// The `recv_from` result for a datagram from `addr` — shared by the
+/// inline executor and the reactor.
+pub(crate) fn recv_from_ok(data: Vec<u8>, addr: Addr) -> OffloadOk {
+    match addr {
+        Addr::Inet(addr) => OffloadOk::RecvFrom {
+            data,
+            host: addr.ip().to_string(),
+            port: addr.port(),
+        },
+        #[cfg(unix)]
+        Addr::Unix(path) => OffloadOk::RecvFromPath { data, path },
+    }
+}
+
+// ---------------------------------------------------------------------
+// Socket options
+// ---------------------------------------------------------------------
+
+/// The group (and interface) of a `join_multicast` / `leave_multicast`
+/// value: a group address String, or `${group, interface}` where the
+/// interface is an IPv4 address String or an IPv6 interface index.
+fn multicast(v: &Value, label: &str) -> Result<(IpAddr, Ipv4Addr, u32), RuntimeError> {
+    let parse_ip = |s: &str| {
+        s.parse::<IpAddr>().map_err(|_| {
+            err(format!("Net.{label}: '{s}' is not an IP address"))
+        })
+    };
+    let (group, iface) = match v {
+        Value::Str(s) => (parse_ip(s)?, None),
+        Value::Object(o) => {
+            let o = o.borrow();
+            let group = match o.get("group") {
+                Some(Value::Str(s)) => parse_ip(s)?,
+                _ => {
+                    return Err(err(format!(
+                        "Net.{label}: a multicast Object needs a String 'group'"
+                    )));
+                }
+            };
+            (group, o.get("interface").cloned())
+        }
+        other => {
+            return Err(err(format!(
+                "Net.{label}: a multicast group is a String or ${{group, interface}}, got {}",
+                other.type_name()
+            )));
+        }
+    };
+    if !group.is_multicast() {
+        return Err(err(format!("Net.{label}: {group} is not a multicast address")));
+    }
+    match (group, iface) {
+        (_, None | Some(Value::Null)) => Ok((group, Ipv4Addr::UNSPECIFIED, 0)),
+        (IpAddr::V4(_), Some(Value::Str(s))) => match s.parse::<Ipv4Addr>() {
+            Ok(ip) => Ok((group, ip, 0)),
+            Err(_) => Err(err(format!(
+                "Net.{label}: interface '{s}' is not an IPv4 address"
+            ))),
+        },
+        (IpAddr::V6(_), Some(Value::Int(n))) if (0..=u32::MAX as i64).contains(&n) => {
+            Ok((group, Ipv4Addr::UNSPECIFIED, n as u32))
+        }
+        (IpAddr::V4(_), Some(_)) => Err(err(format!(
+            "Net.{label}: an IPv4 group's interface is an address String"
+        ))),
+        (IpAddr::V6(_), Some(_)) => Err(err(format!(
+            "Net.{label}: an IPv6 group's interface is an interface index Int"
+        ))),
+    }
+}
+
+/// Parse one option `name` / `value` pair, as `set_option` and the
+/// `listen` / `bind` options Object take them.
+fn sock_opt(name: &str, v: &Value, label: &str) -> Result<SockOpt, RuntimeError> {
+    let size = || match expect_int(v, label)? {
+        n if (1..=i32::MAX as i64).contains(&n) => Ok(n as usize),
+        n => Err(err(format!("Net.{label}: '{name}' must be a positive size, got {n}"))),
+    };
+    Ok(match name {
+        "nodelay" => SockOpt::NoDelay(expect_bool(v, label, name)?),
+        "keepalive" => SockOpt::KeepAlive(expect_bool(v, label, name)?),
+        "reuseaddr" => SockOpt::ReuseAddr(expect_bool(v, label, name)?),
+        "reuseport" => SockOpt::ReusePort(expect_bool(v, label, name)?),
+        "send_buffer" => SockOpt::SendBuffer(size()?),
+        "recv_buffer" => SockOpt::RecvBuffer(size()?),
+        "broadcast" => SockOpt::Broadcast(expect_bool(v, label, name)?),
+        "multicast_loop" => SockOpt::MulticastLoop(expect_bool(v, label, name)?),
+        "multicast_ttl" => match expect_int(v, label)? {
+            n if (0..=255).contains(&n) => SockOpt::MulticastTtl(n as u32),
+            n => return Err(err(format!("Net.{label}: multicast_ttl {n} out of range 0..=255"))),
+        },
+        "join_multicast" => {
+            let (group, v4_iface, v6_index) = multicast(v, label)?;
+            SockOpt::JoinMulticast { group, v4_iface, v6_index }
+        }
+        "leave_multicast" => {
+            let (group, v4_iface, v6_index) = multicast(v, label)?;
+            SockOpt::LeaveMulticast { group, v4_iface, v6_index }
+        }
+        other => return Err(err(format!("Net.{label}: unknown option '{other}'"))),
+    })
+}
+
+/// The optional options Object of `listen` / `bind`, in key order;
+/// `None` when absent or `null`.
+fn bind_opts(v: Option<&Value>, label: &str) -> Result<Option<Vec<SockOpt>>, RuntimeError> {
+    match v {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Object(o)) => {
+            let o = o.borrow();
+            let mut opts = Vec::new();
+            for (k, v) in o.iter() {
+                opts.push(sock_opt(k, v, label)?);
+            }
+            Ok(Some(opts))
+        }
+        Some(other) => Err(err(format!(
+            "Net.{label}: options must be an Object, got {}",
+            other.type_name()
+        ))),
+    }
 }
 
 // ---------------------------------------------------------------------
 // TCP
 // ---------------------------------------------------------------------
 
-/// `listen(host, port)` — a TCP listener bound to `host:port`. Pass
-/// port `0` to let the OS choose; read it back with `local_addr`.
+/// `listen(host, port, opts?)` — a TCP listener bound to `host:port`.
+/// Pass port `0` to let the OS choose; read it back with `local_addr`.
+/// `opts` holds `set_option` settings applied before the bind, the
+/// only time `reuseport` can take effect.
 fn n_listen(args: &[Value]) -> Result<Value, RuntimeError> {
     let host = expect_str(&args[0], "listen")?;
     let port = expect_port(&args[1], "listen")?;
-    let sock = socket::listen(host, port).map_err(|e| map_err("listen", e))?;
-    Ok(Value::Socket(sock))
+    let sock = match bind_opts(args.get(2), "listen")? {
+        None => socket::listen(host, port),
+        Some(opts) => socket::listen_with(host, port, &opts),
+    };
+    Ok(Value::Socket(sock.map_err(|e| map_err("listen", e))?))
 }
 
 /// `accept(listener)` — wait for the next inbound connection. Raises
@@ -292,58 +461,326 @@ fn n_connect(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
 // TLS
 // ---------------------------------------------------------------------
 
-/// `listen_tls(host, port, cert_pem, key_pem)` — a TLS server listener
-/// bound to `host:port`. `cert_pem` / `key_pem` are PEM *content*
-/// (certificate chain and private key), not file paths. `accept` on the
-/// returned listener yields server-side TLS sockets, so
-/// `Http.serve(Net.listen_tls(...), handler)` is an HTTPS server.
+/// A String field of a TLS options Object.
+fn tls_str(v: &Value, label: &str, key: &str) -> Result<String, RuntimeError> {
+    match v {
+        Value::Str(s) => Ok(s.to_string()),
+        other => Err(err(format!(
+            "Net.{label}: '{key}' expects a String, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// The `alpn` field — an Array of protocol-name Strings, each 1 to 255
+/// bytes, in preference order.
+fn tls_alpn(v: &Value, label: &str) -> Result<Vec<String>, RuntimeError> {
+    let Value::Array(a) = v else {
+        return Err(err(format!(
+            "Net.{label}: 'alpn' expects an Array of Strings, got {}",
+            v.type_name()
+        )));
+    };
+    let mut out = Vec::new();
+    for p in a.borrow().iter() {
+        match p {
+            Value::Str(s) if (1..=255).contains(&s.len()) => out.push(s.to_string()),
+            Value::Str(s) => {
+                return Err(err(format!(
+                    "Net.{label}: ALPN protocol '{s}' must be 1 to 255 bytes"
+                )));
+            }
+            other => {
+                return Err(err(format!(
+                    "Net.{label}: 'alpn' expects an Array of Strings, found {}",
+                    other.type_name()
+                )));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The `min_version` field — `'1.2'` or `'1.3'`.
+fn tls_min_version(v: &Value, label: &str) -> Result<TlsVersion, RuntimeError> {
+    match tls_str(v, label, "min_version")?.as_str() {
+        "1.2" => Ok(TlsVersion::V12),
+        "1.3" => Ok(TlsVersion::V13),
+        other => Err(err(format!(
+            "Net.{label}: min_version must be '1.2' or '1.3', got '{other}'"
+        ))),
+    }
+}
+
+/// Reject a `cert` without its `key`, or the reverse.
+fn tls_pair(cert: &Option<String>, key: &Option<String>, label: &str) -> Result<(), RuntimeError> {
+    if cert.is_some() != key.is_some() {
+        return Err(err(format!("Net.{label}: 'cert' and 'key' go together")));
+    }
+    Ok(())
+}
+
+/// The client options of `connect_tls` / `start_tls`: absent, a CA PEM
+/// String (shorthand for `${ca}`), or an options Object. An unknown
+/// key raises, so a misspelt option never silently weakens the config.
+fn tls_client_opts(v: Option<&Value>, label: &str) -> Result<TlsClientOpts, RuntimeError> {
+    let mut opts = TlsClientOpts::default();
+    let o = match v {
+        None | Some(Value::Null) => return Ok(opts),
+        Some(Value::Str(ca)) => {
+            opts.ca_pem = Some(ca.to_string());
+            return Ok(opts);
+        }
+        Some(Value::Object(o)) => o.borrow(),
+        Some(other) => {
+            return Err(err(format!(
+                "Net.{label}: options must be an Object, got {}",
+                other.type_name()
+            )));
+        }
+    };
+    for (k, v) in o.iter() {
+        if matches!(v, Value::Null) {
+            continue;
+        }
+        match &**k {
+            "ca" => opts.ca_pem = Some(tls_str(v, label, k)?),
+            "cert" => opts.cert_pem = Some(tls_str(v, label, k)?),
+            "key" => opts.key_pem = Some(tls_str(v, label, k)?),
+            "alpn" => opts.alpn = tls_alpn(v, label)?,
+            "min_version" => opts.min_version = tls_min_version(v, label)?,
+            "server_name" => opts.server_name = Some(tls_str(v, label, k)?),
+            other => return Err(err(format!("Net.{label}: unknown TLS option '{other}'"))),
+        }
+    }
+    tls_pair(&opts.cert_pem, &opts.key_pem, label)?;
+    Ok(opts)
+}
+
+/// The server options Object of `listen_tls`. Needs a default
+/// `cert` / `key`, an `sni` table, or both.
+fn tls_server_opts(v: &Value, label: &str) -> Result<TlsServerOpts, RuntimeError> {
+    let Value::Object(o) = v else {
+        return Err(err(format!(
+            "Net.{label}: options must be an Object, got {}",
+            v.type_name()
+        )));
+    };
+    let mut opts = TlsServerOpts {
+        cert_pem: None,
+        key_pem: None,
+        sni: Vec::new(),
+        client_ca_pem: None,
+        client_auth: ClientAuth::None,
+        alpn: Vec::new(),
+        min_version: TlsVersion::V12,
+    };
+    let mut client_auth = None;
+    for (k, v) in o.borrow().iter() {
+        if matches!(v, Value::Null) {
+            continue;
+        }
+        match &**k {
+            "cert" => opts.cert_pem = Some(tls_str(v, label, k)?),
+            "key" => opts.key_pem = Some(tls_str(v, label, k)?),
+            "client_ca" => opts.client_ca_pem = Some(tls_str(v, label, k)?),
+            "client_auth" => {
+                client_auth = Some(match tls_str(v, label, k)?.as_str() {
+                    "none" => ClientAuth::None,
+                    "optional" => ClientAuth::Optional,
+                    "required" => ClientAuth::Required,
+                    other => {
+                        return Err(err(format!(
+                            "Net.{label}: client_auth must be 'none', 'optional', or 'required', got '{other}'"
+                        )));
+                    }
+                })
+            }
+            "alpn" => opts.alpn = tls_alpn(v, label)?,
+            "min_version" => opts.min_version = tls_min_version(v, label)?,
+            "sni" => {
+                let Value::Object(names) = v else {
+                    return Err(err(format!(
+                        "Net.{label}: 'sni' expects an Object of name -> ${{cert, key}}, got {}",
+                        v.type_name()
+                    )));
+                };
+                for (name, entry) in names.borrow().iter() {
+                    let pair = match entry {
+                        Value::Object(e) => {
+                            let e = e.borrow();
+                            match (e.get("cert"), e.get("key")) {
+                                (Some(Value::Str(c)), Some(Value::Str(k))) => {
+                                    Some((c.to_string(), k.to_string()))
+                                }
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    let Some((cert, key)) = pair else {
+                        return Err(err(format!(
+                            "Net.{label}: sni entry '{name}' must be ${{cert, key}} PEM Strings"
+                        )));
+                    };
+                    opts.sni.push((name.to_string(), cert, key));
+                }
+            }
+            other => return Err(err(format!("Net.{label}: unknown TLS option '{other}'"))),
+        }
+    }
+    tls_pair(&opts.cert_pem, &opts.key_pem, label)?;
+    if opts.cert_pem.is_none() && opts.sni.is_empty() {
+        return Err(err(format!(
+            "Net.{label}: options need a 'cert' / 'key' pair or an 'sni' table"
+        )));
+    }
+    // A client CA on its own means client certificates are required.
+    opts.client_auth = match (client_auth, &opts.client_ca_pem) {
+        (None, Some(_)) => ClientAuth::Required,
+        (Some(ClientAuth::None) | None, None) => ClientAuth::None,
+        (Some(_), None) => {
+            return Err(err(format!(
+                "Net.{label}: client_auth needs a 'client_ca' to verify against"
+            )));
+        }
+        (Some(auth), Some(_)) => auth,
+    };
+    Ok(opts)
+}
+
+/// `listen_tls(host, port, opts)` — a TLS server listener bound to
+/// `host:port`; `opts` carries the certificates and handshake policy.
+/// The older `listen_tls(host, port, cert_pem, key_pem)` form is
+/// shorthand for `${cert, key}`. PEM fields are *content*, not file
+/// paths. `accept` on the returned listener yields server-side TLS
+/// sockets, so `Http.serve(Net.listen_tls(...), handler)` is an HTTPS
+/// server.
 fn n_listen_tls(args: &[Value]) -> Result<Value, RuntimeError> {
     let host = expect_str(&args[0], "listen_tls")?;
     let port = expect_port(&args[1], "listen_tls")?;
-    let cert = expect_str(&args[2], "listen_tls")?;
-    let key = expect_str(&args[3], "listen_tls")?;
-    let sock = socket::listen_tls(host, port, cert.as_bytes(), key.as_bytes())
+    let opts = match args.get(3) {
+        Some(key) => {
+            let cert = expect_str(&args[2], "listen_tls")?;
+            let key = expect_str(key, "listen_tls")?;
+            tls_server_opts(
+                &object(&[
+                    ("cert", Value::Str(cert.into())),
+                    ("key", Value::Str(key.into())),
+                ]),
+                "listen_tls",
+            )?
+        }
+        None => tls_server_opts(&args[2], "listen_tls")?,
+    };
+    let sock = socket::listen_tls(host, port, &opts)
         .map_err(|e| map_err("listen_tls", e))?;
     Ok(Value::Socket(sock))
 }
 
-/// `connect_tls(host, port, [ca_pem])` — open a TLS-encrypted stream.
-/// `host` is also the name verified against the server certificate. The
-/// optional `ca_pem` adds trusted root certificates beyond the OS trust
-/// store — needed to connect to a private-CA or self-signed service.
+/// `connect_tls(host, port, [opts])` — open a TLS-encrypted stream.
+/// `opts` is an options Object, or a CA PEM String as shorthand for
+/// `${ca}`. Waits on the worker pool: DNS and the handshake block.
 fn n_connect_tls(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
     let host = expect_str(&args[0], "connect_tls")?.to_string();
     let port = expect_port(&args[1], "connect_tls")?;
-    let ca: Option<String> = match args.get(2) {
-        None | Some(Value::Null) => None,
-        Some(v) => Some(expect_str(v, "connect_tls")?.to_string()),
-    };
+    let opts = tls_client_opts(args.get(2), "connect_tls")?;
     Ok(Box::new(move || {
-        let extra = ca.as_deref().map(str::as_bytes);
-        match socket::connect_tls(&host, port, extra) {
+        match socket::connect_tls(&host, port, &opts) {
             Ok(sock) => Ok(OffloadOk::Socket(sock)),
             Err(e) => Err(offload_err("connect_tls", e)),
         }
     }))
 }
 
+/// `start_tls(sock, host, [opts])` — upgrade a connected plain TCP
+/// stream to TLS in place, verifying `host`; `opts` as for
+/// `connect_tls`. `sock` is retired and the TLS socket returned.
+fn n_start_tls(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let sock = take_socket(&args[0], "start_tls")?;
+    let host = expect_str(&args[1], "start_tls")?.to_string();
+    let opts = tls_client_opts(args.get(2), "start_tls")?;
+    Ok(Box::new(move || {
+        match socket::start_tls(&sock, &host, &opts) {
+            Ok(tls) => Ok(OffloadOk::Socket(tls)),
+            Err(e) => Err(offload_err("start_tls", e)),
+        }
+    }))
+}
+
+/// `tls_info(sock)` — describe a TLS connection: `${version, cipher,
+/// alpn, server_name, peer_cert}`, or `null` for a plain socket. An
+/// `accept`ed server socket handshakes lazily, so this first finishes
+/// the handshake — on the reactor inside a green thread.
+fn n_tls_info(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
+    let socket = take_socket(&args[0], "tls_info")?;
+    Ok(ReactorOp { socket, op: SocketOp::TlsInfo, label: "tls_info" })
+}
+
+/// The `tls_info` result, shared by the inline executor and the
+/// reactor. The description is plain data until it reaches the actor
+/// thread, where the deferred thunk builds the Object.
+pub(crate) fn tls_info_ok(info: Option<TlsInfo>) -> OffloadResult {
+    OffloadOk::deferred(move || {
+        let Some(info) = info else {
+            return Ok(Value::Null);
+        };
+        let str_or_null = |s: Option<String>| s.map_or(Value::Null, |s| Value::Str(s.into()));
+        let peer_cert = match info.peer_cert {
+            None => Value::Null,
+            Some((der, None)) => object(&[("der", Value::Bytes(gc::alloc_bytes(der)))]),
+            Some((der, Some(cert))) => object(&[
+                ("subject", Value::Str(cert.subject.into())),
+                ("common_name", str_or_null(cert.common_name)),
+                ("issuer", Value::Str(cert.issuer.into())),
+                (
+                    "sans",
+                    Value::Array(gc::alloc_array(
+                        cert.sans.into_iter().map(|s| Value::Str(s.into())).collect(),
+                    )),
+                ),
+                ("not_before", Value::Int(cert.not_before_ms)),
+                ("not_after", Value::Int(cert.not_after_ms)),
+                ("serial", Value::Str(cert.serial.into())),
+                ("der", Value::Bytes(gc::alloc_bytes(der))),
+            ]),
+        };
+        Ok(object(&[
+            ("version", Value::Str(info.version.into())),
+            ("cipher", Value::Str(info.cipher.into())),
+            ("alpn", str_or_null(info.alpn)),
+            ("server_name", str_or_null(info.server_name)),
+            ("peer_cert", peer_cert),
+        ]))
+    })
+}
+
 // ---------------------------------------------------------------------
 // UDP
 // ---------------------------------------------------------------------
 
-/// `bind(host, port)` — a UDP datagram socket bound to `host:port`.
+/// `bind(host, port, opts?)` — a UDP datagram socket bound to
+/// `host:port`, with `opts` applied before the bind as for `listen`.
 fn n_bind(args: &[Value]) -> Result<Value, RuntimeError> {
     let host = expect_str(&args[0], "bind")?;
     let port = expect_port(&args[1], "bind")?;
-    let sock = socket::udp_bind(host, port).map_err(|e| map_err("bind", e))?;
-    Ok(Value::Socket(sock))
+    let sock = match bind_opts(args.get(2), "bind")? {
+        None => socket::udp_bind(host, port),
+        Some(opts) => socket::udp_bind_with(host, port, &opts),
+    };
+    Ok(Value::Socket(sock.map_err(|e| map_err("bind", e))?))
 }
 
 /// `send_to(sock, bytes, host, port)` — send one UDP datagram; returns
-/// the number of bytes sent.
+/// the number of bytes sent. `send_to(sock, bytes, path)` sends one
+/// Unix-domain datagram to the socket bound at `path`.
 fn n_send_to(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
     let sock = take_socket(&args[0], "send_to")?;
     let data = expect_bytes(&args[1], "send_to")?;
+    if args.len() == 3 {
+        let path = expect_str(&args[2], "send_to")?.to_string();
+        return Ok(Box::new(move || send_to_path(&sock, &data, &path)));
+    }
     let host = expect_str(&args[2], "send_to")?.to_string();
     let port = expect_port(&args[3], "send_to")?;
     Ok(Box::new(move || {
@@ -359,8 +796,25 @@ fn n_send_to(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
     }))
 }
 
-/// `recv_from(sock, n)` — receive one UDP datagram (up to `n` bytes).
-/// Returns `${data: Bytes, host: String, port: Int}`.
+#[cfg(unix)]
+fn send_to_path(sock: &SocketHandle, data: &[u8], path: &str) -> OffloadResult {
+    match sock.send_to_path(data, std::path::Path::new(path)) {
+        Ok(sent) => Ok(OffloadOk::Int(sent as i64)),
+        Err(e) => Err(offload_err("send_to", e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn send_to_path(_sock: &SocketHandle, _data: &[u8], _path: &str) -> OffloadResult {
+    Err(offload_err(
+        "send_to",
+        NetError::WrongKind("send_to with a path expects a Unix datagram socket".into()),
+    ))
+}
+
+/// `recv_from(sock, n)` — receive one datagram (up to `n` bytes).
+/// Returns `${data: Bytes, host: String, port: Int}` for UDP, or
+/// `${data: Bytes, path: String | null}` for a Unix datagram socket.
 fn n_recv_from(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     let socket = take_socket(&args[0], "recv_from")?;
     let n = expect_count(&args[1], "recv_from")?;
@@ -371,6 +825,59 @@ fn n_recv_from(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     })
 }
 
+// ---------------------------------------------------------------------
+// Unix domain
+// ---------------------------------------------------------------------
+
+/// The optional socket type of `listen_unix` / `connect_unix`: `true`
+/// for `'datagram'`, `false` for `'stream'` (the default).
+fn expect_unix_type(v: Option<&Value>, label: &str) -> Result<bool, RuntimeError> {
+    match v {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Str(s)) if &**s == "stream" => Ok(false),
+        Some(Value::Str(s)) if &**s == "datagram" => Ok(true),
+        Some(other) => Err(err(format!(
+            "Net.{label}: type must be 'stream' or 'datagram', got {}",
+            match other {
+                Value::Str(s) => format!("'{s}'"),
+                v => v.type_name().to_string(),
+            }
+        ))),
+    }
+}
+
+/// `listen_unix(path, type?)` — a Unix domain socket bound at `path`:
+/// a stream listener to `accept` on, or with `'datagram'` a datagram
+/// socket to `recv_from` on. `close` removes the socket file.
+fn n_listen_unix(args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = expect_str(&args[0], "listen_unix")?;
+    let sock = if expect_unix_type(args.get(1), "listen_unix")? {
+        socket::bind_unix_datagram(path)
+    } else {
+        socket::listen_unix(path)
+    };
+    Ok(Value::Socket(sock.map_err(|e| map_err("listen_unix", e))?))
+}
+
+/// `connect_unix(path, type?)` — connect to the Unix domain socket at
+/// `path`: a stream, or with `'datagram'` an unbound datagram socket
+/// whose `read` / `write` exchange datagrams with `path`.
+fn n_connect_unix(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let path = expect_str(&args[0], "connect_unix")?.to_string();
+    let datagram = expect_unix_type(args.get(1), "connect_unix")?;
+    Ok(Box::new(move || {
+        let sock = if datagram {
+            socket::connect_unix_datagram(&path)
+        } else {
+            socket::connect_unix(&path)
+        };
+        match sock {
+            Ok(sock) => Ok(OffloadOk::Socket(sock)),
+            Err(e) => Err(offload_err("connect_unix", e)),
+        }
+    }))
+}
+
 // ---------------------------------------------------------------------
 // Stream I/O
 // ---------------------------------------------------------------------
@@ -380,7 +887,7 @@ fn n_recv_from(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
 fn n_read(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     let socket = take_socket(&args[0], "read")?;
     let n = expect_count(&args[1], "read")?;
-    Ok(ReactorOp { socket, op: SocketOp::ReadChunk(n), label: "read" })
+    Ok(ReactorOp { socket, op: SocketOp::ReadChunk { n, eof_on_close: false }, label: "read" })
 }
 
 /// `read_available(sock, n)` — read up to `n` bytes available right now
@@ -420,24 +927,40 @@ fn n_read_exact(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     })
 }
 
-/// `read_line(sock)` — read one `\n`-terminated line as a String, with
-/// the trailing `\r\n` / `\n` stripped. Returns `null` at end-of-
-/// stream. Raises `decode` on invalid UTF-8.
+/// The optional byte limit of a delimited read: `null` or absent means
+/// none; otherwise a positive Int.
+fn expect_max(v: Option<&Value>, label: &str) -> Result<Option<usize>, RuntimeError> {
+    match v {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => match expect_int(v, label)? {
+            n if n > 0 => Ok(Some(n as usize)),
+            n => Err(err(format!("Net.{label}: max must be positive, got {n}"))),
+        },
+    }
+}
+
+/// `read_line(sock, max?)` — read one `\n`-terminated line as a String,
+/// with the trailing `\r\n` / `\n` stripped. Returns `null` at end-of-
+/// stream. Raises `decode` on invalid UTF-8, and `too_large` if `max`
+/// bytes arrive without a line ending.
 fn n_read_line(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     let socket = take_socket(&args[0], "read_line")?;
-    Ok(ReactorOp { socket, op: SocketOp::ReadLine, label: "read_line" })
+    let max = expect_max(args.get(1), "read_line")?;
+    Ok(ReactorOp { socket, op: SocketOp::ReadLine { max }, label: "read_line" })
 }
 
-/// `read_until(sock, byte)` — read up to and including the next `byte`.
-/// Returns a `Bytes` (the delimiter included), or `null` at end-of-
-/// stream. Trailing data with no delimiter is returned as a final
-/// chunk.
+/// `read_until(sock, byte, max?)` — read up to and including the next
+/// `byte`. Returns a `Bytes` (the delimiter included), or `null` at
+/// end-of-stream. Trailing data with no delimiter is returned as a
+/// final chunk. Raises `too_large` if `max` bytes arrive without the
+/// delimiter.
 fn n_read_until(args: &[Value]) -> Result<ReactorOp, RuntimeError> {
     let socket = take_socket(&args[0], "read_until")?;
     let delim = expect_byte(&args[1], "read_until")?;
+    let max = expect_max(args.get(2), "read_until")?;
     Ok(ReactorOp {
         socket,
-        op: SocketOp::ReadUntil(delim),
+        op: SocketOp::ReadUntil { delim, max },
         label: "read_until",
     })
 }
@@ -485,6 +1008,18 @@ fn n_set_timeout(args: &[Value]) -> Result<Value, RuntimeError> {
     Ok(Value::Null)
 }
 
+/// `set_option(sock, name, value)` — set one socket option: `nodelay`,
+/// `keepalive`, `reuseaddr`, `reuseport`, `send_buffer`, `recv_buffer`,
+/// `broadcast`, `multicast_loop`, `multicast_ttl`, `join_multicast` or
+/// `leave_multicast`.
+fn n_set_option(args: &[Value]) -> Result<Value, RuntimeError> {
+    let sock = as_socket(&args[0], "set_option")?;
+    let name = expect_str(&args[1], "set_option")?;
+    let opt = sock_opt(name, &args[2], "set_option")?;
+    sock.set_option(&opt).map_err(|e| map_err("set_option", e))?;
+    Ok(Value::Null)
+}
+
 /// `close(sock)` — close the socket. Idempotent; unblocks a reader
 /// stuck mid-`read`, and an actor stuck in `accept` on a listener
 /// (which then raises `closed`). A coroutine parked on a reactor op
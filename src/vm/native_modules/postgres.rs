@@ -0,0 +1,208 @@
+//! `import '_NativePostgres'` — the hashing backend for the source
+//! `Postgres` module (`stdlib/Postgres.tg`).
+//!
+//! Password authentication needs MD5 (the server's `md5` method) and
+//! SHA-256 with HMAC and PBKDF2 (`SCRAM-SHA-256`, RFC 7677). PBKDF2's
+//! thousands of HMAC rounds are far too slow to run in tigr, so the
+//! primitives live here: SHA-256, HMAC and PBKDF2 from `ring` (already
+//! linked for TLS), and a small MD5, which `ring` does not offer. The
+//! protocol itself is pure tigr, over `Net`.
+//!
+//! Every input is `Bytes` or a `String` taken as UTF-8; every output is
+//! `Bytes`.
+
+use std::num::NonZeroU32;
+
+use ring::{digest, hmac, pbkdf2};
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc;
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object};
+
+pub fn module() -> Value {
+    object(&[
+        ("md5",           native("md5",           Arity::Exact(1), p_md5)),
+        ("sha256",        native("sha256",        Arity::Exact(1), p_sha256)),
+        ("hmac_sha256",   native("hmac_sha256",   Arity::Exact(2), p_hmac_sha256)),
+        ("pbkdf2_sha256", native("pbkdf2_sha256", Arity::Exact(3), p_pbkdf2_sha256)),
+    ])
+}
+
+/// A catchable, string-valued error. The VM backfills the call line.
+fn err(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+fn input(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    match v {
+        Value::Bytes(b) => Ok(b.borrow().clone()),
+        Value::Str(s) => Ok(s.as_bytes().to_vec()),
+        other => Err(err(format!(
+            "Postgres.{label}: expected Bytes or String, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn bytes(v: Vec<u8>) -> Value {
+    Value::Bytes(gc::alloc_bytes(v))
+}
+
+/// `md5(data)` — the 16-byte MD5 digest.
+fn p_md5(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(bytes(md5(&input(&args[0], "md5")?).to_vec()))
+}
+
+/// `sha256(data)` — the 32-byte SHA-256 digest.
+fn p_sha256(args: &[Value]) -> Result<Value, RuntimeError> {
+    let data = input(&args[0], "sha256")?;
+    Ok(bytes(digest::digest(&digest::SHA256, &data).as_ref().to_vec()))
+}
+
+/// `hmac_sha256(key, data)` — the 32-byte HMAC-SHA-256 tag.
+fn p_hmac_sha256(args: &[Value]) -> Result<Value, RuntimeError> {
+    let key = input(&args[0], "hmac_sha256")?;
+    let data = input(&args[1], "hmac_sha256")?;
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), &data);
+    Ok(bytes(tag.as_ref().to_vec()))
+}
+
+/// `pbkdf2_sha256(password, salt, iterations)` — a 32-byte key, as
+/// SCRAM's `Hi()` derives the salted password.
+fn p_pbkdf2_sha256(args: &[Value]) -> Result<Value, RuntimeError> {
+    let password = input(&args[0], "pbkdf2_sha256")?;
+    let salt = input(&args[1], "pbkdf2_sha256")?;
+    let iterations = match &args[2] {
+        Value::Int(n) => u32::try_from(*n).ok().and_then(NonZeroU32::new).ok_or_else(|| {
+            err(format!("Postgres.pbkdf2_sha256: iterations must be positive, got {n}"))
+        })?,
+        other => {
+            return Err(err(format!(
+                "Postgres.pbkdf2_sha256: iterations must be an Int, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let mut out = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, &password, &mut out);
+    Ok(bytes(out.to_vec()))
+}
+
+/// MD5 (RFC 1321). Broken as a hash, but it is what the `md5` auth
+/// method asks for.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+        5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+        4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+        6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    // K[i] = floor(abs(sin(i + 1)) * 2^32).
+    let k: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+
+    let mut msg = data.to_vec();
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&bit_len.to_le_bytes());
+
+    let mut h: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for chunk in msg.chunks(64) {
+        let m: Vec<u32> = chunk
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = h;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(k[i])
+                .wrapping_add(m[g])
+                .rotate_left(S[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+    }
+    let mut out = [0u8; 16];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|x| format!("{x:02x}")).collect()
+    }
+
+    #[test]
+    fn md5_rfc1321_vectors() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex(&md5(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890")),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn scram_rfc7677_client_proof() {
+        // RFC 7677 §3: user "user", password "pencil".
+        let salt = [
+            0x5b, 0x6d, 0x99, 0x68, 0x9d, 0x12, 0x35, 0x8e, 0xec, 0xa0, 0x4b, 0x14, 0x12, 0x36,
+            0xfa, 0x81,
+        ];
+        let auth_message = "n=user,r=rOprNGfwEbeRWgbNEkqO,\
+            r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096,\
+            c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+        let salted = p_pbkdf2_sha256(&[
+            Value::Str("pencil".into()),
+            bytes(salt.to_vec()),
+            Value::Int(4096),
+        ])
+        .unwrap();
+        let salted = match salted {
+            Value::Bytes(b) => b.borrow().clone(),
+            _ => panic!("expected Bytes"),
+        };
+        let client_key = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &salted), b"Client Key");
+        let stored = digest::digest(&digest::SHA256, client_key.as_ref());
+        let stored_key = hmac::Key::new(hmac::HMAC_SHA256, stored.as_ref());
+        let signature = hmac::sign(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .as_ref()
+            .iter()
+            .zip(signature.as_ref())
+            .map(|(a, b)| a ^ b)
+            .collect();
+        // base64 "dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        assert_eq!(hex(&proof), "747cdb65aa56224e2352137e52d7bdcad6a0f738df30782caa69a2cfb0277554");
+    }
+
+    #[test]
+    fn rejects_bad_iterations() {
+        let args = [Value::Str("pw".into()), Value::Str("salt".into()), Value::Int(0)];
+        assert!(p_pbkdf2_sha256(&args).is_err());
+    }
+}
@@ -7,7 +7,10 @@
 use indexmap::IndexSet;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
-use crate::vm::gc::{self, GcRef, SetKind};
+use std::ops::Deref;
+
+use crate::vm::gc::{self, GcReadGuard, GcRef, SetKind};
+use crate::vm::shared::{self, SharedHandle, SharedNode};
 use crate::vm::value::{Arity, MapKey, Value};
 
 use super::{native, object};
@@ -31,12 +34,60 @@ fn err(msg: String) -> RuntimeError {
     RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
 }
 
-fn expect_set(
+/// A `Set` argument an entry only reads: a heap set, or a `Shared.of`
+/// set, which holds the same elements.
+enum SetArg {
+    Heap(GcRef<SetKind>),
+    Shared(SharedHandle),
+}
+
+impl SetArg {
+    fn borrow(&self) -> SetView<'_> {
+        match self {
+            SetArg::Heap(s) => SetView::Heap(s.borrow()),
+            SetArg::Shared(node) => match &**node {
+                SharedNode::Set(s) => SetView::Shared(s),
+                _ => unreachable!("only a shared set becomes a SetArg"),
+            },
+        }
+    }
+}
+
+/// The elements of a [`SetArg`] while it is borrowed.
+enum SetView<'a> {
+    Heap(GcReadGuard<IndexSet<MapKey>>),
+    Shared(&'a IndexSet<MapKey>),
+}
+
+impl Deref for SetView<'_> {
+    type Target = IndexSet<MapKey>;
+    fn deref(&self) -> &IndexSet<MapKey> {
+        match self {
+            SetView::Heap(s) => s,
+            SetView::Shared(s) => s,
+        }
+    }
+}
+
+fn expect_set(v: &Value, label: &str) -> Result<SetArg, RuntimeError> {
+    match v {
+        Value::Set(s) => Ok(SetArg::Heap(*s)),
+        Value::Shared(n) if matches!(**n, SharedNode::Set(_)) => Ok(SetArg::Shared(n.clone())),
+        other => Err(err(format!(
+            "Set.{label}: expected Set, got {}", other.type_name()
+        ))),
+    }
+}
+
+/// [`expect_set`] for an entry that mutates the set: a shared set
+/// raises `immutable` rather than a type error.
+fn expect_set_mut(
     v: &Value,
     label: &str,
 ) -> Result<GcRef<SetKind>, RuntimeError> {
     match v {
         Value::Set(s) => Ok(*s),
+        Value::Shared(_) => Err(shared::immutable(&format!("call Set.{label}"), 0)),
         other => Err(err(format!(
             "Set.{label}: expected Set, got {}", other.type_name()
         ))),
@@ -68,7 +119,7 @@ fn s_new(args: &[Value]) -> Result<Value, RuntimeError> {
 
 /// `add(s, x)` → inserts `x` in place, returns `s`.
 fn s_add(args: &[Value]) -> Result<Value, RuntimeError> {
-    let set = expect_set(&args[0], "add")?;
+    let set = expect_set_mut(&args[0], "add")?;
     let elem = MapKey::from_value(&args[1], 0)?;
     set.borrow_mut().insert(elem);
     Ok(args[0].clone())
@@ -85,7 +136,7 @@ fn s_has(args: &[Value]) -> Result<Value, RuntimeError> {
 /// `delete(s, x)` → removes `x`, returns `true` if it was present.
 /// `shift_remove` preserves the insertion order of the rest.
 fn s_delete(args: &[Value]) -> Result<Value, RuntimeError> {
-    let set = expect_set(&args[0], "delete")?;
+    let set = expect_set_mut(&args[0], "delete")?;
     let elem = MapKey::from_value(&args[1], 0)?;
     let removed = set.borrow_mut().shift_remove(&elem);
     Ok(Value::Bool(removed))
@@ -107,7 +158,7 @@ fn s_size(args: &[Value]) -> Result<Value, RuntimeError> {
 
 /// `clear(s)` → empties `s` in place, returns `s`.
 fn s_clear(args: &[Value]) -> Result<Value, RuntimeError> {
-    let set = expect_set(&args[0], "clear")?;
+    let set = expect_set_mut(&args[0], "clear")?;
     set.borrow_mut().clear();
     Ok(args[0].clone())
 }
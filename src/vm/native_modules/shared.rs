@@ -0,0 +1,50 @@
+//! `Shared` — immutable collections that actors read without copying.
+//!
+//! `Shared.of(v)` freezes a collection into an `Arc`-backed tree (see
+//! [`crate::vm::shared`]); `Shared.thaw(s)` copies one back into a
+//! mutable heap value, and `Shared.kind(s)` names the collection a
+//! shared value holds, since `type` reports `'shared'` for all of them.
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::shared;
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object};
+
+pub fn module() -> Value {
+    object(&[
+        ("of",   native("of",   Arity::Exact(1), s_of)),
+        ("thaw", native("thaw", Arity::Exact(1), s_thaw)),
+        ("kind", native("kind", Arity::Exact(1), s_kind)),
+    ])
+}
+
+/// `of(v)` — a frozen snapshot of `v`. Nested collections are frozen
+/// with it; a value that is already shared or immutable comes back as
+/// it is.
+fn s_of(args: &[Value]) -> Result<Value, RuntimeError> {
+    shared::freeze(&args[0])
+}
+
+/// `thaw(s)` — a mutable deep copy of a shared value on this actor's
+/// heap. Any other value comes back as it is.
+fn s_thaw(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(match &args[0] {
+        Value::Shared(node) => node.thaw(),
+        other => other.clone(),
+    })
+}
+
+/// `kind(s)` — `'array'`, `'object'`, `'map'`, `'set'` or `'bytes'`.
+fn s_kind(args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Shared(node) => Ok(Value::Str(node.kind_name().into())),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "Shared.kind expects a shared value, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
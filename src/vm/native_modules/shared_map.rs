@@ -0,0 +1,133 @@
+//! `import '_NativeSharedMap'` — the concurrent-map primitives backing
+//! the `stdlib/SharedMap.tg` wrapper.
+//!
+//! A shared map lives outside every actor heap (see
+//! [`crate::vm::shared_map`]). `put` and `cas` transfer-encode the
+//! value on the calling thread; `get` decodes into the caller's heap,
+//! so readers and writers never share a mutable value, only the table.
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc;
+use crate::vm::shared_map::{key_of, SharedMapHandle, SharedMapInner};
+use crate::vm::transfer::{decode, encode};
+use crate::vm::value::{Arity, Value};
+
+use super::{native, object};
+
+pub fn module() -> Value {
+    object(&[
+        ("new",    native("new",    Arity::Exact(0), m_new)),
+        ("get",    native("get",    Arity::Exact(3), m_get)),
+        ("put",    native("put",    Arity::Exact(3), m_put)),
+        ("cas",    native("cas",    Arity::Exact(4), m_cas)),
+        ("get_versioned", native("get_versioned", Arity::Exact(2), m_get_versioned)),
+        ("set_if_version", native("set_if_version", Arity::Exact(4), m_set_if_version)),
+        ("delete", native("delete", Arity::Exact(2), m_delete)),
+        ("has",    native("has",    Arity::Exact(2), m_has)),
+        ("keys",   native("keys",   Arity::Exact(1), m_keys)),
+        ("size",   native("size",   Arity::Exact(1), m_size)),
+    ])
+}
+
+/// Extract a shared-map handle from an argument, or raise a type error.
+fn as_map<'a>(v: &'a Value, func: &str) -> Result<&'a SharedMapHandle, RuntimeError> {
+    match v {
+        Value::SharedMap(h) => Ok(h),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "SharedMap.{func} expects a shared map, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+/// `new()` — an empty map.
+fn m_new(_args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::SharedMap(SharedMapInner::new()))
+}
+
+/// `get(m, key, default)` — a copy of the value under `key`, or
+/// `default` when there is none.
+fn m_get(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "get")?;
+    Ok(match m.get(&key_of(&args[1])?) {
+        Some(t) => decode(t),
+        None => args[2].clone(),
+    })
+}
+
+/// `put(m, key, value)` — store a copy of `value`. Returns the value
+/// it replaced, or `null`.
+fn m_put(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "put")?;
+    let key = key_of(&args[1])?;
+    let value = encode(&args[2])?;
+    Ok(m.put(key, value).map(decode).unwrap_or(Value::Null))
+}
+
+/// `cas(m, key, expected, new)` — store `new` if the current value
+/// (`null` when absent) equals `expected`. Returns whether it did.
+fn m_cas(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "cas")?;
+    let key = key_of(&args[1])?;
+    let new = encode(&args[3])?;
+    Ok(Value::Bool(m.compare_and_set(key, &args[2], new)))
+}
+
+/// `get_versioned(m, key)` — `[value, version]`: a copy of the value
+/// under `key` (`null` when absent) and the version of that write, `0`
+/// for a missing key.
+fn m_get_versioned(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "get_versioned")?;
+    let (value, version) = m.get_versioned(&key_of(&args[1])?);
+    let value = value.map(decode).unwrap_or(Value::Null);
+    Ok(Value::Array(gc::alloc_array(vec![value, Value::Int(version as i64)])))
+}
+
+/// `set_if_version(m, key, version, new)` — store `new` if `key` has
+/// not been written since `get_versioned` saw `version`. Returns
+/// whether it did.
+fn m_set_if_version(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "set_if_version")?;
+    let key = key_of(&args[1])?;
+    let version = match &args[2] {
+        Value::Int(n) if *n >= 0 => *n as u64,
+        other => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch(format!(
+                    "SharedMap.set_if_version expects a version from get_versioned, got {}",
+                    other.type_name()
+                )),
+                0,
+            ))
+        }
+    };
+    let new = encode(&args[3])?;
+    Ok(Value::Bool(m.set_if_version(key, version, new)))
+}
+
+/// `delete(m, key)` — remove `key`. Returns whether it was present.
+fn m_delete(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "delete")?;
+    Ok(Value::Bool(m.remove(&key_of(&args[1])?)))
+}
+
+/// `has(m, key)` — whether `key` is present.
+fn m_has(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "has")?;
+    Ok(Value::Bool(m.contains(&key_of(&args[1])?)))
+}
+
+/// `keys(m)` — the keys present now, in insertion order.
+fn m_keys(args: &[Value]) -> Result<Value, RuntimeError> {
+    let m = as_map(&args[0], "keys")?;
+    let keys = m.keys().into_iter().map(Value::from).collect();
+    Ok(Value::Array(gc::alloc_array(keys)))
+}
+
+/// `size(m)` — the number of entries.
+fn m_size(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Int(as_map(&args[0], "size")?.len() as i64))
+}
@@ -0,0 +1,416 @@
+//! `import '_NativeStore'` — the backend for the source `Store` module
+//! (`stdlib/Store.tg`), over [`crate::vm::store`].
+//!
+//! Keys are a `String` (its UTF-8 bytes) or `Bytes`, so `'a'` and
+//! `Bytes.from_string('a')` name the same key; order is bytewise.
+//! Values are kept as JSON text: [`json::encode`] on the way in,
+//! [`json::decode`] on the way out, so `Int`s come back as `Int`s.
+//!
+//! Reads (`get`, `has`, `page`, `size`) are served from memory and run
+//! inline. Writes append to the log and `fsync`, so `open`, `put`,
+//! `delete`, `batch`, `compact` and `close` are blocking natives,
+//! offloaded inside a green thread like `IO`'s file calls.
+//!
+//! Failures of the store itself are structured `${kind, message}`
+//! errors — `locked`, `closed`, `corrupt`, `io` — like `IO`'s handle
+//! API; a bad argument is a plain string error.
+
+use std::ops::Bound;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc;
+use crate::vm::offload::{BlockingJob, OffloadErr, OffloadOk};
+use crate::vm::store::{Op, StoreError, StoreHandle, StoreInner};
+use crate::vm::value::{Arity, Value};
+
+use super::{json, native, native_blocking, object};
+
+pub fn module() -> Value {
+    object(&[
+        ("open",    native_blocking("open",    Arity::Range(1, 2), s_open)),
+        ("get",     native("get",     Arity::Range(2, 3), s_get)),
+        ("has",     native("has",     Arity::Exact(2), s_has)),
+        ("put",     native_blocking("put",     Arity::Exact(3), s_put)),
+        ("delete",  native_blocking("delete",  Arity::Exact(2), s_delete)),
+        ("batch",   native_blocking("batch",   Arity::Exact(2), s_batch)),
+        ("range",   native("range",   Arity::Range(0, 1), s_range)),
+        ("page",    native("page",    Arity::Exact(4), s_page)),
+        ("size",    native("size",    Arity::Exact(1), s_size)),
+        ("compact", native_blocking("compact", Arity::Exact(1), s_compact)),
+        ("close",   native_blocking("close",   Arity::Exact(1), s_close)),
+    ])
+}
+
+/// A catchable, string-valued error. The VM backfills the call line.
+fn err(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+/// A catchable structured error `${kind, message}`.
+fn store_err(kind: &str, msg: String) -> RuntimeError {
+    let obj = object(&[
+        ("kind", Value::Str(kind.into())),
+        ("message", Value::Str(msg.into())),
+    ]);
+    RuntimeError::new(RuntimeErrorKind::Raised(obj), 0)
+}
+
+/// Classify a [`StoreError`] into a structured error kind + message.
+fn classify(label: &str, e: StoreError) -> (&'static str, String) {
+    match e {
+        StoreError::Locked(msg) => ("locked", format!("Store.{label}: {msg}")),
+        StoreError::Closed => ("closed", format!("Store.{label}: store is closed")),
+        StoreError::Corrupt(msg) => ("corrupt", format!("Store.{label}: {msg}")),
+        StoreError::Io(e) => ("io", format!("Store.{label}: {e}")),
+    }
+}
+
+/// Map a [`StoreError`] to a tigr `RuntimeError` for inline natives.
+fn map_err(label: &str, e: StoreError) -> RuntimeError {
+    let (kind, msg) = classify(label, e);
+    store_err(kind, msg)
+}
+
+/// Map a [`StoreError`] to the [`OffloadErr`] a worker posts back.
+fn offload_err(label: &str, e: StoreError) -> OffloadErr {
+    let (kind, message) = classify(label, e);
+    OffloadErr { kind: Some(kind.to_string()), message }
+}
+
+/// Extract an owned store handle, or raise a `type_mismatch`.
+fn take_store(v: &Value, label: &str) -> Result<StoreHandle, RuntimeError> {
+    match v {
+        Value::Store(h) => Ok(h.clone()),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "Store.{label}: expected a store, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+/// A key: a `String`'s UTF-8 bytes or a `Bytes` buffer's contents.
+fn take_key(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    match v {
+        Value::Str(s) => Ok(s.as_bytes().to_vec()),
+        Value::Bytes(b) => Ok(b.borrow().clone()),
+        other => Err(err(format!(
+            "Store.{label}: a key must be a String or Bytes, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// A value, as the JSON text the store keeps.
+fn take_value(v: &Value, label: &str) -> Result<Vec<u8>, RuntimeError> {
+    Ok(json::encode(v, &format!("Store.{label}"))?.into_bytes())
+}
+
+/// Rebuild a stored value on the actor thread.
+fn value_of(data: &[u8]) -> Result<Value, RuntimeError> {
+    match std::str::from_utf8(data) {
+        Ok(s) => json::decode(s),
+        Err(_) => Err(store_err("corrupt", "Store: a stored value is not UTF-8".into())),
+    }
+}
+
+/// A key as scans return it: a `String` when the bytes are UTF-8 (and
+/// `as_bytes` is off), otherwise `Bytes`.
+fn key_of(key: Vec<u8>, as_bytes: bool) -> Value {
+    if as_bytes {
+        return Value::Bytes(gc::alloc_bytes(key));
+    }
+    match String::from_utf8(key) {
+        Ok(s) => Value::Str(s.into()),
+        Err(e) => Value::Bytes(gc::alloc_bytes(e.into_bytes())),
+    }
+}
+
+/// `open(path, opts?)` — open or create the store directory `path`.
+/// `opts.sync` (default `true`) `fsync`s every write before it returns.
+fn s_open(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let path = match &args[0] {
+        Value::Str(s) => s.to_string(),
+        other => {
+            return Err(err(format!(
+                "Store.open: expected a String path, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let mut sync = true;
+    match args.get(1) {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(o)) => match o.borrow().get("sync") {
+            None | Some(Value::Null) => {}
+            Some(Value::Bool(b)) => sync = *b,
+            Some(other) => {
+                return Err(err(format!(
+                    "Store.open: 'sync' expects a Bool, got {}",
+                    other.type_name()
+                )))
+            }
+        },
+        Some(other) => {
+            return Err(err(format!(
+                "Store.open: options must be an Object, got {}",
+                other.type_name()
+            )))
+        }
+    }
+    Ok(Box::new(move || match StoreInner::open(path, sync) {
+        Ok(h) => Ok(OffloadOk::Store(h)),
+        Err(e) => Err(offload_err("open", e)),
+    }))
+}
+
+/// `get(db, key, default?)` — the stored value, or `default` (`null`).
+fn s_get(args: &[Value]) -> Result<Value, RuntimeError> {
+    let db = take_store(&args[0], "get")?;
+    let key = take_key(&args[1], "get")?;
+    match db.get(&key).map_err(|e| map_err("get", e))? {
+        Some(data) => value_of(&data),
+        None => Ok(args.get(2).cloned().unwrap_or(Value::Null)),
+    }
+}
+
+/// `has(db, key)` — whether `key` is stored (even as `null`).
+fn s_has(args: &[Value]) -> Result<Value, RuntimeError> {
+    let db = take_store(&args[0], "has")?;
+    let key = take_key(&args[1], "has")?;
+    db.contains(&key).map(Value::Bool).map_err(|e| map_err("has", e))
+}
+
+/// `put(db, key, value)` — store `value`, replacing any old one.
+fn s_put(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let db = take_store(&args[0], "put")?;
+    let key = take_key(&args[1], "put")?;
+    let value = take_value(&args[2], "put")?;
+    Ok(Box::new(move || match db.write(vec![Op::Put(key, value)]) {
+        Ok(_) => Ok(OffloadOk::Unit),
+        Err(e) => Err(offload_err("put", e)),
+    }))
+}
+
+/// `delete(db, key)` — remove `key`; `true` if it was there.
+fn s_delete(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let db = take_store(&args[0], "delete")?;
+    let key = take_key(&args[1], "delete")?;
+    Ok(Box::new(move || match db.write(vec![Op::Delete(key)]) {
+        Ok(existed) => {
+            let existed = existed[0];
+            OffloadOk::deferred(move || Ok(Value::Bool(existed)))
+        }
+        Err(e) => Err(offload_err("delete", e)),
+    }))
+}
+
+/// `batch(db, ops)` — apply `['put', key, value]` and `['delete', key]`
+/// ops as one atomic write: after a crash, all of them or none.
+fn s_batch(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let db = take_store(&args[0], "batch")?;
+    let items = match &args[1] {
+        Value::Array(a) => a.borrow().clone(),
+        other => {
+            return Err(err(format!(
+                "Store.batch: expected an Array of ops, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let mut ops = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let parts = match item {
+            Value::Array(a) => a.borrow().clone(),
+            _ => Vec::new(),
+        };
+        let op = match (parts.first(), parts.len()) {
+            (Some(Value::Str(s)), 3) if &**s == "put" => {
+                Op::Put(take_key(&parts[1], "batch")?, take_value(&parts[2], "batch")?)
+            }
+            (Some(Value::Str(s)), 2) if &**s == "delete" => {
+                Op::Delete(take_key(&parts[1], "batch")?)
+            }
+            _ => {
+                return Err(err(format!(
+                    "Store.batch: op {i} must be ['put', key, value] or ['delete', key]"
+                )))
+            }
+        };
+        ops.push(op);
+    }
+    Ok(Box::new(move || match db.write(ops) {
+        Ok(_) => Ok(OffloadOk::Unit),
+        Err(e) => Err(offload_err("batch", e)),
+    }))
+}
+
+/// The least key greater than every key starting with `prefix`, or
+/// `None` when there is none (an all-`0xff` prefix).
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// `range(opts?)` — check a scan's options and normalise them to
+/// `${start, end, reverse, limit, keys}`: `start` inclusive and `end`
+/// exclusive (`Bytes` or `null`), with `prefix` folded into both.
+fn s_range(args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut start: Option<Vec<u8>> = None;
+    let mut end: Option<Vec<u8>> = None;
+    let mut prefix: Option<Vec<u8>> = None;
+    let mut reverse = false;
+    let mut limit = Value::Null;
+    let mut as_bytes = false;
+    match args.first() {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(o)) => {
+            let o = o.borrow();
+            for (name, v) in o.iter() {
+                if matches!(v, Value::Null) {
+                    continue;
+                }
+                match &**name {
+                    "start" => start = Some(take_key(v, "scan")?),
+                    "end" => end = Some(take_key(v, "scan")?),
+                    "prefix" => prefix = Some(take_key(v, "scan")?),
+                    "reverse" => match v {
+                        Value::Bool(b) => reverse = *b,
+                        other => {
+                            return Err(err(format!(
+                                "Store.scan: 'reverse' expects a Bool, got {}",
+                                other.type_name()
+                            )))
+                        }
+                    },
+                    "limit" => match v {
+                        Value::Int(n) if *n >= 0 => limit = Value::Int(*n),
+                        other => {
+                            return Err(err(format!(
+                                "Store.scan: 'limit' expects a non-negative Int, got {other}"
+                            )))
+                        }
+                    },
+                    "keys" => match v {
+                        Value::Str(s) if &**s == "bytes" => as_bytes = true,
+                        Value::Str(s) if &**s == "auto" => as_bytes = false,
+                        other => {
+                            return Err(err(format!(
+                                "Store.scan: 'keys' expects 'auto' or 'bytes', got {other}"
+                            )))
+                        }
+                    },
+                    other => {
+                        return Err(err(format!("Store.scan: unknown option '{other}'")))
+                    }
+                }
+            }
+        }
+        Some(other) => {
+            return Err(err(format!(
+                "Store.scan: options must be an Object, got {}",
+                other.type_name()
+            )))
+        }
+    }
+    // Narrow `start..end` to the keys under `prefix`.
+    if let Some(p) = prefix {
+        if start.as_ref().is_none_or(|s| *s < p) {
+            start = Some(p.clone());
+        }
+        if let Some(pe) = prefix_end(&p) {
+            if end.as_ref().is_none_or(|e| *e > pe) {
+                end = Some(pe);
+            }
+        }
+    }
+    let key = |k: Option<Vec<u8>>| k.map_or(Value::Null, |k| Value::Bytes(gc::alloc_bytes(k)));
+    Ok(object(&[
+        ("start", key(start)),
+        ("end", key(end)),
+        ("reverse", Value::Bool(reverse)),
+        ("limit", limit),
+        ("keys", Value::Str(if as_bytes { "bytes" } else { "auto" }.into())),
+    ]))
+}
+
+/// `page(db, range, after, n)` — up to `n` `${key, value}` entries of
+/// a normalised `range`, continuing past the key `after` (`null` to
+/// begin).
+fn s_page(args: &[Value]) -> Result<Value, RuntimeError> {
+    let db = take_store(&args[0], "scan")?;
+    let (start, end, reverse, as_bytes) = match &args[1] {
+        Value::Object(o) => {
+            let o = o.borrow();
+            let key = |name: &str| match o.get(name) {
+                Some(v @ (Value::Str(_) | Value::Bytes(_))) => take_key(v, "scan").map(Some),
+                _ => Ok(None),
+            };
+            (
+                key("start")?,
+                key("end")?,
+                matches!(o.get("reverse"), Some(Value::Bool(true))),
+                matches!(o.get("keys"), Some(Value::Str(s)) if &**s == "bytes"),
+            )
+        }
+        other => {
+            return Err(err(format!(
+                "Store.scan: expected a range, got {}",
+                other.type_name()
+            )))
+        }
+    };
+    let after = match &args[2] {
+        Value::Null => None,
+        v => Some(take_key(v, "scan")?),
+    };
+    let n = match &args[3] {
+        Value::Int(n) if *n >= 0 => *n as usize,
+        other => return Err(err(format!("Store.scan: bad page size {other}"))),
+    };
+    let lo = start.map_or(Bound::Unbounded, Bound::Included);
+    let hi = end.map_or(Bound::Unbounded, Bound::Excluded);
+    let (lo, hi) = match after {
+        None => (lo, hi),
+        Some(a) if reverse => (lo, Bound::Excluded(a)),
+        Some(a) => (Bound::Excluded(a), hi),
+    };
+    let entries = db.range(lo, hi, reverse, n).map_err(|e| map_err("scan", e))?;
+    let mut out = Vec::with_capacity(entries.len());
+    for (k, v) in entries {
+        out.push(object(&[("key", key_of(k, as_bytes)), ("value", value_of(&v)?)]));
+    }
+    Ok(Value::Array(gc::alloc_array(out)))
+}
+
+/// `size(db)` — the number of stored keys.
+fn s_size(args: &[Value]) -> Result<Value, RuntimeError> {
+    let db = take_store(&args[0], "size")?;
+    db.count().map(|n| Value::Int(n as i64)).map_err(|e| map_err("size", e))
+}
+
+/// `compact(db)` — rewrite the log to hold only live entries.
+fn s_compact(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let db = take_store(&args[0], "compact")?;
+    Ok(Box::new(move || match db.compact() {
+        Ok(()) => Ok(OffloadOk::Unit),
+        Err(e) => Err(offload_err("compact", e)),
+    }))
+}
+
+/// `close(db)` — sync and release the store. Idempotent.
+fn s_close(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+    let db = take_store(&args[0], "close")?;
+    Ok(Box::new(move || match db.close() {
+        Ok(()) => Ok(OffloadOk::Unit),
+        Err(e) => Err(offload_err("close", e)),
+    }))
+}
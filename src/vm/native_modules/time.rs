@@ -4,6 +4,10 @@
 //! the common ask in a hobby language. Both fit in `i64`: ms doesn't
 //! overflow until year 292 million; ns doesn't overflow until 2262.
 //!
+//! In simulation mode ([`crate::vm::sim`]) all three use the virtual
+//! clock: `now_*` read it and `sleep_ms` advances it without sleeping,
+//! except in a spawned actor, which stays on the real clock.
+//!
 //! The browser playground build has no OS clock or thread; `now_*` are
 //! backed by JavaScript's `Date.now()` and `sleep_ms` raises a
 //! catchable error (a tab cannot block synchronously).
@@ -14,6 +18,8 @@ use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::vm::sim;
 use crate::vm::value::{Arity, Value};
 
 use super::{native, object};
@@ -32,6 +38,9 @@ fn raise(msg: String) -> RuntimeError {
 
 #[cfg(not(target_arch = "wasm32"))]
 fn now_ms(_args: &[Value]) -> Result<Value, RuntimeError> {
+    if sim::clock_here() {
+        return Ok(Value::Int(sim::unix_nanos() / 1_000_000));
+    }
     let d = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| raise(format!("Time.now_ms: {e}")))?;
@@ -40,6 +49,9 @@ fn now_ms(_args: &[Value]) -> Result<Value, RuntimeError> {
 
 #[cfg(not(target_arch = "wasm32"))]
 fn now_ns(_args: &[Value]) -> Result<Value, RuntimeError> {
+    if sim::clock_here() {
+        return Ok(Value::Int(sim::unix_nanos()));
+    }
     let d = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| raise(format!("Time.now_ns: {e}")))?;
@@ -55,7 +67,11 @@ fn sleep_ms(args: &[Value]) -> Result<Value, RuntimeError> {
             "Time.sleep_ms: expected Int, got {}", other.type_name()
         ))),
     };
-    thread::sleep(Duration::from_millis(ms));
+    if sim::clock_here() {
+        sim::advance_by(Duration::from_millis(ms));
+    } else {
+        thread::sleep(Duration::from_millis(ms));
+    }
     Ok(Value::Null)
 }
 
@@ -0,0 +1,1159 @@
+//! `import 'Toml'` — `parse` and `stringify` for TOML 1.0.
+//!
+//! Tables become Objects (in source order) and arrays of tables become
+//! Arrays of Objects. Integers are `Int` (TOML integers are 64-bit, as
+//! ours are), floats `Float`, including `inf` and `nan`.
+//!
+//! TOML's four date/time kinds become components Objects that
+//! `DateTime.to_ms` accepts directly: `${year, month, day, hour, minute,
+//! second, ms}`, or the date or time half of it. Fractional seconds
+//! are truncated to milliseconds, which TOML allows. An offset date-time
+//! keeps its fields as written and adds its `offset` in minutes east of
+//! UTC and `epoch_ms`, the instant it names, so it writes back as it was
+//! read. Each carries a `_toml` field naming its kind (`'offset-datetime'`,
+//! `'local-datetime'`, `'local-date'`, `'local-time'`), which is how
+//! `stringify` tells a date from a table.
+//! `stringify` also writes an Object straight from `DateTime.from_ms`
+//! or `DateTime.now` as a UTC offset date-time.
+//!
+//! The parser builds a tree of [`Node`]s first, because TOML lets a
+//! table be reopened by later headers and dotted keys, and the
+//! redefinition rules need to know how each table came to exist. The
+//! tree becomes tigr values once the whole document has been read.
+//!
+//! Malformed input raises a catchable `parse_error` — `${kind, message,
+//! line, column}` — whose message reads like `JSON.parse`'s: "Toml.parse:
+//! msg at line L, column C".
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc::{self, ArrayKind, GcRef, ObjectKind};
+use crate::vm::value::{Arity, Value};
+
+use super::datetime::{days_from_civil, MS_PER_DAY};
+use super::{line_col, native, object, parse_error};
+
+pub fn module() -> Value {
+    object(&[
+        ("parse",     native("parse",     Arity::Exact(1), parse)),
+        ("stringify", native("stringify", Arity::Exact(1), stringify)),
+    ])
+}
+
+fn raise(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+// ---------------- parse ----------------
+
+/// A key/value slot in the document tree.
+enum Node {
+    /// A value complete when it was written: a scalar, an array, or an
+    /// inline table. Nothing may be added to it later.
+    Value(Value),
+    Table(Table),
+    /// An array of tables (`[[name]]`); later headers extend the last.
+    Tables(Vec<Table>),
+}
+
+#[derive(Default)]
+struct Table {
+    entries: IndexMap<String, Node>,
+    /// Defined by its own `[header]` — a second header is an error.
+    header: bool,
+    /// Created by a dotted key (`a.b = 1`), which only further dotted
+    /// keys in the same table may extend.
+    dotted: bool,
+}
+
+impl Table {
+    fn into_value(self) -> Value {
+        let map: IndexMap<Arc<str>, Value> = self
+            .entries
+            .into_iter()
+            .map(|(k, node)| (Arc::from(k), node.into_value()))
+            .collect();
+        Value::Object(gc::alloc_object(map))
+    }
+}
+
+impl Node {
+    fn into_value(self) -> Value {
+        match self {
+            Node::Value(v) => v,
+            Node::Table(t) => t.into_value(),
+            Node::Tables(ts) => {
+                Value::Array(gc::alloc_array(ts.into_iter().map(Table::into_value).collect()))
+            }
+        }
+    }
+}
+
+/// One segment of a dotted key, with where it starts for errors.
+type Key = (String, usize);
+
+fn dotted(keys: &[Key]) -> String {
+    keys.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(".")
+}
+
+fn parse(args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = match &args[0] {
+        Value::Str(s) => s.clone(),
+        other => {
+            return Err(raise(format!(
+                "Toml.parse: expected String, got {}",
+                other.type_name()
+            )));
+        }
+    };
+    let mut p = Parser { src: &s, bytes: s.as_bytes(), pos: 0 };
+    let mut root = Table { header: true, ..Table::default() };
+    // The keys of the table the last header opened.
+    let mut current: Vec<String> = Vec::new();
+    loop {
+        p.skip_ws();
+        match p.peek() {
+            None => break,
+            Some(b'#') | Some(b'\r') | Some(b'\n') => p.end_of_line()?,
+            Some(b'[') => {
+                current = p.header(&mut root)?;
+                p.end_of_line()?;
+            }
+            Some(_) => {
+                let table = open(&mut root, &current);
+                p.key_value(table)?;
+                p.end_of_line()?;
+            }
+        }
+    }
+    Ok(root.into_value())
+}
+
+/// The table at `path`, following arrays of tables to their last
+/// element. Every step was created by a header, so it exists.
+fn open<'t>(root: &'t mut Table, path: &[String]) -> &'t mut Table {
+    let mut t = root;
+    for k in path {
+        t = match t.entries.get_mut(k) {
+            Some(Node::Table(next)) => next,
+            Some(Node::Tables(list)) => list.last_mut().expect("array of tables is never empty"),
+            _ => unreachable!("a header path always names a table"),
+        };
+    }
+    t
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn err_at(&self, pos: usize, msg: &str) -> RuntimeError {
+        let (line, col) = line_col(self.bytes, pos);
+        parse_error(format!("Toml.parse: {msg} at line {line}, column {col}"), line, col)
+    }
+
+    fn err(&self, msg: &str) -> RuntimeError {
+        self.err_at(self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.bytes.get(self.pos + n).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, b: u8, label: &str) -> Result<(), RuntimeError> {
+        if self.eat(b) {
+            Ok(())
+        } else {
+            Err(self.unexpected(label))
+        }
+    }
+
+    /// "expected X, got Y" at the current position.
+    fn unexpected(&self, label: &str) -> RuntimeError {
+        match self.src[self.pos..].chars().next() {
+            None => self.err(&format!("expected {label}, got end of input")),
+            Some('\n') | Some('\r') => self.err(&format!("expected {label}, got end of line")),
+            Some(c) => self.err(&format!("expected {label}, got '{c}'")),
+        }
+    }
+
+    /// Spaces and tabs — whitespace that never ends a line.
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /// A `#` comment up to (not including) the line break. Control
+    /// characters other than tab are not allowed in one.
+    fn skip_comment(&mut self) -> Result<(), RuntimeError> {
+        if self.peek() != Some(b'#') {
+            return Ok(());
+        }
+        while let Some(b) = self.peek() {
+            match b {
+                b'\n' => break,
+                b'\r' if self.peek_at(1) == Some(b'\n') => break,
+                b'\t' => {}
+                b if b < 0x20 || b == 0x7f => {
+                    return Err(self.err("control character in comment"))
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn eat_newline(&mut self) -> bool {
+        if self.eat(b'\n') {
+            return true;
+        }
+        if self.peek() == Some(b'\r') && self.peek_at(1) == Some(b'\n') {
+            self.pos += 2;
+            return true;
+        }
+        false
+    }
+
+    /// Trailing whitespace and comment, then a line break or the end.
+    fn end_of_line(&mut self) -> Result<(), RuntimeError> {
+        self.skip_ws();
+        self.skip_comment()?;
+        if self.peek().is_none() || self.eat_newline() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of line"))
+        }
+    }
+
+    /// Whitespace, line breaks and comments, as allowed inside arrays.
+    fn skip_ws_lines(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            self.skip_ws();
+            self.skip_comment()?;
+            if !self.eat_newline() {
+                return Ok(());
+            }
+        }
+    }
+
+    // -- keys and tables ----------------------------------------------
+
+    /// A dotted key: `a`, `"quoted key"`, `a.'b'.c`.
+    fn key(&mut self) -> Result<Vec<Key>, RuntimeError> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_ws();
+            let start = self.pos;
+            let k = match self.peek() {
+                Some(b'"') => {
+                    if self.src[self.pos..].starts_with("\"\"\"") {
+                        return Err(self.err("a key cannot be a multi-line string"));
+                    }
+                    self.basic_string()?
+                }
+                Some(b'\'') => {
+                    if self.src[self.pos..].starts_with("'''") {
+                        return Err(self.err("a key cannot be a multi-line string"));
+                    }
+                    self.literal_string()?
+                }
+                _ => {
+                    while matches!(self.peek(), Some(b) if b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+                        self.pos += 1;
+                    }
+                    if self.pos == start {
+                        return Err(self.unexpected("a key"));
+                    }
+                    self.src[start..self.pos].to_string()
+                }
+            };
+            keys.push((k, start));
+            self.skip_ws();
+            if !self.eat(b'.') {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// `[a.b]` or `[[a.b]]`: define the table (or append to the array
+    /// of tables) and return its path, which becomes the current table.
+    fn header(&mut self, root: &mut Table) -> Result<Vec<String>, RuntimeError> {
+        let start = self.pos;
+        self.pos += 1;
+        let array = self.eat(b'[');
+        let keys = self.key()?;
+        self.expect(b']', "`]`")?;
+        if array && !self.eat(b']') {
+            return Err(self.unexpected("`]]`"));
+        }
+        let (last, prefix) = keys.split_last().expect("a key has at least one segment");
+        let mut t = root;
+        for (i, (k, pos)) in prefix.iter().enumerate() {
+            t = match t.entries.entry(k.clone()).or_insert_with(|| Node::Table(Table::default())) {
+                Node::Table(next) => next,
+                Node::Tables(list) => list.last_mut().expect("array of tables is never empty"),
+                Node::Value(_) => {
+                    return Err(self.err_at(
+                        *pos,
+                        &format!("key `{}` is already defined as a value", dotted(&keys[..=i])),
+                    ))
+                }
+            };
+        }
+        let fresh = Table { header: true, ..Table::default() };
+        match t.entries.get_mut(&last.0) {
+            None if array => {
+                t.entries.insert(last.0.clone(), Node::Tables(vec![fresh]));
+            }
+            None => {
+                t.entries.insert(last.0.clone(), Node::Table(fresh));
+            }
+            Some(Node::Tables(list)) if array => list.push(fresh),
+            Some(Node::Table(existing)) if !array && !existing.header && !existing.dotted => {
+                existing.header = true;
+            }
+            Some(_) => {
+                let what = if array { "array of tables" } else { "table" };
+                return Err(self.err_at(
+                    start,
+                    &format!("cannot define {what} `{}`: it is already defined", dotted(&keys)),
+                ));
+            }
+        }
+        Ok(keys.into_iter().map(|(k, _)| k).collect())
+    }
+
+    /// `key = value`, stored into `table`.
+    fn key_value(&mut self, table: &mut Table) -> Result<(), RuntimeError> {
+        let keys = self.key()?;
+        self.skip_ws();
+        self.expect(b'=', "`=`")?;
+        self.skip_ws();
+        let value = self.value()?;
+        self.insert(table, &keys, value)
+    }
+
+    fn insert(&self, table: &mut Table, keys: &[Key], value: Value) -> Result<(), RuntimeError> {
+        let (last, prefix) = keys.split_last().expect("a key has at least one segment");
+        let mut t = table;
+        for (i, (k, pos)) in prefix.iter().enumerate() {
+            let slot = t
+                .entries
+                .entry(k.clone())
+                .or_insert_with(|| Node::Table(Table { dotted: true, ..Table::default() }));
+            t = match slot {
+                Node::Table(next) if next.dotted => next,
+                _ => {
+                    return Err(self.err_at(
+                        *pos,
+                        &format!("cannot add to `{}`: it is already defined", dotted(&keys[..=i])),
+                    ))
+                }
+            };
+        }
+        if t.entries.contains_key(&last.0) {
+            return Err(self.err_at(last.1, &format!("duplicate key `{}`", dotted(keys))));
+        }
+        t.entries.insert(last.0.clone(), Node::Value(value));
+        Ok(())
+    }
+
+    // -- values -------------------------------------------------------
+
+    fn value(&mut self) -> Result<Value, RuntimeError> {
+        match self.peek() {
+            Some(b'"') => self.basic_string().map(|s| Value::Str(s.into())),
+            Some(b'\'') => self.literal_string().map(|s| Value::Str(s.into())),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.inline_table(),
+            Some(b't') if self.src[self.pos..].starts_with("true") => {
+                self.pos += 4;
+                Ok(Value::Bool(true))
+            }
+            Some(b'f') if self.src[self.pos..].starts_with("false") => {
+                self.pos += 5;
+                Ok(Value::Bool(false))
+            }
+            Some(b) if b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'i' | b'n') => {
+                if self.date_ahead() {
+                    self.datetime()
+                } else if self.time_ahead() {
+                    self.local_time()
+                } else {
+                    self.number()
+                }
+            }
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, RuntimeError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws_lines()?;
+            if self.eat(b']') {
+                break;
+            }
+            items.push(self.value()?);
+            self.skip_ws_lines()?;
+            if self.eat(b']') {
+                break;
+            }
+            self.expect(b',', "`,` or `]`")?;
+        }
+        Ok(Value::Array(gc::alloc_array(items)))
+    }
+
+    /// `{ a = 1, b.c = 2 }` — on one line, with no trailing comma.
+    fn inline_table(&mut self) -> Result<Value, RuntimeError> {
+        self.pos += 1;
+        let mut table = Table::default();
+        self.skip_ws();
+        if !self.eat(b'}') {
+            loop {
+                self.key_value(&mut table)?;
+                self.skip_ws();
+                if self.eat(b'}') {
+                    break;
+                }
+                self.expect(b',', "`,` or `}`")?;
+                self.skip_ws();
+            }
+        }
+        Ok(table.into_value())
+    }
+
+    // -- strings ------------------------------------------------------
+
+    /// `"..."` or `"""..."""`, with escapes.
+    fn basic_string(&mut self) -> Result<String, RuntimeError> {
+        let multi = self.src[self.pos..].starts_with("\"\"\"");
+        self.pos += if multi { 3 } else { 1 };
+        if multi {
+            self.eat_newline();
+        }
+        let mut out = String::new();
+        loop {
+            let Some(c) = self.src[self.pos..].chars().next() else {
+                return Err(self.err("unterminated string"));
+            };
+            match c {
+                '"' if !multi => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                '"' => {
+                    if self.close_quotes(b'"', &mut out)? {
+                        return Ok(out);
+                    }
+                }
+                '\\' => {
+                    let at = self.pos;
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'b') => out.push('\u{0008}'),
+                        Some(b't') => out.push('\t'),
+                        Some(b'n') => out.push('\n'),
+                        Some(b'f') => out.push('\u{000C}'),
+                        Some(b'r') => out.push('\r'),
+                        Some(b'"') => out.push('"'),
+                        Some(b'\\') => out.push('\\'),
+                        Some(b'u') => out.push(self.unicode_escape(at, 4)?),
+                        Some(b'U') => out.push(self.unicode_escape(at, 8)?),
+                        Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') if multi => {
+                            // A line-ending backslash trims the break and
+                            // all whitespace after it.
+                            self.skip_ws();
+                            if !self.eat_newline() {
+                                return Err(self.err_at(at, "invalid escape sequence"));
+                            }
+                            loop {
+                                self.skip_ws();
+                                if !self.eat_newline() {
+                                    break;
+                                }
+                            }
+                            continue;
+                        }
+                        _ => return Err(self.err_at(at, "invalid escape sequence")),
+                    }
+                    self.pos += 1;
+                }
+                '\n' if multi => {
+                    out.push('\n');
+                    self.pos += 1;
+                }
+                '\r' if multi && self.peek_at(1) == Some(b'\n') => {
+                    out.push_str("\r\n");
+                    self.pos += 2;
+                }
+                '\n' | '\r' => return Err(self.err("unterminated string")),
+                c if (c < ' ' && c != '\t') || c == '\u{7f}' => {
+                    return Err(self.err("control character in string must be escaped"))
+                }
+                c => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// At a run of `q` quotes inside a multi-line string: three or more
+    /// close it (up to two extra are content), fewer are content.
+    /// Returns whether the string ended.
+    fn close_quotes(&mut self, q: u8, out: &mut String) -> Result<bool, RuntimeError> {
+        let start = self.pos;
+        while self.peek() == Some(q) {
+            self.pos += 1;
+        }
+        let run = self.pos - start;
+        if run > 5 {
+            return Err(self.err_at(start + 5, "too many quotes at the end of a multi-line string"));
+        }
+        let content = if run >= 3 { run - 3 } else { run };
+        for _ in 0..content {
+            out.push(q as char);
+        }
+        Ok(run >= 3)
+    }
+
+    /// `\uXXXX` / `\UXXXXXXXX` — the escape at `at`, the digits next.
+    fn unicode_escape(&mut self, at: usize, digits: usize) -> Result<char, RuntimeError> {
+        let hex = self.src.get(self.pos + 1..self.pos + 1 + digits);
+        let code = hex
+            .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.err_at(at, &format!("expected {digits} hex digits in unicode escape")))?;
+        self.pos += digits;
+        char::from_u32(code).ok_or_else(|| self.err_at(at, "unicode escape is not a scalar value"))
+    }
+
+    /// `'...'` or `'''...'''`, taken literally.
+    fn literal_string(&mut self) -> Result<String, RuntimeError> {
+        let multi = self.src[self.pos..].starts_with("'''");
+        self.pos += if multi { 3 } else { 1 };
+        if multi {
+            self.eat_newline();
+        }
+        let mut out = String::new();
+        loop {
+            let Some(c) = self.src[self.pos..].chars().next() else {
+                return Err(self.err("unterminated string"));
+            };
+            match c {
+                '\'' if !multi => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                '\'' => {
+                    if self.close_quotes(b'\'', &mut out)? {
+                        return Ok(out);
+                    }
+                }
+                '\n' if multi => {
+                    out.push('\n');
+                    self.pos += 1;
+                }
+                '\r' if multi && self.peek_at(1) == Some(b'\n') => {
+                    out.push_str("\r\n");
+                    self.pos += 2;
+                }
+                '\n' | '\r' => return Err(self.err("unterminated string")),
+                c if (c < ' ' && c != '\t') || c == '\u{7f}' => {
+                    return Err(self.err("control character in string"))
+                }
+                c => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    // -- numbers ------------------------------------------------------
+
+    fn number(&mut self) -> Result<Value, RuntimeError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b) if b.is_ascii_alphanumeric() || matches!(b, b'_' | b'+' | b'-' | b'.')) {
+            self.pos += 1;
+        }
+        let tok = &self.src[start..self.pos];
+        number_value(tok).ok_or_else(|| self.err_at(start, &format!("invalid number `{tok}`")))
+    }
+
+    // -- dates and times ----------------------------------------------
+
+    fn digits_at(&self, at: usize, n: usize) -> bool {
+        self.bytes.len() >= self.pos + at + n
+            && self.bytes[self.pos + at..self.pos + at + n].iter().all(u8::is_ascii_digit)
+    }
+
+    fn date_ahead(&self) -> bool {
+        self.digits_at(0, 4) && self.peek_at(4) == Some(b'-')
+    }
+
+    fn time_ahead(&self) -> bool {
+        self.digits_at(0, 2) && self.peek_at(2) == Some(b':')
+    }
+
+    /// `n` digits as a number, or an error naming `what`.
+    fn fixed(&mut self, n: usize, what: &str) -> Result<i64, RuntimeError> {
+        if !self.digits_at(0, n) {
+            return Err(self.err(&format!("expected {n}-digit {what}")));
+        }
+        let v = self.src[self.pos..self.pos + n].parse().expect("ASCII digits");
+        self.pos += n;
+        Ok(v)
+    }
+
+    fn date(&mut self) -> Result<(i64, i64, i64), RuntimeError> {
+        let start = self.pos;
+        let year = self.fixed(4, "year")?;
+        self.expect(b'-', "`-`")?;
+        let month = self.fixed(2, "month")?;
+        self.expect(b'-', "`-`")?;
+        let day = self.fixed(2, "day")?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(self.err_at(start, "invalid date"));
+        }
+        Ok((year, month, day))
+    }
+
+    /// `HH:MM:SS[.fraction]` → (hour, minute, second, ms).
+    fn time(&mut self) -> Result<(i64, i64, i64, i64), RuntimeError> {
+        let start = self.pos;
+        let hour = self.fixed(2, "hour")?;
+        self.expect(b':', "`:`")?;
+        let minute = self.fixed(2, "minute")?;
+        self.expect(b':', "`:`")?;
+        let second = self.fixed(2, "second")?;
+        let mut ms = 0;
+        if self.eat(b'.') {
+            let frac = self.pos;
+            while matches!(self.peek(), Some(b) if b.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            if self.pos == frac {
+                return Err(self.err("expected digits after `.`"));
+            }
+            // Milliseconds: the first three digits, padded.
+            for i in 0..3 {
+                let d = self.bytes.get(frac + i).filter(|_| frac + i < self.pos);
+                ms = ms * 10 + d.map_or(0, |b| i64::from(b - b'0'));
+            }
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(self.err_at(start, "invalid time"));
+        }
+        Ok((hour, minute, second, ms))
+    }
+
+    fn datetime(&mut self) -> Result<Value, RuntimeError> {
+        let (year, month, day) = self.date()?;
+        let has_time = match self.peek() {
+            Some(b'T') | Some(b't') => true,
+            Some(b' ') => self.digits_at(1, 2) && self.peek_at(3) == Some(b':'),
+            _ => false,
+        };
+        if !has_time {
+            return Ok(components("local-date", &[("year", year), ("month", month), ("day", day)]));
+        }
+        self.pos += 1;
+        let (hour, minute, second, ms) = self.time()?;
+        let offset = match self.peek() {
+            Some(b'Z') | Some(b'z') => {
+                self.pos += 1;
+                Some(0)
+            }
+            Some(sign @ (b'+' | b'-')) => {
+                let start = self.pos;
+                self.pos += 1;
+                let h = self.fixed(2, "offset hour")?;
+                self.expect(b':', "`:`")?;
+                let m = self.fixed(2, "offset minute")?;
+                if h > 23 || m > 59 {
+                    return Err(self.err_at(start, "invalid time offset"));
+                }
+                let minutes = h * 60 + m;
+                Some(if sign == b'-' { -minutes } else { minutes })
+            }
+            _ => None,
+        };
+        let Some(offset) = offset else {
+            return Ok(components("local-datetime", &[
+                ("year", year), ("month", month), ("day", day),
+                ("hour", hour), ("minute", minute), ("second", second), ("ms", ms),
+            ]));
+        };
+        let local = days_from_civil(year, month, day) * MS_PER_DAY
+            + hour * 3_600_000 + minute * 60_000 + second * 1000 + ms;
+        Ok(components("offset-datetime", &[
+            ("year", year), ("month", month), ("day", day),
+            ("hour", hour), ("minute", minute), ("second", second), ("ms", ms),
+            ("offset", offset), ("epoch_ms", local - offset * 60_000),
+        ]))
+    }
+
+    fn local_time(&mut self) -> Result<Value, RuntimeError> {
+        let (hour, minute, second, ms) = self.time()?;
+        Ok(components("local-time", &[("hour", hour), ("minute", minute), ("second", second), ("ms", ms)]))
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A date/time components Object tagged with its TOML kind.
+fn components(kind: &str, fields: &[(&str, i64)]) -> Value {
+    let mut map: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(fields.len() + 1);
+    for (k, v) in fields {
+        map.insert(Arc::from(*k), Value::Int(*v));
+    }
+    map.insert(Arc::from("_toml"), Value::Str(kind.into()));
+    Value::Object(gc::alloc_object(map))
+}
+
+/// Every `_` in `digits` sits between two digits of the radix.
+fn underscores_ok(digits: &str, radix: u32) -> bool {
+    let b = digits.as_bytes();
+    b.iter().enumerate().all(|(i, &c)| {
+        c != b'_'
+            || (i > 0
+                && i + 1 < b.len()
+                && (b[i - 1] as char).is_digit(radix)
+                && (b[i + 1] as char).is_digit(radix))
+    })
+}
+
+/// A TOML integer or float token, or `None` if it is malformed.
+fn number_value(tok: &str) -> Option<Value> {
+    let (sign, body) = match tok.as_bytes().first() {
+        Some(b'+') => ("", &tok[1..]),
+        Some(b'-') => ("-", &tok[1..]),
+        _ => ("", tok),
+    };
+    match body {
+        "inf" => return Some(Value::Float(if sign == "-" { f64::NEG_INFINITY } else { f64::INFINITY })),
+        "nan" => return Some(Value::Float(f64::NAN)),
+        _ => {}
+    }
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = body.strip_prefix(prefix) {
+            // Prefixed integers take no sign.
+            if tok.len() != body.len() || digits.is_empty() || !underscores_ok(digits, radix) {
+                return None;
+            }
+            let clean = digits.replace('_', "");
+            return i64::from_str_radix(&clean, radix).ok().map(Value::Int);
+        }
+    }
+    if !body.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'_' | b'.' | b'e' | b'E' | b'+' | b'-')) {
+        return None;
+    }
+    // Underscores only between digits, in every part.
+    if body.split(['.', 'e', 'E', '+', '-']).any(|part| !underscores_ok(part, 10)) {
+        return None;
+    }
+    let clean = body.replace('_', "");
+    let (mantissa, exponent) = match clean.find(['e', 'E']) {
+        Some(i) => (&clean[..i], Some(&clean[i + 1..])),
+        None => (clean.as_str(), None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(int) || (int.len() > 1 && int.starts_with('0')) {
+        return None;
+    }
+    if frac.is_some_and(|f| !all_digits(f)) {
+        return None;
+    }
+    if let Some(e) = exponent {
+        let digits = e.strip_prefix(['+', '-']).unwrap_or(e);
+        if !all_digits(digits) {
+            return None;
+        }
+    }
+    let text = format!("{sign}{clean}");
+    if frac.is_none() && exponent.is_none() {
+        text.parse::<i64>().ok().map(Value::Int)
+    } else {
+        text.parse::<f64>().ok().map(Value::Float)
+    }
+}
+
+// ---------------- stringify ----------------
+
+fn stringify(args: &[Value]) -> Result<Value, RuntimeError> {
+    let root = match &args[0] {
+        Value::Object(o) if date_kind(o)?.is_none() => *o,
+        other => {
+            return Err(raise(format!(
+                "Toml.stringify: the top level must be a table (an Object), got {}",
+                other.type_name()
+            )));
+        }
+    };
+    let mut w = Writer { out: String::new(), seen_a: Vec::new(), seen_o: Vec::new() };
+    w.table(root, &mut Vec::new())?;
+    Ok(Value::Str(w.out.into()))
+}
+
+/// The fields of a `DateTime.from_ms` / `DateTime.now` Object, which
+/// `stringify` writes as a UTC offset date-time.
+const DATETIME_FIELDS: [&str; 9] =
+    ["year", "month", "day", "hour", "minute", "second", "ms", "weekday", "yearday"];
+
+/// The `_toml` kind of a date/time components Object, or `None` for an
+/// ordinary table.
+fn date_kind(o: &GcRef<ObjectKind>) -> Result<Option<Arc<str>>, RuntimeError> {
+    let obj = o.borrow();
+    match obj.get("_toml") {
+        None if obj.len() == DATETIME_FIELDS.len()
+            && DATETIME_FIELDS.iter().all(|k| matches!(obj.get(*k), Some(Value::Int(_)))) =>
+        {
+            Ok(Some("offset-datetime".into()))
+        }
+        None => Ok(None),
+        Some(Value::Str(k))
+            if matches!(&**k, "offset-datetime" | "local-datetime" | "local-date" | "local-time") =>
+        {
+            Ok(Some(k.clone()))
+        }
+        Some(other) => Err(raise(format!(
+            "Toml.stringify: `_toml` must name a date/time kind, got {}",
+            match other {
+                Value::Str(s) => format!("'{s}'"),
+                v => v.type_name().to_string(),
+            }
+        ))),
+    }
+}
+
+/// How a table entry is written: inline after `key = `, as a
+/// `[header]` section, or as `[[header]]` sections.
+enum Shape {
+    Inline,
+    Table(GcRef<ObjectKind>),
+    Tables(GcRef<ArrayKind>),
+}
+
+fn shape(v: &Value) -> Result<Shape, RuntimeError> {
+    Ok(match v {
+        Value::Object(o) if date_kind(o)?.is_none() => Shape::Table(*o),
+        Value::Array(a) => {
+            let arr = a.borrow();
+            let mut tables = !arr.is_empty();
+            for item in arr.iter() {
+                tables &= matches!(item, Value::Object(o) if date_kind(o)?.is_none());
+            }
+            if tables { Shape::Tables(*a) } else { Shape::Inline }
+        }
+        _ => Shape::Inline,
+    })
+}
+
+/// Raise the catchable `cycle` error, as `JSON.stringify` does.
+fn cycle_err() -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Cycle, 0)
+}
+
+struct Writer {
+    out: String,
+    // Ancestor paths for cycle detection, as in `JSON.stringify`.
+    seen_a: Vec<GcRef<ArrayKind>>,
+    seen_o: Vec<GcRef<ObjectKind>>,
+}
+
+impl Writer {
+    /// The body of the table at `path`: its inline entries, then each
+    /// sub-table and array of tables under its own header.
+    fn table(&mut self, o: GcRef<ObjectKind>, path: &mut Vec<Arc<str>>) -> Result<(), RuntimeError> {
+        if self.seen_o.contains(&o) {
+            return Err(cycle_err());
+        }
+        self.seen_o.push(o);
+        let entries: Vec<(Arc<str>, Value)> =
+            o.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        let mut sections = Vec::new();
+        for (k, v) in &entries {
+            match shape(v)? {
+                Shape::Inline => {
+                    write_key(&mut self.out, k);
+                    self.out.push_str(" = ");
+                    self.inline(v)?;
+                    self.out.push('\n');
+                }
+                s => sections.push((k.clone(), s)),
+            }
+        }
+        for (k, s) in sections {
+            path.push(k);
+            match s {
+                Shape::Table(t) => {
+                    // A table holding only sub-tables needs no header of
+                    // its own: theirs define it implicitly.
+                    let entries: Vec<Value> = t.borrow().values().cloned().collect();
+                    let mut bare = !entries.is_empty();
+                    for v in &entries {
+                        bare &= !matches!(shape(v)?, Shape::Inline);
+                    }
+                    if !bare {
+                        self.header(path, false);
+                    }
+                    self.table(t, path)?;
+                }
+                Shape::Tables(a) => {
+                    if self.seen_a.contains(&a) {
+                        return Err(cycle_err());
+                    }
+                    self.seen_a.push(a);
+                    let items = a.borrow().clone();
+                    for item in items {
+                        let Value::Object(t) = item else { unreachable!("checked by shape") };
+                        self.header(path, true);
+                        self.table(t, path)?;
+                    }
+                    self.seen_a.pop();
+                }
+                Shape::Inline => unreachable!("written above"),
+            }
+            path.pop();
+        }
+        self.seen_o.pop();
+        Ok(())
+    }
+
+    fn header(&mut self, path: &[Arc<str>], array: bool) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str(if array { "[[" } else { "[" });
+        for (i, k) in path.iter().enumerate() {
+            if i > 0 {
+                self.out.push('.');
+            }
+            write_key(&mut self.out, k);
+        }
+        self.out.push_str(if array { "]]\n" } else { "]\n" });
+    }
+
+    fn inline(&mut self, v: &Value) -> Result<(), RuntimeError> {
+        match v {
+            Value::Null => {
+                return Err(raise("Toml.stringify: cannot serialize null (TOML has no null)".into()))
+            }
+            Value::Bool(b) => self.out.push_str(if *b { "true" } else { "false" }),
+            Value::Int(n) => self.out.push_str(&n.to_string()),
+            Value::Float(x) => write_float(&mut self.out, *x),
+            Value::Str(s) => write_string(&mut self.out, s),
+            Value::Array(a) => {
+                if self.seen_a.contains(a) {
+                    return Err(cycle_err());
+                }
+                self.seen_a.push(*a);
+                let items = a.borrow().clone();
+                self.out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.inline(item)?;
+                }
+                self.out.push(']');
+                self.seen_a.pop();
+            }
+            Value::Object(o) => {
+                if let Some(kind) = date_kind(o)? {
+                    return write_date(&mut self.out, o, &kind);
+                }
+                if self.seen_o.contains(o) {
+                    return Err(cycle_err());
+                }
+                self.seen_o.push(*o);
+                let entries: Vec<(Arc<str>, Value)> =
+                    o.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                if entries.is_empty() {
+                    self.out.push_str("{}");
+                } else {
+                    self.out.push_str("{ ");
+                    for (i, (k, v)) in entries.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push_str(", ");
+                        }
+                        write_key(&mut self.out, k);
+                        self.out.push_str(" = ");
+                        self.inline(v)?;
+                    }
+                    self.out.push_str(" }");
+                }
+                self.seen_o.pop();
+            }
+            other => {
+                return Err(raise(format!(
+                    "Toml.stringify: cannot serialize {}",
+                    other.type_name()
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A bare key when it is one, otherwise a quoted one.
+fn write_key(out: &mut String, k: &str) {
+    if !k.is_empty() && k.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        out.push_str(k);
+    } else {
+        write_string(out, k);
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0008}' => out.push_str("\\b"),
+            '\u{000C}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04X}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_float(out: &mut String, x: f64) {
+    if x.is_nan() {
+        out.push_str("nan");
+    } else if x.is_infinite() {
+        out.push_str(if x > 0.0 { "inf" } else { "-inf" });
+    } else {
+        let s = x.to_string();
+        out.push_str(&s);
+        // A float needs a fraction or exponent to read back as one.
+        if !s.contains(['.', 'e', 'E']) {
+            out.push_str(".0");
+        }
+    }
+}
+
+fn write_date(out: &mut String, o: &GcRef<ObjectKind>, kind: &str) -> Result<(), RuntimeError> {
+    let obj = o.borrow();
+    let field = |key: &str| match obj.get(key) {
+        None | Some(Value::Null) if key == "ms" => Ok(0),
+        Some(Value::Int(n)) => Ok(*n),
+        _ => Err(raise(format!("Toml.stringify: a {kind} needs an Int `{key}` field"))),
+    };
+    if kind != "local-time" {
+        out.push_str(&format!("{:04}-{:02}-{:02}", field("year")?, field("month")?, field("day")?));
+        if kind == "local-date" {
+            return Ok(());
+        }
+        out.push('T');
+    }
+    out.push_str(&format!("{:02}:{:02}:{:02}", field("hour")?, field("minute")?, field("second")?));
+    let ms = field("ms")?;
+    if ms != 0 {
+        out.push_str(&format!(".{ms:03}"));
+    }
+    if kind == "offset-datetime" {
+        let offset = match obj.get("offset") {
+            None | Some(Value::Null) => 0,
+            Some(Value::Int(n)) if n.abs() < 24 * 60 => *n,
+            _ => {
+                return Err(raise(
+                    "Toml.stringify: an offset-datetime `offset` must be an Int of minutes under a day"
+                        .to_string(),
+                ))
+            }
+        };
+        if offset == 0 {
+            out.push('Z');
+        } else {
+            let sign = if offset < 0 { '-' } else { '+' };
+            out.push_str(&format!("{sign}{:02}:{:02}", offset.abs() / 60, offset.abs() % 60));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_follow_the_toml_grammar() {
+        assert_eq!(number_value("+99"), Some(Value::Int(99)));
+        assert_eq!(number_value("1_000"), Some(Value::Int(1000)));
+        assert_eq!(number_value("0xDEAD_beef"), Some(Value::Int(0xdead_beef)));
+        assert_eq!(number_value("0o755"), Some(Value::Int(0o755)));
+        assert_eq!(number_value("0b1101"), Some(Value::Int(13)));
+        assert_eq!(number_value("-0.01"), Some(Value::Float(-0.01)));
+        assert_eq!(number_value("6.626e-34"), Some(Value::Float(6.626e-34)));
+        assert_eq!(number_value("5e+22"), Some(Value::Float(5e22)));
+        assert_eq!(number_value("-inf"), Some(Value::Float(f64::NEG_INFINITY)));
+        assert!(matches!(number_value("nan"), Some(Value::Float(x)) if x.is_nan()));
+        for bad in ["01", "1__0", "_1", "1_", "+0x10", "1.", ".5", "1e", "3.e5", "0x", "9223372036854775808"] {
+            assert_eq!(number_value(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn dates_validate_the_calendar() {
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2023, 4), 30);
+    }
+
+    #[test]
+    fn keys_are_bare_only_when_they_can_be() {
+        let key = |k: &str| {
+            let mut out = String::new();
+            write_key(&mut out, k);
+            out
+        };
+        assert_eq!(key("server-1_a"), "server-1_a");
+        assert_eq!(key("a.b"), "\"a.b\"");
+        assert_eq!(key(""), "\"\"");
+        assert_eq!(key("ключ"), "\"ключ\"");
+    }
+}
@@ -11,9 +11,12 @@
 //! reference implementation is `web/tigr_ws.js`, and the ABI is
 //! documented in `docs/stdlib/ws.md`.
 //!
-//! The surface matches `WS.tg` exactly — `connect` / `send` / `poll` /
+//! The client surface matches `WS.tg` — `connect` / `send` / `poll` /
 //! `drain` / `state` / `close` — so the same tigr program runs unchanged
-//! native and on web. The handle here is an opaque integer id (an object
+//! native and on web. `connect`'s options and `close`'s code and reason
+//! are accepted and ignored: the browser negotiates its own extensions
+//! and the host ABI closes with the default code. The server half
+//! (`upgrade`), `recv`, and `close_info` have no browser counterpart. The handle here is an opaque integer id (an object
 //! on native); either way a program only passes it back to the other
 //! calls.
 
@@ -55,12 +58,12 @@ extern "C" {
 
 pub fn module() -> Value {
     object(&[
-        ("connect", native("connect", Arity::Exact(1), ws_connect)),
+        ("connect", native("connect", Arity::Range(1, 2), ws_connect)),
         ("send",    native("send",    Arity::Exact(2), ws_send)),
         ("poll",    native("poll",    Arity::Exact(1), ws_poll)),
         ("drain",   native("drain",   Arity::Exact(1), ws_drain)),
         ("state",   native("state",   Arity::Exact(1), ws_state)),
-        ("close",   native("close",   Arity::Exact(1), ws_close)),
+        ("close",   native("close",   Arity::Range(1, 3), ws_close)),
     ])
 }
 
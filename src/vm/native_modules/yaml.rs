@@ -0,0 +1,1409 @@
+//! `import 'Yaml'` — `parse`, `parse_all` and `stringify` for a safe
+//! subset of YAML 1.2.
+//!
+//! Supported: block mappings and sequences (including the compact `- a:
+//! 1` form and sequences written at their key's indentation), flow `[...]`
+//! and `{...}` collections, plain, single- and double-quoted scalars
+//! (multi-line, with line folding), literal `|` and folded `>` block
+//! scalars with chomping and indentation indicators, comments, anchors
+//! `&a` and aliases `*a`, the `<<` merge key, and `---` / `...`
+//! document markers.
+//!
+//! Plain scalars resolve by the YAML 1.2 core schema: `null` / `~` /
+//! empty, `true` / `false`, decimal, `0o` and `0x` integers, and floats
+//! including `.inf` and `.nan`; anything else is a String. Mapping keys
+//! are always Strings, taken as written.
+//!
+//! Deliberately unsupported: tags other than the core-schema `!!str`,
+//! `!!int`, `!!float`, `!!bool`, `!!null`, `!!seq` and `!!map` — a
+//! document cannot ask for an arbitrary type to be built — plus complex
+//! `?` keys and `%TAG` directives. Each is a `parse_error`, not silently
+//! ignored. An alias shares its anchored value rather than copying it,
+//! so an alias bomb costs no memory.
+//!
+//! Malformed input raises a catchable `parse_error` — `${kind, message,
+//! line, column}` — whose message reads like `JSON.parse`'s: "Yaml.parse:
+//! msg at line L, column C".
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc::{self, ArrayKind, GcRef, ObjectKind};
+use crate::vm::value::{Arity, Value};
+
+use super::{line_col, native, object, parse_error};
+
+pub fn module() -> Value {
+    object(&[
+        ("parse",     native("parse",     Arity::Exact(1), parse)),
+        ("parse_all", native("parse_all", Arity::Exact(1), parse_all)),
+        ("stringify", native("stringify", Arity::Exact(1), stringify)),
+    ])
+}
+
+fn raise(msg: String) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Str(msg.into())), 0)
+}
+
+// ---------------- parse ----------------
+
+fn text_arg(args: &[Value], label: &str) -> Result<Arc<str>, RuntimeError> {
+    match &args[0] {
+        Value::Str(s) => Ok(s.clone()),
+        other => Err(raise(format!(
+            "Yaml.{label}: expected String, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// `parse(text)` — the single document in `text` (`null` if empty).
+fn parse(args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = text_arg(args, "parse")?;
+    let mut p = Parser::new(&s, "parse");
+    let mut docs = p.stream(false)?;
+    Ok(docs.pop().unwrap_or(Value::Null))
+}
+
+/// `parse_all(text)` — every `---`-separated document, as an Array.
+fn parse_all(args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = text_arg(args, "parse_all")?;
+    let mut p = Parser::new(&s, "parse_all");
+    let docs = p.stream(true)?;
+    Ok(Value::Array(gc::alloc_array(docs)))
+}
+
+/// A scalar as read, before resolution: plain scalars resolve by the
+/// core schema, quoted and block scalars are always Strings.
+struct Scalar {
+    text: String,
+    plain: bool,
+}
+
+/// The `&anchor` and `!!tag` written before a node.
+#[derive(Default)]
+struct Props {
+    anchor: Option<String>,
+    tag: Option<(String, usize)>,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    label: &'static str,
+    anchors: HashMap<String, Value>,
+}
+
+fn is_blank(b: Option<u8>) -> bool {
+    matches!(b, None | Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n'))
+}
+
+fn is_flow_indicator(b: u8) -> bool {
+    matches!(b, b',' | b'[' | b']' | b'{' | b'}')
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str, label: &'static str) -> Self {
+        let pos = if src.starts_with('\u{feff}') { 3 } else { 0 };
+        Parser { src, bytes: src.as_bytes(), pos, label, anchors: HashMap::new() }
+    }
+
+    fn err_at(&self, pos: usize, msg: &str) -> RuntimeError {
+        let (line, col) = line_col(self.bytes, pos);
+        parse_error(
+            format!("Yaml.{}: {msg} at line {line}, column {col}", self.label),
+            line,
+            col,
+        )
+    }
+
+    fn err(&self, msg: &str) -> RuntimeError {
+        self.err_at(self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.bytes.get(self.pos + n).copied()
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The column (0-based) of the current position.
+    fn col(&self) -> usize {
+        let start = self.bytes[..self.pos].iter().rposition(|&c| c == b'\n').map_or(0, |p| p + 1);
+        self.pos - start
+    }
+
+    fn eat_newline(&mut self) -> bool {
+        if self.eat(b'\n') {
+            return true;
+        }
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+            self.eat(b'\n');
+            return true;
+        }
+        false
+    }
+
+    fn skip_inline_space(&mut self) {
+        while matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some(b'#') {
+            while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Only whitespace and perhaps a comment remain on this line.
+    fn at_line_end(&mut self) -> bool {
+        self.skip_inline_space();
+        matches!(self.peek(), None | Some(b'\n') | Some(b'\r') | Some(b'#'))
+    }
+
+    /// `---` or `...` at the start of a line.
+    fn at_marker(&self, marker: &str) -> bool {
+        self.col() == 0 && self.src[self.pos..].starts_with(marker) && is_blank(self.peek_at(3))
+    }
+
+    fn at_document_boundary(&self) -> bool {
+        self.peek().is_none() || self.at_marker("---") || self.at_marker("...")
+    }
+
+    /// `-` starting a block sequence entry.
+    fn at_dash_entry(&self) -> bool {
+        self.peek() == Some(b'-') && is_blank(self.peek_at(1))
+    }
+
+    /// From the start of a line, skip blank and comment-only lines.
+    /// Returns the indentation of the next content line, with the
+    /// position on its first character, or `None` at the end of input.
+    fn next_content_line(&mut self) -> Result<Option<usize>, RuntimeError> {
+        loop {
+            let start = self.pos;
+            while self.peek() == Some(b' ') {
+                self.pos += 1;
+            }
+            let indent = self.pos - start;
+            if self.peek() == Some(b'\t') {
+                self.skip_inline_space();
+                if !matches!(self.peek(), None | Some(b'\n') | Some(b'\r') | Some(b'#')) {
+                    return Err(self.err("tab characters cannot be used for indentation"));
+                }
+            }
+            self.skip_comment();
+            match self.peek() {
+                None => return Ok(None),
+                Some(b'\n') | Some(b'\r') => {
+                    self.eat_newline();
+                }
+                Some(_) => return Ok(Some(indent)),
+            }
+        }
+    }
+
+    /// The rest of this line must be blank or a comment; then move to
+    /// the next content line.
+    fn finish_line(&mut self) -> Result<Option<usize>, RuntimeError> {
+        self.skip_inline_space();
+        self.skip_comment();
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        if !self.eat_newline() {
+            return Err(self.err(&match self.peek_char() {
+                Some(':') => "mapping values are not allowed here".to_string(),
+                Some(c) => format!("unexpected '{c}' after a value"),
+                None => unreachable!("checked above"),
+            }));
+        }
+        self.next_content_line()
+    }
+
+    // -- documents ----------------------------------------------------
+
+    fn stream(&mut self, all: bool) -> Result<Vec<Value>, RuntimeError> {
+        let mut docs = Vec::new();
+        // Whether the last document was closed by `...`, so a bare one
+        // may follow.
+        let mut closed = true;
+        self.next_content_line()?;
+        loop {
+            while self.col() == 0 && self.peek() == Some(b'%') {
+                if !self.src[self.pos..].starts_with("%YAML") {
+                    return Err(self.err("only the %YAML directive is supported"));
+                }
+                while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+                self.finish_line()?;
+            }
+            if self.peek().is_none() {
+                break;
+            }
+            if self.at_marker("...") {
+                self.pos += 3;
+                self.finish_line()?;
+                closed = true;
+                continue;
+            }
+            let explicit = self.at_marker("---");
+            if !explicit && !closed {
+                return Err(self.err("unexpected content after the document"));
+            }
+            if !all && !docs.is_empty() {
+                return Err(self.err("a second document; use Yaml.parse_all for multi-document text"));
+            }
+            self.anchors.clear();
+            let doc = if explicit {
+                self.pos += 3;
+                self.block_value(-1, false, false)?
+            } else {
+                self.node_here(-1, true)?
+            };
+            docs.push(doc);
+            closed = false;
+        }
+        Ok(docs)
+    }
+
+    // -- block structure ----------------------------------------------
+
+    /// The value after a `key:`, a `- ` or `---`, whose indicator sits
+    /// at column `parent`. It may start on this line or on a more
+    /// indented following one; with `seq_ok`, a `- ` sequence at the
+    /// parent's own column also counts (`key:` over `- item`). Nothing
+    /// there is an empty value, `null`.
+    fn block_value(&mut self, parent: isize, seq_ok: bool, compact: bool) -> Result<Value, RuntimeError> {
+        self.skip_inline_space();
+        let props_at = self.pos;
+        let props = self.properties()?;
+        if !self.at_line_end() {
+            if props.anchor.is_some() || props.tag.is_some() {
+                self.pos = props_at;
+            }
+            return self.node_here(parent, compact);
+        }
+        let value = match self.finish_line()? {
+            Some(n)
+                if !self.at_document_boundary()
+                    && (n as isize > parent
+                        || (seq_ok && n as isize == parent && self.at_dash_entry())) =>
+            {
+                self.node_here(parent, true)?
+            }
+            _ => self.empty(&props)?,
+        };
+        self.apply(props, value)
+    }
+
+    /// A node whose first character is at the current position.
+    /// `parent` is the column of the enclosing collection; `compact`
+    /// allows a block collection to start here, mid-line.
+    fn node_here(&mut self, parent: isize, compact: bool) -> Result<Value, RuntimeError> {
+        let props = self.properties()?;
+        if (props.anchor.is_some() || props.tag.is_some()) && self.at_line_end() {
+            // Properties alone on a line: the node follows below.
+            let value = match self.finish_line()? {
+                Some(n) if !self.at_document_boundary() && n as isize > parent => {
+                    self.node_here(parent, true)?
+                }
+                Some(n) if !self.at_document_boundary() && n as isize == parent && self.at_dash_entry() => {
+                    self.node_here(parent, true)?
+                }
+                _ => self.empty(&props)?,
+            };
+            return self.apply(props, value);
+        }
+        let col = self.col();
+        let value = match self.peek() {
+            Some(b'|') | Some(b'>') => {
+                let s = self.block_scalar(parent)?;
+                self.scalar_value(s, &props)?
+            }
+            Some(b'-') if compact && self.at_dash_entry() => {
+                let v = self.block_sequence(col)?;
+                self.check_collection(&v, &props)?;
+                v
+            }
+            Some(b'?') if is_blank(self.peek_at(1)) => {
+                return Err(self.err("complex mapping keys (`?`) are not supported"));
+            }
+            _ if compact && self.mapping_key_ahead() => {
+                let v = self.block_mapping(col)?;
+                self.check_collection(&v, &props)?;
+                v
+            }
+            Some(b'*') => {
+                if props.anchor.is_some() || props.tag.is_some() {
+                    return Err(self.err("an alias cannot have an anchor or tag"));
+                }
+                let v = self.alias()?;
+                self.finish_line()?;
+                return Ok(v);
+            }
+            Some(b'[') | Some(b'{') => {
+                let v = self.flow_collection()?;
+                self.check_collection(&v, &props)?;
+                self.finish_line()?;
+                v
+            }
+            Some(b'"') | Some(b'\'') => {
+                let s = self.quoted()?;
+                let v = self.scalar_value(s, &props)?;
+                self.finish_line()?;
+                v
+            }
+            _ => {
+                let s = self.plain_block(parent)?;
+                let v = self.scalar_value(s, &props)?;
+                self.finish_line()?;
+                v
+            }
+        };
+        self.apply(props, value)
+    }
+
+    fn block_sequence(&mut self, n: usize) -> Result<Value, RuntimeError> {
+        let mut items = Vec::new();
+        loop {
+            self.pos += 1; // the `-`
+            items.push(self.block_value(n as isize, false, true)?);
+            if self.at_document_boundary() {
+                break;
+            }
+            let col = self.col();
+            if col == n && self.at_dash_entry() {
+                continue;
+            }
+            if col > n {
+                return Err(self.err("bad indentation of a sequence entry"));
+            }
+            break;
+        }
+        Ok(Value::Array(gc::alloc_array(items)))
+    }
+
+    fn block_mapping(&mut self, n: usize) -> Result<Value, RuntimeError> {
+        let mut map: IndexMap<Arc<str>, Value> = IndexMap::new();
+        let mut merges = Vec::new();
+        loop {
+            if self.peek() == Some(b'?') && is_blank(self.peek_at(1)) {
+                return Err(self.err("complex mapping keys (`?`) are not supported"));
+            }
+            let key_at = self.pos;
+            let (key, plain) = self.mapping_key()?;
+            let value = self.block_value(n as isize, true, false)?;
+            if plain && key == "<<" {
+                merges.push((value, key_at));
+            } else if map.contains_key(key.as_str()) {
+                return Err(self.err_at(key_at, &format!("duplicate key `{key}`")));
+            } else {
+                map.insert(Arc::from(key), value);
+            }
+            if self.at_document_boundary() {
+                break;
+            }
+            let col = self.col();
+            if col == n {
+                continue;
+            }
+            if col > n {
+                return Err(self.err("bad indentation of a mapping entry"));
+            }
+            break;
+        }
+        self.merge(&mut map, merges)?;
+        Ok(Value::Object(gc::alloc_object(map)))
+    }
+
+    /// Apply `<<` merge keys: each merged mapping contributes the keys
+    /// the mapping does not set itself, earlier merges winning.
+    fn merge(
+        &self,
+        map: &mut IndexMap<Arc<str>, Value>,
+        merges: Vec<(Value, usize)>,
+    ) -> Result<(), RuntimeError> {
+        for (value, at) in merges {
+            let sources = match &value {
+                Value::Object(_) => vec![value.clone()],
+                Value::Array(a) => a.borrow().clone(),
+                _ => Vec::new(),
+            };
+            if sources.is_empty() && !matches!(value, Value::Array(_)) {
+                return Err(self.err_at(at, "a `<<` merge needs a mapping or a list of mappings"));
+            }
+            for src in sources {
+                let Value::Object(o) = src else {
+                    return Err(self.err_at(at, "a `<<` merge needs a mapping or a list of mappings"));
+                };
+                for (k, v) in o.borrow().iter() {
+                    if !map.contains_key(k) {
+                        map.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether this line holds `key:` — a quoted or plain key followed
+    /// by a `:` and whitespace — starting here.
+    fn mapping_key_ahead(&self) -> bool {
+        let line_end = self.bytes[self.pos..]
+            .iter()
+            .position(|&c| c == b'\n' || c == b'\r')
+            .map_or(self.bytes.len(), |p| self.pos + p);
+        let line = &self.bytes[self.pos..line_end];
+        let colon_at = |i: usize| line.get(i) == Some(&b':') && is_blank(line.get(i + 1).copied());
+        match line.first() {
+            Some(&q @ (b'"' | b'\'')) => {
+                let mut i = 1;
+                while i < line.len() {
+                    if q == b'"' && line[i] == b'\\' {
+                        i += 2;
+                        continue;
+                    }
+                    if line[i] == q {
+                        if q == b'\'' && line.get(i + 1) == Some(&b'\'') {
+                            i += 2;
+                            continue;
+                        }
+                        let mut j = i + 1;
+                        while matches!(line.get(j), Some(b' ') | Some(b'\t')) {
+                            j += 1;
+                        }
+                        return colon_at(j);
+                    }
+                    i += 1;
+                }
+                false
+            }
+            Some(b'[' | b'{' | b'#' | b'|' | b'>' | b'*' | b'&' | b'!') | None => false,
+            Some(b'-') if is_blank(line.get(1).copied()) => false,
+            _ => {
+                for i in 0..line.len() {
+                    if colon_at(i) {
+                        return true;
+                    }
+                    if line[i] == b'#' && i > 0 && matches!(line[i - 1], b' ' | b'\t') {
+                        return false;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// A block mapping key and its `:`. Returns the key text and whether
+    /// it was plain (only a plain `<<` is a merge).
+    fn mapping_key(&mut self) -> Result<(String, bool), RuntimeError> {
+        if !self.mapping_key_ahead() {
+            return Err(self.err("expected a mapping key (`key: value`)"));
+        }
+        if matches!(self.peek(), Some(b'"') | Some(b'\'')) {
+            let s = self.quoted()?;
+            self.skip_inline_space();
+            self.pos += 1; // the `:`
+            return Ok((s.text, false));
+        }
+        let start = self.pos;
+        while !(self.peek() == Some(b':') && is_blank(self.peek_at(1))) {
+            self.pos += 1;
+        }
+        let key = self.src[start..self.pos].trim_end().to_string();
+        self.pos += 1;
+        Ok((key, true))
+    }
+
+    // -- node properties, aliases -------------------------------------
+
+    fn name(&mut self) -> String {
+        let start = self.pos;
+        while !is_blank(self.peek()) && !matches!(self.peek(), Some(b) if is_flow_indicator(b)) {
+            self.pos += self.peek_char().map_or(1, char::len_utf8);
+        }
+        self.src[start..self.pos].to_string()
+    }
+
+    /// Any `&anchor` and `!!tag` before a node, in either order.
+    fn properties(&mut self) -> Result<Props, RuntimeError> {
+        let mut props = Props::default();
+        loop {
+            match self.peek() {
+                Some(b'&') if props.anchor.is_none() => {
+                    let at = self.pos;
+                    self.pos += 1;
+                    let name = self.name();
+                    if name.is_empty() {
+                        return Err(self.err_at(at, "expected an anchor name after `&`"));
+                    }
+                    props.anchor = Some(name);
+                }
+                Some(b'!') if props.tag.is_none() => {
+                    let at = self.pos;
+                    let tag = self.name();
+                    if !matches!(tag.as_str(), "!!str" | "!!int" | "!!float" | "!!bool" | "!!null" | "!!seq" | "!!map") {
+                        return Err(self.err_at(at, &format!("tag `{tag}` is not supported")));
+                    }
+                    props.tag = Some((tag, at));
+                }
+                _ => return Ok(props),
+            }
+            self.skip_inline_space();
+        }
+    }
+
+    /// Record the anchor (if any) for `value`.
+    fn apply(&mut self, props: Props, value: Value) -> Result<Value, RuntimeError> {
+        if let Some(name) = props.anchor {
+            self.anchors.insert(name, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// The value of a node with nothing in it: `null`, or `''` when
+    /// tagged `!!str`.
+    fn empty(&self, props: &Props) -> Result<Value, RuntimeError> {
+        self.scalar_value(Scalar { text: String::new(), plain: true }, props)
+    }
+
+    fn alias(&mut self) -> Result<Value, RuntimeError> {
+        let at = self.pos;
+        self.pos += 1;
+        let name = self.name();
+        self.anchors
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| self.err_at(at, &format!("unknown alias `*{name}`")))
+    }
+
+    /// Resolve a scalar, honoring a core-schema tag.
+    fn scalar_value(&self, s: Scalar, props: &Props) -> Result<Value, RuntimeError> {
+        let Some((tag, at)) = &props.tag else {
+            return Ok(if s.plain { resolve_plain(&s.text) } else { Value::Str(s.text.into()) });
+        };
+        let resolved = resolve_plain(&s.text);
+        let value = match (tag.as_str(), resolved) {
+            ("!!str", _) => Some(Value::Str(s.text.into())),
+            ("!!int", v @ Value::Int(_)) => Some(v),
+            ("!!float", Value::Int(n)) => Some(Value::Float(n as f64)),
+            ("!!float", v @ Value::Float(_)) => Some(v),
+            ("!!bool", v @ Value::Bool(_)) => Some(v),
+            ("!!null", v @ Value::Null) => Some(v),
+            _ => None,
+        };
+        value.ok_or_else(|| self.err_at(*at, &format!("value does not match tag `{tag}`")))
+    }
+
+    fn check_collection(&self, v: &Value, props: &Props) -> Result<(), RuntimeError> {
+        match (&props.tag, v) {
+            (None, _) => Ok(()),
+            (Some((t, _)), Value::Array(_)) if t == "!!seq" => Ok(()),
+            (Some((t, _)), Value::Object(_)) if t == "!!map" => Ok(()),
+            (Some((t, at)), _) => Err(self.err_at(*at, &format!("value does not match tag `{t}`"))),
+        }
+    }
+
+    // -- scalars ------------------------------------------------------
+
+    /// A plain scalar in block context, folded over continuation lines
+    /// indented deeper than `parent`.
+    fn plain_block(&mut self, parent: isize) -> Result<Scalar, RuntimeError> {
+        if let Some(c @ ('%' | '@' | '`')) = self.peek_char() {
+            return Err(self.err(&format!("a plain scalar cannot start with '{c}'")));
+        }
+        let mut text = self.plain_line()?;
+        loop {
+            let line_end = self.pos;
+            self.skip_inline_space();
+            if !matches!(self.peek(), Some(b'\n') | Some(b'\r')) {
+                self.pos = line_end;
+                break;
+            }
+            // Look past blank lines for a deeper-indented continuation.
+            let mut breaks = 0;
+            let mut indent;
+            loop {
+                self.eat_newline();
+                breaks += 1;
+                let start = self.pos;
+                self.skip_inline_space();
+                indent = self.pos - start;
+                if !matches!(self.peek(), Some(b'\n') | Some(b'\r')) {
+                    break;
+                }
+            }
+            if self.peek().is_none()
+                || self.peek() == Some(b'#')
+                || indent as isize <= parent
+                || (indent == 0 && (self.at_marker("---") || self.at_marker("...")))
+            {
+                self.pos = line_end;
+                break;
+            }
+            if breaks == 1 {
+                text.push(' ');
+            } else {
+                text.push_str(&"\n".repeat(breaks - 1));
+            }
+            text.push_str(&self.plain_line()?);
+        }
+        Ok(Scalar { text, plain: true })
+    }
+
+    /// One line of a block plain scalar, up to a comment or the end of
+    /// the line, trailing whitespace trimmed.
+    fn plain_line(&mut self) -> Result<String, RuntimeError> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some(b'\n') | Some(b'\r') => break,
+                Some(b':') if is_blank(self.peek_at(1)) => {
+                    return Err(self.err("mapping values are not allowed here"));
+                }
+                Some(b'#') if self.pos > start && matches!(self.bytes[self.pos - 1], b' ' | b'\t') => break,
+                Some(_) => self.pos += self.peek_char().map_or(1, char::len_utf8),
+            }
+        }
+        let line = self.src[start..self.pos].trim_end_matches([' ', '\t']);
+        self.pos = start + line.len();
+        Ok(line.to_string())
+    }
+
+    /// A single- or double-quoted scalar, which may span lines.
+    fn quoted(&mut self) -> Result<Scalar, RuntimeError> {
+        let open = self.pos;
+        let q = self.peek().expect("at a quote");
+        self.pos += 1;
+        let mut out = String::new();
+        // `out` up to its last character that a line fold must keep.
+        let mut keep = 0;
+        loop {
+            let Some(c) = self.peek_char() else {
+                return Err(self.err_at(open, "unterminated quoted scalar"));
+            };
+            match c {
+                '\'' if q == b'\'' => {
+                    self.pos += 1;
+                    if self.eat(b'\'') {
+                        out.push('\'');
+                        keep = out.len();
+                    } else {
+                        break;
+                    }
+                }
+                '"' if q == b'"' => {
+                    self.pos += 1;
+                    break;
+                }
+                '\\' if q == b'"' => {
+                    let at = self.pos;
+                    self.pos += 1;
+                    if matches!(self.peek(), Some(b'\n') | Some(b'\r')) {
+                        // An escaped line break joins the lines as is.
+                        self.eat_newline();
+                        self.skip_inline_space();
+                        keep = out.len();
+                        continue;
+                    }
+                    out.push(self.escape(at)?);
+                    keep = out.len();
+                }
+                '\n' | '\r' => {
+                    out.truncate(keep);
+                    let mut breaks = 0;
+                    loop {
+                        self.eat_newline();
+                        breaks += 1;
+                        self.skip_inline_space();
+                        if !matches!(self.peek(), Some(b'\n') | Some(b'\r')) {
+                            break;
+                        }
+                    }
+                    if self.at_marker("---") || self.at_marker("...") {
+                        return Err(self.err_at(open, "unterminated quoted scalar"));
+                    }
+                    if breaks == 1 {
+                        out.push(' ');
+                    } else {
+                        out.push_str(&"\n".repeat(breaks - 1));
+                    }
+                    keep = out.len();
+                }
+                c => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                    if c != ' ' && c != '\t' {
+                        keep = out.len();
+                    }
+                }
+            }
+        }
+        Ok(Scalar { text: out, plain: false })
+    }
+
+    /// The character a double-quoted `\` escape at `at` stands for.
+    fn escape(&mut self, at: usize) -> Result<char, RuntimeError> {
+        let Some(c) = self.peek_char() else {
+            return Err(self.err_at(at, "unterminated escape sequence"));
+        };
+        self.pos += c.len_utf8();
+        let hex = |p: &mut Self, n: usize| -> Result<char, RuntimeError> {
+            let digits = p.src.get(p.pos..p.pos + n).filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+            let code = digits
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .ok_or_else(|| p.err_at(at, &format!("expected {n} hex digits in escape")))?;
+            p.pos += n;
+            char::from_u32(code).ok_or_else(|| p.err_at(at, "escape is not a valid character"))
+        };
+        Ok(match c {
+            '0' => '\0',
+            'a' => '\u{07}',
+            'b' => '\u{08}',
+            't' | '\t' => '\t',
+            'n' => '\n',
+            'v' => '\u{0B}',
+            'f' => '\u{0C}',
+            'r' => '\r',
+            'e' => '\u{1B}',
+            ' ' => ' ',
+            '"' => '"',
+            '/' => '/',
+            '\\' => '\\',
+            'N' => '\u{85}',
+            '_' => '\u{A0}',
+            'L' => '\u{2028}',
+            'P' => '\u{2029}',
+            'x' => hex(self, 2)?,
+            'u' => hex(self, 4)?,
+            'U' => hex(self, 8)?,
+            c => return Err(self.err_at(at, &format!("invalid escape sequence \\{c}"))),
+        })
+    }
+
+    /// A `|` literal or `>` folded block scalar belonging to a node at
+    /// column `parent`.
+    fn block_scalar(&mut self, parent: isize) -> Result<Scalar, RuntimeError> {
+        let folded = self.peek() == Some(b'>');
+        self.pos += 1;
+        let mut chomp = None;
+        let mut explicit = None;
+        loop {
+            match self.peek() {
+                Some(c @ (b'+' | b'-')) if chomp.is_none() => chomp = Some(c),
+                Some(c @ b'1'..=b'9') if explicit.is_none() => explicit = Some((c - b'0') as usize),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        if !self.at_line_end() {
+            return Err(self.err("expected a line break after a block scalar header"));
+        }
+        self.skip_comment();
+        if !self.eat_newline() {
+            // A header at the end of input: an empty scalar.
+            return Ok(Scalar { text: String::new(), plain: false });
+        }
+        let base = parent.max(0) as usize;
+        let indent = match explicit {
+            Some(d) => base + d,
+            None => self.detect_indent(parent)?,
+        };
+        // Collect content lines (indentation removed); `None` is blank.
+        let mut lines: Vec<Option<&str>> = Vec::new();
+        loop {
+            let start = self.pos;
+            let end = self.bytes[start..]
+                .iter()
+                .position(|&c| c == b'\n' || c == b'\r')
+                .map_or(self.bytes.len(), |p| start + p);
+            let line = &self.src[start..end];
+            let spaces = line.bytes().take_while(|&b| b == b' ').count();
+            if start == self.bytes.len() {
+                break;
+            }
+            if spaces >= indent && indent > 0 || (indent == 0 && !line.trim().is_empty()) {
+                if indent == 0 && (self.at_marker("---") || self.at_marker("...")) {
+                    break;
+                }
+                lines.push(Some(&line[indent..]));
+            } else if line.bytes().all(|b| b == b' ') {
+                lines.push(None);
+            } else {
+                break;
+            }
+            self.pos = end;
+            if !self.eat_newline() {
+                break;
+            }
+        }
+        let trailing = lines.iter().rev().take_while(|l| l.is_none()).count();
+        let content = &lines[..lines.len() - trailing];
+        let mut text = String::new();
+        let mut prev_more = false;
+        let mut blanks = 0;
+        for (i, line) in content.iter().enumerate() {
+            let Some(line) = line else {
+                blanks += 1;
+                continue;
+            };
+            let more = line.starts_with([' ', '\t']);
+            if i > 0 {
+                // Folding joins lines with a space, except around
+                // more-indented lines, which keep their line breaks.
+                if !folded || more || prev_more {
+                    text.push_str(&"\n".repeat(blanks + 1));
+                } else if blanks == 0 {
+                    text.push(' ');
+                } else {
+                    text.push_str(&"\n".repeat(blanks));
+                }
+            } else {
+                text.push_str(&"\n".repeat(blanks));
+            }
+            text.push_str(line);
+            blanks = 0;
+            prev_more = more;
+        }
+        match chomp {
+            Some(b'-') => {}
+            Some(_) => {
+                if !content.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&"\n".repeat(trailing));
+            }
+            None => {
+                if !content.is_empty() {
+                    text.push('\n');
+                }
+            }
+        }
+        self.next_content_line()?;
+        Ok(Scalar { text, plain: false })
+    }
+
+    /// The indentation of a block scalar's first non-blank line.
+    fn detect_indent(&self, parent: isize) -> Result<usize, RuntimeError> {
+        let mut at = self.pos;
+        let mut widest_blank = 0;
+        loop {
+            let spaces = self.bytes[at..].iter().take_while(|&&b| b == b' ').count();
+            match self.bytes.get(at + spaces) {
+                Some(b'\n') | Some(b'\r') => {
+                    widest_blank = widest_blank.max(spaces);
+                    at += spaces + 1;
+                    if self.bytes[at - 1] == b'\r' && self.bytes.get(at) == Some(&b'\n') {
+                        at += 1;
+                    }
+                }
+                None => return Ok((parent + 1).max(0) as usize),
+                Some(_) => {
+                    if (spaces as isize) <= parent {
+                        // No content: every line is blank.
+                        return Ok((parent + 1).max(0) as usize);
+                    }
+                    if widest_blank > spaces {
+                        return Err(self.err_at(at, "a leading blank line is indented more than the block scalar's content"));
+                    }
+                    return Ok(spaces);
+                }
+            }
+        }
+    }
+
+    // -- flow collections ---------------------------------------------
+
+    /// Whitespace, line breaks and comments inside a flow collection.
+    fn skip_flow_space(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => self.pos += 1,
+                Some(b'#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn flow_collection(&mut self) -> Result<Value, RuntimeError> {
+        let open = self.pos;
+        let seq = self.peek() == Some(b'[');
+        let close = if seq { b']' } else { b'}' };
+        self.pos += 1;
+        let mut items = Vec::new();
+        let mut map: IndexMap<Arc<str>, Value> = IndexMap::new();
+        let mut merges = Vec::new();
+        loop {
+            self.skip_flow_space();
+            if self.peek().is_none() {
+                let what = if seq { "flow sequence" } else { "flow mapping" };
+                return Err(self.err_at(open, &format!("unterminated {what}")));
+            }
+            if self.eat(close) {
+                break;
+            }
+            let item_at = self.pos;
+            let (first, key) = self.flow_node()?;
+            self.skip_flow_space();
+            // `key: value` — an entry of a mapping, or a single-pair
+            // mapping inside a sequence. A mapping entry may omit `: value`.
+            let value = if self.eat(b':') {
+                self.skip_flow_space();
+                let value = if self.peek() == Some(b',') || self.peek() == Some(close) {
+                    Value::Null
+                } else {
+                    self.flow_node()?.0
+                };
+                self.skip_flow_space();
+                Some(value)
+            } else if seq {
+                None
+            } else {
+                Some(Value::Null)
+            };
+            match value {
+                None => items.push(first),
+                Some(value) => {
+                    let Some(key) = key else {
+                        return Err(self.err_at(item_at, "a mapping key must be a scalar"));
+                    };
+                    if seq {
+                        let mut single = IndexMap::new();
+                        single.insert(Arc::from(key), value);
+                        items.push(Value::Object(gc::alloc_object(single)));
+                    } else if key == "<<" {
+                        merges.push((value, item_at));
+                    } else if map.contains_key(key.as_str()) {
+                        return Err(self.err_at(item_at, &format!("duplicate key `{key}`")));
+                    } else {
+                        map.insert(Arc::from(key), value);
+                    }
+                }
+            }
+            if self.eat(close) {
+                break;
+            }
+            if !self.eat(b',') {
+                let what = if seq { "`,` or `]`" } else { "`,` or `}`" };
+                return Err(match self.peek_char() {
+                    Some(c) => self.err(&format!("expected {what}, got '{c}'")),
+                    None => self.err_at(open, &format!("unterminated flow {}", if seq { "sequence" } else { "mapping" })),
+                });
+            }
+        }
+        if seq {
+            Ok(Value::Array(gc::alloc_array(items)))
+        } else {
+            self.merge(&mut map, merges)?;
+            Ok(Value::Object(gc::alloc_object(map)))
+        }
+    }
+
+    /// A node inside a flow collection, with its key text when it is a
+    /// scalar (a scalar may turn out to be a mapping key).
+    fn flow_node(&mut self) -> Result<(Value, Option<String>), RuntimeError> {
+        let props = self.properties()?;
+        self.skip_flow_space();
+        let (value, key) = match self.peek() {
+            Some(b'[') | Some(b'{') => {
+                let v = self.flow_collection()?;
+                self.check_collection(&v, &props)?;
+                (v, None)
+            }
+            Some(b'*') => {
+                let v = self.alias()?;
+                return Ok((v, None));
+            }
+            Some(b'"') | Some(b'\'') => {
+                let s = self.quoted()?;
+                let key = s.text.clone();
+                (self.scalar_value(s, &props)?, Some(key))
+            }
+            _ => {
+                let s = self.plain_flow()?;
+                let key = s.text.clone();
+                (self.scalar_value(s, &props)?, Some(key))
+            }
+        };
+        let value = self.apply(props, value)?;
+        Ok((value, key))
+    }
+
+    /// A plain scalar inside a flow collection: it ends at a flow
+    /// indicator or a `: `, and may fold over lines.
+    fn plain_flow(&mut self) -> Result<Scalar, RuntimeError> {
+        let start = self.pos;
+        let mut out = String::new();
+        let mut space = String::new();
+        let mut breaks = 0;
+        let mut after_space = false;
+        while let Some(c) = self.peek_char() {
+            match c {
+                ',' | '[' | ']' | '{' | '}' => break,
+                ':' if is_blank(self.peek_at(1)) || matches!(self.peek_at(1), Some(b) if is_flow_indicator(b)) => break,
+                '#' if after_space => break,
+                ' ' | '\t' => {
+                    space.push(c);
+                    self.pos += 1;
+                    after_space = true;
+                    continue;
+                }
+                '\n' | '\r' => {
+                    self.eat_newline();
+                    breaks += 1;
+                    space.clear();
+                    after_space = true;
+                    continue;
+                }
+                c => {
+                    if !out.is_empty() {
+                        match breaks {
+                            0 => out.push_str(&space),
+                            1 => out.push(' '),
+                            n => out.push_str(&"\n".repeat(n - 1)),
+                        }
+                    }
+                    breaks = 0;
+                    space.clear();
+                    after_space = false;
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+        if out.is_empty() {
+            self.pos = start;
+            return Err(match self.peek_char() {
+                Some(c) => self.err(&format!("unexpected '{c}'")),
+                None => self.err("unexpected end of input"),
+            });
+        }
+        Ok(Scalar { text: out, plain: true })
+    }
+}
+
+/// A plain scalar under the YAML 1.2 core schema.
+fn resolve_plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => return Value::Float(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => return Value::Float(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Value::Float(f64::NAN),
+        _ => {}
+    }
+    for (prefix, radix) in [("0o", 8), ("0x", 16)] {
+        if let Some(digits) = text.strip_prefix(prefix) {
+            if !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix)) {
+                if let Ok(n) = i64::from_str_radix(digits, radix) {
+                    return Value::Int(n);
+                }
+            }
+            return Value::Str(text.into());
+        }
+    }
+    let body = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if digits(body) {
+        return text
+            .parse::<i64>()
+            .map(Value::Int)
+            .unwrap_or_else(|_| Value::Float(text.parse().unwrap_or(f64::NAN)));
+    }
+    let (mantissa, exponent) = match body.find(['e', 'E']) {
+        Some(i) => (&body[..i], Some(&body[i + 1..])),
+        None => (body, None),
+    };
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((int, frac)) => (int.is_empty() || digits(int)) && (frac.is_empty() || digits(frac)) && !(int.is_empty() && frac.is_empty()),
+        None => digits(mantissa),
+    };
+    let exponent_ok = exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+    if mantissa_ok && exponent_ok && (mantissa.contains('.') || exponent.is_some()) {
+        if let Ok(x) = text.parse::<f64>() {
+            return Value::Float(x);
+        }
+    }
+    Value::Str(text.into())
+}
+
+// ---------------- stringify ----------------
+
+fn stringify(args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut w = Writer { out: String::new(), seen_a: Vec::new(), seen_o: Vec::new() };
+    match &args[0] {
+        Value::Object(o) if !o.borrow().is_empty() => w.entries(*o, 0, false)?,
+        Value::Array(a) if !a.borrow().is_empty() => w.items(*a, 0, false)?,
+        v => {
+            // A bare scalar document: drop the leading space `leaf` adds.
+            w.leaf(v, 2)?;
+            w.out.remove(0);
+        }
+    }
+    Ok(Value::Str(w.out.into()))
+}
+
+/// Raise the catchable `cycle` error, as `JSON.stringify` does.
+fn cycle_err() -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Cycle, 0)
+}
+
+struct Writer {
+    out: String,
+    // Ancestor paths for cycle detection, as in `JSON.stringify`.
+    seen_a: Vec<GcRef<ArrayKind>>,
+    seen_o: Vec<GcRef<ObjectKind>>,
+}
+
+/// A non-empty collection, which is written in block style.
+enum Block {
+    Map(GcRef<ObjectKind>),
+    Seq(GcRef<ArrayKind>),
+}
+
+fn block_of(v: &Value) -> Option<Block> {
+    match v {
+        Value::Object(o) if !o.borrow().is_empty() => Some(Block::Map(*o)),
+        Value::Array(a) if !a.borrow().is_empty() => Some(Block::Seq(*a)),
+        _ => None,
+    }
+}
+
+impl Writer {
+    fn indent(&mut self, n: usize) {
+        self.out.extend(std::iter::repeat_n(' ', n));
+    }
+
+    /// A mapping's entries at column `indent`; with `first_inline`, the
+    /// first entry continues the current line (after `- `).
+    fn entries(&mut self, o: GcRef<ObjectKind>, indent: usize, first_inline: bool) -> Result<(), RuntimeError> {
+        if self.seen_o.contains(&o) {
+            return Err(cycle_err());
+        }
+        self.seen_o.push(o);
+        let entries: Vec<(Arc<str>, Value)> =
+            o.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        for (i, (k, v)) in entries.iter().enumerate() {
+            if i > 0 || !first_inline {
+                self.indent(indent);
+            }
+            write_scalar_str(&mut self.out, k);
+            self.out.push(':');
+            match block_of(v) {
+                Some(Block::Map(m)) => {
+                    self.out.push('\n');
+                    self.entries(m, indent + 2, false)?;
+                }
+                Some(Block::Seq(a)) => {
+                    self.out.push('\n');
+                    self.items(a, indent + 2, false)?;
+                }
+                None => self.leaf(v, indent + 2)?,
+            }
+        }
+        self.seen_o.pop();
+        Ok(())
+    }
+
+    /// A sequence's items at column `indent`, as `- item` lines.
+    fn items(&mut self, a: GcRef<ArrayKind>, indent: usize, first_inline: bool) -> Result<(), RuntimeError> {
+        if self.seen_a.contains(&a) {
+            return Err(cycle_err());
+        }
+        self.seen_a.push(a);
+        let items = a.borrow().clone();
+        for (i, v) in items.iter().enumerate() {
+            if i > 0 || !first_inline {
+                self.indent(indent);
+            }
+            self.out.push('-');
+            match block_of(v) {
+                Some(Block::Map(m)) => {
+                    self.out.push(' ');
+                    self.entries(m, indent + 2, true)?;
+                }
+                Some(Block::Seq(s)) => {
+                    self.out.push(' ');
+                    self.items(s, indent + 2, true)?;
+                }
+                None => self.leaf(v, indent + 2)?,
+            }
+        }
+        self.seen_a.pop();
+        Ok(())
+    }
+
+    /// A scalar or empty collection after `key:` or `-`, through the
+    /// end of its line. A multi-line String becomes a `|` block scalar
+    /// whose lines sit at column `indent`.
+    fn leaf(&mut self, v: &Value, indent: usize) -> Result<(), RuntimeError> {
+        match v {
+            Value::Null => self.out.push_str(" null"),
+            Value::Bool(b) => self.out.push_str(if *b { " true" } else { " false" }),
+            Value::Int(n) => {
+                self.out.push(' ');
+                self.out.push_str(&n.to_string());
+            }
+            Value::Float(x) => {
+                self.out.push(' ');
+                write_float(&mut self.out, *x);
+            }
+            Value::Str(s) if literal_ok(s) => {
+                self.out.push_str(if s.ends_with('\n') { " |" } else { " |-" });
+                self.out.push('\n');
+                for line in s.strip_suffix('\n').unwrap_or(s).split('\n') {
+                    if !line.is_empty() {
+                        self.indent(indent);
+                        self.out.push_str(line);
+                    }
+                    self.out.push('\n');
+                }
+                return Ok(());
+            }
+            Value::Str(s) => {
+                self.out.push(' ');
+                write_scalar_str(&mut self.out, s);
+            }
+            Value::Array(_) => self.out.push_str(" []"),
+            Value::Object(_) => self.out.push_str(" {}"),
+            other => {
+                return Err(raise(format!(
+                    "Yaml.stringify: cannot serialize {}",
+                    other.type_name()
+                )))
+            }
+        }
+        self.out.push('\n');
+        Ok(())
+    }
+}
+
+/// Whether a String reads back exactly from a `|` / `|-` block scalar.
+fn literal_ok(s: &str) -> bool {
+    let body = s.strip_suffix('\n').unwrap_or(s);
+    body.contains('\n')
+        && !body.ends_with('\n')
+        && !body.starts_with([' ', '\t'])
+        && body.chars().all(|c| c == '\n' || c == '\t' || !c.is_control())
+        && body.split('\n').all(|line| line.is_empty() || !line.trim().is_empty())
+        && !body.split('\n').any(|line| line.ends_with([' ', '\t']) && line.trim().is_empty())
+}
+
+/// A String as a plain scalar when it reads back as the same String,
+/// otherwise double-quoted.
+fn write_scalar_str(out: &mut String, s: &str) {
+    let plain = !s.is_empty()
+        && matches!(resolve_plain(s), Value::Str(_))
+        && !s.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@` \t".contains(c))
+        && !s.ends_with([' ', '\t', ':'])
+        && !s.starts_with("...")
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.chars().any(|c| c.is_control() || c == '\u{feff}');
+    if plain {
+        out.push_str(s);
+        return;
+    }
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() || c == '\u{feff}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_float(out: &mut String, x: f64) {
+    if x.is_nan() {
+        out.push_str(".nan");
+    } else if x.is_infinite() {
+        out.push_str(if x > 0.0 { ".inf" } else { "-.inf" });
+    } else {
+        let s = x.to_string();
+        out.push_str(&s);
+        // Keep integer-valued floats reading back as floats.
+        if !s.contains(['.', 'e', 'E']) {
+            out.push_str(".0");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_scalars_resolve_by_the_core_schema() {
+        assert_eq!(resolve_plain("~"), Value::Null);
+        assert_eq!(resolve_plain("True"), Value::Bool(true));
+        assert_eq!(resolve_plain("-12"), Value::Int(-12));
+        assert_eq!(resolve_plain("0x1F"), Value::Int(31));
+        assert_eq!(resolve_plain("0o17"), Value::Int(15));
+        assert_eq!(resolve_plain("1.5e3"), Value::Float(1500.0));
+        assert_eq!(resolve_plain(".5"), Value::Float(0.5));
+        assert_eq!(resolve_plain("-.inf"), Value::Float(f64::NEG_INFINITY));
+        for text in ["yes", "on", "1.2.3", "0x", "12abc", "1_000", ".", "e3", "+"] {
+            assert!(matches!(resolve_plain(text), Value::Str(_)), "{text}");
+        }
+    }
+
+    #[test]
+    fn strings_stay_plain_only_when_unambiguous() {
+        let repr = |s: &str| {
+            let mut out = String::new();
+            write_scalar_str(&mut out, s);
+            out
+        };
+        assert_eq!(repr("hello world"), "hello world");
+        assert_eq!(repr("true"), "\"true\"");
+        assert_eq!(repr("42"), "\"42\"");
+        assert_eq!(repr(""), "\"\"");
+        assert_eq!(repr("a: b"), "\"a: b\"");
+        assert_eq!(repr("- item"), "\"- item\"");
+        assert_eq!(repr("tab\there"), "\"tab\\there\"");
+    }
+
+    #[test]
+    fn multi_line_strings_use_a_literal_only_when_exact() {
+        assert!(literal_ok("a\nb"));
+        assert!(literal_ok("a\nb\n"));
+        assert!(!literal_ok("one line\n"));
+        assert!(!literal_ok("a\nb\n\n"));
+        assert!(!literal_ok(" lead\nx"));
+        assert!(!literal_ok("a\n  \nb"));
+    }
+}
@@ -16,17 +16,21 @@
 //! actor thread once the result returns.
 
 #[cfg(not(target_arch = "wasm32"))]
-use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
-#[cfg(not(target_arch = "wasm32"))]
-use std::sync::OnceLock;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
 #[cfg(not(target_arch = "wasm32"))]
 use std::thread;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::file_handle::FileHandle;
+use crate::vm::store::StoreHandle;
 use crate::vm::gc;
 use crate::vm::socket::SocketHandle;
+use crate::vm::trace;
 use crate::vm::value::Value;
 
 /// The closure a `Blocking` native hands to the worker pool. Runs on a
@@ -45,6 +49,14 @@ pub type OffloadResult = Result<OffloadOk, OffloadErr>;
 pub type DeferredDecode =
     Box<dyn FnOnce() -> Result<Value, RuntimeError> + Send>;
 
+/// The closure a `Handoff` native builds on the actor thread. It is
+/// called once with the [`Waiter`] for the parked call and must arrange
+/// for exactly one completion to be posted through it — right away if
+/// the result is already available, or later, from whichever thread
+/// makes it available (a channel `send`, an actor finishing). Unlike a
+/// [`BlockingJob`] it never occupies a worker thread while it waits.
+pub type HandoffOp = Box<dyn FnOnce(Waiter) + Send>;
+
 /// The raw, `Send` success payload of an offloaded blocking call.
 /// [`decode`] turns each variant into a `Value` on the actor thread.
 /// Grows one variant at a time as more natives are converted.
@@ -63,12 +75,18 @@ pub enum OffloadOk {
     StrList(Vec<String>),
     /// An optional string — `None` decodes to `null` (EOF / end-of-stream).
     StrOrNull(Option<String>),
-    /// A socket result (`Net.accept` / `connect` / `connect_tls`).
+    /// A socket result (`Net.accept` / `connect` / `connect_tls` /
+    /// `start_tls`).
     Socket(SocketHandle),
     /// A file handle result (`IO.open`).
     File(FileHandle),
+    /// A store handle result (`Store.open`).
+    Store(StoreHandle),
     /// `Net.recv_from` — one UDP datagram plus its sender's address.
     RecvFrom { data: Vec<u8>, host: String, port: u16 },
+    /// `Net.recv_from` on a Unix-domain datagram socket — the sender's
+    /// path, `None` when it never bound one.
+    RecvFromPath { data: Vec<u8>, path: Option<String> },
     /// `Os.run` — child-process exit code plus captured output.
     Run { code: i64, stdout: String, stderr: String },
     /// An escape hatch for blocking calls whose result needs
@@ -138,6 +156,7 @@ fn decode_ok(ok: OffloadOk) -> Value {
         },
         OffloadOk::Socket(h) => Value::Socket(h),
         OffloadOk::File(h) => Value::File(h),
+        OffloadOk::Store(h) => Value::Store(h),
         OffloadOk::RecvFrom { data, host, port } => {
             crate::vm::native_modules::object(&[
                 ("data", Value::Bytes(gc::alloc_bytes(data))),
@@ -145,6 +164,12 @@ fn decode_ok(ok: OffloadOk) -> Value {
                 ("port", Value::Int(port as i64)),
             ])
         }
+        OffloadOk::RecvFromPath { data, path } => {
+            crate::vm::native_modules::object(&[
+                ("data", Value::Bytes(gc::alloc_bytes(data))),
+                ("path", path.map_or(Value::Null, |p| Value::Str(p.into()))),
+            ])
+        }
         OffloadOk::Run { code, stdout, stderr } => {
             crate::vm::native_modules::object(&[
                 ("code", Value::Int(code)),
@@ -175,6 +200,18 @@ pub struct CompletionMailbox {
     /// Signalled whenever a completion is posted, so an actor that has
     /// nothing else to run can sleep instead of spinning.
     wake: Condvar,
+    /// Called after every post, once set. An actor on the shared actor
+    /// pool ([`crate::vm::actor_pool`]) does not sleep on `wake` — its
+    /// pool thread runs other actors meanwhile — so the pool installs a
+    /// hook here that re-queues the actor instead.
+    waker: OnceLock<Box<dyn Fn() + Send + Sync>>,
+    /// Set by [`interrupt`](Self::interrupt), under the `done` lock, and
+    /// cleared by the wait it ends.
+    interrupted: AtomicBool,
+    /// The owning actor's trace id ([`crate::vm::trace`]), so the
+    /// threads that post here can record the jobs' lifecycles; `0`
+    /// while untraced.
+    trace: AtomicU64,
 }
 
 impl CompletionMailbox {
@@ -182,9 +219,28 @@ impl CompletionMailbox {
         Arc::new(CompletionMailbox {
             done: Mutex::new(Vec::new()),
             wake: Condvar::new(),
+            waker: OnceLock::new(),
+            interrupted: AtomicBool::new(false),
+            trace: AtomicU64::new(0),
         })
     }
 
+    /// Install the hook run after each [`post`](Self::post). Set once,
+    /// before any job can complete; a second call is ignored.
+    pub(crate) fn set_waker(&self, waker: impl Fn() + Send + Sync + 'static) {
+        let _ = self.waker.set(Box::new(waker));
+    }
+
+    /// Record the jobs posted here under the trace id `actor`.
+    pub(crate) fn set_trace(&self, actor: u64) {
+        self.trace.store(actor, Ordering::Relaxed);
+    }
+
+    /// The owning actor's trace id; `0` while untraced.
+    pub(crate) fn trace_actor(&self) -> u64 {
+        self.trace.load(Ordering::Relaxed)
+    }
+
     /// Take every completion posted so far without blocking. Returns an
     /// empty vec if none are ready.
     pub fn drain(&self) -> Vec<(u64, OffloadResult)> {
@@ -193,24 +249,122 @@ impl CompletionMailbox {
 
     /// Block the calling (actor) thread until at least one completion
     /// is posted, then take every completion that is ready.
+    /// Returns early, possibly empty, after an
+    /// [`interrupt`](Self::interrupt).
     pub fn wait_drain(&self) -> Vec<(u64, OffloadResult)> {
         let mut done = self.done.lock().unwrap();
-        while done.is_empty() {
+        while done.is_empty() && !self.interrupted.swap(false, Ordering::Relaxed) {
             done = self.wake.wait(done).unwrap();
         }
         std::mem::take(&mut *done)
     }
 
+    /// [`wait_drain`](Self::wait_drain), giving up after `limit`: the
+    /// result is empty if nothing was posted in time.
+    pub fn wait_drain_for(&self, limit: std::time::Duration) -> Vec<(u64, OffloadResult)> {
+        let done = self.done.lock().unwrap();
+        let (mut done, _) = self
+            .wake
+            .wait_timeout_while(done, limit, |d| {
+                d.is_empty() && !self.interrupted.swap(false, Ordering::Relaxed)
+            })
+            .unwrap();
+        std::mem::take(&mut *done)
+    }
+
+    /// Wake the actor without posting anything, so it notices an exit
+    /// signal ([`crate::vm::task`]): ends a `wait_drain` in progress (or
+    /// the next one) and runs the waker.
+    pub(crate) fn interrupt(&self) {
+        let done = self.done.lock().unwrap();
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.wake.notify_one();
+        drop(done);
+        if let Some(waker) = self.waker.get() {
+            waker();
+        }
+    }
+
     /// Post a finished job. Called by a worker thread and by the
     /// async-IO reactor thread ([`crate::vm::reactor`]) — both are
     /// completion producers for the same actor mailbox.
     pub(crate) fn post(&self, id: u64, result: OffloadResult) {
+        trace::op_end(self.trace_actor(), id, result.is_err());
         let mut done = self.done.lock().unwrap();
         done.push((id, result));
         self.wake.notify_one();
+        drop(done);
+        if let Some(waker) = self.waker.get() {
+            waker();
+        }
+    }
+}
+
+/// The completion slot of one parked `Handoff` call: the job id its
+/// coroutine is parked under plus the mailbox to post to. `Send`, so a
+/// [`HandoffOp`] can store it wherever the result will come from — a
+/// channel's queue of parked receivers, a task's list of joiners.
+pub struct Waiter {
+    id: u64,
+    mailbox: Arc<CompletionMailbox>,
+}
+
+impl Waiter {
+    pub(crate) fn new(id: u64, mailbox: Arc<CompletionMailbox>) -> Waiter {
+        Waiter { id, mailbox }
+    }
+
+    /// Deliver the call's result, waking the parked coroutine.
+    pub fn complete(self, result: OffloadResult) {
+        self.mailbox.post(self.id, result);
     }
 }
 
+/// The wake-up of a parked `select`, shared by every channel and task
+/// it waits on: the first of them to have something fires it, posting
+/// the select's job, and the rest find it spent. The select re-checks
+/// its arms when it resumes, so the signal carries no result.
+pub struct Signal(Mutex<Option<Waiter>>);
+
+impl Signal {
+    pub(crate) fn new(waiter: Waiter) -> Arc<Signal> {
+        Arc::new(Signal(Mutex::new(Some(waiter))))
+    }
+
+    /// Wake the select, unless it already woke.
+    pub fn fire(&self) {
+        let waiter = self.0.lock().unwrap().take();
+        if let Some(w) = waiter {
+            w.complete(Ok(OffloadOk::Unit));
+        }
+    }
+
+    /// Spend the signal without waking anyone — the select woke for
+    /// another reason. A source holding it drops it at its next
+    /// registration.
+    pub(crate) fn cancel(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    pub fn is_spent(&self) -> bool {
+        self.0.lock().unwrap().is_none()
+    }
+}
+
+/// Run a `Handoff` op to completion on the calling thread — the inline
+/// path, taken when nothing else in the actor could run meanwhile. A
+/// private mailbox stands in for the actor's, and this thread sleeps on
+/// it until the op's completion arrives.
+pub fn run_handoff(op: HandoffOp) -> OffloadResult {
+    let mailbox = CompletionMailbox::new();
+    op(Waiter::new(0, mailbox.clone()));
+    mailbox
+        .wait_drain()
+        .pop()
+        .map(|(_, result)| result)
+        .expect("wait_drain returns at least one completion")
+}
+
 /// One unit of blocking work plus the mailbox to post its completion
 /// to. The `mailbox` `Arc` routes the result back to the right actor.
 #[cfg(not(target_arch = "wasm32"))]
@@ -296,6 +450,7 @@ impl Pool {
                     inner.idle -= 1;
                 }
             };
+            trace::op_step(job.mailbox.trace_actor(), job.id, "worker");
             let result = (job.work)();
             job.mailbox.post(job.id, result);
         }
@@ -314,6 +469,7 @@ fn pool() -> &'static Pool {
 /// completion arrives.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn submit(id: u64, mailbox: Arc<CompletionMailbox>, work: BlockingJob) {
+    trace::op_step(mailbox.trace_actor(), id, "queued");
     pool().submit(OffloadJob { id, mailbox, work });
 }
 
@@ -328,3 +484,96 @@ pub fn submit(id: u64, mailbox: Arc<CompletionMailbox>, work: BlockingJob) {
     let result = work();
     mailbox.post(id, result);
 }
+
+// ---- timed completions ---------------------------------------------
+
+/// A callback due at an instant, ordered earliest first.
+#[cfg(not(target_arch = "wasm32"))]
+struct Timed {
+    at: Instant,
+    seq: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PartialEq for Timed {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Eq for Timed {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PartialOrd for Timed {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Ord for Timed {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (other.at, other.seq).cmp(&(self.at, self.seq))
+    }
+}
+
+/// The process-wide timer: one thread sleeping until the earliest
+/// pending callback is due. Started by the first [`run_after`].
+#[cfg(not(target_arch = "wasm32"))]
+struct Timer {
+    pending: Mutex<(BinaryHeap<Timed>, u64)>,
+    changed: Condvar,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Timer {
+    fn run(&self) {
+        let mut g = self.pending.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            match g.0.peek().map(|t| t.at) {
+                Some(at) if at <= now => {
+                    let due = g.0.pop().unwrap();
+                    drop(g);
+                    (due.run)();
+                    g = self.pending.lock().unwrap();
+                }
+                Some(at) => g = self.changed.wait_timeout(g, at - now).unwrap().0,
+                None => g = self.changed.wait(g).unwrap(),
+            }
+        }
+    }
+}
+
+/// Run `f` on the timer thread once `delay` has passed — the timeout
+/// half of a handoff that races a completion against the clock (a
+/// `receive` with `after`). `f` must tolerate losing that race: it runs
+/// even if the completion came first.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn run_after(delay: Duration, f: Box<dyn FnOnce() + Send>) {
+    let at = Instant::now() + delay;
+    static TIMER: OnceLock<&'static Timer> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let t: &'static Timer = Box::leak(Box::new(Timer {
+            pending: Mutex::new((BinaryHeap::new(), 0)),
+            changed: Condvar::new(),
+        }));
+        thread::spawn(move || t.run());
+        t
+    });
+    let mut g = timer.pending.lock().unwrap();
+    let seq = g.1;
+    g.1 += 1;
+    g.0.push(Timed { at, seq, run: f });
+    drop(g);
+    timer.changed.notify_one();
+}
+
+/// `wasm32` has no timer thread, and no other actor could complete the
+/// race meanwhile, so the timeout fires at once.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn run_after(_delay: Duration, f: Box<dyn FnOnce() + Send>) {
+    f();
+}
@@ -644,6 +644,7 @@ impl Parser {
             Token::Raise => self.parse_raise(),
             Token::Spawn => self.parse_spawn(),
             Token::Select => self.parse_select(),
+            Token::Receive => self.parse_receive(),
             Token::Parallel => self.parse_parallel(),
             Token::Go => self.parse_go(),
             Token::Yield => self.parse_yield(),
@@ -727,31 +728,53 @@ impl Parser {
         }
     }
 
-    /// `select { name := chan => body, ..., else => body }` (v0.14).
+    /// `select { name := source => body, ..., after ms => body, else => body }`
+    /// (v0.14).
     ///
-    /// Desugars — there is no dedicated AST node — to a `match` over
-    /// the result of the internal `__select` builtin:
+    /// Waits for the first of several sources: a `Channel` or
+    /// `LocalChannel` message, a `go` handle, `Task` or `Deferred`
+    /// finishing, a stream socket turning readable, or — with `after` —
+    /// `ms` milliseconds passing. `after` is contextual, as in
+    /// [`parse_receive`](Self::parse_receive). Desugars — no dedicated
+    /// AST node — to a loop over the internal `__select` builtin, which
+    /// parks the coroutine on every source at once and returns `null`
+    /// when woken so the sources are checked again:
     ///
     /// ```text
-    /// match __select([chan0, chan1], <has_else>) {
+    /// {
+    ///   $sel_sources := [source0, source1];
+    ///   $sel_until := __select_deadline(ms);      // null: no `after`
+    ///   $sel := null;
+    ///   while $sel == null {
+    ///     $sel = __select($sel_sources, <has_else>, $sel_until);
+    ///   };
+    ///   match $sel {
     ///     ${index: 0, value: name0} => body0,
     ///     ${index: 1, value: name1} => body1,
-    ///     _ => else_body,            // only when an `else` arm exists
+    ///     _ => else_or_after_body,     // only with an `else` or `after`
+    ///   }
     /// }
     /// ```
     ///
-    /// Each arm binds a plain identifier (or `_`) to the received
-    /// message value.
+    /// Each arm binds a plain identifier (or `_`) to what its source
+    /// produced: the message, the joined result, or the socket itself.
     fn parse_select(&mut self) -> Result<SpannedExpr, ParseError> {
         let kw_span = self.expect(&Token::Select)?;
         self.expect(&Token::LBrace)?;
 
-        let mut channels: Vec<SpannedExpr> = Vec::new();
+        let mut sources: Vec<SpannedExpr> = Vec::new();
         let mut arms: Vec<MatchArm> = Vec::new();
         let mut else_body: Option<SpannedExpr> = None;
+        let mut after: Option<SpannedExpr> = None;
 
         while !self.check(&Token::RBrace) {
-            if self.matches(&Token::Else) {
+            // `else` never waits, so it cannot share a `select` with an
+            // `after`, which says how long to.
+            if self.check(&Token::Else) {
+                if after.is_some() {
+                    return Err(self.err(ParseErrorKind::UnexpectedToken(Token::Else)));
+                }
+                self.advance();
                 self.expect(&Token::FatArrow)?;
                 else_body = Some(self.parse_expr()?);
                 if !self.matches(&Token::Comma) {
@@ -759,31 +782,50 @@ impl Parser {
                 }
                 continue;
             }
-            // `name := channel => body`
+            let is_after = matches!(self.peek(), Token::Ident(n) if n == "after")
+                && !matches!(
+                    self.tokens.get(self.pos + 1).map(|t| &t.token),
+                    Some(Token::FatArrow | Token::ColonEq)
+                );
+            if is_after {
+                if else_body.is_some() {
+                    return Err(self.err(ParseErrorKind::UnexpectedToken(self.peek().clone())));
+                }
+                self.advance();
+                let ms = self.parse_expr()?;
+                self.expect(&Token::FatArrow)?;
+                after = Some(ms);
+                else_body = Some(self.parse_expr()?);
+                if !self.matches(&Token::Comma) {
+                    break;
+                }
+                continue;
+            }
+            // `name := source => body`
             let bind = self.parse_select_binding()?;
             self.expect(&Token::ColonEq)?;
-            let channel = self.parse_expr()?;
+            let source = self.parse_expr()?;
             self.expect(&Token::FatArrow)?;
             let body = self.parse_expr()?;
-            let index = channels.len() as i64;
-            // Synthetic field keys carry the channel span; they are never
+            let index = sources.len() as i64;
+            // Synthetic field keys carry the source span; they are never
             // bindings (both have a `pattern`), so the span is unused by
             // tooling but must be present.
-            let chan_span = channel.span;
-            channels.push(channel);
+            let source_span = source.span;
+            sources.push(source);
             arms.push(MatchArm {
                 pattern: MatchPattern::Object {
                     fields: vec![
                         MatchField {
                             key: "index".to_string(),
-                            key_span: chan_span,
+                            key_span: source_span,
                             pattern: Some(MatchPattern::Literal(
                                 LiteralPat::Int(index),
                             )),
                         },
                         MatchField {
                             key: "value".to_string(),
-                            key_span: chan_span,
+                            key_span: source_span,
                             pattern: Some(bind),
                         },
                     ],
@@ -799,7 +841,18 @@ impl Parser {
         let rbrace = self.expect(&Token::RBrace)?;
         let span = kw_span.join(rbrace);
 
-        let has_else = else_body.is_some();
+        let s = |e| SpannedExpr::new(e, span);
+        let ident = |n: &str| s(Expr::Ident(n.to_string()));
+        let call = |f: &str, args: Vec<SpannedExpr>| {
+            s(Expr::Call(Box::new(s(Expr::Ident(f.to_string()))), args))
+        };
+        let decl = |name: &str, init: SpannedExpr| {
+            s(Expr::Decl(Pattern::Ident(Binder::new(name, span)), Box::new(init)))
+        };
+
+        // An `else` or `after` body runs from the wildcard arm: `__select`
+        // reports either as `${index: -1}`.
+        let has_else = else_body.is_some() && after.is_none();
         if let Some(eb) = else_body {
             arms.push(MatchArm {
                 pattern: MatchPattern::Wildcard,
@@ -808,20 +861,220 @@ impl Parser {
             });
         }
 
-        // subject: __select([channels...], has_else)
-        let arr = SpannedExpr::new(Expr::Array(channels), span);
-        let callee =
-            SpannedExpr::new(Expr::Ident("__select".to_string()), span);
-        let flag = SpannedExpr::new(Expr::Bool(has_else), span);
-        let subject = SpannedExpr::new(
-            Expr::Call(Box::new(callee), vec![arr, flag]),
-            span,
-        );
+        let wait = s(Expr::While {
+            is_array: false,
+            cond: Box::new(s(Expr::BinOp(
+                BinOp::Eq,
+                Box::new(ident("$sel")),
+                Box::new(s(Expr::Null)),
+            ))),
+            body: Box::new(s(Expr::Scope(Block {
+                stmts: Vec::new(),
+                tail: Some(Box::new(s(Expr::Assign(
+                    Binder::new("$sel", span),
+                    None,
+                    Box::new(call(
+                        "__select",
+                        vec![
+                            ident("$sel_sources"),
+                            s(Expr::Bool(has_else)),
+                            ident("$sel_until"),
+                        ],
+                    )),
+                )))),
+            }))),
+        });
 
-        Ok(SpannedExpr::new(
-            Expr::Match { subject: Box::new(subject), arms },
-            span,
-        ))
+        Ok(s(Expr::Scope(Block {
+            stmts: vec![
+                decl("$sel_sources", s(Expr::Array(sources))),
+                decl(
+                    "$sel_until",
+                    call("__select_deadline", vec![after.unwrap_or_else(|| s(Expr::Null))]),
+                ),
+                decl("$sel", s(Expr::Null)),
+                wait,
+            ],
+            tail: Some(Box::new(s(Expr::Match {
+                subject: Box::new(ident("$sel")),
+                arms,
+            }))),
+        })))
+    }
+
+    /// `receive { pat => body, pat if guard => body, ..., after ms => body }`
+    /// — a selective receive from the calling actor's mailbox.
+    ///
+    /// Takes the oldest message that some arm matches, leaving the rest
+    /// queued in order; with `after`, gives up once `ms` milliseconds
+    /// pass with no match. `after` is contextual: an arm reads as the timeout when it
+    /// starts with the identifier `after` followed by anything but `=>`,
+    /// `if` or `|`. Desugars — no dedicated AST node — to a scan, then a
+    /// `match` that runs the chosen arm outside the scan loop, so a
+    /// `break` or `continue` in a body reaches the enclosing loop:
+    ///
+    /// ```text
+    /// {
+    ///   $rx_me := self();
+    ///   $rx_until := __receive_deadline(ms);       // null: no `after`
+    ///   $rx_i := 0;
+    ///   $rx_hit := null;
+    ///   while $rx_hit == null {
+    ///     $rx_hit = match __receive_at($rx_me, $rx_i, $rx_until) {
+    ///       ${timeout: true} => ${index: -1},
+    ///       ${value: pat0} if guard0 => __receive_take($rx_me, $rx_i, 0),
+    ///       ...
+    ///       _ => { $rx_i = $rx_i + 1; null },
+    ///     };
+    ///   };
+    ///   match $rx_hit {
+    ///     ${index: 0, value: pat0} => body0,
+    ///     ...
+    ///     _ => after_body,                         // or null
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// The second `match` binds the arm's names again from the taken
+    /// message; its guard already passed.
+    fn parse_receive(&mut self) -> Result<SpannedExpr, ParseError> {
+        let kw_span = self.expect(&Token::Receive)?;
+        self.expect(&Token::LBrace)?;
+
+        let mut arms: Vec<(MatchPattern, Option<SpannedExpr>, SpannedExpr)> = Vec::new();
+        let mut after: Option<(SpannedExpr, SpannedExpr)> = None;
+        while !self.check(&Token::RBrace) {
+            let is_after = matches!(self.peek(), Token::Ident(n) if n == "after")
+                && !matches!(
+                    self.tokens.get(self.pos + 1).map(|t| &t.token),
+                    Some(Token::FatArrow | Token::If | Token::Pipe)
+                );
+            if is_after {
+                self.advance();
+                let ms = self.parse_expr()?;
+                self.expect(&Token::FatArrow)?;
+                after = Some((ms, self.parse_expr()?));
+                if !self.matches(&Token::Comma) {
+                    break;
+                }
+                continue;
+            }
+            let pattern = self.parse_match_pattern()?;
+            let guard = if self.matches(&Token::If) {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.expect(&Token::FatArrow)?;
+            let body = self.parse_expr()?;
+            arms.push((pattern, guard, body));
+            if !self.matches(&Token::Comma) {
+                break;
+            }
+        }
+        let rbrace = self.expect(&Token::RBrace)?;
+        let span = kw_span.join(rbrace);
+
+        let s = |e| SpannedExpr::new(e, span);
+        let ident = |n: &str| s(Expr::Ident(n.to_string()));
+        let call = |f: &str, args: Vec<SpannedExpr>| {
+            s(Expr::Call(Box::new(s(Expr::Ident(f.to_string()))), args))
+        };
+        let field = |key: &str, pattern: MatchPattern| MatchField {
+            key: key.to_string(),
+            key_span: span,
+            pattern: Some(pattern),
+        };
+        let object_pat = |fields: Vec<MatchField>| MatchPattern::Object { fields, rest: None };
+        let decl = |name: &str, init: SpannedExpr| {
+            s(Expr::Decl(Pattern::Ident(Binder::new(name, span)), Box::new(init)))
+        };
+
+        // The scan: find the first message an arm accepts.
+        let mut scan_arms = vec![MatchArm {
+            pattern: object_pat(vec![field(
+                "timeout",
+                MatchPattern::Literal(LiteralPat::Bool(true)),
+            )]),
+            guard: None,
+            body: s(Expr::Object(vec![ObjectMember::Pair(
+                "index".to_string(),
+                s(Expr::Int(-1)),
+            )])),
+        }];
+        let mut run_arms = Vec::with_capacity(arms.len() + 1);
+        for (k, (pattern, guard, body)) in arms.into_iter().enumerate() {
+            scan_arms.push(MatchArm {
+                pattern: object_pat(vec![field("value", pattern.clone())]),
+                guard,
+                body: call(
+                    "__receive_take",
+                    vec![ident("$rx_me"), ident("$rx_i"), s(Expr::Int(k as i64))],
+                ),
+            });
+            run_arms.push(MatchArm {
+                pattern: object_pat(vec![
+                    field("index", MatchPattern::Literal(LiteralPat::Int(k as i64))),
+                    field("value", pattern),
+                ]),
+                guard: None,
+                body,
+            });
+        }
+        scan_arms.push(MatchArm {
+            pattern: MatchPattern::Wildcard,
+            guard: None,
+            body: s(Expr::Scope(Block {
+                stmts: vec![s(Expr::Assign(
+                    Binder::new("$rx_i", span),
+                    Some(BinOp::Add),
+                    Box::new(s(Expr::Int(1))),
+                ))],
+                tail: Some(Box::new(s(Expr::Null))),
+            })),
+        });
+        let (ms, after_body) = match after {
+            Some((ms, body)) => (ms, body),
+            None => (s(Expr::Null), s(Expr::Null)),
+        };
+        run_arms.push(MatchArm { pattern: MatchPattern::Wildcard, guard: None, body: after_body });
+
+        let scan = s(Expr::While {
+            is_array: false,
+            cond: Box::new(s(Expr::BinOp(
+                BinOp::Eq,
+                Box::new(ident("$rx_hit")),
+                Box::new(s(Expr::Null)),
+            ))),
+            body: Box::new(s(Expr::Scope(Block {
+                stmts: Vec::new(),
+                tail: Some(Box::new(s(Expr::Assign(
+                    Binder::new("$rx_hit", span),
+                    None,
+                    Box::new(s(Expr::Match {
+                        subject: Box::new(call(
+                            "__receive_at",
+                            vec![ident("$rx_me"), ident("$rx_i"), ident("$rx_until")],
+                        )),
+                        arms: scan_arms,
+                    })),
+                )))),
+            }))),
+        });
+
+        Ok(s(Expr::Scope(Block {
+            stmts: vec![
+                decl("$rx_me", call("self", Vec::new())),
+                decl("$rx_until", call("__receive_deadline", vec![ms])),
+                decl("$rx_i", s(Expr::Int(0))),
+                decl("$rx_hit", s(Expr::Null)),
+                scan,
+            ],
+            tail: Some(Box::new(s(Expr::Match {
+                subject: Box::new(ident("$rx_hit")),
+                arms: run_arms,
+            }))),
+        })))
     }
 
     /// Parse a `select` arm's binding — a plain identifier, or `_`.
@@ -0,0 +1,114 @@
+//! Opt-in preemption for green threads, and slow-native reporting.
+//!
+//! The scheduler is cooperative: a coroutine runs until it parks or
+//! `yield`s, so one CPU-heavy `go` body stalls every other coroutine on
+//! its actor. With a *quantum* set, the dispatch loop counts a
+//! coroutine's safe points — backward jumps (every loop iteration ends
+//! in one) and calls into tigr functions — and once it has passed a
+//! quantum of them, switches away there if another coroutine is ready.
+//! Only the safe points count, so straight-line code between them pays
+//! nothing for the bookkeeping. A pooled actor with nothing else
+//! ready steps aside for the other actors on its pool thread instead,
+//! the way a `yield` does.
+//!
+//! A forced switch is invisible to the program: the coroutine resumes
+//! exactly where it stopped, with no value delivered and no
+//! cancellation checkpoint, so `go_cancel` still fires only at parks.
+//! Generator bodies are never preempted — they run only while a
+//! `next()` pulls them and hand control back at their own `yield`s —
+//! and neither is a callback a native is running re-entrantly (an
+//! `Array.map` function, say), which has no coroutine to switch out.
+//! `wait` timers are unaffected: a preempted coroutine is simply ready
+//! again, and timers fire on the dispatch loop as before.
+//!
+//! Preemption cannot interrupt a native call, so a long one — a
+//! `JSON.parse` of a large body — still holds the actor. With a
+//! *slow-native threshold* set, every inline native call is timed, and
+//! one that runs longer is reported: to stderr by default, or to a hook
+//! installed with [`set_slow_native_hook`].
+//!
+//! Both settings are process-wide, read by each `Vm` as it is made, so
+//! spawned actors inherit them: the CLI's `--preempt N` and
+//! `--slow-native MS`, or `Session::set_preemption` and
+//! `Session::set_slow_native` for an embedder. Both are off by default,
+//! and then the dispatch loop pays only a counter decrement per loop
+//! iteration or call, and a native call is made directly, untimed.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The preemption quantum, in safe points; `0` when off.
+static QUANTUM: AtomicU32 = AtomicU32::new(0);
+
+/// The slow-native threshold, in nanoseconds; `0` when off.
+static SLOW_NATIVE_NANOS: AtomicU64 = AtomicU64::new(0);
+
+type SlowNativeHook = Arc<dyn Fn(&SlowNative) + Send + Sync>;
+
+static HOOK: RwLock<Option<SlowNativeHook>> = RwLock::new(None);
+
+/// Set the preemption quantum for VMs made from now on: a coroutine is
+/// switched out after `n` loop iterations and calls. `0` turns preemption
+/// off.
+pub fn set_quantum(n: u32) {
+    QUANTUM.store(n, Ordering::Relaxed);
+}
+
+/// The preemption quantum new VMs start with; `0` when off.
+pub fn quantum() -> u32 {
+    QUANTUM.load(Ordering::Relaxed)
+}
+
+/// Report native calls that run for longer than `threshold`, in VMs
+/// made from now on. `None` turns reporting off.
+pub fn set_slow_native(threshold: Option<Duration>) {
+    let nanos = threshold.map_or(0, |t| (t.as_nanos() as u64).max(1));
+    SLOW_NATIVE_NANOS.store(nanos, Ordering::Relaxed);
+}
+
+/// The slow-native threshold new VMs start with; `None` when off.
+pub fn slow_native() -> Option<Duration> {
+    match SLOW_NATIVE_NANOS.load(Ordering::Relaxed) {
+        0 => None,
+        n => Some(Duration::from_nanos(n)),
+    }
+}
+
+/// Send slow-native reports to `hook` instead of stderr. The hook runs
+/// on whichever thread made the call, so it must be quick and must not
+/// call back into a VM.
+pub fn set_slow_native_hook(hook: impl Fn(&SlowNative) + Send + Sync + 'static) {
+    *HOOK.write().unwrap() = Some(Arc::new(hook));
+}
+
+/// Parse a count or threshold as given on the command line: a
+/// non-negative integer, where `0` means off.
+pub fn parse_setting(s: &str) -> Option<u32> {
+    s.trim().parse().ok()
+}
+
+/// A native call that ran past the slow-native threshold.
+#[derive(Debug, Clone)]
+pub struct SlowNative {
+    /// The native's name, e.g. `parse` for `JSON.parse`.
+    pub name: &'static str,
+    /// How long the call took.
+    pub elapsed: Duration,
+    /// The source line of the call.
+    pub line: u32,
+}
+
+/// Report `call` to the installed hook, or to stderr.
+pub(crate) fn report(call: SlowNative) {
+    let hook = HOOK.read().unwrap().clone();
+    match hook {
+        Some(hook) => hook(&call),
+        None => eprintln!(
+            "tigr: slow native call: `{}` took {:.1} ms (line {})",
+            call.name,
+            call.elapsed.as_secs_f64() * 1000.0,
+            call.line
+        ),
+    }
+}
@@ -47,11 +47,13 @@
 //! cover every socket kind, TLS included — a TLS op is just a plain op
 //! whose `nb_read` / `nb_write` hand-drive `rustls` (see `socket.rs`).
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::io;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::Instant;
 
 #[cfg(unix)]
 use std::os::fd::BorrowedFd;
@@ -60,12 +62,14 @@ use std::os::windows::io::BorrowedSocket;
 
 use polling::{Event, Events, Poller};
 
+use crate::vm::loopback;
 use crate::vm::native_modules::net;
 use crate::vm::offload::{CompletionMailbox, OffloadOk, OffloadResult};
 use crate::vm::socket::{
     NetError, RawHandle, ReactorOp, SocketHandle, SocketInner, SocketOp, CHUNK,
     MAX_DIRECT_READ,
 };
+use crate::vm::trace;
 
 /// Borrow a stored raw handle as a `polling` source for `add` /
 /// `modify` / `delete`. The borrow is used transiently and never
@@ -93,6 +97,11 @@ unsafe fn borrow_source(handle: RawHandle) -> BorrowedSocket<'static> {
 /// and for a re-entrant native call. A thin wrapper over the blocking
 /// `socket.rs` methods.
 pub fn run_blocking(rop: ReactorOp) -> OffloadResult {
+    // An in-memory socket has no blocking form: park the op on the
+    // loopback network and wait here for it to finish.
+    if rop.socket.is_loopback() {
+        return loopback::run_inline(rop);
+    }
     let ReactorOp { socket, op, label } = rop;
     // A prior reactor op may have left the handle non-blocking; the
     // blocking methods below need it blocking again.
@@ -100,7 +109,7 @@ pub fn run_blocking(rop: ReactorOp) -> OffloadResult {
         return Err(net::offload_err(label, NetError::Io(e)));
     }
     match op {
-        SocketOp::ReadChunk(n) => match socket.read_chunk(n) {
+        SocketOp::ReadChunk { n, .. } => match socket.read_chunk(n) {
             Ok(data) => Ok(OffloadOk::Bytes(data)),
             Err(e) => Err(net::offload_err(label, e)),
         },
@@ -123,11 +132,11 @@ pub fn run_blocking(rop: ReactorOp) -> OffloadResult {
             }
             Ok(OffloadOk::Bytes(got))
         }
-        SocketOp::ReadLine => match socket.read_until(b'\n') {
+        SocketOp::ReadLine { max } => match socket.read_until(b'\n', max) {
             Ok(opt) => net::finish_line(opt),
             Err(e) => Err(net::offload_err(label, e)),
         },
-        SocketOp::ReadUntil(delim) => match socket.read_until(delim) {
+        SocketOp::ReadUntil { delim, max } => match socket.read_until(delim, max) {
             Ok(opt) => Ok(OffloadOk::BytesOrNull(opt)),
             Err(e) => Err(net::offload_err(label, e)),
         },
@@ -150,13 +159,25 @@ pub fn run_blocking(rop: ReactorOp) -> OffloadResult {
             Err(e) => Err(net::offload_err(label, e)),
         },
         SocketOp::RecvFrom(n) => match socket.recv_from(n) {
-            Ok((data, addr)) => Ok(OffloadOk::RecvFrom {
-                data,
-                host: addr.ip().to_string(),
-                port: addr.port(),
-            }),
+            Ok((data, addr)) => Ok(net::recv_from_ok(data, addr)),
+            Err(e) => Err(net::offload_err(label, e)),
+        },
+        SocketOp::TlsInfo => match socket.tls_info() {
+            Ok(info) => net::tls_info_ok(info),
             Err(e) => Err(net::offload_err(label, e)),
         },
+        SocketOp::Readable => {
+            if !socket.poll_readable() {
+                if let Err(e) = socket.set_nonblocking_mode(false) {
+                    return Err(net::offload_err(label, NetError::Io(e)));
+                }
+                match socket.read_chunk(CHUNK) {
+                    Ok(data) => socket.push_buffered(&data),
+                    Err(e) => return Err(net::offload_err(label, e)),
+                }
+            }
+            Ok(OffloadOk::Unit)
+        }
     }
 }
 
@@ -170,7 +191,7 @@ fn would_block(e: &io::Error) -> bool {
 }
 
 /// The result of one [`advance`] pass over an op.
-enum Advance {
+pub(crate) enum Advance {
     /// The op finished — post this result, deregister the handle.
     Done(OffloadResult),
     /// The handle would block; stay registered and wait for the next event.
@@ -179,13 +200,14 @@ enum Advance {
 
 /// Drive `op` against `socket` as far as a non-blocking syscall allows.
 /// Called once at submit time (data may already be buffered) and again
-/// on every readiness event for the op's handle.
-fn advance(op: &mut SocketOp, socket: &SocketInner, label: &'static str) -> Advance {
+/// on every readiness event for the op's handle. The loopback network
+/// ([`crate::vm::loopback`]) drives its in-memory sockets with it too.
+pub(crate) fn advance(op: &mut SocketOp, socket: &SocketInner, label: &'static str) -> Advance {
     // A concurrent `close` beats any pending readiness — without this a
     // `shutdown`-induced readable event would resolve as a clean EOF
     // rather than the catchable `closed` the spec promises.
     if socket.is_closed() {
-        return Advance::Done(Err(net::offload_err(label, NetError::Closed)));
+        return Advance::Done(closed_result(op, label));
     }
     match op {
         SocketOp::Accept => match socket.nb_accept() {
@@ -194,15 +216,18 @@ fn advance(op: &mut SocketOp, socket: &SocketInner, label: &'static str) -> Adva
             Err(e) => Advance::Done(Err(net::offload_err(label, e))),
         },
         SocketOp::RecvFrom(n) => match socket.recv_from(*n) {
-            Ok((data, addr)) => Advance::Done(Ok(OffloadOk::RecvFrom {
-                data,
-                host: addr.ip().to_string(),
-                port: addr.port(),
-            })),
+            Ok((data, addr)) => Advance::Done(Ok(net::recv_from_ok(data, addr))),
+            Err(NetError::Io(e)) if would_block(&e) => Advance::Pending,
+            Err(e) => Advance::Done(Err(net::offload_err(label, e))),
+        },
+        SocketOp::TlsInfo => match socket.tls_info() {
+            Ok(info) => Advance::Done(net::tls_info_ok(info)),
             Err(NetError::Io(e)) if would_block(&e) => Advance::Pending,
             Err(e) => Advance::Done(Err(net::offload_err(label, e))),
         },
-        SocketOp::ReadChunk(n) => {
+        SocketOp::Readable if socket.poll_readable() => Advance::Done(Ok(OffloadOk::Unit)),
+        SocketOp::Readable => Advance::Pending,
+        SocketOp::ReadChunk { n, .. } => {
             let n = *n;
             if n == 0 {
                 return Advance::Done(Ok(OffloadOk::Bytes(Vec::new())));
@@ -256,12 +281,14 @@ fn advance(op: &mut SocketOp, socket: &SocketInner, label: &'static str) -> Adva
                 }
             }
         },
-        SocketOp::ReadUntil(delim) => match read_until_step(socket, *delim, label) {
-            Step::Done(opt) => Advance::Done(Ok(OffloadOk::BytesOrNull(opt))),
-            Step::Pending => Advance::Pending,
-            Step::Err(result) => Advance::Done(Err(result)),
-        },
-        SocketOp::ReadLine => match read_until_step(socket, b'\n', label) {
+        SocketOp::ReadUntil { delim, max } => {
+            match read_until_step(socket, *delim, *max, label) {
+                Step::Done(opt) => Advance::Done(Ok(OffloadOk::BytesOrNull(opt))),
+                Step::Pending => Advance::Pending,
+                Step::Err(result) => Advance::Done(Err(result)),
+            }
+        }
+        SocketOp::ReadLine { max } => match read_until_step(socket, b'\n', *max, label) {
             Step::Done(opt) => Advance::Done(net::finish_line(opt)),
             Step::Pending => Advance::Pending,
             Step::Err(result) => Advance::Done(Err(result)),
@@ -335,10 +362,17 @@ enum Step {
     Err(crate::vm::offload::OffloadErr),
 }
 
-fn read_until_step(socket: &SocketInner, delim: u8, label: &'static str) -> Step {
+fn read_until_step(
+    socket: &SocketInner,
+    delim: u8,
+    max: Option<usize>,
+    label: &'static str,
+) -> Step {
     loop {
-        if let Some(line) = socket.take_buffered_until(delim) {
-            return Step::Done(Some(line));
+        match socket.take_buffered_until(delim, max) {
+            Ok(Some(line)) => return Step::Done(Some(line)),
+            Ok(None) => {}
+            Err(e) => return Step::Err(net::offload_err(label, e)),
         }
         let mut tmp = vec![0u8; CHUNK];
         match socket.nb_read(&mut tmp) {
@@ -374,6 +408,11 @@ enum Msg {
     },
     /// `Net.close(sock)` — fail every op on that socket with `closed`.
     Cancel { socket_id: u64 },
+    /// A `select` woke on another arm — drop its readability ops.
+    Withdraw {
+        job_id: u64,
+        mailbox: Arc<CompletionMailbox>,
+    },
 }
 
 /// An op the reactor is currently driving, keyed by its registration
@@ -389,8 +428,18 @@ struct PendingOp {
     /// The interests this op waits on, re-applied on each oneshot re-arm.
     readable: bool,
     writable: bool,
+    /// When the op fails with `timeout`, from the socket's
+    /// `Net.set_timeout` at submission; `None` waits indefinitely.
+    deadline: Option<Instant>,
 }
 
+/// Accepts submitted while another accept already holds the listener's
+/// registration, queued per handle in arrival order. A poller takes one
+/// registration per handle, and a listener shared by several actors
+/// sees one accept per actor; each parked accept is started when the
+/// one ahead of it leaves the table.
+type Parked = HashMap<RawHandle, VecDeque<(u64, Arc<CompletionMailbox>, ReactorOp)>>;
+
 /// The process-wide reactor handle. Actors talk to the reactor thread
 /// through `tx` (the registration channel) and `poller.notify()` (which
 /// pulls the thread out of `wait`).
@@ -418,7 +467,14 @@ fn reactor() -> &'static Reactor {
 /// Hand a socket op to the reactor. The completion is posted to
 /// `mailbox` tagged with `job_id`; the caller parks the running
 /// coroutine under the same id (exactly as for a worker-pool offload).
-pub fn submit(job_id: u64, mailbox: Arc<CompletionMailbox>, rop: ReactorOp) {
+/// `sim_clock` says the caller runs on simulation time, so a loopback
+/// op's timeout is virtual.
+pub fn submit(job_id: u64, mailbox: Arc<CompletionMailbox>, rop: ReactorOp, sim_clock: bool) {
+    if rop.socket.is_loopback() {
+        let done = Box::new(move |result| mailbox.post(job_id, result));
+        loopback::submit(rop, done, sim_clock);
+        return;
+    }
     let r = reactor();
     r.tx
         .lock()
@@ -439,16 +495,32 @@ pub fn cancel(socket_id: u64) {
     }
 }
 
-/// The reactor thread's event loop. Owns the [`Poller`], the op table
-/// and the key counter; never returns.
+/// Drop the ops submitted as `job_id` for `mailbox` without posting
+/// them — a `select` that woke on another arm leaves the rest of its
+/// socket arms this way, freeing each handle's registration for the
+/// next op on it. Ops on loopback sockets are left to finish: a stale
+/// post finds no waiter.
+pub fn withdraw(job_id: u64, mailbox: &Arc<CompletionMailbox>) {
+    if let Some(r) = REACTOR.get() {
+        let msg = Msg::Withdraw { job_id, mailbox: Arc::clone(mailbox) };
+        if r.tx.lock().unwrap().send(msg).is_ok() {
+            let _ = r.poller.notify();
+        }
+    }
+}
+
+/// The reactor thread's event loop. Owns the [`Poller`] and the op
+/// [`Table`]; never returns.
 fn reactor_loop(poller: Arc<Poller>, rx: Receiver<Msg>) {
     let mut events = Events::new();
-    let mut ops: HashMap<usize, PendingOp> = HashMap::new();
-    // Op keys start at 1; `notify` reports `usize::MAX`, never a key.
-    let mut next_key: usize = 1;
+    let mut table = Table::new();
     loop {
         events.clear();
-        if let Err(e) = poller.wait(&mut events, None) {
+        // Sleep until the earliest op deadline, if any op has one.
+        let timeout = table
+            .next_deadline()
+            .map(|at| at.saturating_duration_since(Instant::now()));
+        if let Err(e) = poller.wait(&mut events, timeout) {
             if e.kind() == io::ErrorKind::Interrupted {
                 continue;
             }
@@ -457,27 +529,61 @@ fn reactor_loop(poller: Arc<Poller>, rx: Receiver<Msg>) {
         // A `notify` (new submit / cancel) or any op event wakes us.
         // Drain the registration channel first, so a `Cancel` is
         // observed before a stale readiness event for the same socket.
-        drain_messages(&rx, &poller, &mut ops, &mut next_key);
+        drain_messages(&rx, &poller, &mut table);
         for event in events.iter() {
-            advance_token(event.key, &poller, &mut ops);
+            advance_token(event.key, &poller, &mut table);
+        }
+        expire(&poller, &mut table);
+    }
+}
+
+/// Everything the reactor thread tracks between events.
+struct Table {
+    /// Registered ops, by registration key.
+    ops: HashMap<usize, PendingOp>,
+    /// Accepts waiting their turn on a listener (see [`Parked`]).
+    parked: Parked,
+    /// `(deadline, key)` for every op with a deadline, earliest first.
+    /// An entry whose op has already left `ops` is skipped when popped.
+    timers: BinaryHeap<Reverse<(Instant, usize)>>,
+    /// Op keys start at 1; `notify` reports `usize::MAX`, never a key.
+    next_key: usize,
+}
+
+impl Table {
+    fn new() -> Self {
+        Table {
+            ops: HashMap::new(),
+            parked: HashMap::new(),
+            timers: BinaryHeap::new(),
+            next_key: 1,
         }
     }
+
+    /// The earliest deadline still worth waking for.
+    fn next_deadline(&mut self) -> Option<Instant> {
+        while let Some(Reverse((at, key))) = self.timers.peek().copied() {
+            if self.ops.get(&key).is_some_and(|p| p.deadline == Some(at)) {
+                return Some(at);
+            }
+            self.timers.pop();
+        }
+        None
+    }
 }
 
 /// Drain every queued registration / cancellation message.
-fn drain_messages(
-    rx: &Receiver<Msg>,
-    poller: &Poller,
-    ops: &mut HashMap<usize, PendingOp>,
-    next_key: &mut usize,
-) {
+fn drain_messages(rx: &Receiver<Msg>, poller: &Poller, table: &mut Table) {
     while let Ok(msg) = rx.try_recv() {
         match msg {
             Msg::Submit { job_id, mailbox, rop } => {
-                start_op(job_id, mailbox, rop, poller, ops, next_key);
+                start_op(job_id, mailbox, rop, poller, table);
             }
             Msg::Cancel { socket_id } => {
-                cancel_socket(socket_id, poller, ops);
+                cancel_socket(socket_id, poller, table);
+            }
+            Msg::Withdraw { job_id, mailbox } => {
+                withdraw_job(job_id, &mailbox, poller, table);
             }
         }
     }
@@ -485,15 +591,15 @@ fn drain_messages(
 
 /// Register a freshly-submitted op and drive it once — data may already
 /// be buffered or the handle already ready, in which case it completes
-/// here and is never registered.
+/// here and is never registered. Returns whether the op is still
+/// waiting — registered, or parked behind another accept.
 fn start_op(
     job_id: u64,
     mailbox: Arc<CompletionMailbox>,
     rop: ReactorOp,
     poller: &Poller,
-    ops: &mut HashMap<usize, PendingOp>,
-    next_key: &mut usize,
-) {
+    table: &mut Table,
+) -> bool {
     let ReactorOp { socket, mut op, label } = rop;
     // Read ops watch the read half, writes the write half — distinct
     // `dup`'d handles, so a concurrent read + write register
@@ -523,20 +629,21 @@ fn start_op(
                 NetError::WrongKind("reactor: not a connected stream".into()),
             )),
         );
-        return;
+        return false;
     };
     if let Err(e) = socket.set_nonblocking_mode(true) {
         mailbox.post(job_id, Err(net::offload_err(label, NetError::Io(e))));
-        return;
+        return false;
     }
     // Try once: buffered data has no readiness to wait on, and the
     // handle may already be ready.
     if let Advance::Done(result) = advance(&mut op, &socket, label) {
         mailbox.post(job_id, result);
-        return;
+        return false;
     }
-    let key = *next_key;
-    *next_key = next_key.wrapping_add(1).max(1);
+    let traced = mailbox.trace_actor();
+    let key = table.next_key;
+    table.next_key = table.next_key.wrapping_add(1).max(1);
     // SAFETY: borrowing the raw handle is sound because the `PendingOp`
     // inserted below owns `socket`, keeping the handle open until the op
     // is `delete`d (on completion, cancellation, or a re-arm failure).
@@ -545,10 +652,29 @@ fn start_op(
         poller.add(&source, Event::new(key, readable, writable))
     };
     if let Err(e) = added {
+        // Another actor's accept on a shared listener holds the handle:
+        // wait for it to leave the table rather than fail.
+        if e.kind() == io::ErrorKind::AlreadyExists && matches!(op, SocketOp::Accept) {
+            let rop = ReactorOp { socket, op, label };
+            table.parked.entry(handle).or_default().push_back((job_id, mailbox, rop));
+            trace::op_step(traced, job_id, "queued behind accept");
+            return true;
+        }
         mailbox.post(job_id, Err(net::offload_err(label, NetError::Io(e))));
-        return;
+        return false;
+    }
+    // `Net.set_timeout` bounds the whole op, as it bounds each inline
+    // call; a listener has no timeout, so an accept never expires, and
+    // a `select` arm waits as long as its `select` does.
+    let deadline = match op {
+        SocketOp::Readable => None,
+        _ => socket.timeout().map(|t| Instant::now() + t),
+    };
+    if let Some(at) = deadline {
+        table.timers.push(Reverse((at, key)));
     }
-    ops.insert(
+    trace::op_step(traced, job_id, "registered");
+    table.ops.insert(
         key,
         PendingOp {
             socket,
@@ -559,79 +685,130 @@ fn start_op(
             handle,
             readable,
             writable,
+            deadline,
         },
     );
+    true
+}
+
+/// Start the accepts parked on `handle` now that its registration is
+/// free, until one of them is left waiting (which re-takes it).
+fn promote(handle: RawHandle, poller: &Poller, table: &mut Table) {
+    while let Some(queue) = table.parked.get_mut(&handle) {
+        let next = queue.pop_front();
+        if queue.is_empty() {
+            table.parked.remove(&handle);
+        }
+        let Some((job_id, mailbox, rop)) = next else { break };
+        if start_op(job_id, mailbox, rop, poller, table) {
+            break;
+        }
+    }
+}
+
+/// Deregister op `key` and post `result` for it, then let any accept
+/// parked on its handle take the registration.
+fn finish(key: usize, result: OffloadResult, poller: &Poller, table: &mut Table) {
+    let pending = table.ops.remove(&key).unwrap();
+    // SAFETY: `pending` still owns the socket here.
+    let source = unsafe { borrow_source(pending.handle) };
+    let _ = poller.delete(&source);
+    pending.mailbox.post(pending.job_id, result);
+    promote(pending.handle, poller, table);
 }
 
 /// A readiness event fired for `key` — drive that op forward, then
 /// either remove it (done) or re-arm it (oneshot, still pending).
-fn advance_token(key: usize, poller: &Poller, ops: &mut HashMap<usize, PendingOp>) {
-    // Driving the op borrows the table mutably; the re-arm / removal
-    // that follows borrows it again, so resolve the outcome first.
-    enum Outcome {
-        Done(OffloadResult),
-        Rearm,
-        Gone,
-    }
-    let outcome = match ops.get_mut(&key) {
-        Some(pending) => {
-            match advance(&mut pending.op, &pending.socket, pending.label) {
-                Advance::Done(result) => Outcome::Done(result),
-                Advance::Pending => Outcome::Rearm,
-            }
-        }
+fn advance_token(key: usize, poller: &Poller, table: &mut Table) {
+    let Some(pending) = table.ops.get_mut(&key) else {
         // The op already completed or was cancelled — a stale event.
-        None => Outcome::Gone,
+        return;
     };
-    match outcome {
-        Outcome::Gone => {}
-        Outcome::Done(result) => {
-            let pending = ops.remove(&key).unwrap();
-            // SAFETY: `pending` still owns the socket here.
-            let source = unsafe { borrow_source(pending.handle) };
-            let _ = poller.delete(&source);
-            pending.mailbox.post(pending.job_id, result);
-        }
-        Outcome::Rearm => {
+    match advance(&mut pending.op, &pending.socket, pending.label) {
+        Advance::Done(result) => finish(key, result, poller, table),
+        Advance::Pending => {
             // `polling` is oneshot — re-arm for the next event.
-            let (handle, readable, writable) = {
-                let p = ops.get(&key).unwrap();
-                (p.handle, p.readable, p.writable)
-            };
             // SAFETY: the op still owns the socket (still in `ops`).
-            let source = unsafe { borrow_source(handle) };
-            let event = Event::new(key, readable, writable);
+            let source = unsafe { borrow_source(pending.handle) };
+            let event = Event::new(key, pending.readable, pending.writable);
             if let Err(e) = poller.modify(&source, event) {
-                let _ = poller.delete(&source);
-                let pending = ops.remove(&key).unwrap();
-                pending.mailbox.post(
-                    pending.job_id,
-                    Err(net::offload_err(pending.label, NetError::Io(e))),
-                );
+                let label = pending.label;
+                finish(key, Err(net::offload_err(label, NetError::Io(e))), poller, table);
             }
         }
     }
 }
 
-/// Fail every op on socket `socket_id` with `closed`, deregistering
+/// Fail every op whose deadline has passed with `timeout`.
+fn expire(poller: &Poller, table: &mut Table) {
+    let now = Instant::now();
+    while let Some(at) = table.next_deadline() {
+        if at > now {
+            break;
+        }
+        let Reverse((_, key)) = table.timers.pop().unwrap();
+        let pending = &table.ops[&key];
+        let label = pending.label;
+        trace::op_step(pending.mailbox.trace_actor(), pending.job_id, "timed out");
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, "operation timed out");
+        finish(key, Err(net::offload_err(label, NetError::Io(timed_out))), poller, table);
+    }
+}
+
+/// How `op` ends when its socket is closed under it: `closed`, or
+/// end-of-stream for a read that asked for it (see
+/// [`SocketOp::ReadChunk`]).
+fn closed_result(op: &SocketOp, label: &'static str) -> OffloadResult {
+    match op {
+        SocketOp::ReadChunk { eof_on_close: true, .. } => Ok(OffloadOk::Bytes(Vec::new())),
+        _ => Err(net::offload_err(label, NetError::Closed)),
+    }
+}
+
+/// End every op on socket `socket_id` as [`closed_result`] says —
+/// `closed` for all but a read asking for end-of-stream — deregistering
 /// each handle. The socket's `closed` flag is already set (by `close`),
 /// so the woken coroutine sees the same `closed` an inline op would
-/// raise.
-fn cancel_socket(socket_id: u64, poller: &Poller, ops: &mut HashMap<usize, PendingOp>) {
-    let keys: Vec<usize> = ops
+/// raise. Accepts parked on the socket fail the same way.
+fn cancel_socket(socket_id: u64, poller: &Poller, table: &mut Table) {
+    for queue in table.parked.values_mut() {
+        queue.retain(|(job_id, mailbox, rop)| {
+            if rop.socket.id() != socket_id {
+                return true;
+            }
+            mailbox.post(*job_id, Err(net::offload_err(rop.label, NetError::Closed)));
+            false
+        });
+    }
+    table.parked.retain(|_, queue| !queue.is_empty());
+    let keys: Vec<usize> = table
+        .ops
         .iter()
         .filter(|(_, p)| p.socket.id() == socket_id)
         .map(|(k, _)| *k)
         .collect();
     for key in keys {
-        let pending = ops.remove(&key).unwrap();
+        let pending = &table.ops[&key];
+        let result = closed_result(&pending.op, pending.label);
+        finish(key, result, poller, table);
+    }
+}
+
+/// Deregister every op submitted as `job_id` for `mailbox`, posting
+/// nothing, and let any accept parked on a freed handle take it.
+fn withdraw_job(job_id: u64, mailbox: &Arc<CompletionMailbox>, poller: &Poller, table: &mut Table) {
+    let keys: Vec<usize> = table
+        .ops
+        .iter()
+        .filter(|(_, p)| p.job_id == job_id && Arc::ptr_eq(&p.mailbox, mailbox))
+        .map(|(k, _)| *k)
+        .collect();
+    for key in keys {
+        let pending = table.ops.remove(&key).unwrap();
         // SAFETY: `pending` still owns the socket here.
         let source = unsafe { borrow_source(pending.handle) };
-        let _ = poller.delete(&source);
-        pending.mailbox.post(
-            pending.job_id,
-            Err(net::offload_err(pending.label, NetError::Closed)),
-        );
+        let _ = poller.delete(source);
+        promote(pending.handle, poller, table);
     }
 }
 
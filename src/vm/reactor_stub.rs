@@ -20,10 +20,14 @@ pub fn run_blocking(rop: ReactorOp) -> OffloadResult {
 
 /// Unreachable on `wasm32` — no `ReactorOp` is ever constructed.
 #[allow(unused_variables)]
-pub fn submit(job_id: u64, mailbox: Arc<CompletionMailbox>, rop: ReactorOp) {
+pub fn submit(job_id: u64, mailbox: Arc<CompletionMailbox>, rop: ReactorOp, sim_clock: bool) {
     unreachable!("reactor::submit: no socket ops exist on wasm32")
 }
 
 /// No-op on `wasm32` — there is no reactor and no socket to cancel.
 #[allow(unused_variables)]
 pub fn cancel(socket_id: u64) {}
+
+/// No-op on `wasm32` — no socket op was ever submitted to withdraw.
+#[allow(unused_variables)]
+pub fn withdraw(job_id: u64, mailbox: &Arc<CompletionMailbox>) {}
@@ -6,7 +6,8 @@
 //! stream, so `Random.seed(n)` makes `rand()` reproducible too.
 //!
 //! State `0` means "not yet seeded"; the first draw lazily seeds from
-//! the wall clock. [`seed`] mixes its argument so any value — `0`
+//! the wall clock, or from the seed in simulation mode
+//! ([`crate::vm::sim`]). [`seed`] mixes its argument so any value — `0`
 //! included — yields a usable non-zero state.
 
 use std::cell::Cell;
@@ -66,13 +67,21 @@ pub fn seed(raw: u64) {
     STATE.with(|s| s.set(mix(raw)));
 }
 
+/// Install `state` as this thread's stream and return the one it
+/// replaces. The actor pool swaps each actor's stream in and out with
+/// its heap, so `Random.seed` in one actor never reseeds another that
+/// shares its pool thread. A fresh actor starts at `0`, unseeded.
+pub(crate) fn swap_state(state: u64) -> u64 {
+    STATE.with(|s| s.replace(state))
+}
+
 /// Advance the generator and return the next 64-bit word. Lazily
 /// seeds from the wall clock if the stream has never been used.
 pub fn next_u64() -> u64 {
     STATE.with(|s| {
         let mut x = s.get();
         if x == 0 {
-            x = mix(entropy());
+            x = mix(crate::vm::sim::seed().unwrap_or_else(entropy));
         }
         x ^= x << 13;
         x ^= x >> 7;
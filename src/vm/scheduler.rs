@@ -5,19 +5,30 @@
 //! list. Every green thread in one actor shares that actor's
 //! thread-local heap; the scheduler multiplexes them cooperatively
 //! onto the single OS thread, switching only at `yield` points and
-//! when a coroutine returns. There is no preemption: a coroutine that
-//! never yields starves the others.
+//! when a coroutine returns. By default there is no preemption: a
+//! coroutine that never yields starves the others. An opt-in quantum
+//! (see [`crate::vm::preempt`]) adds forced switches at safe points.
 //!
 //! The *running* coroutine's execution state lives directly in the
 //! `Vm`'s own fields. Only *parked* coroutines are stored here as
 //! [`GreenThread`]s. Coroutine #0 is always the actor's main program.
+//!
+//! In simulation mode ([`crate::vm::sim`]) the run-queue is not taken
+//! in order: each pick draws a random ready coroutine from a stream
+//! seeded from the simulation seed, so one seed replays one
+//! interleaving and another seed tries a different one.
+//!
+//! With a trace being recorded ([`crate::vm::trace`]) every change of a
+//! coroutine's state here — ready, parked and on what — is a slice on
+//! its track.
 
 use std::collections::VecDeque;
 
 use crate::vm::error::RuntimeError;
-use crate::vm::gc::{DeferredKind, GcRef, GreenHandleKind, UpvalueKind};
+use crate::vm::gc::{DeferredKind, GcRef, GreenHandleKind, LocalChannelKind, UpvalueKind};
+use crate::vm::trace;
 use crate::vm::value::Value;
-use crate::vm::vm::CallFrame;
+use crate::vm::vm::{CallFrame, GenChain};
 
 /// How a parked coroutine resumes once it is unblocked. A `yield` or a
 /// `join` resumes with a plain value pushed onto its stack; an
@@ -50,6 +61,32 @@ pub struct GreenThread {
     /// where its return value is recorded so a `join` can read it.
     /// `None` for the actor's main coroutine (#0), which has no handle.
     pub(crate) handle: Option<GcRef<GreenHandleKind>>,
+    /// Set when the coroutine parked inside a generator body: the
+    /// generator that was running and the resumers that pulled it.
+    /// `frames`/`stack`/`open_upvalues` above are then the generator's,
+    /// while `id` and `is_main` stay the coroutine's own.
+    pub(crate) generator: Option<Box<GenChain>>,
+}
+
+impl GreenThread {
+    /// The value stack coroutine `id` left parked here — this thread's
+    /// own, or one of the generator chain it parked inside.
+    fn stack_for(&self, id: u32) -> Option<&Vec<Value>> {
+        match &self.generator {
+            Some(chain) if chain.id == id => Some(&self.stack),
+            Some(chain) => chain.resumer_stack(id),
+            None => (self.id == id).then_some(&self.stack),
+        }
+    }
+
+    /// Mutable counterpart of [`stack_for`](GreenThread::stack_for).
+    fn stack_for_mut(&mut self, id: u32) -> Option<&mut Vec<Value>> {
+        match &mut self.generator {
+            Some(chain) if chain.id == id => Some(&mut self.stack),
+            Some(chain) => chain.resumer_stack_mut(id),
+            None => (self.id == id).then_some(&mut self.stack),
+        }
+    }
 }
 
 /// The result side of a `go` — the value a green-thread handle hands
@@ -129,6 +166,33 @@ struct TimerBlockedThread {
     thread: GreenThread,
 }
 
+/// Everything a parked `select` waits on at once. Whichever comes
+/// first wakes it — through the same wake calls that serve `join`,
+/// `wait` and the offloads — and the rest are dropped.
+pub struct SelectWaits {
+    /// `go` handles, by coroutine id ([`Scheduler::wake_joiners`]).
+    pub greens: Vec<u32>,
+    /// Deferreds ([`Scheduler::wake_deferred`]).
+    pub deferreds: Vec<GcRef<DeferredKind>>,
+    /// Local channels ([`Scheduler::wake_local`]).
+    pub locals: Vec<GcRef<LocalChannelKind>>,
+    /// The job every cross-actor channel, task and socket arm posts to
+    /// the actor's mailbox on ([`Scheduler::wake_io`]); `None` when the
+    /// select has no such arm.
+    pub job: Option<u64>,
+    /// When an `after` arm comes due ([`Scheduler::wake_timers`]).
+    pub wake_time: Option<f64>,
+    /// Runs as the select wakes, whatever woke it, so the sources that
+    /// did not fire stop holding its wake-up.
+    pub on_wake: Option<Box<dyn FnOnce()>>,
+}
+
+/// A coroutine parked in a `select`, waiting on several sources.
+struct SelectBlockedThread {
+    waits: SelectWaits,
+    thread: GreenThread,
+}
+
 /// Lifecycle of a generator coroutine.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GenStatus {
@@ -189,9 +253,21 @@ pub struct Scheduler {
     /// deferred awaiter is not, by itself, evidence the actor can make
     /// progress on its own thread).
     deferred_blocked: Vec<DeferredBlockedThread>,
+    /// Coroutines parked in a `select` — not ready until any one of the
+    /// sources it waits on is. Each counts toward `has_io_blocked` while
+    /// it waits on a mailbox job and toward `has_timer_blocked` while it
+    /// has an `after` arm, so the actor's drive loops wait for it as they
+    /// would for a plain offload or `wait`.
+    select_blocked: Vec<SelectBlockedThread>,
     next_id: u32,
     current_id: u32,
     current_is_main: bool,
+    /// Xorshift state for seeded picks in simulation mode; `None` keeps
+    /// the run-queue round-robin. Survives [`reset`](Scheduler::reset),
+    /// so a replayed run draws the same sequence.
+    pick: Option<u64>,
+    /// The actor's trace id; `0` while untraced.
+    trace: u64,
 }
 
 impl Scheduler {
@@ -202,12 +278,38 @@ impl Scheduler {
             io_blocked: Vec::new(),
             timer_blocked: Vec::new(),
             deferred_blocked: Vec::new(),
+            select_blocked: Vec::new(),
             next_id: 1,
             current_id: 0,
             current_is_main: true,
+            pick: crate::vm::sim::pick_stream(),
+            trace: 0,
         }
     }
 
+    /// Draw the next ready coroutine at random from the stream `state`
+    /// (simulation mode), or take the run-queue in order with `None`.
+    pub fn set_pick_stream(&mut self, state: Option<u64>) {
+        self.pick = state.map(|s| s | 1);
+    }
+
+    /// Record this actor's state changes under trace id `actor`; `0`
+    /// stops.
+    pub fn set_trace(&mut self, actor: u64) {
+        self.trace = actor;
+    }
+
+    /// Coroutine `id` is now in `state`, for the trace.
+    fn mark(&self, id: u32, state: impl FnOnce() -> String) {
+        trace::state(self.trace, id, state);
+    }
+
+    /// Put `thread` on the run-queue. Every wake goes through here.
+    fn ready(&mut self, thread: GreenThread) {
+        self.mark(thread.id, || "ready".into());
+        self.queue.push_back(thread);
+    }
+
     /// Reset to a single running main coroutine (#0). Called when a
     /// `Vm` (re)starts a top-level program or an actor closure.
     pub fn reset(&mut self) {
@@ -216,9 +318,15 @@ impl Scheduler {
         self.io_blocked.clear();
         self.timer_blocked.clear();
         self.deferred_blocked.clear();
+        for st in std::mem::take(&mut self.select_blocked) {
+            if let Some(on_wake) = st.waits.on_wake {
+                on_wake();
+            }
+        }
         self.next_id = 1;
         self.current_id = 0;
         self.current_is_main = true;
+        self.mark(0, || "running".into());
     }
 
     /// Is the currently-running coroutine the actor's main program?
@@ -240,12 +348,22 @@ impl Scheduler {
 
     /// Append a ready coroutine to the back of the run-queue.
     pub fn enqueue(&mut self, gt: GreenThread) {
-        self.queue.push_back(gt);
+        self.ready(gt);
     }
 
-    /// Take the next ready coroutine, if any (round-robin: front).
+    /// Take the next ready coroutine, if any (round-robin: front; in
+    /// simulation mode, a seeded random pick).
     pub fn take_next(&mut self) -> Option<GreenThread> {
-        self.queue.pop_front()
+        match &mut self.pick {
+            Some(x) if self.queue.len() > 1 => {
+                *x ^= *x << 13;
+                *x ^= *x >> 7;
+                *x ^= *x << 17;
+                let i = (*x % self.queue.len() as u64) as usize;
+                self.queue.remove(i)
+            }
+            _ => self.queue.pop_front(),
+        }
     }
 
     /// Is any coroutine sitting on the ready run-queue right now? Lets a
@@ -259,6 +377,7 @@ impl Scheduler {
     /// cooperative `join`: the joiner leaves the run-queue entirely and
     /// is re-enqueued by [`wake_joiners`] when its target returns.
     pub fn block(&mut self, awaiting: u32, thread: GreenThread) {
+        self.mark(thread.id, || format!("join #{awaiting}"));
         self.blocked.push(BlockedThread { awaiting, thread });
     }
 
@@ -273,17 +392,19 @@ impl Scheduler {
             if self.blocked[i].awaiting == finished {
                 let mut bt = self.blocked.swap_remove(i);
                 bt.thread.parked_resume = Some(outcome.clone());
-                self.queue.push_back(bt.thread);
+                self.ready(bt.thread);
             } else {
                 i += 1;
             }
         }
+        self.wake_selects(|w| w.greens.contains(&finished));
     }
 
     /// Park `thread` on the in-flight offload job `job_id`. Used when a
     /// coroutine calls a blocking native that has been offloaded to the
     /// worker pool; [`wake_io`] re-enqueues it once the job completes.
     pub fn park_io(&mut self, job_id: u64, thread: GreenThread) {
+        trace::park_io(self.trace, thread.id, job_id);
         self.io_blocked.push(IoBlockedThread { job_id, thread });
     }
 
@@ -297,22 +418,24 @@ impl Scheduler {
         {
             let mut t = self.io_blocked.swap_remove(pos);
             t.thread.parked_resume = Some(outcome);
-            self.queue.push_back(t.thread);
+            self.ready(t.thread);
             true
         } else {
-            false
+            self.wake_selects(|w| w.job == Some(job_id))
         }
     }
 
     /// Is any coroutine parked on an in-flight offload job?
     pub fn has_io_blocked(&self) -> bool {
         !self.io_blocked.is_empty()
+            || self.select_blocked.iter().any(|t| t.waits.job.is_some())
     }
 
     /// Park `thread` in a cooperative `wait` until the host clock reaches
     /// `wake_time` (a `wait_frame()` passes `f64::NEG_INFINITY`, so it is
     /// due on the next tick). Re-enqueued by [`wake_timers`].
     pub fn park_timer(&mut self, wake_time: f64, thread: GreenThread) {
+        self.mark(thread.id, || "wait".into());
         self.timer_blocked.push(TimerBlockedThread { wake_time, thread });
     }
 
@@ -328,23 +451,26 @@ impl Scheduler {
                 let mut t = self.timer_blocked.swap_remove(i);
                 t.thread.parked_resume =
                     Some(ResumeOutcome::Value(Value::Null));
-                self.queue.push_back(t.thread);
+                self.ready(t.thread);
                 woke = true;
             } else {
                 i += 1;
             }
         }
-        woke
+        woke | self.wake_selects(|w| w.wake_time.is_some_and(|at| at <= now))
     }
 
-    /// Is any coroutine parked in a cooperative `wait`?
+    /// Is any coroutine parked in a cooperative `wait`, or in a `select`
+    /// with an `after` arm?
     pub fn has_timer_blocked(&self) -> bool {
         !self.timer_blocked.is_empty()
+            || self.select_blocked.iter().any(|t| t.waits.wake_time.is_some())
     }
 
     /// Park `thread` in a `join(d)` on deferred `d` until it is settled.
     /// Re-enqueued by [`wake_deferred`].
     pub fn park_deferred(&mut self, d: GcRef<DeferredKind>, thread: GreenThread) {
+        self.mark(thread.id, || "join deferred".into());
         self.deferred_blocked.push(DeferredBlockedThread { deferred: d, thread });
     }
 
@@ -359,11 +485,12 @@ impl Scheduler {
             if self.deferred_blocked[i].deferred == d {
                 let mut bt = self.deferred_blocked.swap_remove(i);
                 bt.thread.parked_resume = Some(outcome.clone());
-                self.queue.push_back(bt.thread);
+                self.ready(bt.thread);
             } else {
                 i += 1;
             }
         }
+        self.wake_selects(|w| w.deferreds.contains(&d));
     }
 
     /// Is any coroutine parked in a `join` on a deferred?
@@ -371,12 +498,54 @@ impl Scheduler {
         !self.deferred_blocked.is_empty()
     }
 
-    /// The earliest `wake_time` among timer-parked coroutines, if any.
-    /// The standalone driver sleeps the actor thread to this point.
+    /// Park `thread` in a `select` until one of `waits` is ready.
+    /// Re-enqueued with `Value::Null` — "check the arms again" — by
+    /// whichever wake call reaches it first.
+    pub fn park_select(&mut self, waits: SelectWaits, thread: GreenThread) {
+        self.mark(thread.id, || "select".into());
+        self.select_blocked.push(SelectBlockedThread { waits, thread });
+    }
+
+    /// Local channel `ch` was sent on or closed: wake every `select`
+    /// waiting on it.
+    pub fn wake_local(&mut self, ch: GcRef<LocalChannelKind>) {
+        self.wake_selects(|w| w.locals.contains(&ch));
+    }
+
+    /// Wake every `select` whose waits satisfy `hit`, running each one's
+    /// `on_wake`. Returns `true` if any woke.
+    fn wake_selects(&mut self, hit: impl Fn(&SelectWaits) -> bool) -> bool {
+        let mut woke = false;
+        let mut i = 0;
+        while i < self.select_blocked.len() {
+            if hit(&self.select_blocked[i].waits) {
+                let mut st = self.select_blocked.swap_remove(i);
+                if let Some(on_wake) = st.waits.on_wake.take() {
+                    on_wake();
+                }
+                st.thread.parked_resume = Some(ResumeOutcome::Value(Value::Null));
+                self.ready(st.thread);
+                woke = true;
+            } else {
+                i += 1;
+            }
+        }
+        woke
+    }
+
+    /// Is any coroutine parked in a `select`?
+    pub fn has_select_blocked(&self) -> bool {
+        !self.select_blocked.is_empty()
+    }
+
+    /// The earliest `wake_time` among timer-parked coroutines and
+    /// `select`s with an `after` arm, if any. The standalone driver
+    /// sleeps the actor thread to this point.
     pub fn next_timer_wake(&self) -> Option<f64> {
         self.timer_blocked
             .iter()
             .map(|t| t.wake_time)
+            .chain(self.select_blocked.iter().filter_map(|t| t.waits.wake_time))
             .min_by(|a, b| {
                 a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
             })
@@ -393,22 +562,22 @@ impl Scheduler {
             && self.io_blocked.is_empty()
             && self.timer_blocked.is_empty()
             && self.deferred_blocked.is_empty()
+            && self.select_blocked.is_empty()
     }
 
     /// Could the actor make progress if the running coroutine parked
     /// itself? True when another coroutine is ready, an offload job is
     /// outstanding (its completion will make one ready), or a `wait`
     /// timer is pending (it wakes once its time comes — the host advances
-    /// the clock, or the standalone driver sleeps to it).
+    /// the clock, or the standalone driver sleeps to it). A `select`
+    /// counts when it waits on a job or has an `after` arm.
     pub fn can_make_progress(&self) -> bool {
-        !self.queue.is_empty()
-            || !self.io_blocked.is_empty()
-            || !self.timer_blocked.is_empty()
+        !self.queue.is_empty() || self.has_io_blocked() || self.has_timer_blocked()
     }
 
     /// A `cancel(handle)` marked coroutine `id` for cancellation: if it
-    /// is parked in a `join`, an offloaded blocking call, or a `wait`
-    /// timer, abandon that wait and move it onto the run-queue so it
+    /// is parked in a `join`, an offloaded blocking call, a `wait`
+    /// timer or a `select`, abandon that wait and move it onto the run-queue so it
     /// resumes promptly — where [`crate::vm::vm::Vm::load_green`] turns
     /// the resume into the `cancelled` raise. Without this, cancelling a
     /// coroutine asleep in `wait(10)` would not take effect until the ten
@@ -426,7 +595,7 @@ impl Scheduler {
         {
             let mut bt = self.blocked.swap_remove(pos);
             bt.thread.parked_resume = ready;
-            self.queue.push_back(bt.thread);
+            self.ready(bt.thread);
             return true;
         }
         if let Some(pos) =
@@ -434,7 +603,7 @@ impl Scheduler {
         {
             let mut t = self.io_blocked.swap_remove(pos);
             t.thread.parked_resume = ready;
-            self.queue.push_back(t.thread);
+            self.ready(t.thread);
             return true;
         }
         if let Some(pos) =
@@ -442,7 +611,7 @@ impl Scheduler {
         {
             let mut t = self.timer_blocked.swap_remove(pos);
             t.thread.parked_resume = ready;
-            self.queue.push_back(t.thread);
+            self.ready(t.thread);
             return true;
         }
         if let Some(pos) =
@@ -450,7 +619,18 @@ impl Scheduler {
         {
             let mut t = self.deferred_blocked.swap_remove(pos);
             t.thread.parked_resume = ready;
-            self.queue.push_back(t.thread);
+            self.ready(t.thread);
+            return true;
+        }
+        if let Some(pos) =
+            self.select_blocked.iter().position(|t| t.thread.id == id)
+        {
+            let mut t = self.select_blocked.swap_remove(pos);
+            if let Some(on_wake) = t.waits.on_wake.take() {
+                on_wake();
+            }
+            t.thread.parked_resume = ready;
+            self.ready(t.thread);
             return true;
         }
         false
@@ -463,7 +643,8 @@ impl Scheduler {
     }
 
     /// Every parked coroutine — ready, `join`-blocked, IO-blocked,
-    /// timer-blocked *and* deferred-blocked — for GC root tracing.
+    /// timer-blocked, deferred-blocked *and* in a `select` — for GC root
+    /// tracing.
     pub fn queued(&self) -> impl Iterator<Item = &GreenThread> {
         self.queue
             .iter()
@@ -471,6 +652,7 @@ impl Scheduler {
             .chain(self.io_blocked.iter().map(|t| &t.thread))
             .chain(self.timer_blocked.iter().map(|t| &t.thread))
             .chain(self.deferred_blocked.iter().map(|t| &t.thread))
+            .chain(self.select_blocked.iter().map(|t| &t.thread))
     }
 
     /// Borrow a parked coroutine's value stack by id — used to resolve
@@ -485,8 +667,8 @@ impl Scheduler {
             .chain(self.io_blocked.iter().map(|t| &t.thread))
             .chain(self.timer_blocked.iter().map(|t| &t.thread))
             .chain(self.deferred_blocked.iter().map(|t| &t.thread))
-            .find(|gt| gt.id == id)
-            .map(|gt| &gt.stack)
+            .chain(self.select_blocked.iter().map(|t| &t.thread))
+            .find_map(|gt| gt.stack_for(id))
     }
 
     /// Mutable counterpart of [`stack_of`].
@@ -497,8 +679,8 @@ impl Scheduler {
             .chain(self.io_blocked.iter_mut().map(|t| &mut t.thread))
             .chain(self.timer_blocked.iter_mut().map(|t| &mut t.thread))
             .chain(self.deferred_blocked.iter_mut().map(|t| &mut t.thread))
-            .find(|gt| gt.id == id)
-            .map(|gt| &mut gt.stack)
+            .chain(self.select_blocked.iter_mut().map(|t| &mut t.thread))
+            .find_map(|gt| gt.stack_for_mut(id))
     }
 }
 
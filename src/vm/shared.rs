@@ -0,0 +1,401 @@
+//! Shared immutable values — `Shared.of(v)`.
+//!
+//! Every message and `spawn` capture is deep-copied into the receiving
+//! heap (see [`crate::vm::transfer`]), which is the right default but
+//! expensive for a large read-only table handed to many actors.
+//! [`freeze`] instead walks a collection once into an immutable tree of
+//! [`SharedNode`]s behind an `Arc`. The tree holds no `GcRef` and no
+//! heap-bound `Rc`, so it is `Send + Sync`: it crosses an actor
+//! boundary by handle clone, every actor reads it in place, and the
+//! per-thread collector never traces it (a `Value::Shared` is a GC
+//! leaf, like a channel).
+//!
+//! Reading a shared collection yields primitives as ordinary values
+//! and nested collections as further `Value::Shared` handles into the
+//! same tree, so indexing never copies. [`thaw`](SharedNode::thaw) is
+//! the way back to a mutable heap value. Any mutation raises the
+//! catchable `immutable` error.
+
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::gc::{self, ArrayKind, GcRef, MapKind, ObjectKind};
+use crate::vm::value::{fmt_bytes, MapKey, RangeData, Value};
+use crate::vm::vm::{normalize_index, range_indices};
+
+/// A shared handle to a frozen collection. Cloning bumps the `Arc`
+/// refcount; the tree itself is never copied.
+pub type SharedHandle = Arc<SharedNode>;
+
+/// A frozen collection. Mirrors the five heap collections.
+#[derive(PartialEq)]
+pub enum SharedNode {
+    Array(Vec<SharedItem>),
+    Object(IndexMap<Arc<str>, SharedItem>),
+    Map(IndexMap<MapKey, SharedItem>),
+    Set(IndexSet<MapKey>),
+    Bytes(Vec<u8>),
+}
+
+/// One element of a [`SharedNode`]: a primitive held by value, or a
+/// nested collection held by handle.
+#[derive(Clone, PartialEq)]
+pub enum SharedItem {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Arc<str>),
+    BigInt(Arc<BigInt>),
+    Range(RangeData),
+    Node(SharedHandle),
+}
+
+/// The error every mutation of a shared value raises.
+pub fn immutable(what: &str, line: u32) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::Immutable(what.to_string()), line)
+}
+
+/// Ancestor paths for cycle detection, as in `transfer`'s encoder.
+#[derive(Default)]
+struct CycleGuard {
+    arrays: Vec<GcRef<ArrayKind>>,
+    objects: Vec<GcRef<ObjectKind>>,
+    maps: Vec<GcRef<MapKind>>,
+}
+
+/// Freeze `v` into a shared value. A collection (array, object, map,
+/// set or bytes) is deep-copied once into a new tree; a value that is
+/// already shared, or already immutable and sendable (a primitive,
+/// string, bigint or range), is returned as it is. Raises `cycle` on a
+/// self-referential collection and `type_mismatch` on anything that
+/// cannot be frozen, such as a function or a channel.
+pub fn freeze(v: &Value) -> Result<Value, RuntimeError> {
+    Ok(match freeze_item(v, &mut CycleGuard::default())? {
+        SharedItem::Node(node) => Value::Shared(node),
+        _ => v.clone(),
+    })
+}
+
+fn freeze_item(v: &Value, g: &mut CycleGuard) -> Result<SharedItem, RuntimeError> {
+    Ok(match v {
+        Value::Null => SharedItem::Null,
+        Value::Bool(b) => SharedItem::Bool(*b),
+        Value::Int(n) => SharedItem::Int(*n),
+        Value::Float(x) => SharedItem::Float(*x),
+        Value::Str(s) => SharedItem::Str(s.clone()),
+        Value::BigInt(n) => SharedItem::BigInt(Arc::new((**n).clone())),
+        Value::Range(r) => SharedItem::Range((**r).clone()),
+        Value::Shared(node) => SharedItem::Node(node.clone()),
+        Value::Bytes(b) => node(SharedNode::Bytes(b.borrow().clone())),
+        Value::Set(s) => node(SharedNode::Set(s.borrow().clone())),
+        Value::Array(a) => {
+            if g.arrays.contains(a) {
+                return Err(RuntimeError::new(RuntimeErrorKind::Cycle, 0));
+            }
+            g.arrays.push(*a);
+            // Snapshot then release the borrow before recursing.
+            let items: Vec<Value> = a.borrow().clone();
+            let mut out = Vec::with_capacity(items.len());
+            for it in &items {
+                out.push(freeze_item(it, g)?);
+            }
+            g.arrays.pop();
+            node(SharedNode::Array(out))
+        }
+        Value::Object(o) => {
+            if g.objects.contains(o) {
+                return Err(RuntimeError::new(RuntimeErrorKind::Cycle, 0));
+            }
+            g.objects.push(*o);
+            let pairs: Vec<(Arc<str>, Value)> = o
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            let mut out = IndexMap::with_capacity(pairs.len());
+            for (k, v) in pairs {
+                out.insert(k, freeze_item(&v, g)?);
+            }
+            g.objects.pop();
+            node(SharedNode::Object(out))
+        }
+        Value::Map(m) => {
+            if g.maps.contains(m) {
+                return Err(RuntimeError::new(RuntimeErrorKind::Cycle, 0));
+            }
+            g.maps.push(*m);
+            let pairs: Vec<(MapKey, Value)> = m
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            let mut out = IndexMap::with_capacity(pairs.len());
+            for (k, v) in pairs {
+                out.insert(k, freeze_item(&v, g)?);
+            }
+            g.maps.pop();
+            node(SharedNode::Map(out))
+        }
+        other => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch(format!(
+                    "Shared.of cannot share {}",
+                    other.type_name()
+                )),
+                0,
+            ))
+        }
+    })
+}
+
+fn node(n: SharedNode) -> SharedItem {
+    SharedItem::Node(Arc::new(n))
+}
+
+impl SharedItem {
+    /// The item as a `Value`: a primitive by value, a nested collection
+    /// as another handle into the same tree.
+    pub fn to_value(&self) -> Value {
+        match self {
+            SharedItem::Null => Value::Null,
+            SharedItem::Bool(b) => Value::Bool(*b),
+            SharedItem::Int(n) => Value::Int(*n),
+            SharedItem::Float(x) => Value::Float(*x),
+            SharedItem::Str(s) => Value::Str(s.clone()),
+            SharedItem::BigInt(n) => Value::BigInt(Rc::new((**n).clone())),
+            SharedItem::Range(r) => Value::Range(Rc::new(r.clone())),
+            SharedItem::Node(n) => Value::Shared(n.clone()),
+        }
+    }
+}
+
+impl SharedNode {
+    /// What `type` would report for the thawed value.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            SharedNode::Array(_) => "array",
+            SharedNode::Object(_) => "object",
+            SharedNode::Map(_) => "map",
+            SharedNode::Set(_) => "set",
+            SharedNode::Bytes(_) => "bytes",
+        }
+    }
+
+    /// Element count, as `#` reports it.
+    pub fn len(&self) -> usize {
+        match self {
+            SharedNode::Array(a) => a.len(),
+            SharedNode::Object(o) => o.len(),
+            SharedNode::Map(m) => m.len(),
+            SharedNode::Set(s) => s.len(),
+            SharedNode::Bytes(b) => b.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `index`th `(key, value)` pair in iteration order, shaped as
+    /// `for` sees the thawed collection: `(position, element)` for an
+    /// array or bytes, `(key, value)` for an object or map, and
+    /// `(position, key)` for a set.
+    pub fn entry(&self, index: usize) -> Option<(Value, Value)> {
+        match self {
+            SharedNode::Array(a) => {
+                a.get(index).map(|it| (Value::Int(index as i64), it.to_value()))
+            }
+            SharedNode::Object(o) => o
+                .get_index(index)
+                .map(|(k, v)| (Value::Str(k.clone()), v.to_value())),
+            SharedNode::Map(m) => m
+                .get_index(index)
+                .map(|(k, v)| (Value::from(k.clone()), v.to_value())),
+            SharedNode::Set(s) => s
+                .get_index(index)
+                .map(|k| (Value::Int(index as i64), Value::from(k.clone()))),
+            SharedNode::Bytes(b) => b
+                .get(index)
+                .map(|byte| (Value::Int(index as i64), Value::Int(*byte as i64))),
+        }
+    }
+
+    /// Every value `for` would yield, in order.
+    pub fn values(&self) -> Vec<Value> {
+        (0..self.len()).filter_map(|i| self.entry(i)).map(|(_, v)| v).collect()
+    }
+
+    /// `coll[key]`, with the same rules as on the thawed collection. A
+    /// missing key or out-of-range position reads as `null`; a range
+    /// slice of an array or bytes is itself shared.
+    pub fn index(&self, key: &Value, line: u32) -> Result<Value, RuntimeError> {
+        let bad_index = |k: &Value| {
+            RuntimeError::new(RuntimeErrorKind::InvalidIndexType(k.type_name().into()), line)
+        };
+        match self {
+            SharedNode::Array(a) => match key {
+                Value::Int(n) => Ok(normalize_index(*n, a.len())
+                    .map(|i| a[i].to_value())
+                    .unwrap_or(Value::Null)),
+                Value::Range(r) => {
+                    let items = range_indices(r, a.len()).into_iter().map(|i| a[i].clone()).collect();
+                    Ok(Value::Shared(Arc::new(SharedNode::Array(items))))
+                }
+                other => Err(bad_index(other)),
+            },
+            SharedNode::Bytes(b) => match key {
+                Value::Int(n) => Ok(normalize_index(*n, b.len())
+                    .map(|i| Value::Int(b[i] as i64))
+                    .unwrap_or(Value::Null)),
+                Value::Range(r) => {
+                    let bytes = range_indices(r, b.len()).into_iter().map(|i| b[i]).collect();
+                    Ok(Value::Shared(Arc::new(SharedNode::Bytes(bytes))))
+                }
+                other => Err(bad_index(other)),
+            },
+            SharedNode::Object(o) => match key {
+                Value::Str(s) => Ok(o.get(s).map(SharedItem::to_value).unwrap_or(Value::Null)),
+                other => Err(bad_index(other)),
+            },
+            SharedNode::Map(m) => {
+                let key = MapKey::from_value(key, line)?;
+                Ok(m.get(&key).map(SharedItem::to_value).unwrap_or(Value::Null))
+            }
+            SharedNode::Set(s) => {
+                let key = MapKey::from_value(key, line)?;
+                Ok(Value::Bool(s.contains(&key)))
+            }
+        }
+    }
+
+    /// A mutable deep copy on the current thread's heap.
+    pub fn thaw(&self) -> Value {
+        match self {
+            SharedNode::Array(a) => {
+                Value::Array(gc::alloc_array(a.iter().map(SharedItem::thaw).collect()))
+            }
+            SharedNode::Object(o) => Value::Object(gc::alloc_object(
+                o.iter().map(|(k, v)| (k.clone(), v.thaw())).collect(),
+            )),
+            SharedNode::Map(m) => Value::Map(gc::alloc_map(
+                m.iter().map(|(k, v)| (k.clone(), v.thaw())).collect(),
+            )),
+            SharedNode::Set(s) => Value::Set(gc::alloc_set(s.clone())),
+            SharedNode::Bytes(b) => Value::Bytes(gc::alloc_bytes(b.clone())),
+        }
+    }
+}
+
+impl SharedItem {
+    fn thaw(&self) -> Value {
+        match self {
+            SharedItem::Node(n) => n.thaw(),
+            other => other.to_value(),
+        }
+    }
+}
+
+/// Displayed as the thawed collection would be.
+impl fmt::Display for SharedNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharedNode::Array(a) => {
+                f.write_str("[")?;
+                for (i, v) in a.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{}", v.to_value())?;
+                }
+                f.write_str("]")
+            }
+            SharedNode::Object(o) => {
+                f.write_str("${")?;
+                for (i, (k, v)) in o.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{k}: {}", v.to_value())?;
+                }
+                f.write_str("}")
+            }
+            SharedNode::Map(m) => {
+                f.write_str("Map{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{}: {}", Value::from(k.clone()), v.to_value())?;
+                }
+                f.write_str("}")
+            }
+            SharedNode::Set(s) => {
+                f.write_str("Set{")?;
+                for (i, k) in s.iter().enumerate() {
+                    if i > 0 { f.write_str(", ")?; }
+                    write!(f, "{}", Value::from(k.clone()))?;
+                }
+                f.write_str("}")
+            }
+            SharedNode::Bytes(b) => fmt_bytes(f, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shared tree crosses actor threads by handle — a compile-time
+    /// check that it stays `Send + Sync`.
+    #[test]
+    fn shared_handle_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedHandle>();
+    }
+
+    #[test]
+    fn freeze_then_thaw_roundtrips() {
+        let mut obj = IndexMap::new();
+        obj.insert(Arc::from("k"), Value::Str("v".into()));
+        let v = Value::Array(gc::alloc_array(vec![
+            Value::Int(1),
+            Value::Object(gc::alloc_object(obj)),
+            Value::Bytes(gc::alloc_bytes(vec![7, 8])),
+        ]));
+        let Value::Shared(node) = freeze(&v).unwrap() else { panic!("expected shared") };
+        assert_eq!(node.thaw(), v);
+    }
+
+    #[test]
+    fn nested_collections_read_as_shared_handles() {
+        let inner = Value::Array(gc::alloc_array(vec![Value::Int(5)]));
+        let outer = Value::Array(gc::alloc_array(vec![inner]));
+        let Value::Shared(node) = freeze(&outer).unwrap() else { panic!("expected shared") };
+        let Value::Shared(first) = node.index(&Value::Int(0), 0).unwrap() else {
+            panic!("a nested array reads as shared")
+        };
+        assert_eq!(first.index(&Value::Int(-1), 0).unwrap(), Value::Int(5));
+        assert_eq!(node.index(&Value::Int(3), 0).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn primitives_pass_through_and_functions_are_rejected() {
+        assert_eq!(freeze(&Value::Int(3)).unwrap(), Value::Int(3));
+        let it = Value::Iter(gc::alloc_iter(crate::vm::value::IterState::Range {
+            current: 0,
+            to: 1,
+            step: 1,
+            inclusive: false,
+            index: 0,
+        }));
+        assert_eq!(freeze(&it).unwrap_err().kind.kind_tag(), "type_mismatch");
+    }
+
+    #[test]
+    fn cyclic_array_raises_cycle() {
+        let a = gc::alloc_array(vec![]);
+        a.borrow_mut().push(Value::Array(a));
+        assert_eq!(freeze(&Value::Array(a)).unwrap_err().kind.kind_tag(), "cycle");
+    }
+}
@@ -0,0 +1,203 @@
+//! A concurrent map shared between actors (`SharedMap`).
+//!
+//! Like a [`Channel`](crate::vm::channel), a shared map lives outside
+//! any per-thread heap: an `Arc` around a mutex-guarded table, `Send`,
+//! a GC leaf, crossing an actor boundary by handle clone. Keys are
+//! strings or ints. Values are stored transfer-encoded (see
+//! [`crate::vm::transfer`]), so `put` copies a value in and `get`
+//! copies it out into the reader's heap, exactly as a channel message
+//! would be; a `Shared.of` value is stored by handle and not copied.
+//!
+//! Each operation holds the lock only for its own step and never runs
+//! tigr code under it. A read-modify-write is a compare-and-set loop
+//! in `stdlib/SharedMap.tg` (`update`), retried when another actor
+//! wrote the key in between. Every write stamps its entry with a fresh
+//! version, and `update` compares versions rather than values, so a
+//! value that is not `==` to itself (`NaN`) or equal to a different
+//! one (`1` and `1.0`) cannot confuse it.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use indexmap::IndexMap;
+
+use crate::vm::error::{RuntimeError, RuntimeErrorKind};
+use crate::vm::transfer::{decode, Transfer};
+use crate::vm::value::{MapKey, Value};
+
+/// A shared handle to a concurrent map. Cloning bumps the `Arc`
+/// refcount; every holder sees the same entries.
+pub type SharedMapHandle = Arc<SharedMapInner>;
+
+pub struct SharedMapInner {
+    table: Mutex<Table>,
+}
+
+struct Table {
+    entries: IndexMap<MapKey, Entry>,
+    /// The version the next write stamps. Starts at 1: version 0
+    /// stands for a missing key.
+    next_version: u64,
+}
+
+struct Entry {
+    version: u64,
+    value: Transfer,
+}
+
+impl Table {
+    /// Store `value` under `key` with a fresh version, returning the
+    /// value it replaced.
+    fn write(&mut self, key: MapKey, value: Transfer) -> Option<Transfer> {
+        let version = self.next_version;
+        self.next_version += 1;
+        self.entries.insert(key, Entry { version, value }).map(|e| e.value)
+    }
+
+    fn version(&self, key: &MapKey) -> u64 {
+        self.entries.get(key).map_or(0, |e| e.version)
+    }
+}
+
+/// Convert a key argument, which must be a string or an int.
+pub fn key_of(v: &Value) -> Result<MapKey, RuntimeError> {
+    match v {
+        Value::Str(s) => Ok(MapKey::Str(s.clone())),
+        Value::Int(n) => Ok(MapKey::Int(*n)),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "SharedMap keys must be a string or an int, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+impl SharedMapInner {
+    pub fn new() -> SharedMapHandle {
+        Arc::new(SharedMapInner {
+            table: Mutex::new(Table { entries: IndexMap::new(), next_version: 1 }),
+        })
+    }
+
+    /// The table, recovering from a poisoned lock: every step leaves
+    /// the table consistent, so a panic elsewhere cannot corrupt it.
+    fn lock(&self) -> MutexGuard<'_, Table> {
+        self.table.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Transfer> {
+        self.lock().entries.get(key).map(|e| e.value.clone())
+    }
+
+    /// The value under `key` together with its version, `0` for a
+    /// missing key. Pass the version to [`set_if_version`] to write
+    /// back only if nothing wrote the key in between.
+    ///
+    /// [`set_if_version`]: SharedMapInner::set_if_version
+    pub fn get_versioned(&self, key: &MapKey) -> (Option<Transfer>, u64) {
+        let table = self.lock();
+        match table.entries.get(key) {
+            Some(e) => (Some(e.value.clone()), e.version),
+            None => (None, 0),
+        }
+    }
+
+    /// Store `value` under `key`, returning what it replaced.
+    pub fn put(&self, key: MapKey, value: Transfer) -> Option<Transfer> {
+        self.lock().write(key, value)
+    }
+
+    /// Store `new` under `key` if the key's version is still `version`
+    /// (`0`: still missing). Returns whether it did.
+    pub fn set_if_version(&self, key: MapKey, version: u64, new: Transfer) -> bool {
+        let mut table = self.lock();
+        if table.version(&key) != version {
+            return false;
+        }
+        table.write(key, new);
+        true
+    }
+
+    /// Store `new` under `key` if the current value equals `expected`,
+    /// a missing key counting as `null`. Returns whether it did. The
+    /// current value is decoded into the caller's heap for the
+    /// comparison, under the lock, so no write can slip in between.
+    pub fn compare_and_set(&self, key: MapKey, expected: &Value, new: Transfer) -> bool {
+        let mut table = self.lock();
+        let current = table
+            .entries
+            .get(&key)
+            .map(|e| decode(e.value.clone()))
+            .unwrap_or(Value::Null);
+        if current != *expected {
+            return false;
+        }
+        table.write(key, new);
+        true
+    }
+
+    /// Remove `key`, returning whether it was present.
+    pub fn remove(&self, key: &MapKey) -> bool {
+        self.lock().entries.shift_remove(key).is_some()
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.lock().entries.contains_key(key)
+    }
+
+    /// The keys, in insertion order, as of this moment.
+    pub fn keys(&self) -> Vec<MapKey> {
+        self.lock().entries.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_and_set_checks_the_current_value() {
+        let m = SharedMapInner::new();
+        let k = MapKey::Str("hits".into());
+        // A missing key compares equal to null.
+        assert!(m.compare_and_set(k.clone(), &Value::Null, Transfer::Int(1)));
+        assert!(!m.compare_and_set(k.clone(), &Value::Int(0), Transfer::Int(5)));
+        assert!(m.compare_and_set(k.clone(), &Value::Int(1), Transfer::Int(2)));
+        assert!(matches!(m.get(&k), Some(Transfer::Int(2))));
+    }
+
+    #[test]
+    fn set_if_version_fails_after_any_write() {
+        let m = SharedMapInner::new();
+        let k = MapKey::Str("x".into());
+        let (none, v0) = m.get_versioned(&k);
+        assert!(none.is_none() && v0 == 0);
+        assert!(m.set_if_version(k.clone(), 0, Transfer::Float(f64::NAN)));
+        let (_, v1) = m.get_versioned(&k);
+        // Rewriting the same value still moves the version on.
+        m.put(k.clone(), Transfer::Float(f64::NAN));
+        assert!(!m.set_if_version(k.clone(), v1, Transfer::Int(1)));
+        let (_, v2) = m.get_versioned(&k);
+        assert!(m.set_if_version(k.clone(), v2, Transfer::Int(1)));
+        // A deleted and re-added key does not reuse an old version.
+        m.remove(&k);
+        m.put(k.clone(), Transfer::Int(1));
+        assert!(!m.set_if_version(k.clone(), v2, Transfer::Int(2)));
+    }
+
+    #[test]
+    fn keys_must_be_strings_or_ints() {
+        assert!(key_of(&Value::Int(1)).is_ok());
+        let err = key_of(&Value::Bool(true)).err().expect("a bool key is rejected");
+        assert_eq!(err.kind.kind_tag(), "type_mismatch");
+    }
+}
@@ -0,0 +1,174 @@
+//! Deterministic simulation mode, for shaking out concurrency bugs.
+//!
+//! A test that mixes `go`, `wait`, `Time.now_ms` and `select` depends on
+//! two things the program does not control: which ready coroutine runs
+//! next, and how much real time passes between steps. Simulation mode
+//! fixes both from a single seed:
+//!
+//! * **Virtual time.** The clock a `wait` parks against, `Time.now_ms` /
+//!   `now_ns`, `DateTime.now` and `Time.sleep_ms` all read one virtual
+//!   clock that starts at [`EPOCH_MS`] and never moves on its own. When
+//!   every coroutine is parked and a `wait` is pending, the scheduler
+//!   jumps the clock straight to the earliest wake time instead of
+//!   sleeping, so a test of a thirty-second timeout runs instantly. A
+//!   `yield` counts as [`YIELD_TICK`], or as parking when nothing else
+//!   is ready, so a coroutine spinning on `yield` still sees time pass.
+//! * **Seeded ordering.** The scheduler picks the next ready
+//!   coroutine at random from a stream seeded from the simulation seed,
+//!   rather than round-robin, and the `Random` stream is seeded from it
+//!   too. The same seed replays the same interleaving; a different
+//!   seed tries a different one.
+//! * **Loopback network.** `Net.listen` and `Net.connect` stay in
+//!   memory ([`crate::vm::loopback`]): every host name is this process,
+//!   and a connection is a pair of in-process pipes. A read that would
+//!   block parks until a write on the other end, never on the OS.
+//!
+//! The CLI's `--sim SEED` turns it on, and `--explore N` runs a program
+//! (or `tigr test`) under N seeds in turn, stopping at the first that
+//! fails and printing it for replay. An embedder calls
+//! `Session::set_simulation`.
+//!
+//! The guarantee covers the main actor's green threads. Spawned actors
+//! run on OS threads and interleave as the OS schedules them, so they
+//! stay out of the simulation: their `wait`s, `Time` calls and socket
+//! timeouts use the real clock. Work handed to the worker pool — file
+//! IO, a `select` over channels — still finishes in real time. Its
+//! result is waited for briefly before virtual time moves on (see
+//! [`SETTLE`]), which covers the common case of a call that finishes at
+//! once. A seed therefore replays exactly only when no coroutine's next
+//! step depends on when an actor or a slower job finishes.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Is simulation mode on?
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// The simulation seed; meaningful only while [`ACTIVE`].
+static SEED: AtomicU64 = AtomicU64::new(0);
+
+/// Virtual nanoseconds since the simulation started.
+static CLOCK_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// The wall-clock time, in Unix milliseconds, that virtual time `0`
+/// reads as: 2000-01-01T00:00:00Z. Fixed, so a simulated
+/// `Time.now_ms()` is the same on every run of a seed.
+pub const EPOCH_MS: i64 = 946_684_800_000;
+
+/// How long, in real time, the scheduler waits for a worker-pool job
+/// to post its result before it treats the job as blocked on something
+/// else and lets virtual time move on.
+pub(crate) const SETTLE: Duration = Duration::from_millis(25);
+
+/// The virtual time a `yield` takes, so that coroutines spinning on
+/// `yield` until a sibling's `wait` fires do not spin forever.
+pub(crate) const YIELD_TICK: Duration = Duration::from_micros(100);
+
+/// Turn simulation mode on with `seed`, restarting the virtual clock at
+/// zero and clearing the loopback network. VMs made from now on use it;
+/// the `Random` stream of the calling thread is reseeded.
+pub fn enable(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+    CLOCK_NANOS.store(0, Ordering::Relaxed);
+    ACTIVE.store(true, Ordering::Release);
+    crate::vm::rng::seed(seed);
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::vm::loopback::reset();
+}
+
+/// Turn simulation mode off for VMs made from now on.
+pub fn disable() {
+    ACTIVE.store(false, Ordering::Release);
+}
+
+/// The simulation seed, or `None` when simulation mode is off.
+pub fn seed() -> Option<u64> {
+    ACTIVE
+        .load(Ordering::Acquire)
+        .then(|| SEED.load(Ordering::Relaxed))
+}
+
+/// Is simulation mode on?
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+thread_local! {
+    /// Cleared on threads that run outside the simulation.
+    static ON_CLOCK: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Keep the calling thread on the real clock even in simulation mode.
+/// The actor pool's threads call this: spawned actors run in real time.
+pub(crate) fn leave_clock() {
+    ON_CLOCK.with(|c| c.set(false));
+}
+
+/// Do `Time` and `DateTime` read the virtual clock on this thread?
+pub(crate) fn clock_here() -> bool {
+    active() && ON_CLOCK.with(Cell::get)
+}
+
+/// Virtual seconds since the simulation started.
+pub(crate) fn now() -> f64 {
+    Duration::from_nanos(CLOCK_NANOS.load(Ordering::Acquire)).as_secs_f64()
+}
+
+/// Virtual time as a Unix timestamp, in nanoseconds.
+pub(crate) fn unix_nanos() -> i64 {
+    EPOCH_MS * 1_000_000 + CLOCK_NANOS.load(Ordering::Acquire) as i64
+}
+
+/// Move the virtual clock forward to `secs`. Never moves it back: of
+/// two actors jumping to different wake times, the later one wins.
+/// Rounds up to the next nanosecond, so [`now`] reads at least `secs`
+/// and a timer due at `secs` fires.
+pub(crate) fn advance_to(secs: f64) {
+    let nanos = (secs.max(0.0) * 1e9).ceil() as u64;
+    CLOCK_NANOS.fetch_max(nanos, Ordering::AcqRel);
+    expire_net();
+}
+
+/// Move the virtual clock forward by `d` — a simulated `Time.sleep_ms`.
+pub(crate) fn advance_by(d: Duration) {
+    CLOCK_NANOS.fetch_add(d.as_nanos() as u64, Ordering::AcqRel);
+    expire_net();
+}
+
+/// Time out the loopback ops the clock has just moved past.
+fn expire_net() {
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::vm::loopback::expire(now());
+}
+
+/// The earliest virtual time a loopback socket op times out at, if any
+/// is parked with a `Net.set_timeout`.
+pub(crate) fn net_deadline() -> Option<f64> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return crate::vm::loopback::next_deadline();
+    #[cfg(target_arch = "wasm32")]
+    None
+}
+
+/// Parse a seed as given on the command line: any `u64`.
+pub fn parse_seed(s: &str) -> Option<u64> {
+    s.trim().parse().ok()
+}
+
+/// A fresh seed to start an `--explore` run from, when none was given.
+pub fn random_seed() -> u64 {
+    crate::vm::rng::next_u64() >> 16
+}
+
+/// The state for a scheduler's pick stream under `seed`, derived so
+/// that it differs from the `Random` stream the same seed starts.
+pub(crate) fn pick_stream_for(seed: u64) -> u64 {
+    (seed ^ 0xD1B5_4A32_D192_ED03).rotate_left(17) | 1
+}
+
+/// The pick stream new schedulers start with; `None` when simulation
+/// mode is off.
+pub(crate) fn pick_stream() -> Option<u64> {
+    seed().map(pick_stream_for)
+}
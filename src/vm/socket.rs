@@ -1,5 +1,11 @@
 //! Network socket — the handle behind the v0.15 `Net` module.
 //!
+//! TCP, UDP, TLS-over-TCP and (on unix) Unix-domain stream and datagram
+//! sockets all sit behind the one handle type; [`SocketKind`] says which.
+//! In simulation mode ([`crate::vm::sim`]) TCP listeners and streams are
+//! in-memory instead ([`crate::vm::loopback`]), and the other kinds
+//! raise.
+//!
 //! A `Socket` is `Arc`-shared and `Send + Sync`, like a `Channel` or a
 //! `Task`: it lives outside any heap (a GC leaf) and crosses actor
 //! threads by handle-clone via [`crate::vm::transfer::Transfer::Socket`].
@@ -13,7 +19,7 @@
 //! lock that `close` needs. Each connected stream is split into
 //! independent read / write halves (via `TcpStream::try_clone`) behind
 //! their own mutexes, so a reader actor and a writer actor never
-//! contend. `close` records a flag and fires `shutdown` on a third,
+//! contend (a Unix-domain stream is split the same way). `close` records a flag and fires `shutdown` on a third,
 //! never-locked clone, which unblocks a reader stuck mid-`read`.
 //!
 //! ## Read buffer
@@ -22,25 +28,40 @@
 //! surplus is kept in `read_buf` so the next read sees it first. The
 //! buffer lock is never held across a blocking syscall.
 
+use std::collections::HashMap;
 use std::io::{self, Read, Write};
 use std::net::{
-    Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+    IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream,
+    ToSocketAddrs, UdpSocket,
 };
 #[cfg(unix)]
 use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
 #[cfg(windows)]
 use std::os::windows::io::{AsRawSocket, RawSocket};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
+use socket2::{Domain, SockRef, Socket, Type};
+
 use rustls::pki_types::pem::PemObject;
+use rustls::crypto::CryptoProvider;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
 use rustls::{
     ClientConfig, ClientConnection, Connection, RootCertStore, ServerConfig,
-    ServerConnection,
+    ProtocolVersion, ServerConnection, SupportedProtocolVersion,
 };
 
+use crate::vm::loopback;
+use crate::vm::sim;
+use crate::vm::x509;
+
 /// A shared, `Send` socket handle. Cloning bumps the `Arc` refcount.
 pub type SocketHandle = Arc<SocketInner>;
 
@@ -87,6 +108,9 @@ pub(crate) const CHUNK: usize = 8192;
 pub(crate) const MAX_DIRECT_READ: usize = 65536;
 /// Cap on a single UDP datagram receive buffer.
 const MAX_DATAGRAM: usize = 65536;
+/// The accept backlog of a listener bound with options (see
+/// [`listen_with`]); a plain `listen` keeps std's own.
+const BACKLOG: i32 = 1024;
 /// Poll cadence for the blocking [`SocketInner::accept`] — the wait
 /// between retries while no connection is pending. A listener has no
 /// `shutdown`, so `close` cannot wake a blocked `accept` directly;
@@ -111,6 +135,8 @@ pub enum NetError {
     Dns(String),
     /// A TLS handshake or certificate failure.
     Tls(String),
+    /// A delimited read found no delimiter within its byte limit.
+    TooLong(usize),
     /// Any other I/O error; `net.rs` refines it by `io::ErrorKind`.
     Io(io::Error),
 }
@@ -121,6 +147,102 @@ impl From<io::Error> for NetError {
     }
 }
 
+/// A socket's own or peer address: an IP endpoint, or the path of a
+/// Unix-domain socket (`None` when the socket is unnamed, e.g. a client
+/// that never bound).
+pub enum Addr {
+    Inet(SocketAddr),
+    #[cfg(unix)]
+    Unix(Option<String>),
+}
+
+#[cfg(unix)]
+fn unix_addr(addr: std::os::unix::net::SocketAddr) -> Addr {
+    Addr::Unix(addr.as_pathname().map(|p| p.to_string_lossy().into_owned()))
+}
+
+/// A `Net.set_option` setting, already validated by `net.rs`.
+pub enum SockOpt {
+    /// `TCP_NODELAY` — send small writes at once (no Nagle batching).
+    NoDelay(bool),
+    /// `SO_KEEPALIVE` — probe an idle TCP connection.
+    KeepAlive(bool),
+    /// `SO_REUSEADDR`.
+    ReuseAddr(bool),
+    /// `SO_REUSEPORT` — several sockets share one port (unix only).
+    ReusePort(bool),
+    /// `SO_SNDBUF`, in bytes.
+    SendBuffer(usize),
+    /// `SO_RCVBUF`, in bytes.
+    RecvBuffer(usize),
+    /// `SO_BROADCAST` — allow UDP sends to a broadcast address.
+    Broadcast(bool),
+    /// `IP_MULTICAST_LOOP` / `IPV6_MULTICAST_LOOP`.
+    MulticastLoop(bool),
+    /// `IP_MULTICAST_TTL` / `IPV6_MULTICAST_HOPS`.
+    MulticastTtl(u32),
+    /// Join a multicast group. `v4_iface` picks the interface of an
+    /// IPv4 group by address, `v6_index` that of an IPv6 group by index.
+    JoinMulticast { group: IpAddr, v4_iface: Ipv4Addr, v6_index: u32 },
+    /// Leave a group joined with [`SockOpt::JoinMulticast`].
+    LeaveMulticast { group: IpAddr, v4_iface: Ipv4Addr, v6_index: u32 },
+}
+
+impl SockOpt {
+    /// Options that only mean something on a TCP socket.
+    fn tcp_only(&self) -> bool {
+        matches!(self, SockOpt::NoDelay(_) | SockOpt::KeepAlive(_))
+    }
+
+    /// Options that only mean something on a UDP socket.
+    fn udp_only(&self) -> bool {
+        matches!(
+            self,
+            SockOpt::Broadcast(_)
+                | SockOpt::MulticastLoop(_)
+                | SockOpt::MulticastTtl(_)
+                | SockOpt::JoinMulticast { .. }
+                | SockOpt::LeaveMulticast { .. }
+        )
+    }
+}
+
+/// Apply one option to a raw socket. The IP-level multicast options
+/// pick their v4 or v6 form from the group address (or, for loop and
+/// TTL, from the socket's own family).
+fn apply_opt(s: SockRef<'_>, opt: &SockOpt) -> io::Result<()> {
+    let v6 = || -> io::Result<bool> {
+        Ok(s.local_addr()?.as_socket().is_some_and(|a| a.is_ipv6()))
+    };
+    match *opt {
+        SockOpt::NoDelay(on) => s.set_tcp_nodelay(on),
+        SockOpt::KeepAlive(on) => s.set_keepalive(on),
+        SockOpt::ReuseAddr(on) => s.set_reuse_address(on),
+        #[cfg(unix)]
+        SockOpt::ReusePort(on) => s.set_reuse_port(on),
+        #[cfg(not(unix))]
+        SockOpt::ReusePort(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "reuseport is not available on this platform",
+        )),
+        SockOpt::SendBuffer(n) => s.set_send_buffer_size(n),
+        SockOpt::RecvBuffer(n) => s.set_recv_buffer_size(n),
+        SockOpt::Broadcast(on) => s.set_broadcast(on),
+        SockOpt::MulticastLoop(on) if v6()? => s.set_multicast_loop_v6(on),
+        SockOpt::MulticastLoop(on) => s.set_multicast_loop_v4(on),
+        SockOpt::MulticastTtl(n) if v6()? => s.set_multicast_hops_v6(n),
+        SockOpt::MulticastTtl(n) => s.set_multicast_ttl_v4(n),
+        SockOpt::JoinMulticast { group, v4_iface, v6_index } => match group {
+            IpAddr::V4(g) => s.join_multicast_v4(&g, &v4_iface),
+            IpAddr::V6(g) => s.join_multicast_v6(&g, v6_index),
+        },
+        SockOpt::LeaveMulticast { group, v4_iface, v6_index } => match group {
+            IpAddr::V4(g) => s.leave_multicast_v4(&g, &v4_iface),
+            IpAddr::V6(g) => s.leave_multicast_v6(&g, v6_index),
+        },
+    }
+}
+
 /// The transport behind a socket. Set once at construction.
 enum SocketKind {
     /// A listening TCP server socket. `accept` takes `&self`.
@@ -137,22 +259,70 @@ enum SocketKind {
     /// then wraps the stream in a server-side `rustls` connection built
     /// from `config`.
     TlsListener { listener: TcpListener, config: Arc<ServerConfig> },
+    /// A listening Unix-domain stream socket, bound to the file `path`,
+    /// which `close` removes again.
+    #[cfg(unix)]
+    UnixListener { listener: UnixListener, path: PathBuf },
+    /// A connected Unix-domain stream, split like a TCP stream.
+    #[cfg(unix)]
+    UnixStream { read: Mutex<UnixStream>, write: Mutex<UnixStream> },
+    /// A Unix-domain datagram socket, as two handles on one socket so a
+    /// concurrent receive and send register with the reactor apart.
+    /// `path` is the file it bound (removed on `close`), `None` for a
+    /// `connect_unix` client.
+    #[cfg(unix)]
+    UnixDatagram { read: UnixDatagram, write: UnixDatagram, path: Option<PathBuf> },
+    /// One end of an in-memory connection (simulation mode).
+    Loopback(loopback::Stream),
+    /// An in-memory listener (simulation mode).
+    LoopbackListener(loopback::Listener),
+}
+
+/// A spare clone of a connected stream, held only so `close` can fire
+/// `shutdown(Both)` on it — and so `set_option` can reach the socket
+/// without taking an I/O lock.
+enum Spare {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Spare {
+    fn shutdown(&self) {
+        let _ = match self {
+            Spare::Tcp(s) => s.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Spare::Unix(s) => s.shutdown(Shutdown::Both),
+        };
+    }
+
+    fn sock_ref(&self) -> SockRef<'_> {
+        match self {
+            Spare::Tcp(s) => SockRef::from(s),
+            #[cfg(unix)]
+            Spare::Unix(s) => SockRef::from(s),
+        }
+    }
 }
 
 pub struct SocketInner {
     kind: SocketKind,
     /// Set by `close`; every operation checks it and raises `Closed`.
     closed: AtomicBool,
+    /// The `Net.set_timeout` in milliseconds, `0` for none. The OS
+    /// enforces it on an inline call; the reactor reads it back to give
+    /// an op it drives the same bound.
+    timeout_ms: AtomicU64,
     /// Tracks whether the fd is currently in non-blocking mode. The
     /// reactor sets it non-blocking to drive an op on the poll thread;
     /// the inline blocking executor sets it back. Only meaningful for
     /// reactor-managed kinds (a connected `TcpStream`); a redundant
     /// fcntl is skipped when the mode already matches.
     nonblocking: AtomicBool,
-    /// A spare `TcpStream` clone held outside every I/O lock, used only
-    /// by `close` to fire `shutdown(Both)` — that unblocks a reader
-    /// stuck on a different clone. `None` for listeners / UDP.
-    shutdown: Mutex<Option<TcpStream>>,
+    /// A spare stream clone held outside every I/O lock, used by
+    /// `close` to fire `shutdown(Both)` — that unblocks a reader stuck
+    /// on a different clone. `None` for listeners and datagram sockets.
+    shutdown: Mutex<Option<Spare>>,
     /// Surplus bytes read past what `read_until` needed.
     read_buf: Mutex<Vec<u8>>,
     /// Stable id for legible display; identity is `Arc::ptr_eq`.
@@ -188,9 +358,10 @@ fn tcp_stream_socket(stream: TcpStream) -> Result<SocketHandle, NetError> {
             write: Mutex::new(write),
         },
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
         // `stream` itself becomes the close-only shutdown handle.
-        shutdown: Mutex::new(Some(stream)),
+        shutdown: Mutex::new(Some(Spare::Tcp(stream))),
         read_buf: Mutex::new(Vec::new()),
         id: next_id(),
     }))
@@ -198,6 +369,9 @@ fn tcp_stream_socket(stream: TcpStream) -> Result<SocketHandle, NetError> {
 
 /// Bind a listening TCP socket.
 pub fn listen(host: &str, port: u16) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return loopback_listen(port);
+    }
     let listener = TcpListener::bind((host, port))?;
     // Non-blocking so `accept` can poll the `closed` flag and stay
     // interruptible — see `SocketInner::accept`.
@@ -205,6 +379,7 @@ pub fn listen(host: &str, port: u16) -> Result<SocketHandle, NetError> {
     Ok(Arc::new(SocketInner {
         kind: SocketKind::TcpListener(listener),
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
         shutdown: Mutex::new(None),
         read_buf: Mutex::new(Vec::new()),
@@ -214,6 +389,9 @@ pub fn listen(host: &str, port: u16) -> Result<SocketHandle, NetError> {
 
 /// Open a TCP connection to `host:port`.
 pub fn connect(host: &str, port: u16) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return loopback::connect(port, |end| new_socket(SocketKind::Loopback(end), None));
+    }
     let addr = resolve(host, port)?;
     let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
     tcp_stream_socket(stream)
@@ -221,10 +399,14 @@ pub fn connect(host: &str, port: u16) -> Result<SocketHandle, NetError> {
 
 /// Bind a UDP datagram socket.
 pub fn udp_bind(host: &str, port: u16) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("UDP"));
+    }
     let socket = UdpSocket::bind((host, port))?;
     Ok(Arc::new(SocketInner {
         kind: SocketKind::Udp(socket),
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
         shutdown: Mutex::new(None),
         read_buf: Mutex::new(Vec::new()),
@@ -232,6 +414,319 @@ pub fn udp_bind(host: &str, port: u16) -> Result<SocketHandle, NetError> {
     }))
 }
 
+/// Bind an in-memory listener — `listen` in simulation mode.
+fn loopback_listen(port: u16) -> Result<SocketHandle, NetError> {
+    loopback::listen(port, |l| new_socket(SocketKind::LoopbackListener(l), None))
+}
+
+/// The error a socket kind the loopback network does not simulate
+/// raises in simulation mode.
+fn no_sim(what: &str) -> NetError {
+    NetError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{what} sockets are not available in simulation mode"),
+    ))
+}
+
+/// Wrap a socket kind in a fresh, open handle.
+fn new_socket(kind: SocketKind, spare: Option<Spare>) -> SocketHandle {
+    Arc::new(SocketInner {
+        kind,
+        closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
+        nonblocking: AtomicBool::new(false),
+        shutdown: Mutex::new(spare),
+        read_buf: Mutex::new(Vec::new()),
+        id: next_id(),
+    })
+}
+
+/// Create a socket for `addr` and apply `opts` *before* binding it —
+/// `reuseaddr` / `reuseport` only take effect on an unbound socket.
+fn bind_with(addr: SocketAddr, ty: Type, opts: &[SockOpt]) -> Result<Socket, NetError> {
+    let sock = Socket::new(Domain::for_address(addr), ty, None)?;
+    // std's `TcpListener::bind` sets `SO_REUSEADDR` on unix; keep that
+    // default so a listener with options restarts as readily as one
+    // without. An explicit `reuseaddr: false` below still wins.
+    #[cfg(unix)]
+    if ty == Type::STREAM {
+        sock.set_reuse_address(true)?;
+    }
+    for opt in opts {
+        apply_opt(SockRef::from(&sock), opt)?;
+    }
+    sock.bind(&addr.into())?;
+    Ok(sock)
+}
+
+/// Bind a listening TCP socket, applying `opts` before the bind.
+pub fn listen_with(host: &str, port: u16, opts: &[SockOpt]) -> Result<SocketHandle, NetError> {
+    // Bind options have nothing to tune on an in-memory listener.
+    if sim::active() {
+        return loopback_listen(port);
+    }
+    let sock = bind_with(resolve(host, port)?, Type::STREAM, opts)?;
+    sock.listen(BACKLOG)?;
+    let listener = TcpListener::from(sock);
+    listener.set_nonblocking(true)?;
+    Ok(new_socket(SocketKind::TcpListener(listener), None))
+}
+
+/// Bind a UDP datagram socket, applying `opts` before the bind.
+pub fn udp_bind_with(host: &str, port: u16, opts: &[SockOpt]) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("UDP"));
+    }
+    let sock = bind_with(resolve(host, port)?, Type::DGRAM, opts)?;
+    Ok(new_socket(SocketKind::Udp(UdpSocket::from(sock)), None))
+}
+
+/// Bind a listening Unix-domain stream socket at `path`.
+#[cfg(unix)]
+pub fn listen_unix(path: &str) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("Unix-domain"));
+    }
+    let listener = UnixListener::bind(path)?;
+    // Non-blocking, exactly like `listen` — see `SocketInner::accept`.
+    listener.set_nonblocking(true)?;
+    Ok(new_socket(
+        SocketKind::UnixListener { listener, path: PathBuf::from(path) },
+        None,
+    ))
+}
+
+/// Wrap a connected `UnixStream`, splitting it like a TCP stream.
+#[cfg(unix)]
+fn unix_stream_socket(stream: UnixStream) -> Result<SocketHandle, NetError> {
+    let read = stream.try_clone()?;
+    let write = stream.try_clone()?;
+    Ok(new_socket(
+        SocketKind::UnixStream { read: Mutex::new(read), write: Mutex::new(write) },
+        Some(Spare::Unix(stream)),
+    ))
+}
+
+/// Connect a Unix-domain stream socket to the listener at `path`.
+#[cfg(unix)]
+pub fn connect_unix(path: &str) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("Unix-domain"));
+    }
+    unix_stream_socket(UnixStream::connect(path)?)
+}
+
+/// Wrap a Unix-domain datagram socket, cloning its send handle.
+#[cfg(unix)]
+fn unix_datagram_socket(
+    read: UnixDatagram,
+    path: Option<PathBuf>,
+) -> Result<SocketHandle, NetError> {
+    let write = read.try_clone()?;
+    Ok(new_socket(SocketKind::UnixDatagram { read, write, path }, None))
+}
+
+/// Bind a Unix-domain datagram socket at `path`, to receive on.
+#[cfg(unix)]
+pub fn bind_unix_datagram(path: &str) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("Unix-domain"));
+    }
+    unix_datagram_socket(UnixDatagram::bind(path)?, Some(PathBuf::from(path)))
+}
+
+/// An unbound Unix-domain datagram socket connected to `path`, so
+/// `read` / `write` exchange datagrams with it.
+#[cfg(unix)]
+pub fn connect_unix_datagram(path: &str) -> Result<SocketHandle, NetError> {
+    if sim::active() {
+        return Err(no_sim("Unix-domain"));
+    }
+    let sock = UnixDatagram::unbound()?;
+    sock.connect(path)?;
+    unix_datagram_socket(sock, None)
+}
+
+/// The error every Unix-domain entry point raises where std has no
+/// Unix-domain sockets (Windows).
+#[cfg(not(unix))]
+fn no_unix() -> NetError {
+    NetError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not available on this platform",
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn listen_unix(_path: &str) -> Result<SocketHandle, NetError> {
+    Err(no_unix())
+}
+
+#[cfg(not(unix))]
+pub fn connect_unix(_path: &str) -> Result<SocketHandle, NetError> {
+    Err(no_unix())
+}
+
+#[cfg(not(unix))]
+pub fn bind_unix_datagram(_path: &str) -> Result<SocketHandle, NetError> {
+    Err(no_unix())
+}
+
+#[cfg(not(unix))]
+pub fn connect_unix_datagram(_path: &str) -> Result<SocketHandle, NetError> {
+    Err(no_unix())
+}
+
+/// What `Net.tls_info` reports about an established TLS connection.
+pub struct TlsInfo {
+    /// `TLSv1.2` or `TLSv1.3`.
+    pub version: String,
+    /// The negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`.
+    pub cipher: String,
+    /// The ALPN protocol both sides agreed on, if any.
+    pub alpn: Option<String>,
+    /// The SNI name the client asked for — known on the server side only.
+    pub server_name: Option<String>,
+    /// The peer's end-entity certificate as DER, with its description
+    /// when [`x509::parse`] can read it. `None` when the peer sent no
+    /// certificate (a client, unless the server asked for one).
+    pub peer_cert: Option<(Vec<u8>, Option<x509::CertInfo>)>,
+}
+
+/// The lowest TLS version a `min_version` option can ask for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+    /// TLS 1.2 and 1.3 — the default.
+    V12,
+    /// TLS 1.3 only.
+    V13,
+}
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+/// The protocol versions a config built for `min` offers.
+fn tls_versions(min: TlsVersion) -> &'static [&'static SupportedProtocolVersion] {
+    match min {
+        TlsVersion::V12 => rustls::DEFAULT_VERSIONS,
+        TlsVersion::V13 => TLS13_ONLY,
+    }
+}
+
+/// `connect_tls` / `start_tls` options, parsed by the `Net` module from
+/// its options Object. PEM fields hold PEM content, not file paths.
+pub struct TlsClientOpts {
+    /// Extra trusted CA certificates, on top of the OS trust store.
+    pub ca_pem: Option<String>,
+    /// A client certificate chain and its key, presented when the
+    /// server asks for one (mutual TLS). Set together or not at all.
+    pub cert_pem: Option<String>,
+    pub key_pem: Option<String>,
+    /// ALPN protocols to offer, in preference order.
+    pub alpn: Vec<String>,
+    pub min_version: TlsVersion,
+    /// The name sent as SNI and verified against the server
+    /// certificate, when it differs from the host dialled.
+    pub server_name: Option<String>,
+}
+
+impl Default for TlsClientOpts {
+    fn default() -> Self {
+        TlsClientOpts {
+            ca_pem: None,
+            cert_pem: None,
+            key_pem: None,
+            alpn: Vec::new(),
+            min_version: TlsVersion::V12,
+            server_name: None,
+        }
+    }
+}
+
+impl TlsClientOpts {
+    /// Whether these are the defaults the shared [`tls_config`] serves.
+    fn is_default(&self) -> bool {
+        self.ca_pem.is_none()
+            && self.cert_pem.is_none()
+            && self.alpn.is_empty()
+            && self.min_version == TlsVersion::V12
+    }
+}
+
+/// Whether a TLS server asks connecting clients for a certificate.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// No certificate is requested.
+    None,
+    /// A certificate is requested and verified if sent; a client
+    /// without one is still admitted.
+    Optional,
+    /// A verified certificate is required to complete the handshake.
+    Required,
+}
+
+/// `listen_tls` options, parsed by the `Net` module.
+pub struct TlsServerOpts {
+    /// The default certificate chain and key — served when no `sni`
+    /// entry matches the requested name. Optional when `sni` is given.
+    pub cert_pem: Option<String>,
+    pub key_pem: Option<String>,
+    /// Per-name certificates as `(server name, cert PEM, key PEM)`. A
+    /// name may be a `*.` wildcard covering one leftmost label.
+    pub sni: Vec<(String, String, String)>,
+    /// The CA bundle client certificates are verified against.
+    pub client_ca_pem: Option<String>,
+    pub client_auth: ClientAuth,
+    /// ALPN protocols to accept, in preference order.
+    pub alpn: Vec<String>,
+    pub min_version: TlsVersion,
+}
+
+/// The `ring` crypto provider every TLS config is built on.
+fn tls_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Parse a PEM certificate chain; `what` names it in errors.
+fn pem_certs(pem: &str, what: &str) -> Result<Vec<CertificateDer<'static>>, NetError> {
+    let certs: Vec<CertificateDer<'static>> =
+        CertificateDer::pem_slice_iter(pem.as_bytes())
+            .collect::<Result<_, _>>()
+            .map_err(|e| NetError::Tls(format!("{what} PEM: {e}")))?;
+    if certs.is_empty() {
+        return Err(NetError::Tls(format!("{what} PEM contained no certificates")));
+    }
+    Ok(certs)
+}
+
+/// Parse a PEM private key; `what` names it in errors.
+fn pem_key(pem: &str, what: &str) -> Result<PrivateKeyDer<'static>, NetError> {
+    PrivateKeyDer::from_pem_slice(pem.as_bytes())
+        .map_err(|e| NetError::Tls(format!("{what} PEM: {e}")))
+}
+
+/// A root store holding every certificate in the PEM bundle `pem`, on
+/// top of the OS trust roots when `native` is set.
+fn root_store(pem: Option<&str>, what: &str, native: bool) -> Result<RootCertStore, NetError> {
+    let mut roots = RootCertStore::empty();
+    if native {
+        let loaded = rustls_native_certs::load_native_certs();
+        roots.add_parsable_certificates(loaded.certs);
+    }
+    if let Some(pem) = pem {
+        for cert in pem_certs(pem, what)? {
+            roots
+                .add(cert)
+                .map_err(|e| NetError::Tls(format!("{what}: {e}")))?;
+        }
+    }
+    Ok(roots)
+}
+
+/// The owned ALPN protocol list a config carries.
+fn alpn_list(alpn: &[String]) -> Vec<Vec<u8>> {
+    alpn.iter().map(|p| p.as_bytes().to_vec()).collect()
+}
+
 /// The shared TLS client config — native trust roots, built once.
 fn tls_config() -> Arc<ClientConfig> {
     static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
@@ -240,8 +735,7 @@ fn tls_config() -> Arc<ClientConfig> {
             let mut roots = RootCertStore::empty();
             let loaded = rustls_native_certs::load_native_certs();
             roots.add_parsable_certificates(loaded.certs);
-            let provider = rustls::crypto::ring::default_provider();
-            let config = ClientConfig::builder_with_provider(provider.into())
+            let config = ClientConfig::builder_with_provider(tls_provider())
                 .with_safe_default_protocol_versions()
                 .expect("ring provider supports the default TLS versions")
                 .with_root_certificates(roots)
@@ -251,59 +745,100 @@ fn tls_config() -> Arc<ClientConfig> {
         .clone()
 }
 
-/// Build a TLS client config. With no `extra_ca` this is the shared,
-/// cached [`tls_config`]. With `extra_ca` (a PEM bundle of additional
-/// trusted certificates) a fresh, uncached config is built whose root
-/// store holds the OS trust roots *plus* those certificates — used to
-/// connect to a private-CA or self-signed service (e.g. tigr's own
-/// `listen_tls` server in the test suite).
-fn tls_client_config(
-    extra_ca: Option<&[u8]>,
-) -> Result<Arc<ClientConfig>, NetError> {
-    let Some(ca_pem) = extra_ca else {
+/// Build a TLS client config. Default options share the cached
+/// [`tls_config`]; anything else builds a fresh, uncached config. An
+/// extra `ca_pem` bundle joins the OS trust roots — used to connect to
+/// a private-CA or self-signed service (e.g. tigr's own `listen_tls`
+/// server in the test suite).
+fn tls_client_config(opts: &TlsClientOpts) -> Result<Arc<ClientConfig>, NetError> {
+    if opts.is_default() {
         return Ok(tls_config());
-    };
-    let mut roots = RootCertStore::empty();
-    let loaded = rustls_native_certs::load_native_certs();
-    roots.add_parsable_certificates(loaded.certs);
-    let extra: Vec<CertificateDer<'static>> =
-        CertificateDer::pem_slice_iter(ca_pem)
-            .collect::<Result<_, _>>()
-            .map_err(|e| NetError::Tls(format!("trusted CA PEM: {e}")))?;
-    if extra.is_empty() {
-        return Err(NetError::Tls(
-            "trusted CA PEM contained no certificates".into(),
-        ));
     }
-    for cert in extra {
-        roots
-            .add(cert)
-            .map_err(|e| NetError::Tls(format!("trusted CA: {e}")))?;
-    }
-    let provider = rustls::crypto::ring::default_provider();
-    let config = ClientConfig::builder_with_provider(provider.into())
-        .with_safe_default_protocol_versions()
-        .expect("ring provider supports the default TLS versions")
-        .with_root_certificates(roots)
-        .with_no_client_auth();
+    let roots = root_store(opts.ca_pem.as_deref(), "trusted CA", true)?;
+    let builder = ClientConfig::builder_with_provider(tls_provider())
+        .with_protocol_versions(tls_versions(opts.min_version))
+        .map_err(|e| NetError::Tls(e.to_string()))?
+        .with_root_certificates(roots);
+    let mut config = match (&opts.cert_pem, &opts.key_pem) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(
+                pem_certs(cert, "client certificate")?,
+                pem_key(key, "client key")?,
+            )
+            .map_err(|e| NetError::Tls(format!("client certificate / key: {e}")))?,
+        _ => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = alpn_list(&opts.alpn);
     Ok(Arc::new(config))
 }
 
 /// Open a TLS-encrypted TCP connection to `host:port`. `host` doubles
-/// as the certificate-verification server name. `extra_ca_pem`, when
-/// given, adds trusted root certificates beyond the OS trust store. The
-/// handshake runs to completion here (this is offloaded to the worker
-/// pool, since DNS and the handshake are blocking); the resulting
-/// connection is then driven for steady-state data I/O on the reactor.
+/// as the certificate-verification server name unless `opts` names
+/// another. The handshake runs to completion here (this is offloaded
+/// to the worker pool, since DNS and the handshake are blocking); the
+/// resulting connection is then driven for steady-state data I/O on the
+/// reactor.
 pub fn connect_tls(
     host: &str,
     port: u16,
-    extra_ca_pem: Option<&[u8]>,
+    opts: &TlsClientOpts,
 ) -> Result<SocketHandle, NetError> {
-    // Build the config first — bad CA PEM fails before we touch the net.
-    let config = tls_client_config(extra_ca_pem)?;
+    // Build the config first — bad PEM fails before we touch the net.
+    let config = tls_client_config(opts)?;
+    if sim::active() {
+        return Err(no_sim("TLS"));
+    }
     let addr = resolve(host, port)?;
-    let mut tcp = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    let tcp = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    tls_client_socket(tcp, opts.server_name.as_deref().unwrap_or(host), config)
+}
+
+/// Upgrade the connected plain TCP stream `plain` to TLS — the client
+/// side of an HTTP `CONNECT` tunnel or a STARTTLS exchange. The
+/// handshake runs over the same connection, verifying `host`; on
+/// success `plain` is retired (marked closed *without* a shutdown, so
+/// the connection itself stays up) and the TLS socket is returned. It
+/// inherits `plain`'s timeout. Bytes already buffered on `plain` would
+/// belong to the peer's TLS stream, so they refuse the upgrade.
+pub fn start_tls(
+    plain: &SocketInner,
+    host: &str,
+    opts: &TlsClientOpts,
+) -> Result<SocketHandle, NetError> {
+    plain.ensure_open()?;
+    let SocketKind::TcpStream { read, .. } = &plain.kind else {
+        return Err(NetError::WrongKind(
+            "start_tls expects a connected plain TCP stream".into(),
+        ));
+    };
+    if !plain.read_buf.lock().unwrap().is_empty() {
+        return Err(NetError::Tls(
+            "unread bytes are buffered ahead of the handshake".into(),
+        ));
+    }
+    let config = tls_client_config(opts)?;
+    let tcp = read.lock().unwrap().try_clone()?;
+    // A prior reactor op may have left the shared fd non-blocking; the
+    // handshake below runs blocking, bounded by the socket's timeout.
+    tcp.set_nonblocking(false)?;
+    tcp.set_read_timeout(plain.timeout())?;
+    tcp.set_write_timeout(plain.timeout())?;
+    let name = opts.server_name.as_deref().unwrap_or(host);
+    let sock = tls_client_socket(tcp, name, config)?;
+    sock.timeout_ms
+        .store(plain.timeout_ms.load(Ordering::Relaxed), Ordering::Relaxed);
+    plain.closed.store(true, Ordering::Release);
+    plain.shutdown.lock().unwrap().take();
+    Ok(sock)
+}
+
+/// Run a client-side TLS handshake for `host` over the connected `tcp`
+/// and wrap the result in a socket.
+fn tls_client_socket(
+    mut tcp: TcpStream,
+    host: &str,
+    config: Arc<ClientConfig>,
+) -> Result<SocketHandle, NetError> {
     let shutdown = tcp.try_clone()?;
     let server_name = ServerName::try_from(host.to_string())
         .map_err(|e| NetError::Tls(format!("invalid server name '{host}': {e}")))?;
@@ -318,57 +853,111 @@ pub fn connect_tls(
             sock: tcp,
         }))),
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
-        shutdown: Mutex::new(Some(shutdown)),
+        shutdown: Mutex::new(Some(Spare::Tcp(shutdown))),
         read_buf: Mutex::new(Vec::new()),
         id: next_id(),
     }))
 }
 
-/// Build a TLS server config from a PEM certificate chain and a PEM
-/// private key. A bad PEM, an empty chain, or a cert / key mismatch all
-/// surface as [`NetError::Tls`].
-fn tls_server_config(
-    cert_pem: &[u8],
-    key_pem: &[u8],
-) -> Result<Arc<ServerConfig>, NetError> {
-    let chain: Vec<CertificateDer<'static>> =
-        CertificateDer::pem_slice_iter(cert_pem)
-            .collect::<Result<_, _>>()
-            .map_err(|e| NetError::Tls(format!("certificate PEM: {e}")))?;
-    if chain.is_empty() {
-        return Err(NetError::Tls(
-            "certificate PEM contained no certificates".into(),
-        ));
+/// Picks the server certificate by the SNI name a client asks for:
+/// an exact name first, then a `*.` wildcard one label up, then the
+/// default certificate. With no match and no default the handshake
+/// fails.
+#[derive(Debug)]
+struct SniResolver {
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        if let Some(name) = hello.server_name() {
+            let name = name.to_ascii_lowercase();
+            if let Some(key) = self.by_name.get(&name) {
+                return Some(key.clone());
+            }
+            if let Some((_, parent)) = name.split_once('.') {
+                if let Some(key) = self.by_name.get(&format!("*.{parent}")) {
+                    return Some(key.clone());
+                }
+            }
+        }
+        self.default.clone()
+    }
+}
+
+/// Load one certificate chain plus key, checking that they match.
+fn certified_key(
+    cert_pem: &str,
+    key_pem: &str,
+    provider: &CryptoProvider,
+) -> Result<Arc<CertifiedKey>, NetError> {
+    let key = CertifiedKey::from_der(
+        pem_certs(cert_pem, "certificate")?,
+        pem_key(key_pem, "private key")?,
+        provider,
+    )
+    .map_err(|e| NetError::Tls(format!("certificate / key: {e}")))?;
+    Ok(Arc::new(key))
+}
+
+/// Build a TLS server config from `opts`. A bad PEM, an empty chain, a
+/// cert / key mismatch, or an unusable client CA bundle all surface as
+/// [`NetError::Tls`].
+fn tls_server_config(opts: &TlsServerOpts) -> Result<Arc<ServerConfig>, NetError> {
+    let provider = tls_provider();
+    let default = match (&opts.cert_pem, &opts.key_pem) {
+        (Some(cert), Some(key)) => Some(certified_key(cert, key, &provider)?),
+        _ => None,
+    };
+    let mut by_name = HashMap::new();
+    for (name, cert, key) in &opts.sni {
+        by_name.insert(name.to_ascii_lowercase(), certified_key(cert, key, &provider)?);
     }
-    let key = PrivateKeyDer::from_pem_slice(key_pem)
-        .map_err(|e| NetError::Tls(format!("private key PEM: {e}")))?;
-    let provider = rustls::crypto::ring::default_provider();
-    let config = ServerConfig::builder_with_provider(provider.into())
-        .with_safe_default_protocol_versions()
-        .expect("ring provider supports the default TLS versions")
-        .with_no_client_auth()
-        .with_single_cert(chain, key)
-        .map_err(|e| NetError::Tls(format!("certificate / key: {e}")))?;
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(tls_versions(opts.min_version))
+        .map_err(|e| NetError::Tls(e.to_string()))?;
+    let builder = match (&opts.client_ca_pem, opts.client_auth) {
+        (Some(ca), auth) if auth != ClientAuth::None => {
+            let roots = root_store(Some(ca), "client CA", false)?;
+            let mut verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            if auth == ClientAuth::Optional {
+                verifier = verifier.allow_unauthenticated();
+            }
+            let verifier = verifier
+                .build()
+                .map_err(|e| NetError::Tls(format!("client CA: {e}")))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        _ => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_cert_resolver(Arc::new(SniResolver { by_name, default }));
+    config.alpn_protocols = alpn_list(&opts.alpn);
     Ok(Arc::new(config))
 }
 
-/// Bind a listening TLS server socket. The certificate and key are PEM
+/// Bind a listening TLS server socket. Certificates and keys are PEM
 /// content (not file paths), keeping `Net` filesystem-free. The config
 /// is built *before* binding, so a bad cert / key fails fast.
 pub fn listen_tls(
     host: &str,
     port: u16,
-    cert_pem: &[u8],
-    key_pem: &[u8],
+    opts: &TlsServerOpts,
 ) -> Result<SocketHandle, NetError> {
-    let config = tls_server_config(cert_pem, key_pem)?;
+    let config = tls_server_config(opts)?;
+    if sim::active() {
+        return Err(no_sim("TLS"));
+    }
     let listener = TcpListener::bind((host, port))?;
     // Non-blocking, exactly like `listen` — see `SocketInner::accept`.
     listener.set_nonblocking(true)?;
     Ok(Arc::new(SocketInner {
         kind: SocketKind::TlsListener { listener, config },
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
         shutdown: Mutex::new(None),
         read_buf: Mutex::new(Vec::new()),
@@ -392,8 +981,9 @@ fn tls_server_socket(
             sock: stream,
         }))),
         closed: AtomicBool::new(false),
+        timeout_ms: AtomicU64::new(0),
         nonblocking: AtomicBool::new(false),
-        shutdown: Mutex::new(Some(shutdown)),
+        shutdown: Mutex::new(Some(Spare::Tcp(shutdown))),
         read_buf: Mutex::new(Vec::new()),
         id: next_id(),
     }))
@@ -430,9 +1020,16 @@ impl SocketInner {
                 let st: &mut TlsState = &mut guard;
                 Ok(tls_read(st, buf)?)
             }
-            SocketKind::TcpListener(_)
-            | SocketKind::Udp(_)
-            | SocketKind::TlsListener { .. } => Err(NetError::WrongKind(
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, .. } => {
+                let guard = read.lock().unwrap();
+                let mut stream: &UnixStream = &guard;
+                Ok(stream.read(buf)?)
+            }
+            // A connected datagram socket reads one datagram per call.
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, .. } => Ok(read.recv(buf)?),
+            _ => Err(NetError::WrongKind(
                 "read expects a connected stream".into(),
             )),
         }
@@ -508,14 +1105,18 @@ impl SocketInner {
     /// Read up to and including the next `delim` byte. `None` means a
     /// clean end-of-stream with nothing buffered; trailing bytes with
     /// no delimiter at EOF are returned as a final unterminated chunk.
-    pub fn read_until(&self, delim: u8) -> Result<Option<Vec<u8>>, NetError> {
+    /// With a `max`, a frame longer than `max` bytes (delimiter
+    /// included) fails with [`NetError::TooLong`] instead of buffering
+    /// without bound.
+    pub fn read_until(
+        &self,
+        delim: u8,
+        max: Option<usize>,
+    ) -> Result<Option<Vec<u8>>, NetError> {
         self.ensure_open()?;
         loop {
-            {
-                let mut buf = self.read_buf.lock().unwrap();
-                if let Some(pos) = buf.iter().position(|&b| b == delim) {
-                    return Ok(Some(buf.drain(..=pos).collect()));
-                }
+            if let Some(frame) = self.take_buffered_until(delim, max)? {
+                return Ok(Some(frame));
             }
             if self.recv_more()? == 0 {
                 let mut buf = self.read_buf.lock().unwrap();
@@ -549,9 +1150,21 @@ impl SocketInner {
                 flush_tls(st)?;
                 Ok(())
             }
-            SocketKind::TcpListener(_)
-            | SocketKind::Udp(_)
-            | SocketKind::TlsListener { .. } => Err(NetError::WrongKind(
+            #[cfg(unix)]
+            SocketKind::UnixStream { write, .. } => {
+                let guard = write.lock().unwrap();
+                let mut stream: &UnixStream = &guard;
+                stream.write_all(data)?;
+                stream.flush()?;
+                Ok(())
+            }
+            // One `write` is one datagram, sent whole or not at all.
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { write, .. } => {
+                write.send(data)?;
+                Ok(())
+            }
+            _ => Err(NetError::WrongKind(
                 "write expects a connected stream".into(),
             )),
         }
@@ -563,39 +1176,36 @@ impl SocketInner {
     /// within `ACCEPT_POLL` and raising `Closed` — rather than parking
     /// in a syscall that `close` could not interrupt.
     pub fn accept(&self) -> Result<SocketHandle, NetError> {
-        let (listener, config) = match &self.kind {
-            SocketKind::TcpListener(l) => (l, None),
-            SocketKind::TlsListener { listener, config } => {
-                (listener, Some(config))
-            }
-            _ => {
-                return Err(NetError::WrongKind(
-                    "accept expects a listener".into(),
-                ));
-            }
-        };
+        if !self.is_listener() {
+            return Err(NetError::WrongKind(
+                "accept expects a listener".into(),
+            ));
+        }
         loop {
             if self.closed.load(Ordering::Acquire) {
                 return Err(NetError::Closed);
             }
-            match listener.accept() {
-                Ok((stream, _addr)) => {
-                    // The listener is non-blocking; hand back a blocking
-                    // stream (its clones inherit the mode).
-                    stream.set_nonblocking(false)?;
-                    return match config {
-                        Some(cfg) => tls_server_socket(stream, cfg.clone()),
-                        None => tcp_stream_socket(stream),
-                    };
-                }
-                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+            match self.nb_accept() {
+                Ok(conn) => return Ok(conn),
+                Err(NetError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
                     std::thread::sleep(ACCEPT_POLL);
                 }
-                Err(e) => return Err(e.into()),
+                Err(e) => return Err(e),
             }
         }
     }
 
+    /// Is this a listening socket of any kind?
+    fn is_listener(&self) -> bool {
+        match &self.kind {
+            SocketKind::TcpListener(_) | SocketKind::TlsListener { .. } => true,
+            SocketKind::LoopbackListener(_) => true,
+            #[cfg(unix)]
+            SocketKind::UnixListener { .. } => true,
+            _ => false,
+        }
+    }
+
     /// Send a UDP datagram to `addr`; returns the byte count sent.
     pub fn send_to(&self, data: &[u8], addr: SocketAddr) -> Result<usize, NetError> {
         self.ensure_open()?;
@@ -605,82 +1215,216 @@ impl SocketInner {
         }
     }
 
-    /// Receive a UDP datagram (up to `n` bytes) and its sender address.
-    pub fn recv_from(&self, n: usize) -> Result<(Vec<u8>, SocketAddr), NetError> {
+    /// Send a Unix-domain datagram to the socket bound at `path`.
+    #[cfg(unix)]
+    pub fn send_to_path(&self, data: &[u8], path: &Path) -> Result<usize, NetError> {
         self.ensure_open()?;
         match &self.kind {
+            SocketKind::UnixDatagram { write, .. } => Ok(write.send_to(data, path)?),
+            _ => Err(NetError::WrongKind(
+                "send_to with a path expects a Unix datagram socket".into(),
+            )),
+        }
+    }
+
+    /// Receive a datagram (up to `n` bytes) and its sender address —
+    /// UDP, or Unix-domain datagram.
+    pub fn recv_from(&self, n: usize) -> Result<(Vec<u8>, Addr), NetError> {
+        self.ensure_open()?;
+        let mut tmp = vec![0u8; n.min(MAX_DATAGRAM)];
+        let (got, addr) = match &self.kind {
             SocketKind::Udp(u) => {
-                let mut tmp = vec![0u8; n.min(MAX_DATAGRAM)];
                 let (got, addr) = u.recv_from(&mut tmp)?;
-                tmp.truncate(got);
-                Ok((tmp, addr))
+                (got, Addr::Inet(addr))
             }
-            _ => Err(NetError::WrongKind("recv_from expects a UDP socket".into())),
-        }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, .. } => {
+                let (got, addr) = read.recv_from(&mut tmp)?;
+                (got, unix_addr(addr))
+            }
+            _ => {
+                return Err(NetError::WrongKind(
+                    "recv_from expects a datagram socket".into(),
+                ));
+            }
+        };
+        tmp.truncate(got);
+        Ok((tmp, addr))
     }
 
     /// The socket's own bound address.
-    pub fn local_addr(&self) -> Result<SocketAddr, NetError> {
+    pub fn local_addr(&self) -> Result<Addr, NetError> {
         Ok(match &self.kind {
-            SocketKind::TcpListener(l) => l.local_addr()?,
+            SocketKind::TcpListener(l) => Addr::Inet(l.local_addr()?),
             SocketKind::TcpStream { read, .. } => {
-                read.lock().unwrap().local_addr()?
+                Addr::Inet(read.lock().unwrap().local_addr()?)
+            }
+            SocketKind::Udp(u) => Addr::Inet(u.local_addr()?),
+            SocketKind::Tls(m) => Addr::Inet(m.lock().unwrap().sock.local_addr()?),
+            SocketKind::TlsListener { listener, .. } => {
+                Addr::Inet(listener.local_addr()?)
             }
-            SocketKind::Udp(u) => u.local_addr()?,
-            SocketKind::Tls(m) => m.lock().unwrap().sock.local_addr()?,
-            SocketKind::TlsListener { listener, .. } => listener.local_addr()?,
+            #[cfg(unix)]
+            SocketKind::UnixListener { listener, .. } => unix_addr(listener.local_addr()?),
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, .. } => {
+                unix_addr(read.lock().unwrap().local_addr()?)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, .. } => unix_addr(read.local_addr()?),
+            SocketKind::Loopback(end) => Addr::Inet(end.local_addr()),
+            SocketKind::LoopbackListener(l) => Addr::Inet(l.local_addr()),
         })
     }
 
     /// The address of the connected peer.
-    pub fn peer_addr(&self) -> Result<SocketAddr, NetError> {
+    pub fn peer_addr(&self) -> Result<Addr, NetError> {
         match &self.kind {
             SocketKind::TcpStream { read, .. } => {
-                Ok(read.lock().unwrap().peer_addr()?)
+                Ok(Addr::Inet(read.lock().unwrap().peer_addr()?))
             }
-            SocketKind::Tls(m) => Ok(m.lock().unwrap().sock.peer_addr()?),
-            SocketKind::TcpListener(_)
-            | SocketKind::Udp(_)
-            | SocketKind::TlsListener { .. } => Err(NetError::WrongKind(
+            SocketKind::Tls(m) => Ok(Addr::Inet(m.lock().unwrap().sock.peer_addr()?)),
+            SocketKind::Loopback(end) => Ok(Addr::Inet(end.peer_addr())),
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, .. } => {
+                Ok(unix_addr(read.lock().unwrap().peer_addr()?))
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, path: None, .. } => {
+                Ok(unix_addr(read.peer_addr()?))
+            }
+            _ => Err(NetError::WrongKind(
                 "peer_addr expects a connected stream".into(),
             )),
         }
     }
 
+    /// Apply a socket option. Stream options (`nodelay`, `keepalive`)
+    /// need a TCP socket and the datagram ones a UDP socket; the rest
+    /// apply to any kind the OS accepts them on. A connected stream is
+    /// reached through its spare clone, so this never waits on an I/O
+    /// lock held by a blocked read or write.
+    pub fn set_option(&self, opt: &SockOpt) -> Result<(), NetError> {
+        self.ensure_open()?;
+        let tcp = matches!(
+            self.kind,
+            SocketKind::TcpListener(_)
+                | SocketKind::TcpStream { .. }
+                | SocketKind::Tls(_)
+                | SocketKind::TlsListener { .. }
+                | SocketKind::Loopback(_)
+                | SocketKind::LoopbackListener(_)
+        );
+        let udp = matches!(self.kind, SocketKind::Udp(_));
+        if opt.tcp_only() && !tcp {
+            return Err(NetError::WrongKind("that option expects a TCP socket".into()));
+        }
+        if opt.udp_only() && !udp {
+            return Err(NetError::WrongKind("that option expects a UDP socket".into()));
+        }
+        if let Some(spare) = &*self.shutdown.lock().unwrap() {
+            return Ok(apply_opt(spare.sock_ref(), opt)?);
+        }
+        let res = match &self.kind {
+            SocketKind::TcpListener(l) => apply_opt(SockRef::from(l), opt),
+            SocketKind::TcpStream { write, .. } => {
+                apply_opt(SockRef::from(&*write.lock().unwrap()), opt)
+            }
+            SocketKind::Udp(u) => apply_opt(SockRef::from(u), opt),
+            SocketKind::Tls(m) => apply_opt(SockRef::from(&m.lock().unwrap().sock), opt),
+            SocketKind::TlsListener { listener, .. } => {
+                apply_opt(SockRef::from(listener), opt)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixListener { listener, .. } => {
+                apply_opt(SockRef::from(listener), opt)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixStream { write, .. } => {
+                apply_opt(SockRef::from(&*write.lock().unwrap()), opt)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { write, .. } => apply_opt(SockRef::from(write), opt),
+            // Nothing to tune on an in-memory socket.
+            SocketKind::Loopback(_) | SocketKind::LoopbackListener(_) => Ok(()),
+        };
+        Ok(res?)
+    }
+
     /// Set (or with `None`, clear) the read and write timeouts.
     pub fn set_timeout(&self, dur: Option<Duration>) -> Result<(), NetError> {
         match &self.kind {
             SocketKind::TcpStream { read, write } => {
                 read.lock().unwrap().set_read_timeout(dur)?;
                 write.lock().unwrap().set_write_timeout(dur)?;
-                Ok(())
             }
             SocketKind::Udp(u) => {
                 u.set_read_timeout(dur)?;
                 u.set_write_timeout(dur)?;
-                Ok(())
             }
             SocketKind::Tls(m) => {
                 let tls = m.lock().unwrap();
                 tls.sock.set_read_timeout(dur)?;
                 tls.sock.set_write_timeout(dur)?;
-                Ok(())
             }
-            SocketKind::TcpListener(_) | SocketKind::TlsListener { .. } => {
-                Err(NetError::WrongKind(
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, write } => {
+                read.lock().unwrap().set_read_timeout(dur)?;
+                write.lock().unwrap().set_write_timeout(dur)?;
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, write, .. } => {
+                read.set_read_timeout(dur)?;
+                write.set_write_timeout(dur)?;
+            }
+            // Recorded below, but virtual time never runs an op out.
+            SocketKind::Loopback(_) => {}
+            _ => {
+                return Err(NetError::WrongKind(
                     "set_timeout is not supported on a listener socket".into(),
-                ))
+                ));
             }
         }
+        let ms = dur.map_or(0, |d| (d.as_millis() as u64).max(1));
+        self.timeout_ms.store(ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The timeout last set with [`set_timeout`](Self::set_timeout).
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
     }
 
     /// Close the socket. Idempotent. Fires `shutdown` on the spare
-    /// handle so a reader blocked mid-`read` wakes and observes EOF.
+    /// handle so a reader blocked mid-`read` wakes and observes EOF. A
+    /// Unix-domain socket bound to a path removes its file.
     pub fn close(&self) {
-        self.closed.store(true, Ordering::Release);
+        let was_closed = self.closed.swap(true, Ordering::AcqRel);
         if let Some(stream) = self.shutdown.lock().unwrap().take() {
-            let _ = stream.shutdown(Shutdown::Both);
+            stream.shutdown();
+        }
+        #[cfg(unix)]
+        if !was_closed {
+            match &self.kind {
+                SocketKind::UnixListener { path, .. }
+                | SocketKind::UnixDatagram { path: Some(path), .. } => {
+                    let _ = std::fs::remove_file(path);
+                }
+                _ => {}
+            }
         }
+        #[cfg(not(unix))]
+        let _ = was_closed;
+        match &self.kind {
+            SocketKind::Loopback(end) => end.close(),
+            SocketKind::LoopbackListener(l) => l.close(),
+            _ => return,
+        }
+        // The other end, or an `accept`, may have been waiting on this.
+        loopback::kick();
     }
 
     // -- reactor support ---------------------------------------------
@@ -696,6 +1440,52 @@ impl SocketInner {
         self.closed.load(Ordering::Acquire)
     }
 
+    /// Is this a connected byte stream — one `Net.read` reads from?
+    pub fn is_stream(&self) -> bool {
+        match &self.kind {
+            SocketKind::TcpStream { .. } | SocketKind::Tls(_) | SocketKind::Loopback(_) => true,
+            #[cfg(unix)]
+            SocketKind::UnixStream { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Would a read return without waiting? True with bytes buffered,
+    /// at end-of-stream, on a read error and once closed — the read
+    /// then reports which. Otherwise one non-blocking read runs and
+    /// whatever it gets is buffered for the next read. Backs a
+    /// `select` arm on a stream socket ([`SocketOp::Readable`]).
+    pub fn poll_readable(&self) -> bool {
+        if self.is_closed() || !self.read_buf.lock().unwrap().is_empty() {
+            return true;
+        }
+        if self.set_nonblocking_mode(true).is_err() {
+            return true;
+        }
+        let mut tmp = vec![0u8; CHUNK];
+        match self.nb_read(&mut tmp) {
+            Ok(got) => {
+                self.push_buffered(&tmp[..got]);
+                true
+            }
+            Err(e) => e.kind() != io::ErrorKind::WouldBlock,
+        }
+    }
+
+    /// Is this an in-memory socket? Its ops run on the loopback network
+    /// ([`crate::vm::loopback`]) rather than the reactor.
+    pub fn is_loopback(&self) -> bool {
+        matches!(self.kind, SocketKind::Loopback(_) | SocketKind::LoopbackListener(_))
+    }
+
+    /// The listener behind an in-memory listening socket.
+    pub(crate) fn loopback_listener(&self) -> Option<&loopback::Listener> {
+        match &self.kind {
+            SocketKind::LoopbackListener(l) => Some(l),
+            _ => None,
+        }
+    }
+
     /// Is this a TLS socket? A TLS op oscillates between needing read
     /// and write readiness as `rustls` drives its records, so the
     /// reactor registers it for both interests at once.
@@ -703,6 +1493,52 @@ impl SocketInner {
         matches!(self.kind, SocketKind::Tls(_))
     }
 
+    /// Finish a pending TLS handshake, then describe the connection;
+    /// `None` for a socket that is not TLS. Mode-agnostic like
+    /// [`pump_handshake`]: on a non-blocking handle an unfinished
+    /// handshake surfaces as `WouldBlock`, for the reactor to re-drive
+    /// on the next readiness event.
+    pub fn tls_info(&self) -> Result<Option<TlsInfo>, NetError> {
+        self.ensure_open()?;
+        let SocketKind::Tls(m) = &self.kind else {
+            return Ok(None);
+        };
+        let mut guard = m.lock().unwrap();
+        let st: &mut TlsState = &mut guard;
+        match pump_handshake(st).and_then(|()| flush_tls(st)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Err(NetError::Tls(format!("handshake failed: {e}")));
+            }
+            Err(e) => return Err(NetError::Io(e)),
+        }
+        let conn = &st.conn;
+        let version = match conn.protocol_version() {
+            Some(ProtocolVersion::TLSv1_3) => "TLSv1.3".to_string(),
+            Some(ProtocolVersion::TLSv1_2) => "TLSv1.2".to_string(),
+            other => format!("{other:?}"),
+        };
+        let server_name = match conn {
+            Connection::Server(server) => server.server_name().map(str::to_string),
+            Connection::Client(_) => None,
+        };
+        let peer_cert = conn
+            .peer_certificates()
+            .and_then(|chain| chain.first())
+            .map(|leaf| (leaf.to_vec(), x509::parse(leaf)));
+        Ok(Some(TlsInfo {
+            version,
+            cipher: conn
+                .negotiated_cipher_suite()
+                .map_or_else(String::new, |cs| format!("{:?}", cs.suite())),
+            alpn: conn
+                .alpn_protocol()
+                .map(|p| String::from_utf8_lossy(p).into_owned()),
+            server_name,
+            peer_cert,
+        }))
+    }
+
     /// The raw handle to register for a read-direction reactor op — the
     /// read half of a connected stream, the listener handle for
     /// `accept`, the datagram handle for `recv_from`, or the single TCP
@@ -718,6 +1554,15 @@ impl SocketInner {
             SocketKind::TlsListener { listener, .. } => {
                 Some(raw_handle_of(listener))
             }
+            #[cfg(unix)]
+            SocketKind::UnixListener { listener, .. } => Some(raw_handle_of(listener)),
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, .. } => {
+                Some(raw_handle_of(&*read.lock().unwrap()))
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, .. } => Some(raw_handle_of(read)),
+            SocketKind::Loopback(_) | SocketKind::LoopbackListener(_) => None,
         }
     }
 
@@ -731,6 +1576,12 @@ impl SocketInner {
                 Some(raw_handle_of(&*write.lock().unwrap()))
             }
             SocketKind::Tls(m) => Some(raw_handle_of(&m.lock().unwrap().sock)),
+            #[cfg(unix)]
+            SocketKind::UnixStream { write, .. } => {
+                Some(raw_handle_of(&*write.lock().unwrap()))
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { write, .. } => Some(raw_handle_of(write)),
             _ => None,
         }
     }
@@ -755,11 +1606,19 @@ impl SocketInner {
                 m.lock().unwrap().sock.set_nonblocking(nb)?;
             }
             SocketKind::Udp(u) => u.set_nonblocking(nb)?,
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, write } => {
+                read.lock().unwrap().set_nonblocking(nb)?;
+                write.lock().unwrap().set_nonblocking(nb)?;
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, write, .. } => {
+                read.set_nonblocking(nb)?;
+                write.set_nonblocking(nb)?;
+            }
             // A listener manages its own non-blocking mode (it is bound
             // permanently non-blocking — see `listen` / `listen_tls`).
-            SocketKind::TcpListener(_) | SocketKind::TlsListener { .. } => {
-                return Ok(());
-            }
+            _ => return Ok(()),
         }
         self.nonblocking.store(nb, Ordering::Release);
         Ok(())
@@ -781,9 +1640,16 @@ impl SocketInner {
                 let st: &mut TlsState = &mut guard;
                 tls_read(st, buf)
             }
-            SocketKind::TcpListener(_)
-            | SocketKind::Udp(_)
-            | SocketKind::TlsListener { .. } => Err(io::Error::new(
+            #[cfg(unix)]
+            SocketKind::UnixStream { read, .. } => {
+                let guard = read.lock().unwrap();
+                let mut stream: &UnixStream = &guard;
+                stream.read(buf)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { read, .. } => read.recv(buf),
+            SocketKind::Loopback(end) => end.read(buf),
+            _ => Err(io::Error::new(
                 io::ErrorKind::Unsupported,
                 "reactor read expects a connected stream",
             )),
@@ -806,9 +1672,16 @@ impl SocketInner {
                 let st: &mut TlsState = &mut guard;
                 tls_write(st, buf)
             }
-            SocketKind::TcpListener(_)
-            | SocketKind::Udp(_)
-            | SocketKind::TlsListener { .. } => Err(io::Error::new(
+            #[cfg(unix)]
+            SocketKind::UnixStream { write, .. } => {
+                let guard = write.lock().unwrap();
+                let mut stream: &UnixStream = &guard;
+                stream.write(buf)
+            }
+            #[cfg(unix)]
+            SocketKind::UnixDatagram { write, .. } => write.send(buf),
+            SocketKind::Loopback(end) => end.write(buf),
+            _ => Err(io::Error::new(
                 io::ErrorKind::Unsupported,
                 "reactor write expects a connected stream",
             )),
@@ -848,6 +1721,13 @@ impl SocketInner {
                 stream.set_nonblocking(false)?;
                 tls_server_socket(stream, config.clone())
             }
+            #[cfg(unix)]
+            SocketKind::UnixListener { listener, .. } => {
+                let (stream, _addr) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                unix_stream_socket(stream)
+            }
+            SocketKind::LoopbackListener(l) => l.accept(),
             _ => Err(NetError::WrongKind(
                 "accept expects a listener".into(),
             )),
@@ -863,11 +1743,20 @@ impl SocketInner {
     }
 
     /// Drain the buffer through the first `delim` byte (inclusive);
-    /// `None` when the delimiter is not buffered yet.
-    pub fn take_buffered_until(&self, delim: u8) -> Option<Vec<u8>> {
+    /// `None` when the delimiter is not buffered yet. Fails with
+    /// [`NetError::TooLong`] once the frame is known to exceed `max`.
+    pub fn take_buffered_until(
+        &self,
+        delim: u8,
+        max: Option<usize>,
+    ) -> Result<Option<Vec<u8>>, NetError> {
         let mut buf = self.read_buf.lock().unwrap();
-        let pos = buf.iter().position(|&b| b == delim)?;
-        Some(buf.drain(..=pos).collect())
+        let limit = max.unwrap_or(usize::MAX);
+        match buf.iter().position(|&b| b == delim) {
+            Some(pos) if pos < limit => Ok(Some(buf.drain(..=pos).collect())),
+            None if buf.len() < limit => Ok(None),
+            _ => Err(NetError::TooLong(limit)),
+        }
     }
 
     /// Drain every buffered byte.
@@ -918,6 +1807,10 @@ fn flush_tls(st: &mut TlsState) -> io::Result<()> {
 /// progress — the reactor parks until the fd is ready again.
 fn pump_handshake(st: &mut TlsState) -> io::Result<()> {
     while st.conn.is_handshaking() {
+        // A handshake that already failed stays failed — rustls keeps
+        // re-reporting its error. Check before reading on, or a second
+        // call would wait for a peer that is itself waiting on us.
+        process_packets(st)?;
         // Push whatever the handshake currently wants to send.
         flush_tls(st)?;
         if !st.conn.is_handshaking() {
@@ -944,17 +1837,25 @@ fn pump_handshake(st: &mut TlsState) -> io::Result<()> {
                     "tls: peer closed during handshake",
                 ));
             }
-            Ok(_) => {
-                st.conn.process_new_packets().map_err(|e| {
-                    io::Error::new(io::ErrorKind::InvalidData, e)
-                })?;
-            }
+            Ok(_) => process_packets(st)?,
             Err(e) => return Err(e),
         }
     }
     Ok(())
 }
 
+/// Process the TLS records `read_tls` buffered. On a protocol failure
+/// rustls queues an alert saying why (e.g. a missing client
+/// certificate); push it out best-effort so the peer learns the reason
+/// rather than just seeing the connection drop.
+fn process_packets(st: &mut TlsState) -> io::Result<()> {
+    if let Err(e) = st.conn.process_new_packets() {
+        let _ = flush_tls(st);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+    Ok(())
+}
+
 /// Drive `rustls` until it yields decrypted plaintext into `buf`.
 /// `Ok(0)` is a clean end-of-stream; an unclean close surfaces as an
 /// `io::Error`. `WouldBlock` (non-blocking socket) means the op should
@@ -980,11 +1881,7 @@ fn tls_read(st: &mut TlsState, buf: &mut [u8]) -> io::Result<usize> {
         }
         match st.conn.read_tls(&mut st.sock) {
             Ok(0) => return Ok(0),
-            Ok(_) => {
-                st.conn.process_new_packets().map_err(|e| {
-                    io::Error::new(io::ErrorKind::InvalidData, e)
-                })?;
-            }
+            Ok(_) => process_packets(st)?,
             Err(e) => return Err(e),
         }
     }
@@ -1040,24 +1937,33 @@ fn tls_write(st: &mut TlsState, buf: &[u8]) -> io::Result<usize> {
 pub enum SocketOp {
     /// `Net.accept(listener)` — the next inbound connection.
     Accept,
-    /// `Net.recv_from(sock, n)` — one UDP datagram (up to `n` bytes)
-    /// plus its sender's address.
+    /// `Net.recv_from(sock, n)` — one UDP or Unix-domain datagram (up
+    /// to `n` bytes) plus its sender's address.
     RecvFrom(usize),
     /// `Net.read(sock, n)` — up to `n` bytes (empty = end-of-stream).
-    ReadChunk(usize),
+    /// With `eof_on_close` a `close` under the pending read ends it as
+    /// end-of-stream, as it ends a blocking read, rather than `closed`.
+    ReadChunk { n: usize, eof_on_close: bool },
     /// `Net.read_exact(sock, n)` — exactly `need` bytes; `got` holds
     /// what has arrived so far.
     ReadExact { need: usize, got: Vec<u8> },
-    /// `Net.read_line(sock)` — one `\n`-terminated line, decoded.
-    ReadLine,
-    /// `Net.read_until(sock, byte)` — up to and including `delim`.
-    ReadUntil(u8),
+    /// `Net.read_line(sock, max?)` — one `\n`-terminated line, decoded.
+    ReadLine { max: Option<usize> },
+    /// `Net.read_until(sock, byte, max?)` — up to and including `delim`.
+    ReadUntil { delim: u8, max: Option<usize> },
     /// `Net.read_all(sock)` — every byte until end-of-stream; `got`
     /// accumulates across readiness events.
     ReadAll(Vec<u8>),
     /// `Net.write(sock, bytes)` — every byte of `data`; `sent` is the
     /// count written so far.
     WriteAll { data: Vec<u8>, sent: usize },
+    /// `Net.tls_info(sock)` — finish a server socket's lazy handshake,
+    /// then describe the connection.
+    TlsInfo,
+    /// A `select` arm on a stream socket — done once a read would not
+    /// wait (see [`SocketInner::poll_readable`]). Reads nothing itself
+    /// beyond what it buffers for the next read; never times out.
+    Readable,
 }
 
 /// A [`SocketOp`] bound to the socket it runs against, plus a label for
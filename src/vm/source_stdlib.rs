@@ -1,6 +1,7 @@
-//! Embedded tigr-source stdlib modules (`Array`, `Channel`, `Http`,
-//! `Iter`, `LocalChannel`, `Map`, `Math`, `Object`, `Set`, `String`,
-//! `Test`, `Url`).
+//! Embedded tigr-source stdlib modules (`Array`, `Channel`, `Csv`,
+//! `Dns`, `Http`, `Iter`, `LocalChannel`, `Map`, `Math`, `Object`,
+//! `Postgres`, `Redis`, `Router`, `Set`, `Store`, `String`,
+//! `Supervisor`, `Test`, `Url`).
 //!
 //! These are `.tg` files at the repo's `stdlib/` directory, embedded
 //! at compile time via `include_str!`. Bare-name imports check this
@@ -16,8 +17,9 @@
 /// without an explicit `import`); must stay in sync with [`source`].
 pub fn names() -> &'static [&'static str] {
     &[
-        "Array", "Channel", "Http", "Iter", "LocalChannel", "Map",
-        "Math", "Object", "Set", "String", "Test", "Url", "WS",
+        "Array", "Channel", "Csv", "Dns", "Http", "Iter", "LocalChannel",
+        "Map", "Math", "Object", "Postgres", "Redis", "Router", "Set", "SharedMap",
+        "Store", "String", "Supervisor", "Test", "Url", "WS",
     ]
 }
 
@@ -27,19 +29,35 @@ pub fn source(name: &str) -> Option<&'static str> {
     match name {
         "Array"  => Some(include_str!("../../stdlib/Array.tg")),
         "Channel" => Some(include_str!("../../stdlib/Channel.tg")),
+        "Csv"    => Some(include_str!("../../stdlib/Csv.tg")),
+        // `Dns.tg` queries servers over `Net` and reaches the system
+        // resolver through `_NativeDns`, neither of which `wasm32` has.
+        #[cfg(not(target_arch = "wasm32"))]
+        "Dns"    => Some(include_str!("../../stdlib/Dns.tg")),
         "Http"   => Some(include_str!("../../stdlib/Http.tg")),
         "Iter"   => Some(include_str!("../../stdlib/Iter.tg")),
         "LocalChannel" => Some(include_str!("../../stdlib/LocalChannel.tg")),
         "Map"    => Some(include_str!("../../stdlib/Map.tg")),
         "Math"   => Some(include_str!("../../stdlib/Math.tg")),
         "Object" => Some(include_str!("../../stdlib/Object.tg")),
+        // `Postgres.tg` speaks the wire protocol over `Net`, and hashes
+        // through `_NativePostgres`; `wasm32` has neither.
+        #[cfg(not(target_arch = "wasm32"))]
+        "Postgres" => Some(include_str!("../../stdlib/Postgres.tg")),
+        // `Redis.tg` is a RESP client over `Net`, which `wasm32` lacks.
+        #[cfg(not(target_arch = "wasm32"))]
+        "Redis"  => Some(include_str!("../../stdlib/Redis.tg")),
+        "Router" => Some(include_str!("../../stdlib/Router.tg")),
         "Set"    => Some(include_str!("../../stdlib/Set.tg")),
+        "SharedMap" => Some(include_str!("../../stdlib/SharedMap.tg")),
+        "Store"  => Some(include_str!("../../stdlib/Store.tg")),
         "String" => Some(include_str!("../../stdlib/String.tg")),
+        "Supervisor" => Some(include_str!("../../stdlib/Supervisor.tg")),
         "Test"   => Some(include_str!("../../stdlib/Test.tg")),
         "Url"    => Some(include_str!("../../stdlib/Url.tg")),
-        // `WS.tg` is the pure-tigr WebSocket client (over `Net`). On
-        // `wasm32` there is no `Net`, so it is not offered here — the
-        // import falls through to `native_modules::resolve`, which
+        // `WS.tg` is the pure-tigr WebSocket client and server (over
+        // `Net`). On `wasm32` there is no `Net`, so it is not offered
+        // here — the import falls through to `native_modules::resolve`, which
         // returns the browser-`WebSocket` backend (`ws_web`).
         #[cfg(not(target_arch = "wasm32"))]
         "WS"     => Some(include_str!("../../stdlib/WS.tg")),
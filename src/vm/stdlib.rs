@@ -15,10 +15,11 @@ use num_traits::ToPrimitive;
 
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::gc;
-use crate::vm::offload::{BlockingJob, OffloadOk};
+use crate::vm::offload::{self, HandoffOp, OffloadOk, Waiter};
 use crate::vm::rng;
-use crate::vm::task::JoinOutcome;
-use crate::vm::transfer::{decode, TransferError};
+use crate::vm::channel::ChannelInner;
+use crate::vm::task::{self, JoinOutcome, RegisterError, TaskHandle};
+use crate::vm::transfer::{decode, encode, Transfer, TransferError};
 use crate::vm::value::{bigint_to_f64, Arity, NativeFn, NativeKind, Value, WaitKind};
 
 /// Returns the ordered list of built-in names. The compiler uses this
@@ -78,19 +79,20 @@ const BUILTINS: &[Spec] = &[
     Spec { name: "type",  arity: Arity::Exact(1), kind: pure(native_type) },
     Spec { name: "gc",    arity: Arity::Exact(0), kind: pure(native_gc) },
     // v0.14 — concurrency. `join` waits for a `spawn`ed actor; it is
-    // also the desugar target for `parallel[]`. `__select` backs the
-    // `select` block — internal: a user writes `select`, never it.
-    // Both wait, so they are `Blocking`: inside a green thread they
-    // offload to the worker pool instead of freezing the actor.
-    Spec {
-        name: "__select",
-        arity: Arity::Exact(2),
-        kind: NativeKind::Blocking(native_select),
-    },
+    // also the desugar target for `parallel[]`. `__select` and
+    // `__select_deadline` back the `select` block — internal: a user
+    // writes `select`, never them. `join` is `Handoff`: it parks, and
+    // the finishing actor hands over its result, so a parked join holds
+    // no thread. The `__select` pair is intercepted by the VM, which
+    // parks the coroutine on every arm through the scheduler (see
+    // `Vm::coop_select`); these natives are the fallbacks for a call
+    // with no coroutine to park.
+    Spec { name: "__select", arity: Arity::Exact(3), kind: pure(native_select) },
+    Spec { name: "__select_deadline", arity: Arity::Exact(1), kind: pure(native_select) },
     Spec {
         name: "join",
         arity: Arity::Exact(1),
-        kind: NativeKind::Blocking(native_join),
+        kind: NativeKind::Handoff(native_join),
     },
     // Cooperative `wait(secs)` — a `Park` native: the VM's call dispatch
     // suspends the running green thread on the clock instead of pushing a
@@ -112,11 +114,49 @@ const BUILTINS: &[Spec] = &[
     // that act exclusively on a green handle (`join` stays bare because it
     // also joins actor `Task`s).
     Spec { name: "go_alive", arity: Arity::Exact(1), kind: pure(native_go_alive) },
+    // Actor supervision (see [`crate::vm::task`]). `monitor` and `kill`
+    // act on the target task alone, so they are pure; `link`, `unlink`
+    // and `trap_exits` also need the calling actor's own task, so they
+    // are `Actor` natives.
+    Spec { name: "monitor", arity: Arity::Range(1, 2), kind: pure(native_monitor) },
+    Spec { name: "link", arity: Arity::Exact(1), kind: NativeKind::Actor(native_link) },
+    Spec { name: "unlink", arity: Arity::Exact(1), kind: NativeKind::Actor(native_unlink) },
+    Spec { name: "trap_exits", arity: Arity::Exact(1), kind: NativeKind::Actor(native_trap_exits) },
+    Spec { name: "kill", arity: Arity::Exact(1), kind: pure(native_kill) },
+    // Mailboxes and the name registry (see [`crate::vm::task`]). `self`
+    // is the calling actor's task; `send` appends to a task's mailbox.
+    // The `__receive_*` trio backs the `receive` block — internal, like
+    // `__select`: `__receive_at` parks until the mailbox holds a message
+    // at an index (or the deadline passes), and `__receive_take` removes
+    // the one an arm matched.
+    Spec { name: "self", arity: Arity::Exact(0), kind: NativeKind::Actor(native_self) },
+    Spec { name: "send", arity: Arity::Exact(2), kind: pure(native_send) },
+    Spec { name: "register", arity: Arity::Exact(2), kind: pure(native_register) },
+    Spec { name: "unregister", arity: Arity::Exact(1), kind: pure(native_unregister) },
+    Spec { name: "whereis", arity: Arity::Exact(1), kind: pure(native_whereis) },
+    Spec {
+        name: "__receive_deadline",
+        arity: Arity::Exact(1),
+        kind: pure(native_receive_deadline),
+    },
+    Spec {
+        name: "__receive_at",
+        arity: Arity::Exact(3),
+        kind: NativeKind::Handoff(native_receive_at),
+    },
+    Spec {
+        name: "__receive_take",
+        arity: Arity::Exact(3),
+        kind: pure(native_receive_take),
+    },
 ];
 
-const BUILTIN_NAMES: [&str; 16] = [
+const BUILTIN_NAMES: [&str; 30] = [
     "print", "str", "num", "int", "float", "bool", "floor", "ceil", "rand",
-    "type", "gc", "__select", "join", "wait", "go_cancel", "go_alive",
+    "type", "gc", "__select", "__select_deadline", "join", "wait", "go_cancel", "go_alive",
+    "monitor", "link", "unlink", "trap_exits", "kill",
+    "self", "send", "register", "unregister", "whereis",
+    "__receive_deadline", "__receive_at", "__receive_take",
 ];
 
 fn native_print(args: &[Value]) -> Result<Value, RuntimeError> {
@@ -223,6 +263,244 @@ fn native_go_alive(args: &[Value]) -> Result<Value, RuntimeError> {
     }
 }
 
+/// The `Task` argument of a supervision built-in, or a type error.
+fn task_arg(name: &str, v: Option<&Value>) -> Result<TaskHandle, RuntimeError> {
+    match v {
+        Some(Value::Task(t)) => Ok(t.clone()),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "{name} expects a task, got {}",
+                other.map(|v| v.type_name()).unwrap_or("nothing"),
+            )),
+            0,
+        )),
+    }
+}
+
+/// `monitor(task, ch?)` — arrange for `${down: task, reason}` to be sent
+/// on `ch` (a fresh unbounded channel if omitted) when the actor
+/// finishes, and return the channel. `reason` is `'normal'` for a
+/// return, otherwise the value `join` would raise.
+fn native_monitor(args: &[Value]) -> Result<Value, RuntimeError> {
+    let task = task_arg("monitor", args.first())?;
+    let ch = match args.get(1) {
+        None | Some(Value::Null) => ChannelInner::new(None),
+        Some(Value::Channel(ch)) => ch.clone(),
+        Some(other) => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch(format!(
+                    "monitor expects a channel, got {}",
+                    other.type_name()
+                )),
+                0,
+            ));
+        }
+    };
+    task.monitor(ch.clone());
+    Ok(Value::Channel(ch))
+}
+
+/// `link(task)` — link the calling actor and `task` both ways.
+fn native_link(me: &TaskHandle, args: &[Value]) -> Result<Value, RuntimeError> {
+    me.link(&task_arg("link", args.first())?);
+    Ok(Value::Null)
+}
+
+/// `unlink(task)` — remove a link; a no-op if there is none.
+fn native_unlink(me: &TaskHandle, args: &[Value]) -> Result<Value, RuntimeError> {
+    me.unlink(&task_arg("unlink", args.first())?);
+    Ok(Value::Null)
+}
+
+/// `trap_exits(ch)` — deliver the calling actor's exit signals as
+/// `${exit: task, reason}` messages on `ch`; `trap_exits(null)` stops.
+fn native_trap_exits(me: &TaskHandle, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Channel(ch) => me.trap_exits(Some(ch.clone())),
+        Value::Null => me.trap_exits(None),
+        other => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch(format!(
+                    "trap_exits expects a channel or null, got {}",
+                    other.type_name()
+                )),
+                0,
+            ));
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// `kill(task)` — end the actor with reason `${kind: 'killed', ...}` at
+/// its next park, even if it traps exits. `false` if it had already
+/// finished.
+fn native_kill(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Bool(task_arg("kill", args.first())?.kill()))
+}
+
+/// A `${kind, message}` error, raised as a value.
+fn kind_error(kind: &str, message: String) -> RuntimeError {
+    let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(2);
+    m.insert(Arc::from("kind"), Value::Str(kind.into()));
+    m.insert(Arc::from("message"), Value::Str(message.into()));
+    RuntimeError::new(RuntimeErrorKind::Raised(Value::Object(gc::alloc_object(m))), 0)
+}
+
+/// A registry name argument, or a type error.
+fn name_arg<'a>(fname: &str, v: &'a Value) -> Result<&'a str, RuntimeError> {
+    match v {
+        Value::Str(s) => Ok(s),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "{fname} expects a name string, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+/// `self()` — the calling actor's task.
+fn native_self(me: &TaskHandle, _args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Task(me.clone()))
+}
+
+/// `send(to, msg)` — append `msg`, deep-copied, to the mailbox of `to`:
+/// a task, or the name it is registered under. Never waits. A message
+/// to an actor that has finished is dropped; an unknown name raises
+/// `not_registered`.
+fn native_send(args: &[Value]) -> Result<Value, RuntimeError> {
+    let task = match &args[0] {
+        Value::Str(name) => task::whereis(name).ok_or_else(|| {
+            kind_error("not_registered", format!("no actor is registered as '{name}'"))
+        })?,
+        other => task_arg("send", Some(other))?,
+    };
+    let msg = encode(&args[1])?;
+    task.mailbox().send_then(msg, Box::new(|_| {}));
+    Ok(Value::Null)
+}
+
+/// `register(name, task)` — give a running actor a process-wide name.
+/// Raises `name_taken` if another actor holds it, `not_alive` if the
+/// actor has finished.
+fn native_register(args: &[Value]) -> Result<Value, RuntimeError> {
+    let name = name_arg("register", &args[0])?;
+    let task = task_arg("register", args.get(1))?;
+    match task::register(name, &task) {
+        Ok(()) => Ok(Value::Null),
+        Err(RegisterError::Taken) => Err(kind_error(
+            "name_taken",
+            format!("'{name}' is already registered"),
+        )),
+        Err(RegisterError::Finished) => Err(kind_error(
+            "not_alive",
+            format!("cannot register '{name}': the actor has finished"),
+        )),
+    }
+}
+
+/// `unregister(name)` — release a name. `false` if it was not held.
+fn native_unregister(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Bool(task::unregister(name_arg("unregister", &args[0])?)))
+}
+
+/// `whereis(name)` — the task registered as `name`, or `null`.
+fn native_whereis(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(task::whereis(name_arg("whereis", &args[0])?).map_or(Value::Null, Value::Task))
+}
+
+/// Seconds on a process-wide monotonic clock — the unit of a `receive`
+/// deadline.
+fn mono_secs() -> f64 {
+    static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    EPOCH.get_or_init(std::time::Instant::now).elapsed().as_secs_f64()
+}
+
+/// `__receive_deadline(ms)` — when a `receive ... after ms` gives up,
+/// as a [`mono_secs`] instant; `null` (no `after`) never does. Counts
+/// in milliseconds, like a `select`'s `after`.
+fn native_receive_deadline(args: &[Value]) -> Result<Value, RuntimeError> {
+    let ms = match &args[0] {
+        Value::Null => return Ok(Value::Null),
+        Value::Int(n) => *n as f64,
+        Value::Float(x) => *x,
+        other => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch(format!(
+                    "receive `after` expects a number of milliseconds, got {}",
+                    other.type_name()
+                )),
+                0,
+            ));
+        }
+    };
+    Ok(Value::Float(mono_secs() + ms.max(0.0) / 1000.0))
+}
+
+/// `__receive_at(me, i, deadline)` — wait until `me`'s mailbox holds a
+/// message at index `i`, and return a copy as `${value: m}`, leaving it
+/// in place; `${timeout: true}` once `deadline` passes first. The
+/// timeout and the message race for the one completion.
+fn native_receive_at(args: &[Value]) -> Result<HandoffOp, RuntimeError> {
+    let task = task_arg("receive", args.first())?;
+    let at = mailbox_index(&args[1])?;
+    let left = match &args[2] {
+        Value::Float(until) => Some(until - mono_secs()),
+        _ => None,
+    };
+    Ok(Box::new(move |w: Waiter| {
+        let slot = Arc::new(std::sync::Mutex::new(Some(w)));
+        let fire = |slot: &std::sync::Mutex<Option<Waiter>>, msg: Option<Transfer>| {
+            if let Some(w) = slot.lock().unwrap().take() {
+                w.complete(OffloadOk::deferred(move || Ok(match msg {
+                    Some(t) => receive_tagged(&[("value", decode(t))]),
+                    None => receive_tagged(&[("timeout", Value::Bool(true))]),
+                })));
+            }
+        };
+        let peeked = slot.clone();
+        task.mailbox().peek_then(at, Box::new(move |msg| fire(&peeked, msg)));
+        if let Some(left) = left {
+            let timed = slot.clone();
+            let delay = std::time::Duration::from_secs_f64(left.max(0.0));
+            offload::run_after(delay, Box::new(move || fire(&timed, None)));
+        }
+    }))
+}
+
+/// `__receive_take(me, i, arm)` — remove the message at index `i` of
+/// `me`'s mailbox, which arm `arm` matched: `${index: arm, value: m}`.
+fn native_receive_take(args: &[Value]) -> Result<Value, RuntimeError> {
+    let task = task_arg("receive", args.first())?;
+    let at = mailbox_index(&args[1])?;
+    let msg = task.mailbox().take_at(at).map_or(Value::Null, decode);
+    Ok(receive_tagged(&[("index", args[2].clone()), ("value", msg)]))
+}
+
+/// The mailbox position a `receive` scan is at.
+fn mailbox_index(v: &Value) -> Result<usize, RuntimeError> {
+    match v {
+        Value::Int(i) if *i >= 0 => Ok(*i as usize),
+        other => Err(RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch(format!(
+                "expected a mailbox index, got {}",
+                other.type_name()
+            )),
+            0,
+        )),
+    }
+}
+
+/// A small result object for the `receive` desugaring.
+fn receive_tagged(fields: &[(&str, Value)]) -> Value {
+    let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(fields.len());
+    for (k, v) in fields {
+        m.insert(Arc::from(*k), v.clone());
+    }
+    Value::Object(gc::alloc_object(m))
+}
+
 /// `str(x)` — canonical string form. `str(n, radix)` /
 /// `str(n, radix, prefix)` — render an Int in `radix` (2..=36, lowercase
 /// digits); with `prefix == true` prepend the `0b`/`0o`/`0x` literal
@@ -472,66 +750,21 @@ fn native_rand(_args: &[Value]) -> Result<Value, RuntimeError> {
 // -- __select -------------------------------------------------------
 //
 // The runtime backing the `select { ... }` block (v0.14). The parser
-// desugars `select` to a `match` over `__select(channels, has_else)`,
-// which blocks until one channel has a message and returns
-// `${index, value}` (or `${index: -1}` for an `else` arm).
-
-fn native_select(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
-    use crate::vm::channel::{select, SelectResult};
-
-    let chans = match &args[0] {
-        Value::Array(a) => {
-            let arr = a.borrow();
-            let mut v = Vec::with_capacity(arr.len());
-            for item in arr.iter() {
-                match item {
-                    Value::Channel(h) => v.push(h.clone()),
-                    other => {
-                        return Err(RuntimeError::new(
-                            RuntimeErrorKind::TypeMismatch(format!(
-                                "select expects channels, got {}",
-                                other.type_name()
-                            )),
-                            0,
-                        ));
-                    }
-                }
-            }
-            v
-        }
-        other => {
-            return Err(RuntimeError::new(
-                RuntimeErrorKind::TypeMismatch(format!(
-                    "select expects an array of channels, got {}",
-                    other.type_name()
-                )),
-                0,
-            ));
-        }
-    };
-    let has_else = matches!(&args[1], Value::Bool(true));
-
-    Ok(Box::new(move || {
-        let result = select(&chans, has_else);
-        OffloadOk::deferred(move || match result {
-            SelectResult::Fired { index, message } => {
-                let mut m: IndexMap<Arc<str>, Value> =
-                    IndexMap::with_capacity(2);
-                m.insert(Arc::from("index"), Value::Int(index as i64));
-                m.insert(Arc::from("value"), decode(message));
-                Ok(Value::Object(gc::alloc_object(m)))
-            }
-            SelectResult::ElseReady => {
-                let mut m: IndexMap<Arc<str>, Value> =
-                    IndexMap::with_capacity(1);
-                m.insert(Arc::from("index"), Value::Int(-1));
-                Ok(Value::Object(gc::alloc_object(m)))
-            }
-            SelectResult::AllClosed => {
-                Err(RuntimeError::new(RuntimeErrorKind::ChannelClosed, 0))
-            }
-        })
-    }))
+// desugars `select` to a loop over `__select(sources, has_else,
+// deadline)`, with the deadline from `__select_deadline(ms)`. Both are
+// intercepted by the VM: the wait parks the coroutine on every source
+// at once, which only the scheduler can do. Reached as plain natives
+// only from a re-entrant call, where there is no coroutine to park.
+
+fn native_select(_args: &[Value]) -> Result<Value, RuntimeError> {
+    Err(RuntimeError::new(
+        RuntimeErrorKind::Raised(Value::Str(
+            "select is only valid inside a green thread, not a host \
+             call_function entry"
+                .into(),
+        )),
+        0,
+    ))
 }
 
 fn native_gc(_args: &[Value]) -> Result<Value, RuntimeError> {
@@ -550,16 +783,18 @@ fn native_gc(_args: &[Value]) -> Result<Value, RuntimeError> {
 // the actor's return value into the caller's heap, or re-raises the
 // actor's error so the caller can `try`/`catch` it.
 
-/// `join(task)` — block for the actor's result. Returns its value, or
+/// `join(task)` — wait for the actor's result. Returns its value, or
 /// raises: the actor's own `raise`d value verbatim, or — for a
 /// built-in actor error — an object `${kind, message, trace, worker}`.
 ///
-/// A blocking native: the Condvar wait for the worker actor runs on
-/// the offload pool, so a green thread joining a `spawn`ed actor does
-/// not freeze its siblings. (`join` on a *green-thread* handle never
-/// reaches here — the VM intercepts that case for a cooperative
-/// `coop_join` before the native is invoked.)
-fn native_join(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
+/// A handoff native: the joining coroutine parks, and the actor's pool
+/// thread hands the outcome over as the actor finishes, so a green
+/// thread joining a `spawn`ed actor does not freeze its siblings and a
+/// pooled actor joining another does not hold its pool thread. (`join`
+/// on a *green-thread* handle never reaches here — the VM intercepts
+/// that case for a cooperative `coop_join` before the native is
+/// invoked.)
+pub(crate) fn native_join(args: &[Value]) -> Result<HandoffOp, RuntimeError> {
     let task = match &args[0] {
         Value::Task(t) => t.clone(),
         other => {
@@ -572,38 +807,26 @@ fn native_join(args: &[Value]) -> Result<BlockingJob, RuntimeError> {
             ));
         }
     };
-    Ok(Box::new(move || {
-        let outcome = task.join();
-        OffloadOk::deferred(move || match outcome {
-            JoinOutcome::Outcome(Ok(transfer)) => Ok(decode(transfer)),
-            JoinOutcome::Outcome(Err(te)) => Err(actor_error(te)),
-            JoinOutcome::AlreadyJoined => Err(RuntimeError::new(
-                RuntimeErrorKind::Raised(Value::Str(
-                    "task has already been joined".into(),
+    Ok(Box::new(move |w: Waiter| {
+        task.join_then(Box::new(move |outcome| {
+            w.complete(OffloadOk::deferred(move || match outcome {
+                JoinOutcome::Outcome(Ok(transfer)) => Ok(decode(transfer)),
+                JoinOutcome::Outcome(Err(te)) => Err(actor_error(te)),
+                JoinOutcome::AlreadyJoined => Err(RuntimeError::new(
+                    RuntimeErrorKind::Raised(Value::Str(
+                        "task has already been joined".into(),
+                    )),
+                    0,
                 )),
-                0,
-            )),
-        })
+            }))
+        }))
     }))
 }
 
 /// Reconstruct a catchable error in the joining actor from a worker's
-/// `TransferError`.
+/// `TransferError`: a `raise <value>` in the worker re-raises that exact
+/// value, and a built-in worker error surfaces as an object carrying the
+/// worker's kind, message, and rendered trace.
 fn actor_error(te: TransferError) -> RuntimeError {
-    // A `raise <value>` in the worker re-raises that exact value, so
-    // the parent's `catch` binds what the worker raised.
-    if let Some(raised) = te.raised {
-        return RuntimeError::new(RuntimeErrorKind::Raised(decode(raised)), 0);
-    }
-    // A built-in worker error surfaces as an object carrying the
-    // worker's kind, message, and rendered trace.
-    let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(4);
-    m.insert(Arc::from("kind"), Value::Str(te.kind_tag.into()));
-    m.insert(Arc::from("message"), Value::Str(te.message.into()));
-    m.insert(Arc::from("trace"), Value::Str(te.rendered_trace.into()));
-    m.insert(Arc::from("worker"), Value::Bool(true));
-    RuntimeError::new(
-        RuntimeErrorKind::Raised(Value::Object(gc::alloc_object(m))),
-        0,
-    )
+    RuntimeError::new(RuntimeErrorKind::Raised(decode(te.into_value())), 0)
 }
@@ -0,0 +1,546 @@
+//! Embedded key-value store — the resource behind the `Store` module.
+//!
+//! A store is a directory holding three files:
+//!
+//! * `LOCK` — held with an exclusive OS file lock for as long as the
+//!   store is open, so a second `open` of the same directory (from this
+//!   process or another) fails with [`StoreError::Locked`] instead of
+//!   interleaving writes.
+//! * `store.log` — the write-ahead log: an 8-byte magic header, then a
+//!   sequence of records `[u32 len][u32 crc32][payload]` (little
+//!   endian). A payload is one or more ops — `1 klen key vlen value` for
+//!   a put, `2 klen key` for a delete — so a batch is a single record
+//!   and lands atomically.
+//! * `store.log.tmp` — compaction's scratch file, renamed over the log.
+//!
+//! Opening replays the log into an in-memory `BTreeMap`, so reads never
+//! touch the disk and range scans come out in bytewise key order. A
+//! record that is cut short or fails its checksum ends the replay and
+//! is truncated away: that is exactly what a crash mid-append leaves,
+//! and everything before it was acknowledged whole.
+//!
+//! Every write appends one record and (unless opened with `sync: false`)
+//! `fsync`s before it returns. When the log grows past twice the size
+//! of the live data (and [`COMPACT_MIN`]), it is compacted: the live
+//! entries are written to `store.log.tmp`, synced, and renamed over the
+//! log, so a crash at any point leaves either the old log or the new.
+//!
+//! Values are opaque bytes here; the `Store` module keeps JSON text in
+//! them. Like [`crate::vm::file_handle::FileInner`], a handle is
+//! `Arc`-shared and `Send + Sync`, so it crosses into a `spawn`ed
+//! actor; one mutex serialises its operations.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::vm::deflate::crc32;
+
+/// A shared, `Send` store handle. Cloning bumps the `Arc` refcount.
+pub type StoreHandle = Arc<StoreInner>;
+
+/// An operation failure, mapped to a structured tigr error by the
+/// `Store` module.
+pub enum StoreError {
+    /// Another handle (in this process or another) holds the lock.
+    Locked(String),
+    /// The handle was `close`d.
+    Closed,
+    /// The log is not a store log, or a checksummed record is malformed.
+    Corrupt(String),
+    /// Any other OS-level I/O error.
+    Io(io::Error),
+}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+/// A key and its value, as a range scan returns them.
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+/// One write in a batch.
+pub enum Op {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+const MAGIC: &[u8; 8] = b"TGSTORE1";
+const LOG: &str = "store.log";
+const LOG_TMP: &str = "store.log.tmp";
+const LOCK: &str = "LOCK";
+const OP_PUT: u8 = 1;
+const OP_DELETE: u8 = 2;
+/// A log smaller than this is never compacted automatically.
+const COMPACT_MIN: u64 = 1 << 20;
+/// Compaction splits the live entries into records of about this size.
+const RECORD_TARGET: usize = 1 << 20;
+
+/// The open state; `None` once closed. Dropping it closes the log and
+/// the lock file, which releases the lock.
+struct State {
+    map: BTreeMap<Vec<u8>, Vec<u8>>,
+    log: File,
+    _lock: File,
+    /// Bytes in the log file, header included.
+    log_len: u64,
+    /// What the live entries would take as put ops — the size of a
+    /// freshly compacted log, less framing.
+    live_len: u64,
+}
+
+pub struct StoreInner {
+    state: Mutex<Option<State>>,
+    dir: PathBuf,
+    sync: bool,
+    /// Display only — the path the store was opened with.
+    path: String,
+    /// Stable id for legible display; identity is `Arc::ptr_eq`.
+    id: u64,
+}
+
+fn next_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The encoded size of a put op.
+fn put_len(key: &[u8], value: &[u8]) -> u64 {
+    9 + key.len() as u64 + value.len() as u64
+}
+
+fn push_bytes(out: &mut Vec<u8>, b: &[u8]) {
+    out.extend_from_slice(&(b.len() as u32).to_le_bytes());
+    out.extend_from_slice(b);
+}
+
+fn encode_op(out: &mut Vec<u8>, op: &Op) {
+    match op {
+        Op::Put(k, v) => {
+            out.push(OP_PUT);
+            push_bytes(out, k);
+            push_bytes(out, v);
+        }
+        Op::Delete(k) => {
+            out.push(OP_DELETE);
+            push_bytes(out, k);
+        }
+    }
+}
+
+/// Frame a payload as a log record.
+fn record(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 8);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc32(0, payload).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+fn read_u32(b: &[u8], pos: usize) -> Option<u32> {
+    let s = b.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn take_bytes(payload: &[u8], pos: &mut usize) -> Option<Vec<u8>> {
+    let n = read_u32(payload, *pos)? as usize;
+    let out = payload.get(*pos + 4..*pos + 4 + n)?.to_vec();
+    *pos += 4 + n;
+    Some(out)
+}
+
+/// Decode a record payload into its ops. `None` means the payload is
+/// malformed — which, with a matching checksum, is corruption.
+fn decode_ops(payload: &[u8]) -> Option<Vec<Op>> {
+    let mut ops = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        let tag = payload[pos];
+        pos += 1;
+        let key = take_bytes(payload, &mut pos)?;
+        ops.push(match tag {
+            OP_PUT => Op::Put(key, take_bytes(payload, &mut pos)?),
+            OP_DELETE => Op::Delete(key),
+            _ => return None,
+        });
+    }
+    Some(ops)
+}
+
+/// `fsync` a directory so a rename or create in it is durable. Not
+/// every platform can open a directory for this; that is not an error.
+fn sync_dir(dir: &Path) {
+    if let Ok(d) = File::open(dir) {
+        let _ = d.sync_all();
+    }
+}
+
+impl State {
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Put(k, v) => {
+                self.live_len += put_len(&k, &v);
+                if let Some(old) = self.map.insert(k.clone(), v) {
+                    self.live_len -= put_len(&k, &old);
+                }
+            }
+            Op::Delete(k) => {
+                if let Some(old) = self.map.remove(&k) {
+                    self.live_len -= put_len(&k, &old);
+                }
+            }
+        }
+    }
+}
+
+impl StoreInner {
+    /// Open (creating if needed) the store directory `path`, take its
+    /// lock, and replay the log.
+    pub fn open(path: String, sync: bool) -> Result<StoreHandle, StoreError> {
+        let dir = PathBuf::from(&path);
+        fs::create_dir_all(&dir)?;
+        let lock = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK))?;
+        match lock.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(StoreError::Locked(format!(
+                    "{path:?} is open elsewhere"
+                )))
+            }
+            Err(TryLockError::Error(e)) => return Err(StoreError::Io(e)),
+        }
+        // A leftover scratch file is a compaction that never finished;
+        // the log it would have replaced is still intact.
+        let _ = fs::remove_file(dir.join(LOG_TMP));
+
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(LOG))?;
+        let mut data = Vec::new();
+        log.read_to_end(&mut data)?;
+        if data.is_empty() {
+            log.write_all(MAGIC)?;
+            log.sync_all()?;
+            sync_dir(&dir);
+            data.extend_from_slice(MAGIC);
+        } else if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+            return Err(StoreError::Corrupt(format!(
+                "{path:?} does not hold a store log"
+            )));
+        }
+
+        let mut state = State {
+            map: BTreeMap::new(),
+            log,
+            _lock: lock,
+            log_len: 0,
+            live_len: 0,
+        };
+        let mut pos = MAGIC.len();
+        while let (Some(len), Some(sum)) = (read_u32(&data, pos), read_u32(&data, pos + 4)) {
+            let start = pos + 8;
+            let Some(payload) = data.get(start..start + len as usize) else { break };
+            if crc32(0, payload) != sum {
+                break;
+            }
+            let ops = decode_ops(payload).ok_or_else(|| {
+                StoreError::Corrupt(format!("{path:?}: malformed record at byte {pos}"))
+            })?;
+            for op in ops {
+                state.apply(op);
+            }
+            pos = start + len as usize;
+        }
+        // Drop a torn tail so the next append follows the last whole
+        // record.
+        if pos < data.len() {
+            state.log.set_len(pos as u64)?;
+            state.log.sync_all()?;
+        }
+        state.log_len = pos as u64;
+
+        Ok(Arc::new(StoreInner {
+            state: Mutex::new(Some(state)),
+            dir,
+            sync,
+            path,
+            id: next_id(),
+        }))
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Run `f` on the open state, or fail with [`StoreError::Closed`].
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut State) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut guard = self.state.lock().unwrap();
+        match guard.as_mut() {
+            Some(state) => f(state),
+            None => Err(StoreError::Closed),
+        }
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        self.with(|s| Ok(s.map.get(key).cloned()))
+    }
+
+    pub fn contains(&self, key: &[u8]) -> Result<bool, StoreError> {
+        self.with(|s| Ok(s.map.contains_key(key)))
+    }
+
+    /// The number of live keys.
+    pub fn count(&self) -> Result<usize, StoreError> {
+        self.with(|s| Ok(s.map.len()))
+    }
+
+    /// Up to `limit` entries between `lo` and `hi`, in key order or (with
+    /// `reverse`) descending. A scan pages through with an exclusive
+    /// bound on the last key it saw.
+    pub fn range(
+        &self,
+        lo: Bound<Vec<u8>>,
+        hi: Bound<Vec<u8>>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Vec<Entry>, StoreError> {
+        self.with(|s| {
+            // `BTreeMap::range` panics on an inverted range; that is
+            // just an empty one here.
+            let inverted = match (&lo, &hi) {
+                (Bound::Included(a), Bound::Included(b)) => a > b,
+                (Bound::Included(a) | Bound::Excluded(a), Bound::Excluded(b))
+                | (Bound::Excluded(a), Bound::Included(b)) => a >= b,
+                _ => false,
+            };
+            if inverted {
+                return Ok(Vec::new());
+            }
+            let range = s.map.range((lo, hi));
+            let pick = |(k, v): (&Vec<u8>, &Vec<u8>)| (k.clone(), v.clone());
+            Ok(if reverse {
+                range.rev().take(limit).map(pick).collect()
+            } else {
+                range.take(limit).map(pick).collect()
+            })
+        })
+    }
+
+    /// Apply `ops` as one atomic log record. Returns, per op, whether
+    /// the key was present beforehand.
+    pub fn write(&self, ops: Vec<Op>) -> Result<Vec<bool>, StoreError> {
+        self.with(|s| {
+            if ops.is_empty() {
+                return Ok(Vec::new());
+            }
+            let mut payload = Vec::new();
+            for op in &ops {
+                encode_op(&mut payload, op);
+            }
+            if payload.len() > u32::MAX as usize {
+                return Err(StoreError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "batch is larger than 4 GiB",
+                )));
+            }
+            let rec = record(&payload);
+            if let Err(e) = s.log.write_all(&rec).and_then(|()| {
+                if self.sync { s.log.sync_data() } else { Ok(()) }
+            }) {
+                // Cut a partial append back off so the next record does
+                // not land after garbage; replay would stop there.
+                let _ = s.log.set_len(s.log_len);
+                return Err(StoreError::Io(e));
+            }
+            s.log_len += rec.len() as u64;
+            let mut existed = Vec::with_capacity(ops.len());
+            for op in ops {
+                existed.push(match &op {
+                    Op::Put(k, _) | Op::Delete(k) => s.map.contains_key(k),
+                });
+                s.apply(op);
+            }
+            if s.log_len > COMPACT_MIN && s.log_len > 2 * (s.live_len + MAGIC.len() as u64) {
+                self.compact_state(s)?;
+            }
+            Ok(existed)
+        })
+    }
+
+    /// Rewrite the log to hold only the live entries.
+    pub fn compact(&self) -> Result<(), StoreError> {
+        self.with(|s| self.compact_state(s))
+    }
+
+    fn compact_state(&self, s: &mut State) -> Result<(), StoreError> {
+        let tmp_path = self.dir.join(LOG_TMP);
+        let mut tmp = File::create(&tmp_path)?;
+        let mut out = MAGIC.to_vec();
+        let mut payload = Vec::new();
+        for (k, v) in &s.map {
+            encode_op(&mut payload, &Op::Put(k.clone(), v.clone()));
+            if payload.len() >= RECORD_TARGET {
+                out.extend_from_slice(&record(&payload));
+                payload.clear();
+                tmp.write_all(&out)?;
+                out.clear();
+            }
+        }
+        if !payload.is_empty() {
+            out.extend_from_slice(&record(&payload));
+        }
+        tmp.write_all(&out)?;
+        tmp.sync_all()?;
+        let len = tmp.metadata()?.len();
+        drop(tmp);
+        let log_path = self.dir.join(LOG);
+        fs::rename(&tmp_path, &log_path)?;
+        sync_dir(&self.dir);
+        s.log = OpenOptions::new().read(true).append(true).open(&log_path)?;
+        s.log_len = len;
+        Ok(())
+    }
+
+    /// Close the handle: flush, then release the log and the lock.
+    /// Idempotent; later operations see [`StoreError::Closed`].
+    pub fn close(&self) -> Result<(), StoreError> {
+        let state = self.state.lock().unwrap().take();
+        if let Some(s) = state {
+            s.log.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "tigr-store-{name}-{}-{}",
+            std::process::id(),
+            next_id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn put(k: &str, v: &str) -> Op {
+        Op::Put(k.as_bytes().to_vec(), v.as_bytes().to_vec())
+    }
+
+    /// A store may cross actor threads (a `spawn`ed worker writing to a
+    /// store the parent opened), so its handle must be `Send + Sync`.
+    #[test]
+    fn store_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<StoreInner>();
+        assert_send_sync::<StoreHandle>();
+        assert_send_sync::<StoreError>();
+    }
+
+    #[test]
+    fn replays_and_truncates_a_torn_tail() {
+        let path = temp_dir("torn");
+        let db = StoreInner::open(path.clone(), true).ok().unwrap();
+        db.write(vec![put("a", "1"), put("b", "2")]).ok().unwrap();
+        db.write(vec![Op::Delete(b"a".to_vec())]).ok().unwrap();
+        db.close().ok().unwrap();
+
+        // Half a record, as a crash mid-append leaves it.
+        let log = Path::new(&path).join(LOG);
+        let good = fs::metadata(&log).unwrap().len();
+        let mut f = OpenOptions::new().append(true).open(&log).unwrap();
+        f.write_all(&record(b"\x01\x01\x00\x00\x00c\x01\x00\x00\x003")[..10]).unwrap();
+        drop(f);
+
+        let db = StoreInner::open(path.clone(), true).ok().unwrap();
+        assert_eq!(db.get(b"a").ok().unwrap(), None);
+        assert_eq!(db.get(b"b").ok().unwrap(), Some(b"2".to_vec()));
+        assert_eq!(fs::metadata(&log).unwrap().len(), good);
+        db.close().ok().unwrap();
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn second_open_is_locked_until_close() {
+        let path = temp_dir("lock");
+        let db = StoreInner::open(path.clone(), true).ok().unwrap();
+        assert!(matches!(StoreInner::open(path.clone(), true), Err(StoreError::Locked(_))));
+        db.close().ok().unwrap();
+        assert!(matches!(db.get(b"x"), Err(StoreError::Closed)));
+        let again = StoreInner::open(path.clone(), true).ok().unwrap();
+        again.close().ok().unwrap();
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn compaction_keeps_live_entries() {
+        let path = temp_dir("compact");
+        let db = StoreInner::open(path.clone(), false).ok().unwrap();
+        for i in 0..100 {
+            db.write(vec![put("k", &i.to_string())]).ok().unwrap();
+        }
+        db.write(vec![put("other", "x")]).ok().unwrap();
+        let log = Path::new(&path).join(LOG);
+        let before = fs::metadata(&log).unwrap().len();
+        db.compact().ok().unwrap();
+        assert!(fs::metadata(&log).unwrap().len() < before);
+        db.close().ok().unwrap();
+
+        let db = StoreInner::open(path.clone(), false).ok().unwrap();
+        assert_eq!(db.get(b"k").ok().unwrap(), Some(b"99".to_vec()));
+        assert_eq!(db.count().ok().unwrap(), 2);
+        db.close().ok().unwrap();
+        let _ = fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn range_pages_in_order_both_ways() {
+        let path = temp_dir("range");
+        let db = StoreInner::open(path.clone(), false).ok().unwrap();
+        db.write(vec![put("c", "3"), put("a", "1"), put("b", "2"), put("d", "4")]).ok().unwrap();
+        let keys = |v: Vec<Entry>| {
+            v.into_iter().map(|(k, _)| String::from_utf8(k).unwrap()).collect::<Vec<_>>()
+        };
+        let fwd = db
+            .range(Bound::Included(b"b".to_vec()), Bound::Unbounded, false, 10)
+            .ok()
+            .unwrap();
+        assert_eq!(keys(fwd), ["b", "c", "d"]);
+        let rev = db
+            .range(Bound::Unbounded, Bound::Excluded(b"d".to_vec()), true, 2)
+            .ok()
+            .unwrap();
+        assert_eq!(keys(rev), ["c", "b"]);
+        let empty = db
+            .range(Bound::Included(b"c".to_vec()), Bound::Excluded(b"a".to_vec()), false, 10)
+            .ok()
+            .unwrap();
+        assert!(empty.is_empty());
+        db.close().ok().unwrap();
+        let _ = fs::remove_dir_all(&path);
+    }
+}
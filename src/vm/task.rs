@@ -1,13 +1,49 @@
 //! Runtime task handle — the result of `spawn` (v0.14 concurrency).
 //!
-//! A `spawn` starts an actor on its own OS thread and immediately
-//! yields a `Task`. The `join` built-in blocks until the actor finishes and
-//! then produces its result — decoded into the joining thread's heap,
-//! or re-raised as the actor's error. The handle is `Arc`-backed and
-//! `Send`, so it can itself be passed between actors.
+//! A `spawn` starts an actor on the actor pool ([`crate::vm::actor_pool`])
+//! and immediately yields a `Task`. The `join` built-in waits until the
+//! actor finishes and then produces its result — decoded into the
+//! joining actor's heap, or re-raised as the actor's error. A join
+//! parks the joiner rather than blocking its thread: it is handed the
+//! outcome when it arrives ([`TaskInner::join_then`]). The handle is
+//! `Arc`-backed and `Send`, so it can itself be passed between actors.
+//!
+//! A task is also the actor's identity for supervision. Every VM owns
+//! one — a spawned actor's is the `Task` `spawn` returned, the main
+//! program's is minted with its VM — and the supervision state lives
+//! here, next to the outcome:
+//!
+//! - **Exit reason.** An actor that finishes has one: `'normal'` for a
+//!   return, otherwise the value `join` would raise — the raised value,
+//!   or the `${kind, message, trace, worker}` error object.
+//! - **Monitors.** `monitor(task, ch)` sends `${down: task, reason}` on
+//!   `ch` when the actor finishes (at once if it already has).
+//! - **Links.** `link(task)` ties the calling actor and `task` both
+//!   ways: when either finishes with a reason other than `'normal'`, an
+//!   *exit signal* carrying that reason is sent to the other. An actor
+//!   that traps exits (`trap_exits(ch)`) gets every signal as an
+//!   `${exit: task, reason}` message on `ch`, `'normal'` ones included.
+//!   Otherwise the signal ends it with the same reason.
+//! - **Kill.** `kill(task)` sends an exit signal that cannot be trapped.
+//!
+//! An exit signal is recorded here and the target is woken through its
+//! interrupt hook; its VM ends it at the next park or turn boundary
+//! without running any `catch`. Scheduling is cooperative, so an actor
+//! busy in a loop that never parks or yields runs on until it does.
+//!
+//! A task also carries the actor's **mailbox**: an unbounded channel
+//! that `send(task, msg)` appends to and the actor's `receive` scans.
+//! It closes when the actor finishes, so later sends are dropped. And
+//! a process-wide **registry** maps names to running actors
+//! (`register`, `whereis`); a name is released when its actor
+//! finishes.
 
-use std::sync::{Arc, Condvar, Mutex};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 
+use crate::vm::channel::{ChannelHandle, ChannelInner, RecvOutcome};
+use crate::vm::offload::Signal;
 use crate::vm::transfer::{Transfer, TransferError};
 
 /// A shared handle to a spawned actor's eventual result.
@@ -17,21 +53,57 @@ pub type TaskHandle = Arc<TaskInner>;
 /// uncaught error rendered to `Send`-able form.
 pub type ActorOutcome = Result<Transfer, TransferError>;
 
+/// A `join` parked by [`TaskInner::join_then`], run when the actor
+/// finishes.
+type ParkedJoin = Box<dyn FnOnce(JoinOutcome) + Send>;
+
 enum TaskState {
-    /// The actor is still running.
-    Pending,
+    /// The actor is still running. Holds the joins parked on it, in
+    /// arrival order: the first is handed the outcome, any others find
+    /// it already taken.
+    Pending(Vec<ParkedJoin>),
     /// The actor finished; its outcome has not been collected yet.
     Ready(ActorOutcome),
     /// The outcome has already been taken by a `join`.
     Taken,
 }
 
+/// The supervision half of a task, under its own lock so a `join`
+/// never contends with link bookkeeping.
+#[derive(Default)]
+struct Supervision {
+    /// Channels to send `${down: task, reason}` on.
+    monitors: Vec<ChannelHandle>,
+    /// Linked actors. Weak: a link must not keep a finished actor's
+    /// task (and its outcome) alive.
+    links: Vec<Weak<TaskInner>>,
+    /// Where exit signals go as messages while the actor traps exits.
+    trap: Option<ChannelHandle>,
+    /// An exit signal the actor has not acted on yet.
+    exit: Option<TransferError>,
+    /// Wakes the actor so it notices `exit`.
+    interrupt: Option<Box<dyn Fn() + Send + Sync>>,
+    /// The exit reason, once the actor has finished.
+    reason: Option<Transfer>,
+    /// Whether the actor holds a name in the registry.
+    named: bool,
+}
+
 pub struct TaskInner {
     state: Mutex<TaskState>,
-    done: Condvar,
+    supervision: Mutex<Supervision>,
+    /// Mirrors `supervision.exit.is_some()`, so the VM can poll for an
+    /// exit signal at every coroutine switch without taking the lock.
+    signalled: AtomicBool,
+    /// Messages `send` to this actor, in arrival order.
+    mailbox: ChannelHandle,
+    /// Parked `select`s with this task as an arm, fired when the actor
+    /// finishes. Only touched with `state` locked, so a select cannot
+    /// register between the finish and the firing.
+    signals: Mutex<Vec<Arc<Signal>>>,
 }
 
-/// The result of [`TaskInner::join`].
+/// The result of [`TaskInner::join_then`].
 pub enum JoinOutcome {
     /// The actor's outcome (first `join` only).
     Outcome(ActorOutcome),
@@ -39,39 +111,322 @@ pub enum JoinOutcome {
     AlreadyJoined,
 }
 
+/// The reason an actor that finished with `outcome` exited for.
+fn exit_reason(outcome: &ActorOutcome) -> Transfer {
+    match outcome {
+        Ok(_) => Transfer::Str("normal".to_string()),
+        Err(te) => te.clone().into_value(),
+    }
+}
+
+fn is_normal(reason: &Transfer) -> bool {
+    matches!(reason, Transfer::Str(s) if s == "normal")
+}
+
+/// `${<tag>: task, reason}` — the message of a monitor or trapped exit.
+fn notice(tag: &str, task: &TaskHandle, reason: Transfer) -> Transfer {
+    Transfer::Object(vec![
+        (tag.to_string(), Transfer::Task(task.clone())),
+        ("reason".to_string(), reason),
+    ])
+}
+
+/// Send `msg` on `ch`, dropping it if the channel is closed.
+fn post(ch: &ChannelHandle, msg: Transfer) {
+    ch.send_then(msg, Box::new(|_| {}));
+}
+
+/// The process-wide name registry.
+fn registry() -> &'static Mutex<HashMap<String, TaskHandle>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, TaskHandle>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Why [`register`] refused a name.
+pub enum RegisterError {
+    /// Another running actor holds the name.
+    Taken,
+    /// The actor has already finished.
+    Finished,
+}
+
+/// Register `task` under `name`. An actor may hold several names; a
+/// name is free again once its actor finishes or it is unregistered.
+pub fn register(name: &str, task: &TaskHandle) -> Result<(), RegisterError> {
+    let mut names = registry().lock().unwrap();
+    if names.contains_key(name) {
+        return Err(RegisterError::Taken);
+    }
+    let mut sup = task.supervision.lock().unwrap();
+    if sup.reason.is_some() {
+        return Err(RegisterError::Finished);
+    }
+    sup.named = true;
+    drop(sup);
+    names.insert(name.to_string(), task.clone());
+    Ok(())
+}
+
+/// Release `name`. Returns `false` if it was not registered.
+pub fn unregister(name: &str) -> bool {
+    registry().lock().unwrap().remove(name).is_some()
+}
+
+/// The running actor registered as `name`, if any.
+pub fn whereis(name: &str) -> Option<TaskHandle> {
+    registry().lock().unwrap().get(name).cloned()
+}
+
 impl TaskInner {
     pub fn new() -> TaskHandle {
         Arc::new(TaskInner {
-            state: Mutex::new(TaskState::Pending),
-            done: Condvar::new(),
+            state: Mutex::new(TaskState::Pending(Vec::new())),
+            supervision: Mutex::new(Supervision::default()),
+            signalled: AtomicBool::new(false),
+            mailbox: ChannelInner::new(None),
+            signals: Mutex::new(Vec::new()),
         })
     }
 
-    /// Called by the worker thread when the actor finishes.
-    pub fn complete(&self, outcome: ActorOutcome) {
+    /// The actor's mailbox.
+    pub fn mailbox(&self) -> &ChannelHandle {
+        &self.mailbox
+    }
+
+    /// Called on the actor's pool thread when the actor finishes. Hands
+    /// the outcome to a parked join, then notifies monitors and sends
+    /// the exit reason to linked actors. Closes the mailbox and releases
+    /// the actor's registered names.
+    pub fn complete(self: &Arc<Self>, outcome: ActorOutcome) {
+        let reason = exit_reason(&outcome);
+        let (monitors, links, named) = {
+            let mut sup = self.supervision.lock().unwrap();
+            sup.reason = Some(reason.clone());
+            sup.trap = None;
+            sup.exit = None;
+            self.signalled.store(false, Ordering::Release);
+            sup.interrupt = None;
+            (
+                std::mem::take(&mut sup.monitors),
+                std::mem::take(&mut sup.links),
+                sup.named,
+            )
+        };
+        // Unread messages are dropped: nothing can receive them now, and
+        // one holding this task would keep it alive.
+        self.mailbox.close();
+        while let Some(RecvOutcome::Message(_)) = self.mailbox.try_recv() {}
+        if named {
+            registry().lock().unwrap().retain(|_, t| !Arc::ptr_eq(t, self));
+        }
         let mut g = self.state.lock().unwrap();
-        *g = TaskState::Ready(outcome);
-        drop(g);
-        self.done.notify_all();
+        let joiners = match &mut *g {
+            TaskState::Pending(joiners) => std::mem::take(joiners),
+            _ => Vec::new(),
+        };
+        let signals = std::mem::take(&mut *self.signals.lock().unwrap());
+        let mut joiners = joiners.into_iter();
+        if let Some(first) = joiners.next() {
+            *g = TaskState::Taken;
+            drop(g);
+            first(JoinOutcome::Outcome(outcome));
+            for rest in joiners {
+                rest(JoinOutcome::AlreadyJoined);
+            }
+        } else {
+            *g = TaskState::Ready(outcome);
+            drop(g);
+        }
+        for signal in signals {
+            signal.fire();
+        }
+        for ch in &monitors {
+            post(ch, notice("down", self, reason.clone()));
+        }
+        for other in links.iter().filter_map(Weak::upgrade) {
+            other.drop_link(self);
+            other.exit_signal(self, reason.clone());
+        }
     }
 
-    /// Block until the actor finishes, then hand back its outcome.
-    /// A second join reports [`JoinOutcome::AlreadyJoined`].
-    pub fn join(&self) -> JoinOutcome {
+    /// Collect the actor's outcome. `done` runs with it right away if
+    /// the actor has finished, otherwise on the actor's thread as it
+    /// finishes. A second join reports [`JoinOutcome::AlreadyJoined`].
+    pub fn join_then(&self, done: ParkedJoin) {
         let mut g = self.state.lock().unwrap();
-        loop {
-            match &*g {
-                TaskState::Pending => {
-                    g = self.done.wait(g).unwrap();
-                }
-                TaskState::Taken => return JoinOutcome::AlreadyJoined,
-                TaskState::Ready(_) => {
-                    match std::mem::replace(&mut *g, TaskState::Taken) {
-                        TaskState::Ready(o) => return JoinOutcome::Outcome(o),
-                        _ => unreachable!(),
+        match &mut *g {
+            TaskState::Pending(joiners) => joiners.push(done),
+            TaskState::Taken => {
+                drop(g);
+                done(JoinOutcome::AlreadyJoined);
+            }
+            TaskState::Ready(_) => {
+                let TaskState::Ready(o) =
+                    std::mem::replace(&mut *g, TaskState::Taken)
+                else {
+                    unreachable!()
+                };
+                drop(g);
+                done(JoinOutcome::Outcome(o));
+            }
+        }
+    }
+
+    /// Has the actor finished?
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.state.lock().unwrap(), TaskState::Pending(_))
+    }
+
+    /// Fire `signal` once the actor has finished — at once if it
+    /// already has. Backs a `select` with this task as an arm, which
+    /// then joins it like `join` would.
+    pub fn notify(&self, signal: &Arc<Signal>) {
+        let g = self.state.lock().unwrap();
+        if !matches!(*g, TaskState::Pending(_)) {
+            drop(g);
+            signal.fire();
+            return;
+        }
+        let mut signals = self.signals.lock().unwrap();
+        signals.retain(|s| !s.is_spent());
+        signals.push(Arc::clone(signal));
+    }
+
+    /// Install the hook that wakes this task's actor when an exit
+    /// signal arrives.
+    pub(crate) fn set_interrupt(&self, interrupt: impl Fn() + Send + Sync + 'static) {
+        self.supervision.lock().unwrap().interrupt = Some(Box::new(interrupt));
+    }
+
+    /// Send `${down: self, reason}` on `ch` when the actor finishes — at
+    /// once if it already has.
+    pub fn monitor(self: &Arc<Self>, ch: ChannelHandle) {
+        let mut sup = self.supervision.lock().unwrap();
+        match &sup.reason {
+            Some(reason) => {
+                let msg = notice("down", self, reason.clone());
+                drop(sup);
+                post(&ch, msg);
+            }
+            None => sup.monitors.push(ch),
+        }
+    }
+
+    /// Link this task's actor and `other` both ways. Linking to an actor
+    /// that already finished delivers its exit signal at once.
+    pub fn link(self: &Arc<Self>, other: &TaskHandle) {
+        if Arc::ptr_eq(self, other) {
+            return;
+        }
+        let finished = {
+            let mut sup = other.supervision.lock().unwrap();
+            match &sup.reason {
+                Some(reason) => Some(reason.clone()),
+                None => {
+                    if !sup.links.iter().any(|l| l.as_ptr() == Arc::as_ptr(self)) {
+                        sup.links.push(Arc::downgrade(self));
                     }
+                    None
                 }
             }
+        };
+        if let Some(reason) = finished {
+            self.exit_signal(other, reason);
+            return;
+        }
+        let mut sup = self.supervision.lock().unwrap();
+        if sup.reason.is_none()
+            && !sup.links.iter().any(|l| l.as_ptr() == Arc::as_ptr(other))
+        {
+            sup.links.push(Arc::downgrade(other));
+        }
+    }
+
+    /// Remove a link in both directions; a no-op if there is none.
+    pub fn unlink(self: &Arc<Self>, other: &TaskHandle) {
+        self.drop_link(other);
+        other.drop_link(self);
+    }
+
+    fn drop_link(&self, other: &TaskHandle) {
+        let mut sup = self.supervision.lock().unwrap();
+        sup.links.retain(|l| l.as_ptr() != Arc::as_ptr(other));
+    }
+
+    /// Route exit signals to `ch` as messages (`Some`), or stop trapping
+    /// them (`None`).
+    pub fn trap_exits(&self, ch: Option<ChannelHandle>) {
+        self.supervision.lock().unwrap().trap = ch;
+    }
+
+    /// Whether the actor has links, and so may be ended by an exit
+    /// signal at any park.
+    pub fn has_links(&self) -> bool {
+        !self.supervision.lock().unwrap().links.is_empty()
+    }
+
+    /// End the actor with reason `${kind: 'killed', ...}`, trapping or
+    /// not. Returns `false` if it had already finished.
+    pub fn kill(&self) -> bool {
+        let mut sup = self.supervision.lock().unwrap();
+        if sup.reason.is_some() {
+            return false;
+        }
+        if sup.exit.is_none() {
+            sup.exit = Some(TransferError {
+                kind_tag: "killed".to_string(),
+                message: "actor was killed".to_string(),
+                rendered_trace: String::new(),
+                raised: None,
+            });
+            self.signalled.store(true, Ordering::Release);
+        }
+        if let Some(interrupt) = &sup.interrupt {
+            interrupt();
+        }
+        true
+    }
+
+    /// The exit signal of linked actor `from`, which finished for
+    /// `reason`.
+    fn exit_signal(&self, from: &TaskHandle, reason: Transfer) {
+        let mut sup = self.supervision.lock().unwrap();
+        if sup.reason.is_some() {
+            return;
+        }
+        if let Some(ch) = sup.trap.clone() {
+            drop(sup);
+            post(&ch, notice("exit", from, reason));
+            return;
+        }
+        if is_normal(&reason) || sup.exit.is_some() {
+            return;
+        }
+        sup.exit = Some(TransferError {
+            kind_tag: "raised".to_string(),
+            message: "a linked actor exited".to_string(),
+            rendered_trace: String::new(),
+            raised: Some(reason),
+        });
+        self.signalled.store(true, Ordering::Release);
+        if let Some(interrupt) = &sup.interrupt {
+            interrupt();
         }
     }
+
+    /// Take a pending exit signal: the outcome the actor must now end
+    /// with.
+    pub(crate) fn take_exit(&self) -> Option<TransferError> {
+        if !self.exit_pending() {
+            return None;
+        }
+        let mut sup = self.supervision.lock().unwrap();
+        self.signalled.store(false, Ordering::Release);
+        sup.exit.take()
+    }
+
+    /// Whether an exit signal is pending, without taking it.
+    pub(crate) fn exit_pending(&self) -> bool {
+        self.signalled.load(Ordering::Acquire)
+    }
 }
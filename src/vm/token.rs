@@ -61,6 +61,7 @@ pub enum Token {
     Match,
     Spawn,
     Select,
+    Receive,
     Parallel,
     Go,
     Yield,
@@ -155,6 +156,7 @@ impl fmt::Display for Token {
             Return => f.write_str("return"),
             Spawn => f.write_str("spawn"),
             Select => f.write_str("select"),
+            Receive => f.write_str("receive"),
             Parallel => f.write_str("parallel"),
             Go => f.write_str("go"),
             Yield => f.write_str("yield"),
@@ -0,0 +1,313 @@
+//! Concurrency tracing: scheduler events as a Chrome trace.
+//!
+//! When a server stalls, the question is which coroutine was parked on
+//! what, and since when. With tracing on, every actor records its
+//! green threads' state changes — running, ready, parked in a `join`,
+//! a `wait`, or a blocking call — together with the lifecycle of each
+//! call it hands to the worker pool or the async-IO reactor, and the
+//! spawn and exit of every actor. [`finish`] renders the lot in the
+//! Chrome trace-event JSON format, which Perfetto
+//! (<https://ui.perfetto.dev>) and `chrome://tracing` open directly.
+//!
+//! The layout follows tigr's own structure:
+//!
+//! * each actor is a *process* track, named `main` for the program
+//!   itself and after the spawned function for the others;
+//! * each green thread is a *thread* track within it, named after the
+//!   function its `go` started (`go worker #3`), with one slice per
+//!   state it was in;
+//! * each offloaded call is an *async* slice from submission to
+//!   completion, with a step where a worker picks it up or the reactor
+//!   registers it;
+//! * a `spawn` is a flow arrow from the spawning coroutine to the new
+//!   actor's main track.
+//!
+//! The CLI's `tigr run --trace out.json` turns it on for one run; an
+//! embedder calls `Session::start_trace` and `Session::finish_trace`. Tracing is
+//! process-wide and off by default; while off, every hook is a single
+//! comparison against zero.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Is a trace being recorded?
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The next actor (trace process) id; `0` means "not traced".
+static NEXT_ACTOR: AtomicU64 = AtomicU64::new(1);
+
+static TRACER: Mutex<Option<Tracer>> = Mutex::new(None);
+
+/// The trace recorded so far.
+struct Tracer {
+    origin: Instant,
+    /// Finished events, each already rendered as a JSON object.
+    events: Vec<String>,
+    /// The state slice each green thread is in: its name and start.
+    open: HashMap<(u64, u32), (String, f64)>,
+    /// In-flight offloaded calls: the coroutine and the label each
+    /// began with, keyed by actor and job id.
+    ops: HashMap<(u64, u64), (u32, &'static str)>,
+    actors: Vec<(u64, String)>,
+    threads: Vec<(u64, u32, String)>,
+    /// Spawned actors whose flow arrow has not landed yet.
+    flows: HashSet<u64>,
+}
+
+impl Tracer {
+    /// Microseconds since the trace started.
+    fn now(&self) -> f64 {
+        self.origin.elapsed().as_secs_f64() * 1e6
+    }
+
+    fn close(&mut self, actor: u64, tid: u32, at: f64) {
+        if let Some((name, start)) = self.open.remove(&(actor, tid)) {
+            self.events.push(format!(
+                r#"{{"name":{},"cat":"sched","ph":"X","ts":{start:.3},"dur":{:.3},"pid":{actor},"tid":{tid}}}"#,
+                json_str(&name),
+                at - start,
+            ));
+        }
+    }
+
+    fn instant(&mut self, actor: u64, tid: u32, cat: &str, name: &str, at: f64) {
+        self.events.push(format!(
+            r#"{{"name":{},"cat":"{cat}","ph":"i","s":"t","ts":{at:.3},"pid":{actor},"tid":{tid}}}"#,
+            json_str(name),
+        ));
+    }
+
+    fn async_event(&mut self, ph: char, actor: u64, job: u64, name: &str, at: f64) {
+        let Some(&(tid, label)) = self.ops.get(&(actor, job)) else {
+            return;
+        };
+        // A step is named for itself; the ends carry the call's label,
+        // and the closing one its result.
+        let (name, args) = match ph {
+            'n' => (name, String::new()),
+            'e' => (label, format!(r#","args":{{"result":{}}}"#, json_str(name))),
+            _ => (label, String::new()),
+        };
+        self.events.push(format!(
+            r#"{{"name":{},"cat":"io","ph":"{ph}","id":"{actor}.{job}","ts":{at:.3},"pid":{actor},"tid":{tid}{args}}}"#,
+            json_str(name),
+        ));
+    }
+
+    /// Close every open slice and render the trace.
+    fn render(mut self) -> String {
+        let at = self.now();
+        let mut open: Vec<(u64, u32)> = self.open.keys().copied().collect();
+        open.sort_unstable();
+        for (actor, tid) in open {
+            self.close(actor, tid, at);
+        }
+        let mut meta = Vec::new();
+        for (actor, name) in &self.actors {
+            meta.push(format!(
+                r#"{{"name":"process_name","ph":"M","pid":{actor},"tid":0,"args":{{"name":{}}}}}"#,
+                json_str(name),
+            ));
+            meta.push(format!(
+                r#"{{"name":"process_sort_index","ph":"M","pid":{actor},"tid":0,"args":{{"sort_index":{actor}}}}}"#,
+            ));
+        }
+        for (actor, tid, name) in &self.threads {
+            meta.push(format!(
+                r#"{{"name":"thread_name","ph":"M","pid":{actor},"tid":{tid},"args":{{"name":{}}}}}"#,
+                json_str(name),
+            ));
+            meta.push(format!(
+                r#"{{"name":"thread_sort_index","ph":"M","pid":{actor},"tid":{tid},"args":{{"sort_index":{tid}}}}}"#,
+            ));
+        }
+        meta.append(&mut self.events);
+        format!("{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n", meta.join(",\n"))
+    }
+}
+
+/// Run `f` on the tracer, if a trace is being recorded and `actor` is
+/// part of it.
+fn with(actor: u64, f: impl FnOnce(&mut Tracer)) {
+    if actor == 0 || !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(t) = TRACER.lock().unwrap().as_mut() {
+        f(t);
+    }
+}
+
+/// Start recording a trace, discarding any earlier one. VMs made from
+/// now on are traced.
+pub fn start() {
+    *TRACER.lock().unwrap() = Some(Tracer {
+        origin: Instant::now(),
+        events: Vec::new(),
+        open: HashMap::new(),
+        ops: HashMap::new(),
+        actors: Vec::new(),
+        threads: Vec::new(),
+        flows: HashSet::new(),
+    });
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Is a trace being recorded?
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Stop recording and render the trace as Chrome trace-event JSON.
+/// Slices still open — a coroutine parked when the program ended —
+/// end at this moment. Empty if no trace was started.
+pub fn finish() -> String {
+    ENABLED.store(false, Ordering::Release);
+    match TRACER.lock().unwrap().take() {
+        Some(t) => t.render(),
+        None => String::new(),
+    }
+}
+
+/// [`finish`] the trace and write it to `path`.
+pub fn write(path: &std::path::Path) -> std::io::Result<()> {
+    std::fs::write(path, finish())
+}
+
+/// Allocate a trace id for a new actor named `name`, with its main
+/// coroutine (#0) named `main`. `0` — untraced — when no trace is
+/// being recorded.
+pub(crate) fn new_actor(name: &str) -> u64 {
+    if !enabled() {
+        return 0;
+    }
+    let id = NEXT_ACTOR.fetch_add(1, Ordering::Relaxed);
+    with(id, |t| {
+        t.actors.push((id, name.to_string()));
+        t.threads.push((id, 0, "main".to_string()));
+    });
+    id
+}
+
+/// Name green thread `tid`'s track in `actor`.
+pub(crate) fn name_thread(actor: u64, tid: u32, name: impl FnOnce() -> String) {
+    with(actor, |t| t.threads.push((actor, tid, name())));
+}
+
+/// Green thread `tid` of `actor` is now in state `name`: end the slice
+/// for the state it was in and start one for this.
+pub(crate) fn state(actor: u64, tid: u32, name: impl FnOnce() -> String) {
+    with(actor, |t| {
+        let at = t.now();
+        t.close(actor, tid, at);
+        t.open.insert((actor, tid), (name(), at));
+        if tid == 0 && t.flows.remove(&actor) {
+            t.events.push(format!(
+                r#"{{"name":"spawn","cat":"actor","ph":"f","bp":"e","id":{actor},"ts":{at:.3},"pid":{actor},"tid":0}}"#,
+            ));
+        }
+    });
+}
+
+/// Green thread `tid` of `actor` parked on its offloaded call `job`:
+/// the state is named after the call, e.g. `read` or `recv`.
+pub(crate) fn park_io(actor: u64, tid: u32, job: u64) {
+    with(actor, |t| {
+        let at = t.now();
+        let label = t.ops.get(&(actor, job)).map_or("io", |&(_, label)| label);
+        t.close(actor, tid, at);
+        t.open.insert((actor, tid), (label.to_string(), at));
+    });
+}
+
+/// Green thread `tid` of `actor` finished.
+pub(crate) fn end(actor: u64, tid: u32) {
+    with(actor, |t| {
+        let at = t.now();
+        t.close(actor, tid, at);
+    });
+}
+
+/// Coroutine `tid` of `parent` spawned actor `child`: an instant on the
+/// spawner's track and a flow arrow to the child's first slice.
+pub(crate) fn spawn(parent: u64, tid: u32, child: u64) {
+    with(parent, |t| {
+        let at = t.now();
+        let name = t
+            .actors
+            .iter()
+            .find(|(id, _)| *id == child)
+            .map_or_else(|| "spawn".to_string(), |(_, n)| format!("spawn {n}"));
+        t.instant(parent, tid, "actor", &name, at);
+        if child != 0 {
+            t.events.push(format!(
+                r#"{{"name":"spawn","cat":"actor","ph":"s","id":{child},"ts":{at:.3},"pid":{parent},"tid":{tid}}}"#,
+            ));
+            t.flows.insert(child);
+        }
+    });
+}
+
+/// Actor `actor` finished: end every slice still open on its tracks.
+pub(crate) fn exit(actor: u64) {
+    with(actor, |t| {
+        let at = t.now();
+        let mut tids: Vec<u32> =
+            t.open.keys().filter(|(a, _)| *a == actor).map(|&(_, tid)| tid).collect();
+        tids.sort_unstable();
+        for tid in tids {
+            t.close(actor, tid, at);
+        }
+        t.instant(actor, 0, "actor", "exit", at);
+    });
+}
+
+/// Coroutine `tid` of `actor` handed call `label` (a native's name) off
+/// as job `job`.
+pub(crate) fn op_begin(actor: u64, tid: u32, job: u64, label: &'static str) {
+    with(actor, |t| {
+        let at = t.now();
+        t.ops.insert((actor, job), (tid, label));
+        t.async_event('b', actor, job, label, at);
+    });
+}
+
+/// Job `job` of `actor` reached lifecycle step `step`.
+pub(crate) fn op_step(actor: u64, job: u64, step: &str) {
+    with(actor, |t| {
+        let at = t.now();
+        t.async_event('n', actor, job, step, at);
+    });
+}
+
+/// Job `job` of `actor` posted its completion; `failed` if it raises.
+pub(crate) fn op_end(actor: u64, job: u64, failed: bool) {
+    with(actor, |t| {
+        let at = t.now();
+        t.async_event('e', actor, job, if failed { "failed" } else { "done" }, at);
+        t.ops.remove(&(actor, job));
+    });
+}
+
+/// `s` as a JSON string literal.
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -8,11 +8,14 @@
 //! receiver thread's heap, allocating fresh handles.
 //!
 //! Sendable, deep-copied: the primitives, `Str`, `Bytes`, `Range`,
-//! `BigInt`, and the four collections. A closure is sendable iff every
-//! captured upvalue is itself sendable — its compiled code rides along
-//! as a shared `Arc<Function>`. Not sendable: an iterator, a native
+//! `BigInt`, and the four collections. A `Shared.of` value is not
+//! copied at all: like a channel, it crosses by handle clone. A closure
+//! is sendable iff every captured upvalue is itself sendable — its
+//! compiled code rides along as a shared `Arc<Function>`. Not sendable: an iterator, a native
 //! function, or a closure with still-open captures — these raise a
-//! catchable `not_sendable`. A cyclic collection raises `cycle`.
+//! catchable `not_sendable`, except under [`encode_snapshot`], which
+//! copies an open capture's current value. A cyclic collection raises
+//! `cycle`.
 
 // The encode/decode API and `Transfer` types are consumed by the
 // `Channel` module (Phase 3) and the `spawn` opcode (Phase 4). Until
@@ -25,9 +28,13 @@ use std::sync::Arc;
 use indexmap::{IndexMap, IndexSet};
 use num_bigint::BigInt;
 
+use crate::vm::atomic::AtomicHandle;
 use crate::vm::channel::ChannelHandle;
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
 use crate::vm::file_handle::FileHandle;
+use crate::vm::shared::SharedHandle;
+use crate::vm::shared_map::SharedMapHandle;
+use crate::vm::store::StoreHandle;
 use crate::vm::socket::SocketHandle;
 use crate::vm::task::TaskHandle;
 use crate::vm::gc::{
@@ -46,7 +53,9 @@ pub enum TransferKey {
 
 /// The `Send`-able mirror of a [`Value`]. Built by [`encode`], consumed
 /// by [`decode`]. Carries no `GcRef` and no heap-bound `Rc`, so it is
-/// safe to move between threads.
+/// safe to move between threads. Cloning copies the tree; handles
+/// clone their `Arc`.
+#[derive(Clone)]
 pub enum Transfer {
     Null,
     Bool(bool),
@@ -74,11 +83,23 @@ pub enum Transfer {
     /// A file handle (`IO.open`) — `Arc`-backed and `Send`; crosses by
     /// clone, so a `spawn`ed worker can read a file the parent opened.
     File(FileHandle),
+    /// A store handle (`Store.open`) — `Arc`-backed and `Send`; crosses
+    /// by clone, so a `spawn`ed worker shares the parent's open store.
+    Store(StoreHandle),
+    /// A frozen collection (`Shared.of`) — immutable and `Send`, so it
+    /// crosses by clone: every actor reads the one tree, uncopied.
+    Shared(SharedHandle),
+    /// An atomic counter — `Arc`-backed and `Send`; crosses by clone,
+    /// so every actor holding it updates the one counter.
+    Atomic(AtomicHandle),
+    /// A shared map — likewise `Arc`-backed; crosses by clone.
+    SharedMap(SharedMapHandle),
 }
 
 /// A worker actor's error, rendered to `Send`-able form so it can cross
 /// back to the parent — the worker's `SourceMap` is `Rc` and cannot
 /// itself be sent. Used by `join` / `parallel[]` (v0.14 Phase 4).
+#[derive(Clone)]
 pub struct TransferError {
     /// Stable snake-case tag (`RuntimeErrorKind::kind_tag`).
     pub kind_tag: String,
@@ -91,6 +112,24 @@ pub struct TransferError {
     pub raised: Option<Transfer>,
 }
 
+impl TransferError {
+    /// The value a joiner sees: what the worker raised, verbatim, or
+    /// for a built-in error a `${kind, message, trace, worker: true}`
+    /// object. It is also the actor's exit reason (see
+    /// [`crate::vm::task`]).
+    pub fn into_value(self) -> Transfer {
+        if let Some(raised) = self.raised {
+            return raised;
+        }
+        Transfer::Object(vec![
+            ("kind".to_string(), Transfer::Str(self.kind_tag)),
+            ("message".to_string(), Transfer::Str(self.message)),
+            ("trace".to_string(), Transfer::Str(self.rendered_trace)),
+            ("worker".to_string(), Transfer::Bool(true)),
+        ])
+    }
+}
+
 impl std::fmt::Debug for Transfer {
     /// Variant-name only — `Function` is not `Debug`, and this exists
     /// just so `Result<Transfer, _>::unwrap_err` works in tests.
@@ -113,6 +152,10 @@ impl std::fmt::Debug for Transfer {
             Transfer::Task(_) => "Task",
             Transfer::Socket(_) => "Socket",
             Transfer::File(_) => "File",
+            Transfer::Store(_) => "Store",
+            Transfer::Shared(_) => "Shared",
+            Transfer::Atomic(_) => "Atomic",
+            Transfer::SharedMap(_) => "SharedMap",
         };
         write!(f, "Transfer::{name}")
     }
@@ -130,11 +173,14 @@ fn not_sendable(what: &str) -> RuntimeError {
 /// can route through. `Set` is excluded — its keys are primitives, so a
 /// set can never contain a collection and never forms a cycle.
 #[derive(Default)]
-struct CycleGuard {
+struct CycleGuard<'a> {
     arrays: Vec<GcRef<ArrayKind>>,
     objects: Vec<GcRef<ObjectKind>>,
     maps: Vec<GcRef<MapKind>>,
     closures: Vec<GcRef<ClosureKind>>,
+    /// Reads an open upvalue's current value, for [`encode_snapshot`].
+    /// `None` makes an open capture `not_sendable`.
+    open: Option<&'a dyn Fn(u32, usize) -> Value>,
 }
 
 /// Encode a [`Value`] into a `Send`-able [`Transfer`]. Runs on the
@@ -144,6 +190,17 @@ pub fn encode(v: &Value) -> Result<Transfer, RuntimeError> {
     encode_inner(v, &mut CycleGuard::default())
 }
 
+/// [`encode`], except that a closure's still-open captures — at any
+/// depth — are encoded as their current values, read through `open`
+/// (`owner`, `slot`). `spawn` uses this: the actor gets a snapshot of
+/// what its function captured, nested functions included.
+pub fn encode_snapshot(
+    v: &Value,
+    open: &dyn Fn(u32, usize) -> Value,
+) -> Result<Transfer, RuntimeError> {
+    encode_inner(v, &mut CycleGuard { open: Some(open), ..CycleGuard::default() })
+}
+
 fn key_to_transfer(k: &MapKey) -> TransferKey {
     match k {
         MapKey::Null => TransferKey::Null,
@@ -153,7 +210,7 @@ fn key_to_transfer(k: &MapKey) -> TransferKey {
     }
 }
 
-fn encode_inner(v: &Value, g: &mut CycleGuard) -> Result<Transfer, RuntimeError> {
+fn encode_inner(v: &Value, g: &mut CycleGuard<'_>) -> Result<Transfer, RuntimeError> {
     Ok(match v {
         Value::Null => Transfer::Null,
         Value::Bool(b) => Transfer::Bool(*b),
@@ -233,9 +290,10 @@ fn encode_inner(v: &Value, g: &mut CycleGuard) -> Result<Transfer, RuntimeError>
             };
             let mut ups = Vec::with_capacity(cells.len());
             for cell in &cells {
-                let captured = match &*cell.borrow() {
-                    Upvalue::Closed(val) => val.clone(),
-                    Upvalue::Open { .. } => {
+                let captured = match (&*cell.borrow(), g.open) {
+                    (Upvalue::Closed(val), _) => val.clone(),
+                    (Upvalue::Open { owner, slot }, Some(read)) => read(*owner, *slot),
+                    (Upvalue::Open { .. }, None) => {
                         return Err(not_sendable(
                             "a function with live captured variables",
                         ));
@@ -250,6 +308,10 @@ fn encode_inner(v: &Value, g: &mut CycleGuard) -> Result<Transfer, RuntimeError>
         Value::Task(h) => Transfer::Task(h.clone()),
         Value::Socket(h) => Transfer::Socket(h.clone()),
         Value::File(h) => Transfer::File(h.clone()),
+        Value::Store(h) => Transfer::Store(h.clone()),
+        Value::Shared(h) => Transfer::Shared(h.clone()),
+        Value::Atomic(h) => Transfer::Atomic(h.clone()),
+        Value::SharedMap(h) => Transfer::SharedMap(h.clone()),
         Value::Iter(_) => return Err(not_sendable("an iterator")),
         Value::Generator(_) => return Err(not_sendable("a generator")),
         Value::GreenHandle(_) => return Err(not_sendable("a green thread")),
@@ -320,6 +382,10 @@ pub fn decode(t: Transfer) -> Value {
         Transfer::Task(h) => Value::Task(h),
         Transfer::Socket(h) => Value::Socket(h),
         Transfer::File(h) => Value::File(h),
+        Transfer::Store(h) => Value::Store(h),
+        Transfer::Shared(h) => Value::Shared(h),
+        Transfer::Atomic(h) => Value::Atomic(h),
+        Transfer::SharedMap(h) => Value::SharedMap(h),
     }
 }
 
@@ -449,6 +515,28 @@ mod tests {
         assert_eq!(err.kind.kind_tag(), "not_sendable");
     }
 
+    #[test]
+    fn snapshot_reads_open_upvalues_in_nested_closures() {
+        let f = Arc::new(Function {
+            arity: 0,
+            has_rest: false,
+            chunk: Chunk::new(),
+            upvalues: Vec::new(),
+            is_generator: false,
+            name: None,
+        });
+        let up = gc::alloc_upvalue(Upvalue::Open { owner: 0, slot: 3 });
+        let inner = gc::alloc_closure(Closure { function: f, upvalues: vec![up] });
+        let wrapped = Value::Array(gc::alloc_array(vec![Value::Function(inner)]));
+        let t = encode_snapshot(&wrapped, &|_, slot| Value::Int(slot as i64 * 10))
+            .expect("open captures are snapshotted");
+        let Transfer::Array(items) = t else { panic!("expected an array") };
+        let Transfer::Closure { upvalues, .. } = &items[0] else {
+            panic!("expected a closure")
+        };
+        assert!(matches!(upvalues[..], [Transfer::Int(30)]));
+    }
+
     #[test]
     fn iterator_and_native_fn_are_not_sendable() {
         let it = Value::Iter(gc::alloc_iter(IterState::Range {
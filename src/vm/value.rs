@@ -16,9 +16,13 @@ use std::sync::Arc;
 
 use num_bigint::BigInt as BigIntData;
 
+use crate::vm::atomic::AtomicHandle;
 use crate::vm::channel::ChannelHandle;
 use crate::vm::chunk::Chunk;
 use crate::vm::file_handle::FileHandle;
+use crate::vm::shared::SharedHandle;
+use crate::vm::shared_map::SharedMapHandle;
+use crate::vm::store::StoreHandle;
 use crate::vm::socket::SocketHandle;
 use crate::vm::task::TaskHandle;
 use crate::vm::error::{RuntimeError, RuntimeErrorKind};
@@ -92,6 +96,11 @@ pub enum Value {
     // so a `spawn`ed worker can read/write a file the parent opened.
     File(FileHandle),
 
+    // An open key-value store (`Store.open`). Like `File`: `Arc`-backed,
+    // `Send`, a GC leaf, identity equality — a `spawn`ed worker can
+    // write to a store the parent opened.
+    Store(StoreHandle),
+
     // green threads — a paused generator coroutine. Produced by calling
     // a `gen fn`; never directly visible to tigr code, which only sees
     // the `${ next: fn() }` iterator object wrapping it. GC-managed:
@@ -116,6 +125,19 @@ pub enum Value {
     // wait on and anything (including a host) can complete. Like
     // `GreenHandle`: GC-managed, intra-actor only, identity equality.
     Deferred(GcRef<DeferredKind>),
+
+    // A frozen, `Arc`-backed collection (`Shared.of`). `Send` and a GC
+    // leaf like `Channel`, but compared structurally: actors read and
+    // index it in place, and it crosses an actor boundary by handle
+    // clone rather than deep copy. See `crate::vm::shared`.
+    Shared(SharedHandle),
+
+    // An atomic integer counter (`Atomic.new`) and a concurrent map
+    // (`SharedMap.new`): mutable state shared between actors. Like
+    // `Channel`: `Arc`-backed, `Send`, a GC leaf, identity equality.
+    // See `crate::vm::atomic` and `crate::vm::shared_map`.
+    Atomic(AtomicHandle),
+    SharedMap(SharedMapHandle),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -234,6 +256,10 @@ pub enum IterState {
         bytes: GcRef<BytesKind>,
         index: usize,
     },
+    Shared {
+        node: SharedHandle,
+        index: usize,
+    },
     String {
         string: Arc<str>,
         char_index: usize,
@@ -326,6 +352,11 @@ impl IterState {
                 *index += 1;
                 Some((counter, value))
             }
+            IterState::Shared { node, index } => {
+                let entry = node.entry(*index)?;
+                *index += 1;
+                Some(entry)
+            }
             IterState::String { string, char_index, byte_index } => {
                 let rest = &string[*byte_index..];
                 let mut iter = rest.chars();
@@ -426,8 +457,10 @@ pub enum WaitKind {
 /// `Blocking` natives wrap a call that may wait (a child process, file
 /// or network IO); the VM can offload them to a worker pool so a green
 /// thread doing IO does not stall its siblings (see
-/// [`crate::vm::offload`]). `Park` natives cooperatively suspend the
-/// running green thread on the clock (`wait`, `GameTime.wait_frame`).
+/// [`crate::vm::offload`]). `Handoff` natives wait on another actor
+/// and park without any thread at all. `Park` natives cooperatively
+/// suspend the running green thread on the clock (`wait`,
+/// `GameTime.wait_frame`). `Actor` natives act for the calling actor.
 #[derive(Clone, Copy)]
 pub enum NativeKind {
     /// Runs inline on the actor thread — the historical behaviour.
@@ -442,6 +475,18 @@ pub enum NativeKind {
             crate::vm::error::RuntimeError,
         >,
     ),
+    /// A cross-actor wait (`Channel.send` / `recv`, `join` on a `Task`).
+    /// The `fn` runs on the actor thread to validate arguments and build
+    /// a [`crate::vm::offload::HandoffOp`]; the VM parks the calling
+    /// coroutine and the op hands its result straight to the parked
+    /// call's mailbox when another thread makes it available, so a
+    /// parked wait costs no thread at all.
+    Handoff(
+        fn(&[Value]) -> Result<
+            crate::vm::offload::HandoffOp,
+            crate::vm::error::RuntimeError,
+        >,
+    ),
     /// A steady-state socket call (`Net` stream / datagram I/O). The
     /// `fn` runs on the actor thread to validate arguments and build a
     /// declarative [`crate::vm::socket::ReactorOp`]; the VM then drives
@@ -467,6 +512,15 @@ pub enum NativeKind {
             crate::vm::error::RuntimeError,
         >,
     ),
+    /// A call made on behalf of the calling actor. Runs inline like
+    /// `Pure`, but the VM also passes the actor's own task — its
+    /// identity for supervision (`link`, `trap_exits`).
+    Actor(
+        fn(
+            &crate::vm::task::TaskHandle,
+            &[Value],
+        ) -> Result<Value, crate::vm::error::RuntimeError>,
+    ),
 }
 
 #[allow(dead_code)] // AtLeast not used until later phases (e.g. fold)
@@ -523,10 +577,14 @@ impl Value {
             Value::Task(_) => "task",
             Value::Socket(_) => "socket",
             Value::File(_) => "file",
+            Value::Store(_) => "store",
             Value::Generator(_) => "generator",
             Value::GreenHandle(_) => "green_thread",
             Value::LocalChannel(_) => "local_channel",
             Value::Deferred(_) => "deferred",
+            Value::Shared(_) => "shared",
+            Value::Atomic(_) => "atomic",
+            Value::SharedMap(_) => "shared_map",
         }
     }
 
@@ -677,10 +735,14 @@ impl PartialEq for Value {
             (Task(a), Task(b)) => Arc::ptr_eq(a, b),
             (Socket(a), Socket(b)) => Arc::ptr_eq(a, b),
             (File(a), File(b)) => Arc::ptr_eq(a, b),
+            (Store(a), Store(b)) => Arc::ptr_eq(a, b),
             (Generator(a), Generator(b)) => a == b,
             (GreenHandle(a), GreenHandle(b)) => a == b,
             (LocalChannel(a), LocalChannel(b)) => a == b,
             (Deferred(a), Deferred(b)) => a == b,
+            (Shared(a), Shared(b)) => Arc::ptr_eq(a, b) || **a == **b,
+            (Atomic(a), Atomic(b)) => Arc::ptr_eq(a, b),
+            (SharedMap(a), SharedMap(b)) => Arc::ptr_eq(a, b),
             (BigInt(a), BigInt(b)) => a == b,
             // A `BigInt` and an `Int` of equal value compare equal,
             // mirroring `Int`/`Float` cross-type equality above.
@@ -781,21 +843,7 @@ impl fmt::Display for Value {
                 }
                 f.write_str("}")
             }
-            Value::Bytes(b) => {
-                // Space-separated hex, truncated so a large buffer can
-                // never blow up a string interpolation or error message.
-                const SHOWN: usize = 64;
-                let bytes = b.borrow();
-                f.write_str("Bytes[")?;
-                for (i, byte) in bytes.iter().take(SHOWN).enumerate() {
-                    if i > 0 { f.write_str(" ")?; }
-                    write!(f, "{byte:02x}")?;
-                }
-                if bytes.len() > SHOWN {
-                    write!(f, " … ({} total)", bytes.len())?;
-                }
-                f.write_str("]")
-            }
+            Value::Bytes(b) => fmt_bytes(f, &b.borrow()),
             Value::Range(r) => {
                 let dots = if r.inclusive { "..=" } else { ".." };
                 if r.step.abs() == 1 {
@@ -818,14 +866,34 @@ impl fmt::Display for Value {
             Value::Task(_) => f.write_str("<task>"),
             Value::Socket(s) => write!(f, "<socket #{}>", s.id()),
             Value::File(fh) => write!(f, "<file #{}>", fh.id()),
+            Value::Store(db) => write!(f, "<store #{}>", db.id()),
             Value::Generator(_) => f.write_str("<generator>"),
             Value::GreenHandle(_) => f.write_str("<green thread>"),
             Value::LocalChannel(_) => f.write_str("<local channel>"),
             Value::Deferred(_) => f.write_str("<deferred>"),
+            Value::Shared(node) => write!(f, "{node}"),
+            Value::Atomic(_) => f.write_str("<atomic>"),
+            Value::SharedMap(_) => f.write_str("<shared map>"),
         }
     }
 }
 
+/// A byte buffer's display form: space-separated hex, truncated so a
+/// large buffer can never blow up a string interpolation or error
+/// message.
+pub(crate) fn fmt_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    const SHOWN: usize = 64;
+    f.write_str("Bytes[")?;
+    for (i, byte) in bytes.iter().take(SHOWN).enumerate() {
+        if i > 0 { f.write_str(" ")?; }
+        write!(f, "{byte:02x}")?;
+    }
+    if bytes.len() > SHOWN {
+        write!(f, " … ({} total)", bytes.len())?;
+    }
+    f.write_str("]")
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
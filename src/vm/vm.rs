@@ -27,20 +27,28 @@ use num_traits::{Pow, Zero};
 
 use crate::vm::chunk::Chunk;
 use crate::vm::error::{RuntimeError, RuntimeErrorKind, TraceFrame};
+use crate::vm::channel::RecvOutcome;
 use crate::vm::gc::{
     self, ArrayKind, ClosureKind, DeferredKind, GcRef, GeneratorKind,
-    GreenHandleKind, IterKind, Marker, ObjectKind, Trace, UpvalueKind,
+    GreenHandleKind, IterKind, LocalChannelKind, Marker, ObjectKind, Trace,
+    UpvalueKind,
 };
-use crate::vm::offload::{self, BlockingJob, CompletionMailbox};
+use crate::vm::offload::{self, BlockingJob, CompletionMailbox, HandoffOp, Signal, Waiter};
 use crate::vm::opcode::OpCode;
+use crate::vm::preempt;
 use crate::vm::reactor;
-use crate::vm::socket::ReactorOp;
+use crate::vm::sim;
+use crate::vm::socket::{ReactorOp, SocketOp};
 use crate::vm::scheduler::{
     GenStatus, GeneratorState, GreenHandle, GreenThread, ResumeOutcome,
-    Scheduler,
+    Scheduler, SelectWaits,
 };
+use crate::vm::shared::{self, SharedNode};
 use crate::vm::source_map::SourceMap;
 use crate::vm::stdlib;
+use crate::vm::task::{TaskHandle, TaskInner};
+use crate::vm::trace;
+use crate::vm::transfer::{decode, TransferError};
 use crate::vm::value::{
     bigint_to_f64, Closure, Function, IterState, MapKey, NativeKind, RangeData,
     Upvalue, Value, WaitKind,
@@ -289,6 +297,37 @@ pub struct Vm {
     /// spin on `yield`. `None` under a host frame drain (the host's
     /// `frame_now` is the clock there) and on wasm (no threads/clock).
     clock_origin: Option<std::time::Instant>,
+    /// True for an actor running on the shared actor pool
+    /// ([`crate::vm::actor_pool`]), where its OS thread also runs other
+    /// actors. Like `in_drain`, the actor must then never block its
+    /// thread: a blocking call always parks its coroutine (even when
+    /// nothing else in the actor could run), and with every coroutine
+    /// parked the pick sites unwind via `HostYield` to the pool, which
+    /// resumes the actor through [`resume_pooled`](Vm::resume_pooled)
+    /// once a completion or timer makes one ready.
+    pooled: bool,
+    /// This actor's task: its identity for supervision (see
+    /// [`crate::vm::task`]). A spawned actor's is the `Task` `spawn`
+    /// returned; any other VM mints its own.
+    self_task: TaskHandle,
+    /// An exit signal being acted on. Set when one is noticed at a
+    /// park; every `catch` is then bypassed and the actor ends with it.
+    exiting: Option<TransferError>,
+    /// Safe points (backward jumps and calls) a coroutine passes before
+    /// it is preempted at one; `0` (the default) leaves scheduling
+    /// cooperative. See
+    /// [`crate::vm::preempt`].
+    preempt_quantum: u32,
+    /// Inline native calls that run longer than this are reported;
+    /// `None` (the default) skips the timing entirely.
+    slow_native: Option<std::time::Duration>,
+    /// True in simulation mode ([`crate::vm::sim`]): `wait` timers run
+    /// on the virtual clock, which jumps to the next wake instead of
+    /// sleeping, and offload completions surface only at switch points.
+    sim: bool,
+    /// This actor's trace id ([`crate::vm::trace`]); `0` while no trace
+    /// is being recorded.
+    trace: u64,
 }
 
 /// A parked resumer: the coroutine state that was running when a
@@ -309,6 +348,72 @@ struct ResumeCtx {
     prev_is_main: bool,
 }
 
+/// The generator state of a coroutine that parked inside a generator
+/// body — a blocking call pulled through `next()`. Parking takes the
+/// whole `resume_stack` with it (it belongs to the running coroutine
+/// alone) and resuming puts it back, so the generator carries on and
+/// later yields to its resumer as usual.
+pub(crate) struct GenChain {
+    resumers: Vec<ResumeCtx>,
+    current: GcRef<GeneratorKind>,
+    /// The generator's coroutine id, current while its body runs.
+    pub(crate) id: u32,
+}
+
+impl GenChain {
+    /// The stack resumer `owner` left in this chain, if it is one.
+    pub(crate) fn resumer_stack(&self, owner: u32) -> Option<&Vec<Value>> {
+        self.resumers.iter().rev().find(|ctx| ctx.prev_id == owner).map(|ctx| &ctx.stack)
+    }
+
+    /// Mutable counterpart of [`resumer_stack`](GenChain::resumer_stack).
+    pub(crate) fn resumer_stack_mut(&mut self, owner: u32) -> Option<&mut Vec<Value>> {
+        self.resumers
+            .iter_mut()
+            .rev()
+            .find(|ctx| ctx.prev_id == owner)
+            .map(|ctx| &mut ctx.stack)
+    }
+
+    fn trace(&self, m: &mut Marker) {
+        for ctx in &self.resumers {
+            ctx.trace(m);
+        }
+        m.mark_generator(self.current);
+    }
+}
+
+impl ResumeCtx {
+    fn trace(&self, m: &mut Marker) {
+        for v in &self.stack {
+            v.trace(m);
+        }
+        for up in &self.open_upvalues {
+            m.mark_upvalue(*up);
+        }
+        for frame in &self.frames {
+            trace_frame(frame, m);
+        }
+        if let Some(g) = self.prev_gen {
+            m.mark_generator(g);
+        }
+    }
+}
+
+/// How one turn of a pooled actor on its pool thread ended (see
+/// [`Vm::start_pooled`] / [`Vm::resume_pooled`]).
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) enum PoolTurn {
+    /// The actor finished; the outcome is ready for its `Task`.
+    Finished(crate::vm::task::ActorOutcome),
+    /// A coroutine yielded with nothing else in the actor ready: run
+    /// the actor again after the others waiting on its pool thread.
+    Yielded,
+    /// Every coroutine is parked. A mailbox post re-queues the actor;
+    /// `wake` is when its earliest `wait` timer falls due, if it has one.
+    Parked { wake: Option<std::time::Instant> },
+}
+
 impl Vm {
     #[allow(dead_code)]
     pub fn new() -> Self {
@@ -316,6 +421,10 @@ impl Vm {
     }
 
     pub fn with_source_map(source_map: Rc<RefCell<SourceMap>>) -> Self {
+        let mailbox = CompletionMailbox::new();
+        let self_task = TaskInner::new();
+        let interrupt = mailbox.clone();
+        self_task.set_interrupt(move || interrupt.interrupt());
         Vm {
             frames: Vec::with_capacity(64),
             max_call_depth: DEFAULT_MAX_CALL_DEPTH,
@@ -334,7 +443,7 @@ impl Vm {
             current_gen: None,
             resume_stack: Vec::new(),
             current_handle: None,
-            mailbox: CompletionMailbox::new(),
+            mailbox,
             next_job_id: 0,
             frame_now: 0.0,
             in_drain: false,
@@ -342,9 +451,53 @@ impl Vm {
             reload_roots: Vec::new(),
             blocking_timers_ok: false,
             clock_origin: None,
+            pooled: false,
+            self_task,
+            exiting: None,
+            preempt_quantum: preempt::quantum(),
+            slow_native: preempt::slow_native(),
+            sim: sim::active(),
+            trace: 0,
         }
     }
 
+    /// Preempt a coroutine once it has passed `n` backward jumps and
+    /// calls, when another is ready; `0` turns preemption off.
+    /// Starts from the process-wide [`preempt::quantum`].
+    pub fn set_preempt_quantum(&mut self, n: u32) {
+        self.preempt_quantum = n;
+    }
+
+    /// Report inline native calls that run longer than `threshold`;
+    /// `None` turns reporting off. Starts from the process-wide
+    /// [`preempt::slow_native`].
+    pub fn set_slow_native(&mut self, threshold: Option<std::time::Duration>) {
+        self.slow_native = threshold;
+    }
+
+    /// Run this VM in simulation mode with `seed` — virtual `wait` time
+    /// and seeded coroutine picks — or, with `None`, normally. Starts
+    /// from the process-wide [`sim::seed`]; the virtual clock itself is
+    /// process-wide, set up by [`sim::enable`].
+    pub fn set_simulation(&mut self, seed: Option<u64>) {
+        self.sim = seed.is_some();
+        self.scheduler.set_pick_stream(seed.map(sim::pick_stream_for));
+    }
+
+    /// Record this actor in the trace under id `actor`
+    /// ([`trace::new_actor`]); `0` leaves it untraced. A top-level
+    /// [`run`](Vm::run) picks an id of its own while a trace is on.
+    pub(crate) fn set_trace(&mut self, actor: u64) {
+        self.trace = actor;
+        self.scheduler.set_trace(actor);
+        self.mailbox.set_trace(actor);
+    }
+
+    /// This actor's trace id; `0` while untraced.
+    pub(crate) fn trace_actor(&self) -> u64 {
+        self.trace
+    }
+
     /// Register a host-provided module under a bare `import` name.
     ///
     /// `import '<name>'` will resolve to `module` *unless* `<name>` is a
@@ -544,6 +697,9 @@ impl Vm {
 
     /// Run a compiled top-level program. Returns its final value.
     pub fn run(&mut self, main: Function) -> Result<Value, RuntimeError> {
+        if self.trace == 0 {
+            self.set_trace(trace::new_actor("main"));
+        }
         self.stack.clear();
         self.frames.clear();
         self.open_upvalues.clear();
@@ -565,11 +721,16 @@ impl Vm {
             try_frames: Vec::new(),
             kind: FrameKind::Function,
         });
-        // This is the program's own run loop on this thread, so a
-        // cooperative `wait` may block-sleep the thread to its timer (a
-        // re-entrant `call_function` leaves this `false` and so raises).
-        // wasm has no threads/clock, so leave it off there — `wait` then
-        // raises, as it does on a synchronous host call.
+        self.drive_own_thread()
+    }
+
+    /// [`drive`](Vm::drive) as the run loop that owns this thread — a
+    /// program's main, or an actor's closure on its own OS thread — so a
+    /// cooperative `wait` may block-sleep the thread to its timer (a
+    /// re-entrant `call_function` leaves this off and so raises). wasm
+    /// has no threads/clock, so leave it off there — `wait` then raises,
+    /// as it does on a synchronous host call.
+    fn drive_own_thread(&mut self) -> Result<Value, RuntimeError> {
         let prev_blocking = self.blocking_timers_ok;
         let prev_origin = self.clock_origin;
         #[cfg(not(target_arch = "wasm32"))]
@@ -588,6 +749,7 @@ impl Vm {
     /// host-supplied `frame_now` under a frame drive.
     fn now_seconds(&self) -> f64 {
         match self.clock_origin {
+            Some(_) if self.sim => sim::now(),
             Some(origin) => origin.elapsed().as_secs_f64(),
             None => self.frame_now,
         }
@@ -621,6 +783,15 @@ impl Vm {
     /// resumer is restored, and the search retries there — walking the
     /// whole resume chain. Returns `true` once a handler is found.
     fn catch_with_generators(&mut self, err: &mut RuntimeError) -> bool {
+        // An exit signal ends the whole actor: whatever error unwound
+        // to here becomes the exit reason, and no handler may catch it.
+        if let Some(te) = self.exiting.take() {
+            *err = RuntimeError::new(
+                RuntimeErrorKind::Raised(crate::vm::transfer::decode(te.into_value())),
+                0,
+            );
+            return false;
+        }
         // Never absorb the internal host-yield signal here; `drain_ready`
         // is the only thing that handles it.
         if matches!(err.kind, RuntimeErrorKind::HostYield) {
@@ -655,6 +826,13 @@ impl Vm {
                         *err = e;
                         continue;
                     }
+                    // Nothing ready. A pooled actor with coroutines still
+                    // parked steps aside to the pool until one wakes: the
+                    // failure is recorded, so it leaves as a `HostYield`.
+                    GreenFail::Stranded if self.pooled => {
+                        *err = RuntimeError::new(RuntimeErrorKind::HostYield, 0);
+                        return false;
+                    }
                     // Nothing left to run — the actor cannot continue.
                     GreenFail::Stranded => return false,
                 }
@@ -687,6 +865,7 @@ impl Vm {
                 h.result = Some(outcome.clone());
                 h.id
             };
+            trace::end(self.trace, id);
             self.scheduler.wake_joiners(id, &outcome);
         }
         match self.pick_next() {
@@ -706,6 +885,13 @@ impl Vm {
         &mut self,
         closure: GcRef<ClosureKind>,
     ) -> Result<Value, RuntimeError> {
+        self.enter_closure(closure);
+        self.drive_own_thread()
+    }
+
+    /// Make `closure`, called with no arguments, the actor's main
+    /// coroutine, ready for [`drive`](Vm::drive).
+    fn enter_closure(&mut self, closure: GcRef<ClosureKind>) {
         self.stack.clear();
         self.frames.clear();
         self.open_upvalues.clear();
@@ -734,11 +920,167 @@ impl Vm {
             try_frames: Vec::new(),
             kind: FrameKind::Function,
         });
-        self.drive()
+    }
+
+    /// Start a spawned actor on its pool thread: adopt `task` as the
+    /// actor's identity, decode the spawned closure into this (fresh)
+    /// VM's heap, switch the VM into pooled mode and run the closure
+    /// until it finishes or every coroutine parks. The pool calls [`resume_pooled`](Vm::resume_pooled) for
+    /// each later turn.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn start_pooled(
+        &mut self,
+        task: TaskHandle,
+        transfer: crate::vm::transfer::Transfer,
+    ) -> PoolTurn {
+        self.self_task = task;
+        let interrupt = self.mailbox.clone();
+        self.self_task.set_interrupt(move || interrupt.interrupt());
+        if let Some(te) = self.self_task.take_exit() {
+            return PoolTurn::Finished(Err(te));
+        }
+        let closure = match crate::vm::transfer::decode(transfer) {
+            Value::Function(c) => c,
+            _ => unreachable!("spawn always encodes a closure"),
+        };
+        self.pooled = true;
+        self.blocking_timers_ok = true;
+        self.clock_origin = Some(std::time::Instant::now());
+        self.enter_closure(closure);
+        let result = self.drive();
+        self.pool_turn(result)
+    }
+
+    /// Give a parked pooled actor another turn: fire its due timers,
+    /// take its posted completions, and run from the first ready
+    /// coroutine until the actor finishes or parks again. A turn with
+    /// nothing ready (a spurious wake) just reports the actor parked.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn resume_pooled(&mut self) -> PoolTurn {
+        if let Some(te) = self.self_task.take_exit() {
+            return PoolTurn::Finished(Err(te));
+        }
+        self.scheduler.wake_timers(self.now_seconds());
+        if self.scheduler.has_io_blocked() {
+            self.poll_io_completions();
+        }
+        let Some(next) = self.scheduler.take_next() else {
+            return self.pool_turn(Err(RuntimeError::new(
+                RuntimeErrorKind::HostYield,
+                0,
+            )));
+        };
+        // A coroutine resumed by raising (a failed call, `cancelled`)
+        // surfaces against its own frames, as in `drain_ready`.
+        if let Err(mut err) = self.load_green(next) {
+            self.stamp_error_source(&mut err);
+            if !self.catch_with_generators(&mut err) {
+                return self.pool_turn(Err(err));
+            }
+        }
+        let result = self.drive();
+        self.pool_turn(result)
+    }
+
+    /// Classify how a pooled turn ended. `HostYield` means the actor
+    /// stepped aside: still runnable after a pooled `yield`, or parked
+    /// on a completion or timer. Parked with neither outstanding, no
+    /// turn could ever make progress, so the actor ends with a deadlock
+    /// error rather than sitting in the pool forever.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pool_turn(&mut self, result: Result<Value, RuntimeError>) -> PoolTurn {
+        let result = match result {
+            // An exit signal that arrived during the turn ends the actor
+            // now rather than at its next resume.
+            Err(e) if matches!(e.kind, RuntimeErrorKind::HostYield)
+                && self.self_task.exit_pending() =>
+            {
+                let te = self.self_task.take_exit().expect("exit is pending");
+                return PoolTurn::Finished(Err(te));
+            }
+            Err(e) if matches!(e.kind, RuntimeErrorKind::HostYield) => {
+                if self.scheduler.has_ready() {
+                    return PoolTurn::Yielded;
+                }
+                // Virtual time jumps to the next timer rather than
+                // waiting for it, once the worker pool has had its
+                // moment; the actor runs again once the pool gets to it.
+                if self.sim {
+                    if self.scheduler.has_io_blocked() && self.settle(None) {
+                        return PoolTurn::Yielded;
+                    }
+                    if let Some(wake) = self.sim_next_wake() {
+                        sim::advance_to(wake);
+                        return PoolTurn::Yielded;
+                    }
+                }
+                if self.scheduler.has_io_blocked()
+                    || self.scheduler.has_timer_blocked()
+                {
+                    let wake = self.scheduler.next_timer_wake().and_then(|t| {
+                        let origin = self.clock_origin?;
+                        Some(origin + std::time::Duration::from_secs_f64(t.max(0.0)))
+                    });
+                    return PoolTurn::Parked { wake };
+                }
+                Err(RuntimeError::new(
+                    RuntimeErrorKind::Raised(Value::Str(
+                        "deadlock: every coroutine in the actor is parked \
+                         and nothing can wake one"
+                            .into(),
+                    )),
+                    0,
+                ))
+            }
+            other => other,
+        };
+        PoolTurn::Finished(self.actor_outcome(result))
+    }
+
+    /// This actor's completion mailbox, for the pool to hook its
+    /// re-queue waker onto.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn mailbox(&self) -> &Arc<CompletionMailbox> {
+        &self.mailbox
+    }
+
+    /// Encode a finished actor's result into `Send`-able form for its
+    /// `Task`. An uncaught actor error is rendered against this VM's
+    /// own `SourceMap` (the parent's is not `Send`).
+    #[cfg(not(target_arch = "wasm32"))]
+    fn actor_outcome(
+        &self,
+        result: Result<Value, RuntimeError>,
+    ) -> crate::vm::task::ActorOutcome {
+        use crate::vm::transfer::encode;
+
+        match result {
+            Ok(v) => encode(&v).map_err(|e| TransferError {
+                kind_tag: e.kind.kind_tag().to_string(),
+                message: format!("actor return value could not be sent: {e}"),
+                rendered_trace: String::new(),
+                raised: None,
+            }),
+            Err(e) => {
+                let kind_tag = e.kind.kind_tag().to_string();
+                let message = format!("{e}");
+                // If the actor did `raise <value>`, carry that value so the
+                // parent's `catch` binds exactly it. A non-sendable raised
+                // value falls back to `None` — its `str()` form is already
+                // in `message`.
+                let raised = match &e.kind {
+                    RuntimeErrorKind::Raised(v) => encode(v).ok(),
+                    _ => None,
+                };
+                let rendered_trace = crate::vm::error::Error::Runtime(e)
+                    .render(&self.source_map.borrow());
+                Err(TransferError { kind_tag, message, rendered_trace, raised })
+            }
+        }
     }
 
     /// Start `callee` as an actor: deep-copy it across the heap
-    /// boundary, run it on a new OS thread, and return a `Task` handle
+    /// boundary, hand it to the actor pool, and return a `Task` handle
     /// for its eventual result. Raises `not_callable` if `callee` is
     /// not a function, or `not_sendable`/`cycle` if it (or a captured
     /// value) cannot cross the boundary.
@@ -748,7 +1090,7 @@ impl Vm {
         callee: Value,
         line: u32,
     ) -> Result<crate::vm::task::TaskHandle, RuntimeError> {
-        // Actors run on dedicated OS threads, which the browser
+        // Actors run on the actor pool's OS threads, which the browser
         // playground build cannot spawn. Green threads (`go` / `yield`)
         // cover concurrency there; cross-thread `spawn` raises a
         // catchable error rather than aborting the wasm instance.
@@ -771,38 +1113,27 @@ impl Vm {
                 ));
             }
         };
-        // The spawned closure may hold `Open` upvalues pointing into
-        // this live frame's stack. Build a detached copy whose every
-        // upvalue is `Closed` so the transfer encoder can encode it.
-        let (function, cells) = {
-            let cl = closure.borrow();
-            (cl.function.clone(), cl.upvalues.clone())
-        };
-        let mut closed = Vec::with_capacity(cells.len());
-        for cell in &cells {
-            let captured = match &*cell.borrow() {
-                Upvalue::Closed(v) => v.clone(),
-                Upvalue::Open { owner, slot } => self.upvalue_get(*owner, *slot),
-            };
-            closed.push(gc::alloc_upvalue(Upvalue::Closed(captured)));
-        }
-        let detached =
-            gc::alloc_closure(Closure { function, upvalues: closed });
-        let transfer = crate::vm::transfer::encode(&Value::Function(detached))
-            .map_err(|mut e| {
-                if e.line == 0 {
-                    e.line = line;
-                }
-                e
-            })?;
+        // The spawned closure, and any function it captures, may hold
+        // `Open` upvalues pointing into live frames' stacks; the actor
+        // gets their current values.
+        let transfer = crate::vm::transfer::encode_snapshot(
+            &Value::Function(closure),
+            &|owner, slot| self.upvalue_get(owner, slot),
+        )
+        .map_err(|mut e| {
+            if e.line == 0 {
+                e.line = line;
+            }
+            e
+        })?;
 
-        let task = crate::vm::task::TaskInner::new();
-        let task_worker = task.clone();
-        std::thread::spawn(move || {
-            let outcome = run_actor(transfer);
-            task_worker.complete(outcome);
-        });
-        Ok(task)
+        // The new actor's trace track is named after its function.
+        let child = trace::new_actor(
+            closure.borrow().function.name.as_deref().unwrap_or("<anonymous>"),
+        );
+        trace::spawn(self.trace, self.scheduler.current().0, child);
+        #[cfg(not(target_arch = "wasm32"))]
+        return Ok(crate::vm::actor_pool::spawn(transfer, child));
     }
 
     /// Build an `ImportFailed` runtime error from a compile-time
@@ -1166,6 +1497,11 @@ impl Vm {
         // handle and triggers a refresh.
         let mut closure = self.frames.last().expect("at least one frame").closure;
         let mut function_rc = closure.borrow().function.clone();
+        // Safe points left in the running coroutine's preemption
+        // quantum, counted down at each one (`Loop`, a call into a tigr
+        // function) and nowhere else, so with preemption off the other
+        // instructions pay nothing for it.
+        let mut budget = self.fresh_budget();
 
         loop {
             // GC safepoint: collect here, before any opcode work, while
@@ -1174,8 +1510,10 @@ impl Vm {
 
             // Surface any offloaded blocking calls that finished — so a
             // coroutine spinning on `yield` notices a sibling's IO
-            // completing without having to reach a blocking switch.
-            if self.scheduler.has_io_blocked() {
+            // completing without having to reach a blocking switch. A
+            // simulation polls at switch points instead, where the
+            // moment a completion surfaces does not depend on timing.
+            if self.scheduler.has_io_blocked() && !self.sim {
                 self.poll_io_completions();
             }
 
@@ -1278,6 +1616,7 @@ impl Vm {
                             }
                             self.stack.push(Value::Bytes(a));
                         }
+                        Value::Shared(_) => return Err(shared::immutable("append with `+=`", line)),
                         other => {
                             let sum = arith_add(other, rhs, line)?;
                             self.stack.push(sum);
@@ -1321,6 +1660,13 @@ impl Vm {
                         (10, Value::Map(_)) => true,
                         (11, Value::Set(_)) => true,
                         (12, Value::Bytes(_)) => true,
+                        // A shared collection matches the pattern its
+                        // thawed copy would.
+                        (4, Value::Shared(n)) => matches!(**n, SharedNode::Array(_)),
+                        (5, Value::Shared(n)) => matches!(**n, SharedNode::Object(_)),
+                        (10, Value::Shared(n)) => matches!(**n, SharedNode::Map(_)),
+                        (11, Value::Shared(n)) => matches!(**n, SharedNode::Set(_)),
+                        (12, Value::Shared(n)) => matches!(**n, SharedNode::Bytes(_)),
                         _ => false,
                     };
                     self.stack.push(Value::Bool(matched));
@@ -1436,6 +1782,7 @@ impl Vm {
                                 h.result = Some(outcome.clone());
                                 h.id
                             };
+                            trace::end(self.trace, id);
                             self.scheduler.wake_joiners(id, &outcome);
                         }
                         // Pick the next coroutine, blocking for an
@@ -1450,7 +1797,9 @@ impl Vm {
                             // in the scheduler), so an empty pick means
                             // "frame done" — unwind to the host rather
                             // than ending the actor.
-                            None if self.in_drain => {
+                            // Likewise on the actor pool: main is parked,
+                            // waiting on something the pool will deliver.
+                            None if self.in_drain || self.pooled => {
                                 return Err(RuntimeError::new(
                                     RuntimeErrorKind::HostYield,
                                     0,
@@ -1466,6 +1815,14 @@ impl Vm {
                             // already caught at the join site in
                             // `coop_join_deferred`; this is the backstop
                             // for a deferred stranded after other work ran.
+                            // An exit signal was noticed in `pick_next`;
+                            // the catch path ends the actor with it.
+                            None if self.exiting.is_some() => {
+                                return Err(RuntimeError::new(
+                                    RuntimeErrorKind::HostYield,
+                                    0,
+                                ));
+                            }
                             None if self.scheduler.has_deferred_blocked() => {
                                 return Err(RuntimeError::new(
                                     RuntimeErrorKind::Raised(Value::Str(
@@ -1476,6 +1833,17 @@ impl Vm {
                                     0,
                                 ));
                             }
+                            // Likewise a `select` left waiting on sources
+                            // inside the actor that nothing can now wake.
+                            None if self.scheduler.has_select_blocked() => {
+                                return Err(RuntimeError::new(
+                                    RuntimeErrorKind::Raised(Value::Str(
+                                        "deadlock: a select was never woken"
+                                            .into(),
+                                    )),
+                                    0,
+                                ));
+                            }
                             None => return Ok(result),
                         }
                     }
@@ -1528,6 +1896,14 @@ impl Vm {
                     let dist = chunk.read_u32(ip);
                     ip += 4;
                     ip -= dist as usize;
+                    budget -= 1;
+                    if budget <= 0 {
+                        self.frames.last_mut().unwrap().ip = ip;
+                        budget = self.fresh_budget();
+                        if self.preempt(floor)? {
+                            continue;
+                        }
+                    }
                 }
                 OpCode::JumpIfFalse => {
                     let dist = chunk.read_u32(ip);
@@ -1610,6 +1986,7 @@ impl Vm {
                         Value::Bytes(b) => b.borrow().len() as i64,
                         Value::Str(s) => s.chars().count() as i64,
                         Value::Range(r) => r.length(),
+                        Value::Shared(n) => n.len() as i64,
                         other => return Err(RuntimeError::new(
                             RuntimeErrorKind::TypeMismatch(format!(
                                 "cannot apply `#` to {}", other.type_name()
@@ -1665,6 +2042,11 @@ impl Vm {
                                 try_frames: Vec::new(),
                                 kind: FrameKind::Function,
                             });
+                            budget -= 1;
+                            if budget <= 0 {
+                                budget = self.fresh_budget();
+                                self.preempt(floor)?;
+                            }
                             continue;
                         }
                         Value::NativeFn(nf) => {
@@ -1702,6 +2084,21 @@ impl Vm {
                                 self.do_reject(d, v, line);
                                 continue;
                             }
+                            // `select`'s wait parks on every source through
+                            // the scheduler, and its deadline reads the VM's
+                            // clock, so both run here rather than as natives.
+                            if is_select_call(&nf, &args) {
+                                self.coop_select(&args, line)?;
+                                continue;
+                            }
+                            if is_select_deadline_call(&nf, &args) {
+                                let at = self.select_deadline(&args[0], line)?;
+                                self.stack.push(at);
+                                continue;
+                            }
+                            // A local `send`/`close` wakes the `select`s
+                            // parked on its channel once it has run.
+                            let woken = self.local_wakes(&nf, &args);
                             if !nf.arity.check(args.len()) {
                                 return Err(RuntimeError::new(
                                     RuntimeErrorKind::ArityMismatch {
@@ -1714,7 +2111,11 @@ impl Vm {
                             }
                             match &nf.kind {
                                 NativeKind::Pure(f) => {
-                                    let result = f(&args).map_err(|mut e| {
+                                    let result = match self.slow_native {
+                                        None => f(&args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&args)),
+                                    }
+                                    .map_err(|mut e| {
                                         // Backfill the call-site line so
                                         // an uncaught error from a
                                         // builtin reports where it was
@@ -1726,10 +2127,24 @@ impl Vm {
                                     self.stack.push(result);
                                 }
                                 NativeKind::Blocking(f) => {
-                                    self.dispatch_blocking(*f, args, line)?;
+                                    self.dispatch_blocking(nf.name, *f, args, line)?;
+                                }
+                                NativeKind::Handoff(f) => {
+                                    self.dispatch_handoff(nf.name, *f, args, line)?;
                                 }
                                 NativeKind::Socket(f) => {
-                                    self.dispatch_socket(*f, args, line)?;
+                                    self.dispatch_socket(nf.name, *f, args, line)?;
+                                }
+                                NativeKind::Actor(f) => {
+                                    let result = match self.slow_native {
+                                        None => f(&self.self_task, &args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&self.self_task, &args)),
+                                    }
+                                    .map_err(|mut e| {
+                                            if e.line == 0 { e.line = line; }
+                                            e
+                                        })?;
+                                    self.stack.push(result);
                                 }
                                 // `wait` / `GameTime.wait_frame`: park the
                                 // running green thread cooperatively. The
@@ -1742,6 +2157,7 @@ impl Vm {
                                     self.coop_wait(kind, line)?;
                                 }
                             }
+                            self.wake_locals(woken);
                             continue;
                         }
                         other => {
@@ -1804,6 +2220,11 @@ impl Vm {
                             // base_slot unchanged; try_frames is empty —
                             // the compiler never emits TailCall inside a
                             // `try`.
+                            budget -= 1;
+                            if budget <= 0 {
+                                budget = self.fresh_budget();
+                                self.preempt(floor)?;
+                            }
                             continue;
                         }
                         Value::NativeFn(nf) => {
@@ -1837,6 +2258,21 @@ impl Vm {
                                 self.do_reject(d, v, line);
                                 continue;
                             }
+                            // `select`'s wait parks on every source through
+                            // the scheduler, and its deadline reads the VM's
+                            // clock, so both run here rather than as natives.
+                            if is_select_call(&nf, &args) {
+                                self.coop_select(&args, line)?;
+                                continue;
+                            }
+                            if is_select_deadline_call(&nf, &args) {
+                                let at = self.select_deadline(&args[0], line)?;
+                                self.stack.push(at);
+                                continue;
+                            }
+                            // A local `send`/`close` wakes the `select`s
+                            // parked on its channel once it has run.
+                            let woken = self.local_wakes(&nf, &args);
                             if !nf.arity.check(args.len()) {
                                 return Err(RuntimeError::new(
                                     RuntimeErrorKind::ArityMismatch {
@@ -1849,17 +2285,35 @@ impl Vm {
                             }
                             match &nf.kind {
                                 NativeKind::Pure(f) => {
-                                    let result = f(&args).map_err(|mut e| {
+                                    let result = match self.slow_native {
+                                        None => f(&args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&args)),
+                                    }
+                                    .map_err(|mut e| {
                                         if e.line == 0 { e.line = line; }
                                         e
                                     })?;
                                     self.stack.push(result);
                                 }
                                 NativeKind::Blocking(f) => {
-                                    self.dispatch_blocking(*f, args, line)?;
+                                    self.dispatch_blocking(nf.name, *f, args, line)?;
+                                }
+                                NativeKind::Handoff(f) => {
+                                    self.dispatch_handoff(nf.name, *f, args, line)?;
                                 }
                                 NativeKind::Socket(f) => {
-                                    self.dispatch_socket(*f, args, line)?;
+                                    self.dispatch_socket(nf.name, *f, args, line)?;
+                                }
+                                NativeKind::Actor(f) => {
+                                    let result = match self.slow_native {
+                                        None => f(&self.self_task, &args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&self.self_task, &args)),
+                                    }
+                                    .map_err(|mut e| {
+                                            if e.line == 0 { e.line = line; }
+                                            e
+                                        })?;
+                                    self.stack.push(result);
                                 }
                                 // Tail-positioned `wait` / `wait_frame`:
                                 // park cooperatively, leaving the resume
@@ -1872,6 +2326,7 @@ impl Vm {
                                     self.coop_wait(kind, line)?;
                                 }
                             }
+                            self.wake_locals(woken);
                             continue;
                         }
                         other => {
@@ -2366,6 +2821,7 @@ impl Vm {
                             let call_args: Vec<Value> = self.stack
                                 .drain(args_start..).collect();
                             self.stack.pop();
+                            let woken = self.local_wakes(&nf, &call_args);
                             if !nf.arity.check(call_args.len()) {
                                 return Err(RuntimeError::new(
                                     RuntimeErrorKind::ArityMismatch {
@@ -2378,8 +2834,11 @@ impl Vm {
                             }
                             match &nf.kind {
                                 NativeKind::Pure(f) => {
-                                    let result = f(&call_args)
-                                        .map_err(|mut e| {
+                                    let result = match self.slow_native {
+                                        None => f(&call_args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&call_args)),
+                                    }
+                                    .map_err(|mut e| {
                                             if e.line == 0 { e.line = line; }
                                             e
                                         })?;
@@ -2387,14 +2846,30 @@ impl Vm {
                                 }
                                 NativeKind::Blocking(f) => {
                                     self.dispatch_blocking(
-                                        *f, call_args, line,
+                                        nf.name, *f, call_args, line,
+                                    )?;
+                                }
+                                NativeKind::Handoff(f) => {
+                                    self.dispatch_handoff(
+                                        nf.name, *f, call_args, line,
                                     )?;
                                 }
                                 NativeKind::Socket(f) => {
                                     self.dispatch_socket(
-                                        *f, call_args, line,
+                                        nf.name, *f, call_args, line,
                                     )?;
                                 }
+                                NativeKind::Actor(f) => {
+                                    let result = match self.slow_native {
+                                        None => f(&self.self_task, &call_args),
+                                        Some(limit) => timed_native(limit, nf.name, line, || f(&self.self_task, &call_args)),
+                                    }
+                                    .map_err(|mut e| {
+                                            if e.line == 0 { e.line = line; }
+                                            e
+                                        })?;
+                                    self.stack.push(result);
+                                }
                                 // Spread-applied `wait` / `wait_frame`:
                                 // park cooperatively, same as a plain call.
                                 NativeKind::Park(f) => {
@@ -2405,6 +2880,7 @@ impl Vm {
                                     self.coop_wait(kind, line)?;
                                 }
                             }
+                            self.wake_locals(woken);
                             continue;
                         }
                         other => return Err(RuntimeError::new(
@@ -2454,6 +2930,15 @@ impl Vm {
                             let real = real.max(0) as usize;
                             Value::Bytes(gc::alloc_bytes(src[real..].to_vec()))
                         }
+                        Value::Shared(n) if matches!(*n, SharedNode::Array(_) | SharedNode::Bytes(_)) => {
+                            let rest = RangeData {
+                                from: start,
+                                to: n.len() as i64,
+                                step: 1,
+                                inclusive: false,
+                            };
+                            n.index(&Value::Range(Rc::new(rest)), line)?
+                        }
                         other => return Err(RuntimeError::new(
                             RuntimeErrorKind::TypeMismatch(format!(
                                 "cannot slice {} (only Array and Bytes supported)",
@@ -2722,6 +3207,8 @@ impl Vm {
                     // Plain `go` coroutine: the yielded value has no
                     // consumer. The `yield` expression evaluates to the
                     // resume value delivered on resumption.
+                    self.sim_yield();
+                    self.sim_poll();
                     match self.scheduler.take_next() {
                         Some(next) => {
                             self.frames.last_mut().unwrap().ip = ip;
@@ -2732,6 +3219,21 @@ impl Vm {
                             self.load_green(next)?;
                             continue;
                         }
+                        // Nothing else ready on the actor pool: step
+                        // aside so the other actors sharing this pool
+                        // thread get a turn. The coroutine stays ready,
+                        // and the pool re-queues the actor behind them.
+                        None if self.pooled => {
+                            self.frames.last_mut().unwrap().ip = ip;
+                            let parked = self.save_current(Some(
+                                ResumeOutcome::Value(Value::Null),
+                            ));
+                            self.scheduler.enqueue(parked);
+                            return Err(RuntimeError::new(
+                                RuntimeErrorKind::HostYield,
+                                0,
+                            ));
+                        }
                         None => {
                             // Nothing else ready — resume immediately.
                             self.stack.push(Value::Null);
@@ -2823,6 +3325,46 @@ impl Vm {
         Ok(())
     }
 
+    /// A full preemption quantum, or — with preemption off — a budget
+    /// too large to run out in practice (and harmless when it does).
+    fn fresh_budget(&self) -> i32 {
+        match self.preempt_quantum {
+            0 => i32::MAX,
+            n => n.min(i32::MAX as u32) as i32,
+        }
+    }
+
+    /// A preemption safe point, reached once the running coroutine has
+    /// used up its quantum (see [`crate::vm::preempt`]). Switches to the
+    /// next ready coroutine and returns `true`; on the actor pool with
+    /// nothing else ready, steps aside for the pool thread's other
+    /// actors, as a `yield` does. Never switches out a generator body or
+    /// a re-entrant run above `floor`. The caller must have committed
+    /// the frame's `ip`.
+    fn preempt(&mut self, floor: usize) -> Result<bool, RuntimeError> {
+        if self.preempt_quantum == 0 || floor != 0 || self.current_gen.is_some() {
+            return Ok(false);
+        }
+        self.sim_poll();
+        match self.scheduler.take_next() {
+            Some(next) => {
+                // Parked with no resume outcome: the coroutine picks up
+                // exactly where it stopped, and a preemption is not a
+                // cancellation point.
+                let parked = self.save_current(None);
+                self.scheduler.enqueue(parked);
+                self.load_green(next)?;
+                Ok(true)
+            }
+            None if self.pooled => {
+                let parked = self.save_current(None);
+                self.scheduler.enqueue(parked);
+                Err(RuntimeError::new(RuntimeErrorKind::HostYield, 0))
+            }
+            None => Ok(false),
+        }
+    }
+
     /// Pack the args at `[args_start..]` into the rest-array layout
     /// expected by a `has_rest` function. After this:
     ///   - slots `args_start..args_start+arity` hold the fixed args
@@ -2864,14 +3406,19 @@ impl Vm {
                         line,
                     ));
                 }
+                let woken = self.local_wakes(&nf, &args);
                 // Re-entrant from inside opcode execution — there is
                 // no coroutine-switch point here, so a `Blocking`
                 // native runs synchronously on the actor thread.
-                match &nf.kind {
+                let result = match &nf.kind {
                     NativeKind::Pure(f) => f(&args).map_err(|mut e| {
                         if e.line == 0 { e.line = line; }
                         e
                     }),
+                    NativeKind::Actor(f) => f(&self.self_task, &args).map_err(|mut e| {
+                        if e.line == 0 { e.line = line; }
+                        e
+                    }),
                     NativeKind::Blocking(f) => {
                         let job = f(&args).map_err(|mut e| {
                             if e.line == 0 { e.line = line; }
@@ -2882,6 +3429,18 @@ impl Vm {
                             e
                         })
                     }
+                    NativeKind::Handoff(f) => {
+                        let op = f(&args).map_err(|mut e| {
+                            if e.line == 0 { e.line = line; }
+                            e
+                        })?;
+                        offload::decode(offload::run_handoff(op)).map_err(
+                            |mut e| {
+                                if e.line == 0 { e.line = line; }
+                                e
+                            },
+                        )
+                    }
                     NativeKind::Socket(f) => {
                         let rop = f(&args).map_err(|mut e| {
                             if e.line == 0 { e.line = line; }
@@ -2912,7 +3471,9 @@ impl Vm {
                             line,
                         ))
                     }
-                }
+                };
+                self.wake_locals(woken);
+                result
             }
             Value::Function(c) => {
                 let floor = self.frames.len();
@@ -3015,11 +3576,19 @@ impl Vm {
     /// Snapshot the running coroutine's execution state into a
     /// `GreenThread` for later resumption. `parked_resume` is the
     /// outcome to deliver when it resumes.
+    /// Inside a generator body the resumers that pulled it go too (see
+    /// [`GenChain`]), and the coroutine parks under its own id.
     fn save_current(
         &mut self,
         parked_resume: Option<ResumeOutcome>,
     ) -> GreenThread {
-        let (id, is_main) = self.scheduler.current();
+        let (mut id, mut is_main) = self.scheduler.current();
+        let generator = self.current_gen.take().map(|current| {
+            let resumers = std::mem::take(&mut self.resume_stack);
+            let gen_id = id;
+            (id, is_main) = (resumers[0].prev_id, resumers[0].prev_is_main);
+            Box::new(GenChain { resumers, current, id: gen_id })
+        });
         GreenThread {
             id,
             is_main,
@@ -3028,6 +3597,7 @@ impl Vm {
             open_upvalues: std::mem::take(&mut self.open_upvalues),
             parked_resume,
             handle: self.current_handle.take(),
+            generator,
         }
     }
 
@@ -3044,6 +3614,12 @@ impl Vm {
         self.open_upvalues = gt.open_upvalues;
         self.current_handle = gt.handle;
         self.scheduler.set_current(gt.id, gt.is_main);
+        trace::state(self.trace, gt.id, || "running".into());
+        if let Some(chain) = gt.generator {
+            self.resume_stack = chain.resumers;
+            self.current_gen = Some(chain.current);
+            self.scheduler.set_current(chain.id, false);
+        }
         // A coroutine resumes from a park iff it carries a resume
         // outcome; a not-yet-started coroutine carries `None` and just
         // begins at ip 0. The cancellation checkpoint lives on the
@@ -3052,7 +3628,8 @@ impl Vm {
         // resumes through here, so checking the handle's
         // `cancel_requested` flag at this one site makes them all
         // cancellation points with no per-park code. Gating on a real
-        // resume keeps the no-preemption rule: cancelling a coroutine
+        // resume keeps cancellation cooperative — a preempted coroutine
+        // also resumes with `None` — so cancelling a coroutine
         // that has not yet started (or one whose body never parks) does
         // not interrupt it — it runs to completion, and cancellation is
         // observed only at parks.
@@ -3101,6 +3678,10 @@ impl Vm {
             stack.push(Value::Array(gc::alloc_array(Vec::new())));
         }
         let id = self.scheduler.fresh_id();
+        trace::name_thread(self.trace, id, || {
+            let name = closure.borrow().function.name.clone();
+            format!("go {} #{id}", name.as_deref().unwrap_or("<anonymous>"))
+        });
         let handle = gc::alloc_green_handle(GreenHandle {
             id,
             result: None,
@@ -3120,6 +3701,7 @@ impl Vm {
             open_upvalues: Vec::new(),
             parked_resume: None,
             handle: Some(handle),
+            generator: None,
         });
         handle
     }
@@ -3410,11 +3992,289 @@ impl Vm {
         }
     }
 
+    /// `__select_deadline(ms)` — when a `select`'s `after ms` arm comes
+    /// due, on the clock [`now_seconds`](Vm::now_seconds) reads (so
+    /// virtual time in a simulation, `frame_now` under a host drive);
+    /// `null` (no `after`) never does.
+    fn select_deadline(&self, ms: &Value, line: u32) -> Result<Value, RuntimeError> {
+        let ms = match ms {
+            Value::Null => return Ok(Value::Null),
+            Value::Int(n) => *n as f64,
+            Value::Float(x) => *x,
+            other => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::TypeMismatch(format!(
+                        "select `after` expects a number of milliseconds, got {}",
+                        other.type_name()
+                    )),
+                    line,
+                ));
+            }
+        };
+        Ok(Value::Float(self.now_seconds() + ms.max(0.0) / 1000.0))
+    }
+
+    /// `__select(sources, has_else, deadline)` — the wait behind a
+    /// `select` block. Checks every source in order and pushes
+    /// `${index, value}` for the first that is ready: a channel or
+    /// local channel with a message, a `go` handle, task or deferred
+    /// that finished (its joined result, or its error re-raised), a
+    /// stream socket a read would not wait on (the socket itself). With
+    /// none ready it pushes `${index: -1}` if there is an `else` arm or
+    /// the `deadline` has passed, and raises `channel_closed` once every
+    /// source is a closed and drained channel.
+    ///
+    /// Otherwise the coroutine parks on all of them at once
+    /// ([`Scheduler::park_select`]) and, when any one wakes it, resumes
+    /// with `null`; the desugared loop then calls `__select` again.
+    /// In-actor sources wake it directly — a finishing coroutine, a
+    /// settled deferred, a local `send` or `close`. Cross-actor channels
+    /// and tasks share one [`Signal`] that posts the select's job to the
+    /// actor's mailbox, as does a reactor readability op per socket. An
+    /// `after` arm is a timer. Whichever comes first, the rest are
+    /// dropped as it wakes, so nothing is left holding the wake-up.
+    fn coop_select(&mut self, args: &[Value], line: u32) -> Result<(), RuntimeError> {
+        let sources = match &args[0] {
+            Value::Array(a) => a.borrow().clone(),
+            other => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::TypeMismatch(format!(
+                        "select expects an array of sources, got {}",
+                        other.type_name()
+                    )),
+                    line,
+                ));
+            }
+        };
+        let has_else = matches!(args[1], Value::Bool(true));
+        let deadline = match args[2] {
+            Value::Float(at) => Some(at),
+            _ => None,
+        };
+        let fired = |index: usize, value: Value| {
+            let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(2);
+            m.insert(Arc::from("index"), Value::Int(index as i64));
+            m.insert(Arc::from("value"), value);
+            Value::Object(gc::alloc_object(m))
+        };
+        let mut waits = SelectWaits {
+            greens: Vec::new(),
+            deferreds: Vec::new(),
+            locals: Vec::new(),
+            job: None,
+            wake_time: deadline,
+            on_wake: None,
+        };
+        let mut channels = Vec::new();
+        let mut tasks = Vec::new();
+        let mut sockets = Vec::new();
+        for (index, source) in sources.iter().enumerate() {
+            match source {
+                Value::Channel(ch) if ch.is_hub() => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::TypeMismatch(
+                            "select on a broadcast or watch channel: \
+                             select on a subscription to it instead"
+                                .into(),
+                        ),
+                        line,
+                    ));
+                }
+                Value::Channel(ch) => match ch.try_recv() {
+                    Some(RecvOutcome::Message(msg)) => {
+                        self.stack.push(fired(index, decode(msg)));
+                        return Ok(());
+                    }
+                    Some(RecvOutcome::Lagged(n)) => {
+                        return Err(RuntimeError::new(RuntimeErrorKind::Lagged(n), line));
+                    }
+                    Some(RecvOutcome::Closed) => {}
+                    None => channels.push(ch.clone()),
+                },
+                Value::LocalChannel(ch) => {
+                    let mut c = ch.borrow_mut();
+                    if c.is_hub() {
+                        return Err(RuntimeError::new(
+                            RuntimeErrorKind::TypeMismatch(
+                                "select on a broadcast or watch channel: \
+                                 select on a subscription to it instead"
+                                    .into(),
+                            ),
+                            line,
+                        ));
+                    }
+                    if let Some(n) = c.take_missed() {
+                        return Err(RuntimeError::new(RuntimeErrorKind::Lagged(n), line));
+                    }
+                    if let Some(msg) = c.queue.pop_front() {
+                        drop(c);
+                        self.stack.push(fired(index, msg));
+                        return Ok(());
+                    }
+                    if !c.closed {
+                        waits.locals.push(*ch);
+                    }
+                }
+                Value::GreenHandle(h) => {
+                    let (id, result) = {
+                        let h = h.borrow();
+                        (h.id, h.result.clone())
+                    };
+                    match result {
+                        Some(ResumeOutcome::Value(v)) => {
+                            self.stack.push(fired(index, v));
+                            return Ok(());
+                        }
+                        Some(ResumeOutcome::Raise(e)) => return Err(e),
+                        None => waits.greens.push(id),
+                    }
+                }
+                Value::Deferred(d) => {
+                    let result = d.borrow().result.clone();
+                    match result {
+                        Some(ResumeOutcome::Value(v)) => {
+                            self.stack.push(fired(index, v));
+                            return Ok(());
+                        }
+                        Some(ResumeOutcome::Raise(e)) => return Err(e),
+                        None => waits.deferreds.push(*d),
+                    }
+                }
+                Value::Task(t) if t.is_finished() => {
+                    let op = stdlib::native_join(std::slice::from_ref(source))?;
+                    let v = offload::decode(offload::run_handoff(op)).map_err(|mut e| {
+                        if e.line == 0 { e.line = line; }
+                        e
+                    })?;
+                    self.stack.push(fired(index, v));
+                    return Ok(());
+                }
+                Value::Task(t) => tasks.push(t.clone()),
+                Value::Socket(sock) if sock.is_stream() => {
+                    if sock.poll_readable() {
+                        self.stack.push(fired(index, source.clone()));
+                        return Ok(());
+                    }
+                    sockets.push(sock.clone());
+                }
+                other => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::TypeMismatch(format!(
+                            "select expects channels, go handles, tasks, \
+                             deferreds or stream sockets, got {}",
+                            other.type_name()
+                        )),
+                        line,
+                    ));
+                }
+            }
+        }
+        let timed_out = deadline.is_some_and(|at| self.now_seconds() >= at);
+        if has_else || timed_out {
+            let mut m: IndexMap<Arc<str>, Value> = IndexMap::with_capacity(1);
+            m.insert(Arc::from("index"), Value::Int(-1));
+            self.stack.push(Value::Object(gc::alloc_object(m)));
+            return Ok(());
+        }
+        let external = !(channels.is_empty() && tasks.is_empty() && sockets.is_empty());
+        let local = !(waits.greens.is_empty()
+            && waits.deferreds.is_empty()
+            && waits.locals.is_empty());
+        if !external && !local && deadline.is_none() {
+            return Err(RuntimeError::new(RuntimeErrorKind::ChannelClosed, line));
+        }
+        if self.current_gen.is_some() {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::Raised(Value::Str(
+                    "cannot select inside a generator".into(),
+                )),
+                line,
+            ));
+        }
+        // Only this actor's coroutines can wake a select on green
+        // handles and local channels; a deferred may also be settled by
+        // a host, as for `coop_join_deferred`.
+        if !external
+            && deadline.is_none()
+            && !self.scheduler.can_make_progress()
+            && (waits.deferreds.is_empty() || self.blocking_timers_ok)
+        {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::Raised(Value::Str(
+                    "deadlock: select would block but nothing can wake it".into(),
+                )),
+                line,
+            ));
+        }
+        self.check_self_cancelled(line)?;
+        if external {
+            let job_id = self.next_job_id;
+            self.next_job_id += 1;
+            let signal = Signal::new(Waiter::new(job_id, self.mailbox.clone()));
+            for ch in &channels {
+                ch.notify(&signal);
+            }
+            for t in &tasks {
+                t.notify(&signal);
+            }
+            for socket in sockets.iter().cloned() {
+                let rop = ReactorOp { socket, op: SocketOp::Readable, label: "select" };
+                reactor::submit(job_id, self.mailbox.clone(), rop, self.sim);
+            }
+            let mailbox = self.mailbox.clone();
+            let withdraw = !sockets.is_empty();
+            waits.job = Some(job_id);
+            waits.on_wake = Some(Box::new(move || {
+                signal.cancel();
+                if withdraw {
+                    reactor::withdraw(job_id, &mailbox);
+                }
+            }));
+        }
+        let parked = self.save_current(None);
+        self.scheduler.park_select(waits, parked);
+        match self.pick_next() {
+            Some(next) => self.load_green(next),
+            // Host drain or actor pool: nothing is ready now. Unwind;
+            // the select resumes once one of its sources wakes it.
+            None => Err(RuntimeError::new(RuntimeErrorKind::HostYield, 0)),
+        }
+    }
+
+    /// The local channels a `send` or `close` call will wake `select`s
+    /// on: the channel and, for a broadcast or watch channel, each of
+    /// its subscriptions — read before the call, which may drop them.
+    /// Empty unless a `select` is parked.
+    fn local_wakes(
+        &self,
+        nf: &crate::vm::value::NativeFn,
+        args: &[Value],
+    ) -> Vec<GcRef<LocalChannelKind>> {
+        if !self.scheduler.has_select_blocked() {
+            return Vec::new();
+        }
+        match (nf.name, args.first()) {
+            ("send" | "close", Some(Value::LocalChannel(ch))) => {
+                let mut chans = vec![*ch];
+                chans.extend_from_slice(ch.borrow().subscribers());
+                chans
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Wake the `select`s parked on `chans` (see [`local_wakes`](Vm::local_wakes)).
+    fn wake_locals(&mut self, chans: Vec<GcRef<LocalChannelKind>>) {
+        for ch in chans {
+            self.scheduler.wake_local(ch);
+        }
+    }
+
     // -- blocking-IO offload -----------------------------------------
 
-    /// Run a `Blocking` native. `extract` is the native's actor-thread
-    /// argument-validation step; it produces the `Send` closure a
-    /// worker runs. The call either runs inline (no sibling coroutine
+    /// Run a `Blocking` native called `name`. `extract` is the native's
+    /// actor-thread argument-validation step; it produces the `Send`
+    /// closure a worker runs. `name` labels the call in a trace. The call either runs inline (no sibling coroutine
     /// is waiting, so blocking the actor thread stalls nobody) or is
     /// offloaded to the worker pool with the running coroutine parked
     /// until the completion arrives. On the offload path the dispatch
@@ -3422,6 +4282,7 @@ impl Vm {
     /// freshly-loaded coroutine.
     fn dispatch_blocking(
         &mut self,
+        name: &'static str,
         extract: fn(&[Value]) -> Result<BlockingJob, RuntimeError>,
         args: Vec<Value>,
         line: u32,
@@ -3431,9 +4292,9 @@ impl Vm {
             e
         })?;
         // Inline fast path: nothing else is waiting to run, so the
-        // blocking call may as well run here. A generator body is
-        // pulled synchronously and likewise cannot be offload-parked.
-        if self.current_gen.is_some() || self.scheduler.is_idle() {
+        // blocking call may as well run here. A generator body parks
+        // like any other code, taking its resumers with it.
+        if self.may_block_inline() {
             let result = offload::decode(job()).map_err(|mut e| {
                 if e.line == 0 { e.line = line; }
                 e
@@ -3448,9 +4309,13 @@ impl Vm {
         // coroutine until its completion is pumped back.
         let job_id = self.next_job_id;
         self.next_job_id += 1;
+        trace::op_begin(self.trace, self.scheduler.current().0, job_id, name);
         offload::submit(job_id, self.mailbox.clone(), job);
         let parked = self.save_current(None);
         self.scheduler.park_io(job_id, parked);
+        if self.sim {
+            self.settle(Some(job_id));
+        }
         match self.pick_next() {
             Some(next) => self.load_green(next),
             // Host drain only: the offload is in flight but nothing is
@@ -3460,6 +4325,59 @@ impl Vm {
         }
     }
 
+    /// Run a `Handoff` native — a cross-actor wait. Inline, as for
+    /// [`dispatch_blocking`], the call sleeps this thread until the
+    /// result arrives. Otherwise the op is handed a [`Waiter`] for a
+    /// fresh job id and the coroutine parks on that id exactly as for an
+    /// offload; no worker runs meanwhile — the thread that produces the
+    /// result (a channel send, a finishing actor) posts it straight to
+    /// this actor's mailbox.
+    fn dispatch_handoff(
+        &mut self,
+        name: &'static str,
+        extract: fn(&[Value]) -> Result<HandoffOp, RuntimeError>,
+        args: Vec<Value>,
+        line: u32,
+    ) -> Result<(), RuntimeError> {
+        let op = extract(&args).map_err(|mut e| {
+            if e.line == 0 { e.line = line; }
+            e
+        })?;
+        if self.may_block_inline() {
+            let result = offload::decode(offload::run_handoff(op))
+                .map_err(|mut e| {
+                    if e.line == 0 { e.line = line; }
+                    e
+                })?;
+            self.stack.push(result);
+            return Ok(());
+        }
+        self.check_self_cancelled(line)?;
+        let job_id = self.next_job_id;
+        self.next_job_id += 1;
+        // Park before handing the op its waiter: a result that is
+        // already available posts at once, and is picked up by the poll
+        // in `pick_next` below.
+        trace::op_begin(self.trace, self.scheduler.current().0, job_id, name);
+        let parked = self.save_current(None);
+        self.scheduler.park_io(job_id, parked);
+        op(Waiter::new(job_id, self.mailbox.clone()));
+        match self.pick_next() {
+            Some(next) => self.load_green(next),
+            // Host drain or actor pool: nothing is ready now. Unwind;
+            // this coroutine resumes once its completion is polled.
+            None => Err(RuntimeError::new(RuntimeErrorKind::HostYield, 0)),
+        }
+    }
+
+    /// May a blocking call run inline, sleeping this thread? Only when
+    /// no sibling coroutine is waiting to run — and never on the actor
+    /// pool, where the thread is shared with other actors, nor in a
+    /// linked actor, which must stay wakeable by an exit signal.
+    fn may_block_inline(&self) -> bool {
+        !self.pooled && self.scheduler.is_idle() && !self.self_task.has_links()
+    }
+
     /// Run a `Socket` native — a steady-state `Net` read / write /
     /// accept. Like [`dispatch_blocking`] it runs inline when the actor
     /// is idle, but the offload path drives the op on the async-IO
@@ -3467,18 +4385,18 @@ impl Vm {
     /// worker thread. Every socket kind, TLS included, is reactor-driven.
     fn dispatch_socket(
         &mut self,
+        name: &'static str,
         extract: fn(&[Value]) -> Result<ReactorOp, RuntimeError>,
         args: Vec<Value>,
         line: u32,
     ) -> Result<(), RuntimeError> {
-        let rop = extract(&args).map_err(|mut e| {
+        let mut rop = extract(&args).map_err(|mut e| {
             if e.line == 0 { e.line = line; }
             e
         })?;
         // Inline fast path: nothing else is waiting, so the blocking
-        // call may as well run here. A generator body is pulled
-        // synchronously and likewise cannot offload-park.
-        if self.current_gen.is_some() || self.scheduler.is_idle() {
+        // call may as well run here.
+        if self.may_block_inline() {
             let result = offload::decode(reactor::run_blocking(rop))
                 .map_err(|mut e| {
                     if e.line == 0 { e.line = line; }
@@ -3494,7 +4412,15 @@ impl Vm {
         // coroutine until its completion is pumped back.
         let job_id = self.next_job_id;
         self.next_job_id += 1;
-        reactor::submit(job_id, self.mailbox.clone(), rop);
+        trace::op_begin(self.trace, self.scheduler.current().0, job_id, name);
+        // A read offloaded only because the actor is pooled would have
+        // blocked inline off the pool, where a `close` from another
+        // actor ends it as end-of-stream; have the reactor end it so.
+        if let SocketOp::ReadChunk { eof_on_close, .. } = &mut rop.op {
+            *eof_on_close =
+                self.pooled && self.scheduler.is_idle() && !rop.socket.is_closed();
+        }
+        reactor::submit(job_id, self.mailbox.clone(), rop, self.sim);
         let parked = self.save_current(None);
         self.scheduler.park_io(job_id, parked);
         match self.pick_next() {
@@ -3515,9 +4441,32 @@ impl Vm {
     /// owns the clock and the poll loop.
     fn pick_next(&mut self) -> Option<GreenThread> {
         loop {
+            // A linked actor's exit signal, noticed at a park: stop
+            // scheduling so the caller unwinds into the catch path, which
+            // ends the actor. A pooled actor is ended by the pool instead.
+            if !self.pooled && self.exiting.is_none() {
+                self.exiting = self.self_task.take_exit();
+            }
+            if self.exiting.is_some() {
+                return None;
+            }
+            self.sim_poll();
             if let Some(next) = self.scheduler.take_next() {
                 return Some(next);
             }
+            // On the actor pool the thread is shared: surface whatever is
+            // ready right now, and otherwise return `None` so the caller
+            // unwinds to the pool via `HostYield`. The pool resumes the
+            // actor when its mailbox is posted or its next timer is due.
+            if self.pooled {
+                if self.scheduler.has_io_blocked() {
+                    self.poll_io_completions();
+                }
+                if self.scheduler.has_timer_blocked() {
+                    self.scheduler.wake_timers(self.now_seconds());
+                }
+                return self.scheduler.take_next();
+            }
             if self.scheduler.has_io_blocked() {
                 // Inside a host drain the actor thread must not block:
                 // poll completions once (non-blocking) and return
@@ -3528,7 +4477,38 @@ impl Vm {
                     self.poll_io_completions();
                     return self.scheduler.take_next();
                 }
-                self.pump_io_completions();
+                // A simulation gives the worker pool a moment to post,
+                // then treats the rest as waiting on the clock: with a
+                // timer pending, virtual time jumps to it.
+                if self.sim && self.blocking_timers_ok {
+                    if !self.settle(None) {
+                        match self.sim_next_wake() {
+                            Some(wake) => {
+                                sim::advance_to(wake);
+                                self.scheduler.wake_timers(self.now_seconds());
+                            }
+                            None => self.pump_io_completions(None),
+                        }
+                    }
+                    continue;
+                }
+                // A `wait` may come due before any IO completes: bound
+                // the block by the earliest timer, so a coroutine parked
+                // on the clock is not starved by one parked on a socket.
+                let timer = if self.blocking_timers_ok {
+                    self.scheduler.next_timer_wake()
+                } else {
+                    None
+                };
+                match timer {
+                    Some(wake) => {
+                        let dt = (wake - self.now_seconds()).max(0.0);
+                        let limit = std::time::Duration::from_secs_f64(dt);
+                        self.pump_io_completions(Some(limit));
+                        self.scheduler.wake_timers(self.now_seconds());
+                    }
+                    None => self.pump_io_completions(None),
+                }
                 continue;
             }
             if self.scheduler.has_timer_blocked() {
@@ -3557,27 +4537,31 @@ impl Vm {
     /// pumps coroutines instead.
     fn sleep_to_next_timer(&mut self) {
         if let Some(wake) = self.scheduler.next_timer_wake() {
+            // Virtual time does not pass on its own: jump it to the wake.
+            if self.sim {
+                sim::advance_to(wake);
+            }
             let dt = wake - self.now_seconds();
             if dt > 0.0 {
-                std::thread::sleep(std::time::Duration::from_secs_f64(dt));
+                // Sleep on the mailbox, not the bare thread, so an exit
+                // signal's interrupt cuts the sleep short.
+                self.pump_io_completions(Some(std::time::Duration::from_secs_f64(dt)));
             }
             self.scheduler.wake_timers(self.now_seconds());
         }
     }
 
     /// Block the actor thread until at least one outstanding offload
-    /// job completes, then decode every ready completion (on this, the
-    /// actor thread) and move each parked coroutine back onto the
-    /// run-queue. Called only when the queue is empty but IO is in
-    /// flight.
-    fn pump_io_completions(&mut self) {
-        for (job_id, result) in self.mailbox.wait_drain() {
-            let outcome = match offload::decode(result) {
-                Ok(v) => ResumeOutcome::Value(v),
-                Err(e) => ResumeOutcome::Raise(e),
-            };
-            self.scheduler.wake_io(job_id, outcome);
-        }
+    /// job completes (or `limit` passes), then decode every ready
+    /// completion (on this, the actor thread) and move each parked
+    /// coroutine back onto the run-queue. Called only when the queue is
+    /// empty but IO is in flight.
+    fn pump_io_completions(&mut self, limit: Option<std::time::Duration>) {
+        let ready = match limit {
+            Some(limit) => self.mailbox.wait_drain_for(limit),
+            None => self.mailbox.wait_drain(),
+        };
+        self.wake_completed(ready);
     }
 
     /// Non-blocking counterpart of [`pump_io_completions`]: drain only
@@ -3585,7 +4569,18 @@ impl Vm {
     /// safepoint so a coroutine that spins on `yield` still observes a
     /// sibling's IO finishing without ever reaching a blocking switch.
     fn poll_io_completions(&mut self) {
-        for (job_id, result) in self.mailbox.drain() {
+        let ready = self.mailbox.drain();
+        self.wake_completed(ready);
+    }
+
+    /// Decode drained completions (on this, the actor thread) and move
+    /// each parked coroutine back onto the run-queue. A simulation wakes
+    /// them in job order, not in the order the workers finished.
+    fn wake_completed(&mut self, mut ready: Vec<(u64, offload::OffloadResult)>) {
+        if self.sim {
+            ready.sort_by_key(|(job_id, _)| *job_id);
+        }
+        for (job_id, result) in ready {
             let outcome = match offload::decode(result) {
                 Ok(v) => ResumeOutcome::Value(v),
                 Err(e) => ResumeOutcome::Raise(e),
@@ -3594,6 +4589,76 @@ impl Vm {
         }
     }
 
+    /// Simulation mode's stand-in for the per-instruction completion
+    /// poll: surface finished offloads at a switch point.
+    fn sim_poll(&mut self) {
+        if self.sim && self.scheduler.has_io_blocked() {
+            self.poll_io_completions();
+        }
+    }
+
+    /// Simulation mode: the virtual time the clock jumps to when nothing
+    /// can run — the next `wait` wake, or a socket timeout, which is a
+    /// timer too.
+    fn sim_next_wake(&self) -> Option<f64> {
+        [self.scheduler.next_timer_wake(), sim::net_deadline()]
+            .into_iter()
+            .flatten()
+            .min_by(f64::total_cmp)
+    }
+
+    /// Simulation mode's clock at a `yield`. A `yield` takes
+    /// [`sim::YIELD_TICK`] of virtual time, so coroutines spinning on
+    /// `yield` still see a sibling's `wait` come due. One with nothing
+    /// else ready is waiting on something else: once the worker pool has
+    /// had its moment, the clock jumps to the next `wait` or socket
+    /// timeout, as it would if the coroutine had parked.
+    fn sim_yield(&mut self) {
+        if !self.sim {
+            return;
+        }
+        if self.scheduler.has_ready() || !self.blocking_timers_ok {
+            sim::advance_by(sim::YIELD_TICK);
+        } else if !(self.scheduler.has_io_blocked() && self.settle(None)) {
+            match self.sim_next_wake() {
+                Some(wake) => sim::advance_to(wake),
+                None => sim::advance_by(sim::YIELD_TICK),
+            }
+        }
+        self.scheduler.wake_timers(self.now_seconds());
+    }
+
+    /// Simulation mode: wait up to [`sim::SETTLE`] of real time for the
+    /// worker pool to post, so a call that finishes at once is seen
+    /// before anything else runs, at the same point on every run. Stops
+    /// early once `job` has posted or, with `None`, once anything has.
+    /// Returns whether any coroutine woke.
+    fn settle(&mut self, job: Option<u64>) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let deadline = std::time::Instant::now() + sim::SETTLE;
+            let mut woke = false;
+            loop {
+                let left = deadline.saturating_duration_since(std::time::Instant::now());
+                let ready = self.mailbox.wait_drain_for(left);
+                let done = match job {
+                    Some(id) => ready.iter().any(|(j, _)| *j == id),
+                    None => !ready.is_empty(),
+                };
+                woke |= !ready.is_empty();
+                self.wake_completed(ready);
+                if done || left.is_zero() {
+                    return woke;
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = job;
+            false
+        }
+    }
+
     // -- host frame loop ---------------------------------------------
 
     /// Drive every coroutine that is ready *this frame* and return to
@@ -3916,6 +4981,9 @@ impl Vm {
             if let Some(h) = gt.handle {
                 m.mark_green_handle(h);
             }
+            if let Some(chain) = &gt.generator {
+                chain.trace(m);
+            }
         }
         // Main, parked aside during a host drain: its top-level values
         // (and open upvalues a `go` block captured) must survive a
@@ -3934,18 +5002,7 @@ impl Vm {
         // Resumers parked under a running generator — each is a slice
         // of execution state the generator's `next()` will return to.
         for ctx in &self.resume_stack {
-            for v in &ctx.stack {
-                v.trace(m);
-            }
-            for up in &ctx.open_upvalues {
-                m.mark_upvalue(*up);
-            }
-            for frame in &ctx.frames {
-                trace_frame(frame, m);
-            }
-            if let Some(g) = ctx.prev_gen {
-                m.mark_generator(g);
-            }
+            ctx.trace(m);
         }
         // The running generator's handle. Its parked coroutine state is
         // currently live in `frames`/`stack` above; marking the handle
@@ -4017,6 +5074,19 @@ fn green_join_target(
     None
 }
 
+/// Is this native call `__select` — the wait behind a `select` block?
+/// The `Call`/`TailCall` arms run it as [`Vm::coop_select`]. A wrong
+/// arity falls through to the native's own arity error.
+fn is_select_call(nf: &crate::vm::value::NativeFn, args: &[Value]) -> bool {
+    nf.name == "__select" && args.len() == 3
+}
+
+/// Is this native call `__select_deadline` — a `select`'s `after`
+/// deadline, which the VM computes on its own clock?
+fn is_select_deadline_call(nf: &crate::vm::value::NativeFn, args: &[Value]) -> bool {
+    nf.name == "__select_deadline" && args.len() == 1
+}
+
 /// The handle a `go_cancel(handle)` call targets, if this native call is a
 /// `go_cancel` of a green-thread handle. Like [`green_join_target`], the
 /// `Call`/`TailCall` opcode arms intercept it so the VM can reach the
@@ -4150,6 +5220,36 @@ fn cancelled_result() -> Value {
     crate::vm::native_modules::object(&[("cancelled", Value::Bool(true))])
 }
 
+/// Run an inline native call under a slow-native threshold, reporting
+/// it if it ran for `limit` or longer (see [`crate::vm::preempt`]). The
+/// call sites go straight to the native when no threshold is set.
+#[cfg(not(target_arch = "wasm32"))]
+fn timed_native(
+    limit: std::time::Duration,
+    name: &'static str,
+    line: u32,
+    call: impl FnOnce() -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let start = std::time::Instant::now();
+    let result = call();
+    let elapsed = start.elapsed();
+    if elapsed >= limit {
+        preempt::report(preempt::SlowNative { name, elapsed, line });
+    }
+    result
+}
+
+/// No clock to time a native by on wasm: just make the call.
+#[cfg(target_arch = "wasm32")]
+fn timed_native(
+    _limit: std::time::Duration,
+    _name: &'static str,
+    _line: u32,
+    call: impl FnOnce() -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    call()
+}
+
 /// The shared body of every generator's synthetic `next` method:
 /// `GetUpvalue 0` loads the captured generator handle, `Resume` pulls
 /// the next value, `Return` hands back the `${ done, value }` object.
@@ -4222,44 +5322,6 @@ fn parse_iter_result(result: Value, line: u32) -> Result<Option<Value>, RuntimeE
     }
 }
 
-/// Worker-thread entry point for `spawn`. Builds a fresh `Vm` (its own
-/// thread-local heap), decodes the spawned closure into that heap,
-/// runs it, and encodes the outcome back into `Send`-able form. An
-/// uncaught actor error is rendered against the worker's own
-/// `SourceMap` (the parent's is not `Send`).
-fn run_actor(transfer: crate::vm::transfer::Transfer) -> crate::vm::task::ActorOutcome {
-    use crate::vm::transfer::{decode, encode, TransferError};
-
-    let mut vm = Vm::new();
-    let closure = match decode(transfer) {
-        Value::Function(c) => c,
-        _ => unreachable!("spawn always encodes a closure"),
-    };
-    match vm.run_closure(closure) {
-        Ok(v) => encode(&v).map_err(|e| TransferError {
-            kind_tag: e.kind.kind_tag().to_string(),
-            message: format!("actor return value could not be sent: {e}"),
-            rendered_trace: String::new(),
-            raised: None,
-        }),
-        Err(e) => {
-            let kind_tag = e.kind.kind_tag().to_string();
-            let message = format!("{e}");
-            // If the actor did `raise <value>`, carry that value so the
-            // parent's `catch` binds exactly it. A non-sendable raised
-            // value falls back to `None` — its `str()` form is already
-            // in `message`.
-            let raised = match &e.kind {
-                RuntimeErrorKind::Raised(v) => encode(v).ok(),
-                _ => None,
-            };
-            let rendered_trace = crate::vm::error::Error::Runtime(e)
-                .render(&vm.source_map.borrow());
-            Err(TransferError { kind_tag, message, rendered_trace, raised })
-        }
-    }
-}
-
 // -- arithmetic helpers (spec §6.2 + §7.1) --
 
 /// Wrap a `num_bigint::BigInt` back into a `Value`.
@@ -4577,6 +5639,9 @@ fn extend_array(
                 out.push(Value::Int(byte as i64));
             }
         }
+        Value::Shared(n) if matches!(*n, SharedNode::Array(_)) => {
+            target.borrow_mut().extend(n.values());
+        }
         other => {
             return Err(RuntimeError::new(
                 RuntimeErrorKind::TypeMismatch(format!(
@@ -4615,6 +5680,7 @@ fn make_iter(v: Value, line: u32) -> Result<IterState, RuntimeError> {
         Value::Map(m) => Ok(IterState::Map { map: m, index: 0 }),
         Value::Set(s) => Ok(IterState::Set { set: s, index: 0 }),
         Value::Bytes(b) => Ok(IterState::Bytes { bytes: b, index: 0 }),
+        Value::Shared(node) => Ok(IterState::Shared { node, index: 0 }),
         Value::Str(s) => Ok(IterState::String { string: s, char_index: 0, byte_index: 0 }),
         other => Err(RuntimeError::new(
             RuntimeErrorKind::TypeMismatch(format!(
@@ -4719,6 +5785,7 @@ fn index_get(coll: &Value, key: &Value, line: u32) -> Result<Value, RuntimeError
                 )),
             }
         }
+        Value::Shared(n) => n.index(key, line),
         other => Err(RuntimeError::new(
             RuntimeErrorKind::TypeMismatch(format!("cannot index {}", other.type_name())),
             line,
@@ -4802,6 +5869,7 @@ fn index_set(coll: &Value, key: &Value, value: Value, line: u32) -> Result<(), R
             RuntimeErrorKind::ImmutableTarget("string".into()),
             line,
         )),
+        Value::Shared(_) => Err(shared::immutable("assign to an element", line)),
         other => Err(RuntimeError::new(
             RuntimeErrorKind::TypeMismatch(format!("cannot index {}", other.type_name())),
             line,
@@ -4809,7 +5877,7 @@ fn index_set(coll: &Value, key: &Value, value: Value, line: u32) -> Result<(), R
     }
 }
 
-fn normalize_index(idx: i64, len: usize) -> Option<usize> {
+pub(crate) fn normalize_index(idx: i64, len: usize) -> Option<usize> {
     let len_i = len as i64;
     let real = if idx < 0 { idx + len_i } else { idx };
     if real < 0 || real >= len_i { None } else { Some(real as usize) }
@@ -4819,7 +5887,7 @@ fn normalize_index(idx: i64, len: usize) -> Option<usize> {
 /// Negative endpoints count from the end; positions outside `[0, len)`
 /// are dropped — which clamps an over-long slice. Step and inclusivity
 /// are honoured, so a descending range yields a reversed slice.
-fn range_indices(r: &RangeData, len: usize) -> Vec<usize> {
+pub(crate) fn range_indices(r: &RangeData, len: usize) -> Vec<usize> {
     let len_i = len as i64;
     let resolve = |v: i64| if v < 0 { v.saturating_add(len_i) } else { v };
     let from = resolve(r.from);
@@ -0,0 +1,325 @@
+//! A minimal X.509 certificate reader — just enough DER to describe a
+//! TLS peer's certificate to tigr code (`Net.tls_info(sock).peer_cert`):
+//! subject and issuer names, subject alternative names, the validity
+//! window, and the serial number.
+//!
+//! Hand-rolled like [`crate::vm::deflate`]: `rustls` has already
+//! verified the chain by the time anything here runs, so this is a
+//! read-only walk over a trusted structure, not a validator. Anything
+//! it does not recognise is skipped rather than rejected — an unknown
+//! name attribute prints as its dotted OID, an unknown SAN form is
+//! dropped — and a certificate it cannot walk at all yields `None`.
+
+use crate::vm::native_modules::datetime::days_from_civil;
+
+/// The parts of a certificate `Net.tls_info` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertInfo {
+    /// The subject distinguished name, e.g. `CN=localhost, O=Acme`, in
+    /// certificate order (as `openssl x509 -subject` prints it).
+    pub subject: String,
+    /// The subject's first common name (`CN`), if it has one.
+    pub common_name: Option<String>,
+    /// The issuer distinguished name, formatted like `subject`.
+    pub issuer: String,
+    /// Subject alternative names — DNS names, IP addresses, email
+    /// addresses, and URIs — as plain strings, in certificate order.
+    pub sans: Vec<String>,
+    /// Start of the validity window, in epoch milliseconds.
+    pub not_before_ms: i64,
+    /// End of the validity window, in epoch milliseconds.
+    pub not_after_ms: i64,
+    /// The serial number as lowercase hex.
+    pub serial: String,
+}
+
+/// DER tags this reader dispatches on.
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+/// `[0] EXPLICIT Version` in the TBS certificate.
+const TAG_VERSION: u8 = 0xa0;
+/// `[3] EXPLICIT Extensions` in the TBS certificate.
+const TAG_EXTENSIONS: u8 = 0xa3;
+
+/// `id-ce-subjectAltName` (2.5.29.17).
+const OID_SAN: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// One DER element: its tag and content bytes.
+struct Tlv<'a> {
+    tag: u8,
+    body: &'a [u8],
+}
+
+/// A forward-only cursor over a run of DER elements.
+struct Reader<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { rest: bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.rest.first().copied()
+    }
+
+    /// Read the next element. Only single-byte tags and definite
+    /// lengths occur in a certificate, so nothing else is accepted.
+    fn next(&mut self) -> Option<Tlv<'a>> {
+        let (&tag, rest) = self.rest.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n {
+                return None;
+            }
+            let len = rest[..n].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            rest = &rest[n..];
+            len
+        };
+        if rest.len() < len {
+            return None;
+        }
+        let (body, rest) = rest.split_at(len);
+        self.rest = rest;
+        Some(Tlv { tag, body })
+    }
+
+    /// Read the next element, requiring `tag`.
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let tlv = self.next()?;
+        (tlv.tag == tag).then_some(tlv.body)
+    }
+}
+
+/// Describe the DER-encoded certificate `der`, or `None` if it is not a
+/// certificate this reader can walk.
+pub fn parse(der: &[u8]) -> Option<CertInfo> {
+    let cert = Reader::new(der).expect(TAG_SEQUENCE)?;
+    let mut tbs = Reader::new(Reader::new(cert).expect(TAG_SEQUENCE)?);
+    if tbs.peek_tag() == Some(TAG_VERSION) {
+        tbs.next()?;
+    }
+    let serial = tbs.expect(TAG_INTEGER)?;
+    tbs.expect(TAG_SEQUENCE)?; // signature algorithm
+    let issuer = name(tbs.expect(TAG_SEQUENCE)?)?;
+    let mut validity = Reader::new(tbs.expect(TAG_SEQUENCE)?);
+    let not_before_ms = time(validity.next()?)?;
+    let not_after_ms = time(validity.next()?)?;
+    let subject = name(tbs.expect(TAG_SEQUENCE)?)?;
+    tbs.expect(TAG_SEQUENCE)?; // subject public key info
+    let mut sans = Vec::new();
+    // Skip the optional unique identifiers ([1], [2]) up to [3].
+    while let Some(tlv) = tbs.next() {
+        if tlv.tag == TAG_EXTENSIONS {
+            sans = subject_alt_names(tlv.body)?;
+        }
+    }
+    // A positive INTEGER may carry a leading zero byte to keep its
+    // sign bit clear; it is not part of the serial.
+    let serial = match serial {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        s => s,
+    };
+    Some(CertInfo {
+        common_name: subject.1,
+        subject: subject.0,
+        issuer: issuer.0,
+        sans,
+        not_before_ms,
+        not_after_ms,
+        serial: serial.iter().map(|b| format!("{b:02x}")).collect(),
+    })
+}
+
+/// Format a `Name` (a SEQUENCE of RDN SETs) as `K=V, K=V`, returning
+/// the first common name alongside.
+fn name(body: &[u8]) -> Option<(String, Option<String>)> {
+    let mut parts = Vec::new();
+    let mut cn = None;
+    let mut rdns = Reader::new(body);
+    while !rdns.is_empty() {
+        let mut set = Reader::new(rdns.expect(TAG_SET)?);
+        while !set.is_empty() {
+            let mut atv = Reader::new(set.expect(TAG_SEQUENCE)?);
+            let oid = atv.expect(TAG_OID)?;
+            let value = String::from_utf8_lossy(atv.next()?.body).into_owned();
+            let key = attribute_name(oid);
+            if key == "CN" && cn.is_none() {
+                cn = Some(value.clone());
+            }
+            parts.push(format!("{key}={value}"));
+        }
+    }
+    Some((parts.join(", "), cn))
+}
+
+/// The short name of a distinguished-name attribute, or its dotted OID.
+fn attribute_name(oid: &[u8]) -> String {
+    let short = match oid {
+        [0x55, 0x04, 0x03] => "CN",
+        [0x55, 0x04, 0x05] => "serialNumber",
+        [0x55, 0x04, 0x06] => "C",
+        [0x55, 0x04, 0x07] => "L",
+        [0x55, 0x04, 0x08] => "ST",
+        [0x55, 0x04, 0x09] => "street",
+        [0x55, 0x04, 0x0a] => "O",
+        [0x55, 0x04, 0x0b] => "OU",
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01] => "UID",
+        _ => return dotted(oid),
+    };
+    short.to_string()
+}
+
+/// Render an OBJECT IDENTIFIER body in dotted-decimal form.
+fn dotted(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut acc: u64 = 0;
+    for &b in oid {
+        acc = (acc << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (acc / 40).min(2);
+                arcs.push(first);
+                arcs.push(acc - first * 40);
+            } else {
+                arcs.push(acc);
+            }
+            acc = 0;
+        }
+    }
+    arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+/// Decode a UTCTime (`YYMMDDHHMMSSZ`) or GeneralizedTime
+/// (`YYYYMMDDHHMMSSZ`) to epoch milliseconds. RFC 5280 pins both to
+/// UTC with whole seconds, which is all that is accepted.
+fn time(tlv: Tlv<'_>) -> Option<i64> {
+    let s = std::str::from_utf8(tlv.body).ok()?;
+    let s = s.strip_suffix('Z')?;
+    let (year, rest) = match tlv.tag {
+        TAG_UTC_TIME if s.len() == 12 => {
+            let yy: i64 = s[..2].parse().ok()?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &s[2..])
+        }
+        TAG_GENERALIZED_TIME if s.len() == 14 => (s[..4].parse().ok()?, &s[4..]),
+        _ => return None,
+    };
+    if !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| -> i64 { rest[i..i + 2].parse().unwrap_or(0) };
+    let days = days_from_civil(year, field(0), field(2));
+    let secs = days * 86_400 + field(4) * 3600 + field(6) * 60 + field(8);
+    Some(secs * 1000)
+}
+
+/// Pull the subject alternative names out of the `[3]` extensions
+/// block; an absent SAN extension is an empty list.
+fn subject_alt_names(body: &[u8]) -> Option<Vec<String>> {
+    let mut exts = Reader::new(Reader::new(body).expect(TAG_SEQUENCE)?);
+    while !exts.is_empty() {
+        let mut ext = Reader::new(exts.expect(TAG_SEQUENCE)?);
+        if ext.expect(TAG_OID)? != OID_SAN {
+            continue;
+        }
+        // Skip the optional `critical` BOOLEAN.
+        let mut value = ext.next()?;
+        if value.tag != TAG_OCTET_STRING {
+            value = ext.next()?;
+        }
+        let mut names = Reader::new(Reader::new(value.body).expect(TAG_SEQUENCE)?);
+        let mut out = Vec::new();
+        while let Some(gn) = names.next() {
+            match gn.tag {
+                // rfc822Name [1], dNSName [2], uniformResourceIdentifier [6]
+                0x81 | 0x82 | 0x86 => out.push(String::from_utf8_lossy(gn.body).into_owned()),
+                // iPAddress [7]
+                0x87 => match gn.body.len() {
+                    4 => {
+                        let b: [u8; 4] = gn.body.try_into().ok()?;
+                        out.push(std::net::Ipv4Addr::from(b).to_string());
+                    }
+                    16 => {
+                        let b: [u8; 16] = gn.body.try_into().ok()?;
+                        out.push(std::net::Ipv6Addr::from(b).to_string());
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        return Some(out);
+    }
+    Some(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::CertificateDer;
+
+    /// The self-signed `localhost` certificate `tests/net_test.tg` serves.
+    const LOCALHOST_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIDJDCCAgygAwIBAgIUHGaC7x8IAUhI5G7GcTFbJgCIiNMwDQYJKoZIhvcNAQEL
+BQAwFDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MDUxODE1MzIwNVoYDzIxMjYw
+NDI0MTUzMjA1WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwggEiMA0GCSqGSIb3DQEB
+AQUAA4IBDwAwggEKAoIBAQCtBIPv003IaEGG4vHfn2RHy9EYf6Y2wCn0km1ns5Ee
+Hh8eR9rVg++8DuldjqOF5yyz7QHJxfwppRY/BPmhCffX4vR/jbBz+x64YeIU6WV3
+D1n+9YrVrbtzVvN1fUbIPQ3Xl/mIYKAbMgiPTTpqZCthdzdMGi+aZBrWWnGouc9N
+Ia1c654e9XJqN1SeK++Aj5EoIx08smbDgf7qSRp1ZCMoOLHVxWIK+m9+AXofkbjw
+/V2fOgLG4YBbPrmQlCbABFmkgUm7zlqTRMU2uktaB1+GoyuKj4/TNDq+gYusqJjW
+9QhUTGE30gNdQt/M5SnrljwxUNMguFVvyqMeEwNkchUBAgMBAAGjbDBqMB0GA1Ud
+DgQWBBQzRnbOZzyrHYVTUffClvp7sWQrBzAfBgNVHSMEGDAWgBQzRnbOZzyrHYVT
+UffClvp7sWQrBzAaBgNVHREEEzARgglsb2NhbGhvc3SHBH8AAAEwDAYDVR0TAQH/
+BAIwADANBgkqhkiG9w0BAQsFAAOCAQEAMbMrmF9HbranBvsI3vVVl75cgqlG630a
+ybzTJK8il1Bz76Va3Q9uCKRBWm1LIdVKA9Z5xWNSG91hRjMwq5Z/opz3eDrtPg1p
+WL7moKP0oOsJVRdYxLdN90DeQ6Tzvy1R8ZARjNixl9SNZscrsvmXraedD+iT0mGn
+PRVEcp1vxBli9uf7LzJhmNcGG5XG1322MR/qI4NgnNvYs0rrkDTh4E2ZaceXBRbD
+JXXbIeDmC5v5UrQrtc08sgwkEmFbC6f7Che3ninNvmhHHOP3fSALfNetb6QxWVin
+Y/QOmRhZc0qIPTuTbaWNzYSHPhluJLAD4zZLXQAeRJFgA8gVBbC5cw==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn describes_a_self_signed_certificate() {
+        let der = CertificateDer::from_pem_slice(LOCALHOST_PEM.as_bytes()).unwrap();
+        let info = parse(&der).expect("certificate parses");
+        assert_eq!(info.subject, "CN=localhost");
+        assert_eq!(info.common_name.as_deref(), Some("localhost"));
+        assert_eq!(info.issuer, "CN=localhost");
+        assert_eq!(info.sans, vec!["localhost", "127.0.0.1"]);
+        assert_eq!(info.serial, "1c6682ef1f08014848e46ec671315b26008888d3");
+        // 2026-05-18T15:32:05Z (UTCTime) and 2126-04-24T15:32:05Z
+        // (GeneralizedTime — past 2049).
+        assert_eq!(info.not_before_ms, 1_779_118_325_000);
+        assert_eq!(info.not_after_ms, 4_932_718_325_000);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let der = CertificateDer::from_pem_slice(LOCALHOST_PEM.as_bytes()).unwrap();
+        assert!(parse(&der[..der.len() / 2]).is_none());
+        assert!(parse(&[]).is_none());
+    }
+
+    #[test]
+    fn renders_unknown_oids_dotted() {
+        assert_eq!(dotted(&[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37]), "1.3.6.1.4.1.311");
+    }
+}
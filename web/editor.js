@@ -32,7 +32,7 @@ import { tags as t }
 const KEYWORDS = new Set([
   'fn', 'if', 'else', 'for', 'while', 'break', 'continue', 'return',
   'import', 'try', 'catch', 'raise', 'match', 'spawn', 'select',
-  'parallel', 'go', 'yield', 'gen',
+  'receive', 'parallel', 'go', 'yield', 'gen',
 ]);
 const ATOMS = new Set(['true', 'false', 'null']);
 